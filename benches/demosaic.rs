@@ -0,0 +1,104 @@
+//! Times the bilinear demosaicing of large Bayer frames, tiled and per pixel.
+//!
+//! Run with `cargo bench --bench demosaic`, add `--features parallel` for the multi-threaded renders.
+//! The decoding of the uncompressed DNG is timed on its own and left out of the demosaicing times.
+
+use quickraw::{
+    decode_buffer,
+    encode::{
+        dng::{self, DngCompression, DngOptions, RawImage},
+        Metadata,
+    },
+    export::{self, Options},
+    set_tiled_demosaicing_enabled, CFAPattern, ColorSpace, TransferFunction,
+};
+use std::time::{Duration, Instant};
+
+const RUNS: usize = 3;
+
+/// 24MP and 45MP frames in the 3:2 ratio of the usual full-frame sensors.
+const SIZES: [(usize, usize); 2] = [(6000, 4000), (8256, 5504)];
+
+/// An uncompressed DNG of a textured gradient.
+fn synthetic_dng(width: usize, height: usize) -> Vec<u8> {
+    let samples = (0..width * height)
+        .map(|i| {
+            let (x, y) = (i % width, i / width);
+            ((x * 7 + y * 3) % 12000 + i * i % 251) as u16 * 4
+        })
+        .collect::<Vec<_>>();
+    let raw_image = RawImage {
+        image: &samples,
+        width,
+        height,
+        cfa_pattern: CFAPattern::RGGB,
+        black_level: [0; 4],
+        white_level: u16::MAX,
+        bits_per_sample: 16,
+        linearization_table: None,
+        crop: None,
+        white_balance: [1024, 512, 768],
+        cam_matrix: [0.7, 0.2, 0.1, 0.25, 0.6, 0.15, 0.05, 0.15, 0.8],
+    };
+    let options = DngOptions {
+        compression: DngCompression::None,
+        embed_preview: false,
+    };
+    let metadata = Metadata {
+        make: Some("Quickraw".to_owned()),
+        model: Some("Synthetic Camera".to_owned()),
+        ..Default::default()
+    };
+    dng::encode(&raw_image, &metadata, None, &options).unwrap()
+}
+
+/// The fastest of `RUNS` runs of `f`.
+fn fastest(mut f: impl FnMut()) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            f();
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn main() {
+    let options = || Options::new(TransferFunction::Linear, ColorSpace::Raw, false);
+    for (width, height) in SIZES {
+        let data = synthetic_dng(width, height);
+        let decoding = fastest(|| {
+            decode_buffer(&data).unwrap();
+        });
+        println!(
+            "{}x{} ({:.1}MP), decoding {:.0?}",
+            width,
+            height,
+            (width * height) as f64 / 1e6,
+            decoding
+        );
+
+        let mut times = vec![];
+        for tiled in [false, true] {
+            set_tiled_demosaicing_enabled(tiled);
+            let u16_time = fastest(|| {
+                export::load_origin_image_from_buffer(&data, options()).unwrap();
+            });
+            let f32_time = fastest(|| {
+                export::load_float_image_from_buffer(&data, options()).unwrap();
+            });
+            times.push([u16_time, f32_time].map(|x| x.saturating_sub(decoding)));
+        }
+        let [per_pixel, tiled] = [times[0], times[1]];
+        for (i, name) in ["u16", "f32"].into_iter().enumerate() {
+            println!(
+                "  {}: per pixel {:.0?}, tiled {:.0?}, {:.2}x",
+                name,
+                per_pixel[i],
+                tiled[i],
+                per_pixel[i].as_secs_f64() / tiled[i].as_secs_f64()
+            );
+        }
+    }
+}
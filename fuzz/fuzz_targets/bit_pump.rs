@@ -0,0 +1,15 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use quickraw::fuzzing::{bit_pump, Pump};
+
+// the first byte picks the pump and the next ones up to a zero are the lengths of the values to read
+fuzz_target!(|data: &[u8]| {
+    let Some((&pump, rest)) = data.split_first() else {
+        return;
+    };
+    let split = rest.iter().position(|&x| x == 0).unwrap_or(rest.len());
+    let (lengths, data) = rest.split_at(split);
+    let pump = Pump::ALL[pump as usize % Pump::ALL.len()];
+    let _ = bit_pump(pump, data.get(1..).unwrap_or_default(), lengths);
+});
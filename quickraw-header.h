@@ -13,6 +13,10 @@ typedef struct BasicInfo {
   char *exif;
   struct RustVec thumbnail;
   unsigned char orientation;
+  /**
+   * The `RawMetadata` in JSON, empty without the `serde` feature.
+   */
+  char *metadata;
 } BasicInfo;
 
 typedef struct QuickrawResponse_BasicInfo {
@@ -33,6 +37,12 @@ typedef struct QuickrawResponse_Image {
   struct Image content;
 } QuickrawResponse_Image;
 
+typedef struct QuickrawResponse_RustVec {
+  bool has_error;
+  char *error_msg;
+  struct RustVec content;
+} QuickrawResponse_RustVec;
+
 struct QuickrawResponse_BasicInfo quickraw_load_basicinfo(char *cpath);
 
 void quickraw_free_basicinfo(struct QuickrawResponse_BasicInfo response);
@@ -40,3 +50,14 @@ void quickraw_free_basicinfo(struct QuickrawResponse_BasicInfo response);
 struct QuickrawResponse_Image quickraw_load_image(char *cpath);
 
 void quickraw_free_image(struct QuickrawResponse_Image response);
+
+/**
+ * Renders a raw file into JPEG bytes, `subsampling` is one of `444`, `422` or `420`.
+ */
+struct QuickrawResponse_RustVec quickraw_encode_jpeg(char *cpath,
+                                                     unsigned char quality,
+                                                     unsigned short subsampling,
+                                                     bool progressive,
+                                                     bool embed_thumbnail);
+
+void quickraw_free_jpeg(struct QuickrawResponse_RustVec response);
@@ -0,0 +1,91 @@
+//! A minimal glob for the shells that do not expand wildcards, like the Windows command prompt.
+
+use std::path::{Path, PathBuf};
+
+fn has_wildcard(s: &str) -> bool {
+    s.contains(['*', '?', '['])
+}
+
+/// Matches a name against `*`, `?` and `[abc]`/`[a-z]`/`[!abc]` patterns, case-insensitively.
+fn matches(pattern: &[char], name: &[char]) -> bool {
+    match pattern.first() {
+        None => name.is_empty(),
+        Some('*') => (0..=name.len()).any(|i| matches(&pattern[1..], &name[i..])),
+        Some('?') => !name.is_empty() && matches(&pattern[1..], &name[1..]),
+        Some('[') => {
+            let Some(end) = pattern
+                .iter()
+                .skip(2)
+                .position(|&c| c == ']')
+                .map(|x| x + 2)
+            else {
+                return name.first() == Some(&'[') && matches(&pattern[1..], &name[1..]);
+            };
+            let Some(c) = name.first().map(|c| c.to_ascii_lowercase()) else {
+                return false;
+            };
+            let (negate, set) = match pattern[1] {
+                '!' | '^' => (true, &pattern[2..end]),
+                _ => (false, &pattern[1..end]),
+            };
+            let mut found = false;
+            let mut i = 0;
+            while i < set.len() {
+                if i + 2 < set.len() && set[i + 1] == '-' {
+                    let (from, to) = (set[i].to_ascii_lowercase(), set[i + 2].to_ascii_lowercase());
+                    found |= from <= c && c <= to;
+                    i += 3;
+                } else {
+                    found |= set[i].to_ascii_lowercase() == c;
+                    i += 1;
+                }
+            }
+            found != negate && matches(&pattern[end + 1..], &name[1..])
+        }
+        Some(p) => {
+            name.first().map(|c| c.to_ascii_lowercase()) == Some(p.to_ascii_lowercase())
+                && matches(&pattern[1..], &name[1..])
+        }
+    }
+}
+
+/// Expands the wildcards in the file name of a path, the directory part is taken literally.
+///
+/// Paths without wildcards are returned as they are, so missing files are reported later.
+pub fn expand(pattern: &str) -> Vec<PathBuf> {
+    let path = Path::new(pattern);
+    let file_name = path
+        .file_name()
+        .and_then(|x| x.to_str())
+        .unwrap_or_default();
+    if !has_wildcard(file_name) {
+        return vec![path.to_path_buf()];
+    }
+
+    let dir = match path.parent() {
+        Some(x) if !x.as_os_str().is_empty() => x.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let pattern = file_name.chars().collect::<Vec<_>>();
+    let Ok(entries) = std::fs::read_dir(&dir) else {
+        return vec![];
+    };
+    let mut result = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_file())
+        .filter(|entry| {
+            let name = entry
+                .file_name()
+                .to_string_lossy()
+                .chars()
+                .collect::<Vec<_>>();
+            matches(&pattern, &name)
+        })
+        .map(|entry| match path.parent() {
+            Some(x) if !x.as_os_str().is_empty() => x.join(entry.file_name()),
+            _ => PathBuf::from(entry.file_name()),
+        })
+        .collect::<Vec<_>>();
+    result.sort();
+    result
+}
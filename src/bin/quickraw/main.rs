@@ -0,0 +1,527 @@
+//! The `quickraw` command-line tool.
+
+mod glob;
+
+use quickraw::{
+    encode::{jpeg::JpegOptions, BitDepth, ImageFormat},
+    export::{self, Options},
+    ColorSpace, DemosaicingMethod, TransferFunction,
+};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+
+const USAGE: &str = "\
+Usage: quickraw <command> [options] <files...>
+
+Commands:
+  convert     Renders raw files into TIFF, PNG, JPEG, DNG, EXR or PFM files
+  thumb       Extracts the embedded JPEG previews
+  info        Prints the metadata of raw files
+  identify    Prints the camera of raw files and whether they are supported
+
+Options:
+  -o, --output <path>       The output directory, or the output file for a single input
+  -j, --jobs <n>            The count of files processed in parallel, all cores by default
+  -h, --help                Prints this message
+
+Options of convert:
+  -f, --format <format>     tiff(default), png, jpeg, dng, exr or pfm, dng keeps the sensor
+                            data and takes none of the options below
+      --bits <8|16|32>      The sample depth, 32 means float
+      --color-space <name>  srgb(default), adobe-rgb, prophoto-rgb, rec2020, display-p3,
+                            dci-p3, aces-ap0, aces-ap1, xyz or raw
+      --transfer <name>     linear, srgb, rec709, bt1886, prophoto, pq, hlg or a display
+                            gamma like 2.2, the usual curve of the color space by default
+      --wb <mode>           camera(default), none, or the multipliers like 2.0,1.0,1.5
+      --demosaic <method>   linear(default), super-pixel, or none to keep the CFA data as it is
+      --no-demosaic         The same as --demosaic none
+      --crop                Crops to the default crop area of the raw file
+      --rotate              Rotates the pixels upright
+  -q, --quality <1-100>     The JPEG quality, 92 by default
+
+Options of info:
+      --json                Prints the metadata in JSON, needs the `serde` feature
+
+Wildcards(*, ? and [...]) in file names are expanded for the shells that do not do it.";
+
+/// The options of `convert` for rendered images.
+const RENDER_OPTIONS: [&str; 10] = [
+    "--bits",
+    "--color-space",
+    "--transfer",
+    "--wb",
+    "--demosaic",
+    "--no-demosaic",
+    "--crop",
+    "--rotate",
+    "-q",
+    "--quality",
+];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Convert,
+    Thumb,
+    Info,
+    Identify,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Tiff,
+    Png,
+    Jpeg,
+    Dng,
+    Exr,
+    Pfm,
+}
+
+impl Format {
+    fn parse(s: &str) -> Result<Self, String> {
+        match s.to_ascii_lowercase().as_str() {
+            "tif" | "tiff" => Ok(Format::Tiff),
+            "png" => Ok(Format::Png),
+            "jpg" | "jpeg" => Ok(Format::Jpeg),
+            "dng" => Ok(Format::Dng),
+            "exr" => Ok(Format::Exr),
+            "pfm" => Ok(Format::Pfm),
+            _ => Err(format!("unknown format '{}'", s)),
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            Format::Tiff => "tif",
+            Format::Png => "png",
+            Format::Jpeg => "jpg",
+            Format::Dng => "dng",
+            Format::Exr => "exr",
+            Format::Pfm => "pfm",
+        }
+    }
+
+    fn default_depth(&self) -> BitDepth {
+        match self {
+            Format::Jpeg => BitDepth::Eight,
+            Format::Exr | Format::Pfm => BitDepth::Float,
+            _ => BitDepth::Sixteen,
+        }
+    }
+}
+
+enum WhiteBalance {
+    Camera,
+    Multipliers([f32; 3]),
+}
+
+struct Args {
+    command: Command,
+    inputs: Vec<PathBuf>,
+    output: Option<PathBuf>,
+    jobs: usize,
+    format: Option<Format>,
+    depth: Option<BitDepth>,
+    color_space: ColorSpace,
+    transfer: Option<TransferFunction>,
+    white_balance: WhiteBalance,
+    demosaicing: DemosaicingMethod,
+    crop: bool,
+    rotate: bool,
+    quality: u8,
+    json: bool,
+    /// The options of the rendering given, which do not apply to DNG output.
+    render_options: Vec<String>,
+}
+
+fn parse_color_space(s: &str) -> Result<ColorSpace, String> {
+    match s.to_ascii_lowercase().as_str() {
+        "srgb" => Ok(ColorSpace::Srgb),
+        "adobe-rgb" | "adobe" => Ok(ColorSpace::AdobeRgb),
+        "prophoto-rgb" | "prophoto" => Ok(ColorSpace::ProPhotoRgb),
+        "rec2020" => Ok(ColorSpace::Rec2020),
+        "display-p3" | "p3" => Ok(ColorSpace::DisplayP3),
+        "dci-p3" => Ok(ColorSpace::DciP3),
+        "aces-ap0" | "aces" => Ok(ColorSpace::AcesAp0),
+        "aces-ap1" | "acescg" => Ok(ColorSpace::AcesAp1),
+        "xyz" => Ok(ColorSpace::Xyz),
+        "raw" => Ok(ColorSpace::Raw),
+        _ => Err(format!("unknown color space '{}'", s)),
+    }
+}
+
+fn parse_transfer(s: &str) -> Result<TransferFunction, String> {
+    match s.to_ascii_lowercase().as_str() {
+        "linear" => Ok(TransferFunction::Linear),
+        "srgb" => Ok(TransferFunction::Srgb),
+        "rec709" => Ok(TransferFunction::Rec709),
+        "bt1886" => Ok(TransferFunction::Bt1886),
+        "prophoto" => Ok(TransferFunction::ProPhoto),
+        "pq" => Ok(TransferFunction::Pq),
+        "hlg" => Ok(TransferFunction::Hlg),
+        x => match x.parse::<f32>() {
+            Ok(gamma) if gamma > 0. => Ok(TransferFunction::Gamma(1. / gamma)),
+            _ => Err(format!("unknown transfer function '{}'", s)),
+        },
+    }
+}
+
+fn parse_demosaicing(s: &str) -> Result<DemosaicingMethod, String> {
+    match s.to_ascii_lowercase().as_str() {
+        "none" => Ok(DemosaicingMethod::None),
+        "super-pixel" => Ok(DemosaicingMethod::SuperPixel),
+        "linear" => Ok(DemosaicingMethod::Linear),
+        _ => Err(format!("unknown demosaicing method '{}'", s)),
+    }
+}
+
+fn parse_white_balance(s: &str) -> Result<WhiteBalance, String> {
+    match s.to_ascii_lowercase().as_str() {
+        "camera" => Ok(WhiteBalance::Camera),
+        "none" => Ok(WhiteBalance::Multipliers([1.; 3])),
+        x => {
+            let values = x
+                .split(',')
+                .map(|v| v.trim().parse::<f32>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| format!("invalid white balance '{}'", s))?;
+            match values.as_slice() {
+                &[r, g, b] => Ok(WhiteBalance::Multipliers([r, g, b])),
+                _ => Err(format!("invalid white balance '{}'", s)),
+            }
+        }
+    }
+}
+
+fn parse_args(args: &[String]) -> Result<Args, String> {
+    let command = match args.first().map(String::as_str) {
+        Some("convert") => Command::Convert,
+        Some("thumb") => Command::Thumb,
+        Some("info") => Command::Info,
+        Some("identify") => Command::Identify,
+        Some(x) => return Err(format!("unknown command '{}'", x)),
+        None => return Err("no command is given".to_owned()),
+    };
+
+    let mut result = Args {
+        command,
+        inputs: vec![],
+        output: None,
+        jobs: std::thread::available_parallelism().map_or(1, |x| x.get()),
+        format: None,
+        depth: None,
+        color_space: ColorSpace::Srgb,
+        transfer: None,
+        white_balance: WhiteBalance::Camera,
+        demosaicing: DemosaicingMethod::Linear,
+        crop: false,
+        rotate: false,
+        quality: 92,
+        json: false,
+        render_options: vec![],
+    };
+
+    let mut iter = args[1..].iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .map(String::as_str)
+                .ok_or_else(|| format!("'{}' needs a value", arg))
+        };
+        if RENDER_OPTIONS.contains(&arg.as_str()) {
+            result.render_options.push(arg.clone());
+        }
+        match arg.as_str() {
+            "-o" | "--output" => result.output = Some(PathBuf::from(value()?)),
+            "-j" | "--jobs" => {
+                result.jobs = value()?
+                    .parse::<usize>()
+                    .map_err(|_| "the job count should be a number".to_owned())?
+                    .max(1)
+            }
+            "-f" | "--format" => result.format = Some(Format::parse(value()?)?),
+            "--bits" => {
+                result.depth = Some(match value()? {
+                    "8" => BitDepth::Eight,
+                    "16" => BitDepth::Sixteen,
+                    "32" => BitDepth::Float,
+                    x => return Err(format!("unsupported bit depth '{}'", x)),
+                })
+            }
+            "--color-space" => result.color_space = parse_color_space(value()?)?,
+            "--transfer" => result.transfer = Some(parse_transfer(value()?)?),
+            "--wb" => result.white_balance = parse_white_balance(value()?)?,
+            "--demosaic" => result.demosaicing = parse_demosaicing(value()?)?,
+            "--no-demosaic" => result.demosaicing = DemosaicingMethod::None,
+            "--crop" => result.crop = true,
+            "--rotate" => result.rotate = true,
+            "-q" | "--quality" => {
+                result.quality = value()?
+                    .parse::<u8>()
+                    .ok()
+                    .filter(|x| (1..=100).contains(x))
+                    .ok_or_else(|| "the quality should be from 1 to 100".to_owned())?
+            }
+            "--json" => result.json = true,
+            x if x.starts_with('-') && x.len() > 1 => {
+                return Err(format!("unknown option '{}'", x))
+            }
+            x => result.inputs.extend(glob::expand(x)),
+        }
+    }
+
+    if result.inputs.is_empty() {
+        return Err("no input file is given".to_owned());
+    }
+    // DNG files keep the sensor data as it is
+    if result.command == Command::Convert && output_format(&result)? == Format::Dng {
+        if let Some(option) = result.render_options.first() {
+            return Err(format!("'{}' does not apply to DNG output", option));
+        }
+    }
+    Ok(result)
+}
+
+/// The output path of an input, `output` is a file only when there is a single input.
+fn output_path(args: &Args, input: &Path, suffix: &str) -> PathBuf {
+    let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+    let file_name = format!("{}{}", stem, suffix);
+    match &args.output {
+        Some(output) if args.inputs.len() == 1 && !output.is_dir() => output.clone(),
+        Some(output) => output.join(file_name),
+        None => input.with_file_name(file_name),
+    }
+}
+
+/// The format of `convert`, a single output file decides it by its extension.
+fn output_format(args: &Args) -> Result<Format, String> {
+    match (args.format, &args.output) {
+        (Some(x), _) => Ok(x),
+        (None, Some(output)) if args.inputs.len() == 1 && !output.is_dir() => output
+            .extension()
+            .and_then(|x| x.to_str())
+            .map_or(Ok(Format::Tiff), Format::parse),
+        _ => Ok(Format::Tiff),
+    }
+}
+
+/// The file written for an input, `None` for the commands printing their results.
+fn job_output(args: &Args, input: &Path) -> Result<Option<PathBuf>, String> {
+    match args.command {
+        Command::Convert => {
+            let suffix = format!(".{}", output_format(args)?.extension());
+            Ok(Some(output_path(args, input, &suffix)))
+        }
+        Command::Thumb => Ok(Some(output_path(args, input, ".thumb.jpg"))),
+        Command::Info | Command::Identify => Ok(None),
+    }
+}
+
+/// Fails when two inputs would be written to the same file, like files of the same name from
+/// different directories converted into one output directory.
+fn check_collisions(args: &Args) -> Result<(), String> {
+    let mut outputs = HashMap::new();
+    for input in &args.inputs {
+        let Some(output) = job_output(args, input)? else {
+            continue;
+        };
+        if let Some(other) = outputs.insert(output.clone(), input) {
+            return Err(format!(
+                "'{}' and '{}' would both be written to '{}'",
+                other.display(),
+                input.display(),
+                output.display()
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn read(input: &Path) -> Result<Vec<u8>, String> {
+    std::fs::read(input).map_err(|e| e.to_string())
+}
+
+fn write(path: &Path, data: &[u8]) -> Result<String, String> {
+    std::fs::write(path, data).map_err(|e| format!("cannot write '{}': {}", path.display(), e))?;
+    Ok(format!("-> {}", path.display()))
+}
+
+fn convert(args: &Args, input: &Path) -> Result<String, String> {
+    let format = output_format(args)?;
+    let output = output_path(args, input, &format!(".{}", format.extension()));
+    let buffer = read(input)?;
+
+    if format == Format::Dng {
+        let data = export::encode_dng_from_buffer(buffer, &Default::default())
+            .map_err(|e| e.to_string())?;
+        return write(&output, &data);
+    }
+
+    let transfer = args.transfer.unwrap_or_else(|| match format {
+        Format::Exr | Format::Pfm => TransferFunction::Linear,
+        _ => args.color_space.default_transfer(),
+    });
+    let mut options = Options::new(transfer, args.color_space, false)
+        .with_demosaicing(args.demosaicing)
+        .with_crop(args.crop)
+        .with_rotation(args.rotate);
+    if let WhiteBalance::Multipliers(white_balance) = args.white_balance {
+        options = options.with_white_balance(white_balance);
+    }
+
+    let image_format = match format {
+        Format::Tiff => ImageFormat::Tiff(Default::default()),
+        Format::Png => ImageFormat::Png(Default::default()),
+        Format::Jpeg => ImageFormat::Jpeg(JpegOptions {
+            quality: args.quality,
+            ..Default::default()
+        }),
+        Format::Exr => ImageFormat::Exr(Default::default()),
+        Format::Pfm => ImageFormat::Pfm,
+        Format::Dng => unreachable!(),
+    };
+    let depth = args.depth.unwrap_or(format.default_depth());
+    let data = export::encode_image_from_buffer(buffer, options, &image_format, depth)
+        .map_err(|e| e.to_string())?;
+    write(&output, &data)
+}
+
+fn thumb(args: &Args, input: &Path) -> Result<String, String> {
+    let buffer = read(input)?;
+    let (thumbnail, _) = export::load_thumbnail_from_buffer(&buffer).map_err(|e| e.to_string())?;
+    write(&output_path(args, input, ".thumb.jpg"), &thumbnail)
+}
+
+/// The metadata of an input as a JSON object indented into the array of all inputs, with the path
+/// of the input in `file`.
+#[cfg(feature = "serde")]
+fn info_json(input: &Path, buffer: &[u8]) -> Result<String, String> {
+    let metadata = export::load_metadata_from_buffer(buffer).map_err(|e| e.to_string())?;
+    let mut value = serde_json::to_value(metadata).map_err(|e| e.to_string())?;
+    if let serde_json::Value::Object(fields) = &mut value {
+        fields.insert("file".to_owned(), input.to_string_lossy().into());
+    }
+    let json = serde_json::to_string_pretty(&value).map_err(|e| e.to_string())?;
+    Ok(json
+        .lines()
+        .map(|line| format!("  {}", line))
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+#[cfg(not(feature = "serde"))]
+fn info_json(_: &Path, _: &[u8]) -> Result<String, String> {
+    Err("JSON output needs quickraw built with the `serde` feature".to_owned())
+}
+
+fn info(args: &Args, input: &Path) -> Result<String, String> {
+    let buffer = read(input)?;
+    if args.json {
+        return info_json(input, &buffer);
+    }
+    let info = export::load_exif_info_from_buffer(&buffer).map_err(|e| e.to_string())?;
+    let text = info.stringify_all().map_err(|e| e.to_string())?;
+    Ok(format!("== {} ==\n{}", input.display(), text.trim_end()))
+}
+
+fn identify(_: &Args, input: &Path) -> Result<String, String> {
+    let buffer = read(input)?;
+    let identity = export::identify_buffer(&buffer).map_err(|e| e.to_string())?;
+    let kind = if identity.is_dng { " (DNG)" } else { "" };
+    let support = match identity.support {
+        Ok(()) => "supported".to_owned(),
+        Err(e) => format!("unsupported: {}", e),
+    };
+    Ok(format!(
+        "{}: {} {}{}, {}",
+        input.display(),
+        identity.make,
+        identity.model,
+        kind,
+        support
+    ))
+}
+
+/// Runs the job over the inputs on `jobs` threads, results are kept in the input order.
+fn run_parallel(
+    args: &Args,
+    job: fn(&Args, &Path) -> Result<String, String>,
+) -> Vec<Result<String, String>> {
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new((0..args.inputs.len()).map(|_| None).collect::<Vec<_>>());
+
+    std::thread::scope(|scope| {
+        for _ in 0..args.jobs.min(args.inputs.len()) {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(input) = args.inputs.get(i) else {
+                    break;
+                };
+                let result = job(args, input);
+                results.lock().unwrap()[i] = Some(result);
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|x| x.unwrap_or_else(|| Err("not processed".to_owned())))
+        .collect()
+}
+
+fn main() {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    if args.is_empty() || args.iter().any(|x| x == "-h" || x == "--help") {
+        println!("{}", USAGE);
+        return;
+    }
+    let args = match parse_args(&args) {
+        Ok(x) => x,
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+    if let Err(e) = check_collisions(&args) {
+        eprintln!("error: {}", e);
+        std::process::exit(2);
+    }
+
+    let job = match args.command {
+        Command::Convert => convert,
+        Command::Thumb => thumb,
+        Command::Info => info,
+        Command::Identify => identify,
+    };
+    let results = run_parallel(&args, job);
+
+    let json = args.command == Command::Info && args.json;
+    let mut outputs = vec![];
+    let mut failed = false;
+    for (input, result) in args.inputs.iter().zip(results) {
+        match result {
+            Ok(x) if json => outputs.push(x),
+            Ok(x) if args.command == Command::Convert || args.command == Command::Thumb => {
+                println!("{} {}", input.display(), x)
+            }
+            Ok(x) => println!("{}", x),
+            Err(e) => {
+                failed = true;
+                eprintln!("{}: {}", input.display(), e);
+            }
+        }
+    }
+    if json {
+        println!("[\n{}\n]", outputs.join(",\n"));
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
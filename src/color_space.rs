@@ -0,0 +1,115 @@
+use super::*;
+
+/// Output color spaces supported by the renderer.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ColorSpace {
+    Srgb,
+    AdobeRgb,
+    ProPhotoRgb,
+    Rec2020,
+    DisplayP3,
+    DciP3,
+    AcesAp0,
+    AcesAp1,
+    /// CIE XYZ relative to D65.
+    Xyz,
+    /// The normalized XYZ space without any conversion, same as `data::XYZ2RAW`.
+    Raw,
+    /// A custom matrix from the normalized XYZ space to the target.
+    Custom([f32; 9]),
+}
+
+const WHITE_D65: [f32; 2] = [0.3127, 0.3290];
+const WHITE_D50: [f32; 2] = [0.3457, 0.3585];
+const WHITE_DCI: [f32; 2] = [0.314, 0.351];
+const WHITE_ACES: [f32; 2] = [0.32168, 0.33767];
+
+impl From<&[f32; 9]> for ColorSpace {
+    fn from(matrix: &[f32; 9]) -> Self {
+        ColorSpace::ALL
+            .into_iter()
+            .find(|x| x.matrix() == *matrix)
+            .unwrap_or(ColorSpace::Custom(*matrix))
+    }
+}
+
+impl ColorSpace {
+    /// All the predefined color spaces.
+    pub const ALL: [ColorSpace; 10] = [
+        ColorSpace::Srgb,
+        ColorSpace::AdobeRgb,
+        ColorSpace::ProPhotoRgb,
+        ColorSpace::Rec2020,
+        ColorSpace::DisplayP3,
+        ColorSpace::DciP3,
+        ColorSpace::AcesAp0,
+        ColorSpace::AcesAp1,
+        ColorSpace::Xyz,
+        ColorSpace::Raw,
+    ];
+
+    /// The matrix from the normalized XYZ space to this color space.
+    pub fn matrix(&self) -> [f32; 9] {
+        match self {
+            ColorSpace::Srgb => data::XYZ2SRGB,
+            ColorSpace::AdobeRgb => data::XYZ2ADOBE_RGB,
+            ColorSpace::ProPhotoRgb => data::XYZ2PROPHOTO_RGB,
+            ColorSpace::Rec2020 => data::XYZ2REC2020,
+            ColorSpace::DisplayP3 => data::XYZ2DISPLAY_P3,
+            ColorSpace::DciP3 => data::XYZ2DCI_P3,
+            ColorSpace::AcesAp0 => data::XYZ2ACES_AP0,
+            ColorSpace::AcesAp1 => data::XYZ2ACES_AP1,
+            ColorSpace::Xyz => data::XYZ2XYZ,
+            ColorSpace::Raw => data::XYZ2RAW,
+            ColorSpace::Custom(matrix) => *matrix,
+        }
+    }
+
+    /// The CIE xy chromaticities as `[red, green, blue, white]`.
+    ///
+    /// Returns `None` for the spaces without RGB primaries.
+    pub fn chromaticities(&self) -> Option<[[f32; 2]; 4]> {
+        let result = match self {
+            ColorSpace::Srgb => [[0.64, 0.33], [0.30, 0.60], [0.15, 0.06], WHITE_D65],
+            ColorSpace::AdobeRgb => [[0.64, 0.33], [0.21, 0.71], [0.15, 0.06], WHITE_D65],
+            ColorSpace::ProPhotoRgb => [[0.7347, 0.2653], [0.1596, 0.8404], [0.0366, 0.0001], WHITE_D50],
+            ColorSpace::Rec2020 => [[0.708, 0.292], [0.170, 0.797], [0.131, 0.046], WHITE_D65],
+            ColorSpace::DisplayP3 => [[0.680, 0.320], [0.265, 0.690], [0.150, 0.060], WHITE_D65],
+            ColorSpace::DciP3 => [[0.680, 0.320], [0.265, 0.690], [0.150, 0.060], WHITE_DCI],
+            ColorSpace::AcesAp0 => [[0.7347, 0.2653], [0.0, 1.0], [0.0001, -0.0770], WHITE_ACES],
+            ColorSpace::AcesAp1 => [[0.713, 0.293], [0.165, 0.830], [0.128, 0.044], WHITE_ACES],
+            ColorSpace::Xyz => [[1., 0.], [0., 1.], [0., 0.], WHITE_D65],
+            ColorSpace::Raw | ColorSpace::Custom(_) => return None,
+        };
+        Some(result)
+    }
+
+    /// The transfer function usually paired with this color space.
+    pub fn default_transfer(&self) -> TransferFunction {
+        match self {
+            ColorSpace::Srgb | ColorSpace::DisplayP3 => TransferFunction::Srgb,
+            ColorSpace::AdobeRgb => TransferFunction::Gamma(256. / 563.),
+            ColorSpace::ProPhotoRgb => TransferFunction::ProPhoto,
+            ColorSpace::Rec2020 => TransferFunction::Rec709,
+            ColorSpace::DciP3 => TransferFunction::Gamma(1. / 2.6),
+            ColorSpace::AcesAp0
+            | ColorSpace::AcesAp1
+            | ColorSpace::Xyz
+            | ColorSpace::Raw
+            | ColorSpace::Custom(_) => TransferFunction::Linear,
+        }
+    }
+
+    /// The ColourPrimaries code point from ITU-T H.273, used by cICP and similar tags.
+    pub fn cicp_code(&self) -> Option<u8> {
+        match self {
+            ColorSpace::Srgb => Some(1),
+            ColorSpace::Rec2020 => Some(9),
+            ColorSpace::Xyz => Some(10),
+            ColorSpace::DciP3 => Some(11),
+            ColorSpace::DisplayP3 => Some(12),
+            _ => None,
+        }
+    }
+}
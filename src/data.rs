@@ -27,7 +27,93 @@ pub static XYZ2SRGB: [f32; 9] = [
 
 pub static XYZ2RAW: [f32; 9] = [1.0, 0., 0., 0., 1.0, 0., 0., 0., 1.0];
 
+// The matrices below map the normalized XYZ space used by the pipeline, in which the D65 white is
+// `[1.0, 1.0, 1.0]`, to the target primaries. They are derived as
+// `inv(RGB to XYZ) * Bradford(D65 to the white of the space) * diag(D65)`.
+
+/// ProPhoto(ROMM) RGB, adapted from D65 to D50 with the Bradford transform.
+pub static XYZ2PROPHOTO_RGB: [f32; 9] = [
+    1.3336825,
+    -0.22302286,
+    -0.11065968,
+    -0.5001587,
+    1.4816175,
+    0.018541304,
+    -0.010647259,
+    0.018246403,
+    0.9924009,
+];
+
+/// ITU-R BT.2020 primaries with a D65 white point.
+pub static XYZ2REC2020: [f32; 9] = [
+    1.6316013,
+    -0.35567078,
+    -0.27593052,
+    -0.6336541,
+    1.6164812,
+    0.017172856,
+    0.016765907,
+    -0.042770613,
+    1.0260047,
+];
+
+/// Display P3, the DCI-P3 primaries with a D65 white point.
+pub static XYZ2DISPLAY_P3: [f32; 9] = [
+    2.3699589,
+    -0.9313836,
+    -0.4385753,
+    -0.7883927,
+    1.7626641,
+    0.025728647,
+    0.03406988,
+    -0.07617239,
+    1.0421025,
+];
+
+/// DCI-P3 with the DCI white point, adapted from D65 with the Bradford transform.
+pub static XYZ2DCI_P3: [f32; 9] = [
+    2.5569413,
+    -1.0940019,
+    -0.46293923,
+    -0.77945197,
+    1.7504809,
+    0.028971065,
+    0.03444999,
+    -0.078580834,
+    1.0441308,
+];
+
+/// ACES2065-1(AP0 primaries), adapted from D65 to the ACES white point with the Bradford transform.
+pub static XYZ2ACES_AP0: [f32; 9] = [
+    1.0108056,
+    0.0064089103,
+    -0.017214503,
+    -0.46769476,
+    1.3682234,
+    0.09947136,
+    -0.0026769226,
+    0.004644171,
+    0.99803275,
+];
+
+/// ACEScg(AP1 primaries), adapted from D65 to the ACES white point with the Bradford transform.
+pub static XYZ2ACES_AP1: [f32; 9] = [
+    1.5783132,
+    -0.31529555,
+    -0.26301762,
+    -0.62723064,
+    1.6083915,
+    0.01883917,
+    0.008556545,
+    -0.0035668763,
+    0.9950103,
+];
+
+/// CIE XYZ relative to the D65 white point of the camera matrices.
+pub static XYZ2XYZ: [f32; 9] = [0.9504559, 0., 0., 0., 1.0, 0., 0., 0., 1.0890578];
+
 pub static GAMMA_LINEAR: f32 = 1.0;
+/// A pure `0.45` power, the piecewise sRGB curve is `TransferFunction::Srgb`.
 pub static GAMMA_SRGB: f32 = 0.45;
 
 
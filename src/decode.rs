@@ -1,8 +1,10 @@
 use super::*;
-use std::{fs::File, io::Read};
+use metadata::RawMetadata;
+use std::ops::Range;
 
 #[allow(clippy::upper_case_acronyms)]
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CFAPattern {
     RGGB,
     GRBG,
@@ -12,6 +14,8 @@ pub enum CFAPattern {
     XTrans1, // GGRGGB
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Crop {
     pub x: u32,
     pub y: u32,
@@ -19,18 +23,61 @@ pub struct Crop {
     pub height: u32,
 }
 
-pub struct DecodedImage {
+pub struct DecodedImage<T = u16> {
     pub cfa_pattern: CFAPattern,
     pub width: usize,
     pub height: usize,
     pub crop: Option<Crop>,
     pub orientation: Orientation,
-    pub image: Vec<u16>,
+    pub image: Vec<T>,
     pub white_balance: [i32; 3],
     pub cam_matrix: [f32; 9],
     pub parsed_info: quickexif::ParsedInfo,
 }
 
+/// The sensor values exactly as stored in the raw file, for scientific use.
+///
+/// Unlike `DecodedImage`, no black level is subtracted and no scaling is applied,
+/// so the original ADC values and the noise floor are kept.
+pub struct SensorData {
+    /// The CFA samples in rows of `width`.
+    pub image: Vec<u16>,
+    pub width: usize,
+    pub height: usize,
+    pub cfa_pattern: CFAPattern,
+    /// Of the 2x2 block at the top left of the CFA pattern in row-major order, in the linearized values.
+    pub black_level: Option<[u16; 4]>,
+    pub white_level: Option<u16>,
+    /// The curve mapping the samples to linear values, which some compressions code the samples along.
+    ///
+    /// A sample `x` is linearized as `table[x]`, the values past the end of the table take its last entry.
+    pub linearization_table: Option<Vec<u16>>,
+    pub bit_depth: Option<u16>,
+    /// The active area of the image, the samples out of it like the masked borders are kept.
+    pub crop: Option<Crop>,
+}
+
+/// The scales of reduced-resolution previews.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PreviewScale {
+    Half,
+    Quarter,
+    Eighth,
+}
+impl PreviewScale {
+    /// The number of sensor pixels binned into a preview pixel along each side.
+    pub fn factor(&self) -> usize {
+        match self {
+            PreviewScale::Half => 2,
+            PreviewScale::Quarter => 4,
+            PreviewScale::Eighth => 8,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Orientation {
     Horizontal = 0,
     Rotate90 = 90,
@@ -38,59 +85,220 @@ pub enum Orientation {
     Rotate270 = 270,
 }
 
-pub(super) fn get_buffer_from_file(path: &str) -> Result<Vec<u8>, RawFileReadingError> {
-    let mut f =
-        File::open(path).map_err(|_| RawFileReadingError::FileNotExisted(path.to_owned()))?;
-    let len = f
-        .metadata()
-        .map_err(|_| RawFileReadingError::FileMetadataReadingError(path.to_owned()))?
-        .len() as usize;
-    let mut buffer = vec![0u8; len];
-    f.read(&mut buffer)
-        .map_err(|_| RawFileReadingError::FileContentReadingError(path.to_owned()))?;
-
-    Ok(buffer)
-}
-fn prepare_buffer(mut buffer: Vec<u8>) -> Vec<u8> {
-    buffer.extend([0u8; 16]); // + 16 is for BitPumpMSB fix
-
-    fuji_buffer_fix(buffer)
-}
-fn fuji_buffer_fix(buffer: Vec<u8>) -> Vec<u8> {
-    if buffer[..4] == [0x46, 0x55, 0x4a, 0x49] {
-        buffer[148..].to_vec()
-    } else {
-        buffer
+fn fuji_buffer_slice_fix(buffer: &[u8]) -> &[u8] {
+    match buffer.get(148..) {
+        Some(fixed) if buffer.starts_with(b"FUJI") => fixed,
+        _ => buffer,
     }
 }
-fn fuji_buffer_slice_fix(buffer: &[u8]) -> &[u8] {
-    if buffer[..4] == [0x46, 0x55, 0x4a, 0x49] {
-        &buffer[148..]
-    } else {
-        buffer
+
+/// The embedded JPEG of RAF files, from their header.
+pub(super) fn raf_jpeg_range(buffer: &[u8]) -> Option<Range<usize>> {
+    if !buffer.starts_with(b"FUJI") {
+        return None;
     }
+    let offset = u32::from_be_bytes(buffer.get(84..88)?.try_into().ok()?) as usize;
+    let len = u32::from_be_bytes(buffer.get(88..92)?.try_into().ok()?) as usize;
+    Some(offset..offset.checked_add(len)?)
 }
 
 /// Gets `RawImage` from a file
 #[cfg_attr(not(feature = "wasm-bindgen"), fn_util::bench(decoding))]
 pub fn decode_file(path: &str) -> Result<DecodedImage, RawFileReadingError> {
-    let buffer = get_buffer_from_file(path)?;
+    let buffer = RawBuffer::from_file(path)?;
     decode_buffer(buffer)
 }
 
-/// Gets `RawImage` from a buffer
+/// Gets `RawImage` from a buffer, like a `Vec<u8>` or a `RawBuffer`
 #[inline(always)]
-pub fn decode_buffer(buffer: Vec<u8>) -> Result<DecodedImage, RawFileReadingError> {
-    let buffer = prepare_buffer(buffer);
+pub fn decode_buffer(buffer: impl AsRef<[u8]>) -> Result<DecodedImage, RawFileReadingError> {
+    let buffer = fuji_buffer_slice_fix(buffer.as_ref());
 
     let rule = &utility::BASIC_INFO_RULE;
-    let decoder_select_info = quickexif::parse(&buffer, rule)?;
+    let decoder_select_info = quickexif::parse(buffer, rule)?;
 
-    let decoded_image = maker::selector::select_and_decode(buffer.as_slice(), decoder_select_info)?;
+    let decoded_image = maker::selector::select_and_decode(
+        buffer,
+        decoder_select_info,
+        |_| maker::Area::ALL,
+    )?;
 
     Ok(decoded_image)
 }
 
+/// Decodes the image in `f32` for the float renders, normalized before any rounding or clipping.
+pub(super) fn decode_float(buffer: &[u8]) -> Result<DecodedImage<f32>, RawFileReadingError> {
+    let buffer = fuji_buffer_slice_fix(buffer);
+    let basic_info = quickexif::parse(buffer, &utility::BASIC_INFO_RULE)?;
+    maker::selector::select_and_decode(buffer, basic_info, |_| maker::Area::ALL)
+}
+
+/// Decodes the image in `f32` with only the samples in `columns` and `rows` guaranteed, the others
+/// may be zeros.
+pub(super) fn decode_region(
+    buffer: &[u8],
+    columns: Range<usize>,
+    rows: Range<usize>,
+) -> Result<DecodedImage<f32>, RawFileReadingError> {
+    let buffer = fuji_buffer_slice_fix(buffer);
+    let basic_info = quickexif::parse(buffer, &utility::BASIC_INFO_RULE)?;
+    maker::selector::select_and_decode(buffer, basic_info, |_| maker::Area {
+        rows: rows.clone(),
+        columns: columns.clone(),
+        ..maker::Area::ALL
+    })
+}
+
+/// Decodes a preview with every block of `scale` binned into an RGB pixel, the crop area is scaled along.
+///
+/// Returns `true` along when the pixels are binned from CFA data and still need the white balance
+/// and the color conversion, linear data is binned as it is.
+pub(super) fn decode_preview(
+    buffer: &[u8],
+    scale: PreviewScale,
+) -> Result<(DecodedImage, bool), RawFileReadingError> {
+    let buffer = fuji_buffer_slice_fix(buffer);
+    let factor = scale.factor();
+
+    let basic_info = quickexif::parse(buffer, &utility::BASIC_INFO_RULE)?;
+    let decoded_image =
+        maker::selector::select_and_decode(buffer, basic_info, |cfa_pattern| {
+            match cfa_pattern {
+                // blocks may lack a color of the 6x6 pattern and widen to the rows around
+                CFAPattern::XTrans0 | CFAPattern::XTrans1 => maker::Area::ALL,
+                // a row pair of each block has all the colors of the 2x2 pattern
+                _ => maker::Area {
+                    step: factor,
+                    count: 2,
+                    ..maker::Area::ALL
+                },
+            }
+        })?;
+
+    let (width, height) = (decoded_image.width, decoded_image.height);
+    let is_cfa = decoded_image.image.len() != width * height * 3;
+    let image = if is_cfa {
+        bin_cfa(&decoded_image.image, width, height, decoded_image.cfa_pattern, factor)
+    } else {
+        bin_rgb(&decoded_image.image, width, height, factor)
+    };
+    let crop = decoded_image.crop.map(|crop| Crop {
+        x: crop.x / factor as u32,
+        y: crop.y / factor as u32,
+        width: crop.width / factor as u32,
+        height: crop.height / factor as u32,
+    });
+
+    let preview = DecodedImage {
+        image,
+        width: width / factor,
+        height: height / factor,
+        crop,
+        ..decoded_image
+    };
+    Ok((preview, is_cfa))
+}
+
+/// The color of the CFA sample at `(x, y)`, `0` is red, `1` is green and `2` is blue.
+#[inline(always)]
+pub(crate) fn cfa_color(cfa_pattern: CFAPattern, x: usize, y: usize) -> usize {
+    const XTRANS: [[usize; 6]; 6] = [
+        [0, 2, 1, 2, 0, 1],
+        [1, 1, 0, 1, 1, 2],
+        [1, 1, 2, 1, 1, 0],
+        [2, 0, 1, 0, 2, 1],
+        [1, 1, 2, 1, 1, 0],
+        [1, 1, 0, 1, 1, 2],
+    ];
+    let bayer = |pattern: [usize; 4]| pattern[y % 2 * 2 + x % 2];
+    match cfa_pattern {
+        CFAPattern::RGGB => bayer([0, 1, 1, 2]),
+        CFAPattern::GRBG => bayer([1, 0, 2, 1]),
+        CFAPattern::GBRG => bayer([1, 2, 0, 1]),
+        CFAPattern::BGGR => bayer([2, 1, 1, 0]),
+        CFAPattern::XTrans0 => XTRANS[y % 6][x % 6],
+        CFAPattern::XTrans1 => XTRANS[(y + 1) % 6][x % 6],
+    }
+}
+
+/// Averages the samples of each color in every `factor` x `factor` block into an RGB pixel.
+///
+/// Only the first row pair of the blocks is read for Bayer patterns, the other rows are skipped
+/// at decoding. A block lacking a color, like a small block of X-Trans, widens until it has one.
+fn bin_cfa(
+    image: &[u16],
+    width: usize,
+    height: usize,
+    cfa_pattern: CFAPattern,
+    factor: usize,
+) -> Vec<u16> {
+    let is_xtrans = matches!(cfa_pattern, CFAPattern::XTrans0 | CFAPattern::XTrans1);
+    let rows = if is_xtrans { factor } else { 2 };
+
+    let (out_width, out_height) = (width / factor, height / factor);
+    let mut out = Vec::with_capacity(out_width * out_height * 3);
+    for by in 0..out_height {
+        for bx in 0..out_width {
+            let mut pad = 0;
+            loop {
+                let (mut sums, mut counts) = ([0u32; 3], [0u32; 3]);
+                let x_range = (bx * factor).saturating_sub(pad)..((bx + 1) * factor + pad).min(width);
+                let y_range = (by * factor).saturating_sub(pad)..(by * factor + rows + pad).min(height);
+                for y in y_range {
+                    for x in x_range.clone() {
+                        let color = cfa_color(cfa_pattern, x, y);
+                        sums[color] += image[y * width + x] as u32;
+                        counts[color] += 1;
+                    }
+                }
+
+                if counts.iter().all(|&x| x > 0) || pad >= factor {
+                    out.extend((0..3).map(|i| (sums[i] / counts[i].max(1)) as u16));
+                    break;
+                }
+                pad += 1;
+            }
+        }
+    }
+    out
+}
+
+/// Averages every `factor` x `factor` block of an interleaved RGB image.
+fn bin_rgb(image: &[u16], width: usize, height: usize, factor: usize) -> Vec<u16> {
+    let (out_width, out_height) = (width / factor, height / factor);
+    let mut out = Vec::with_capacity(out_width * out_height * 3);
+    for by in 0..out_height {
+        for bx in 0..out_width {
+            let mut sums = [0u32; 3];
+            for y in by * factor..(by + 1) * factor {
+                let start = (y * width + bx * factor) * 3;
+                image[start..start + factor * 3]
+                    .chunks_exact(3)
+                    .for_each(|x| (0..3).for_each(|i| sums[i] += x[i] as u32));
+            }
+            out.extend(sums.map(|x| (x / (factor * factor) as u32) as u16));
+        }
+    }
+    out
+}
+
+pub(super) fn get_sensor_data(buffer: &[u8]) -> Result<SensorData, RawFileReadingError> {
+    let buffer = fuji_buffer_slice_fix(buffer);
+    let basic_info = quickexif::parse(buffer, &utility::BASIC_INFO_RULE)?;
+    maker::selector::select_and_decode_sensor_data(buffer, basic_info)
+}
+
+pub(super) fn get_calibration(buffer: &[u8]) -> Result<([i32; 3], [f32; 9]), RawFileReadingError> {
+    let buffer = fuji_buffer_slice_fix(buffer);
+    let basic_info = quickexif::parse(buffer, &utility::BASIC_INFO_RULE)?;
+    maker::selector::select_and_decode_calibration(buffer, basic_info)
+}
+
+pub(super) fn get_basic_info(buffer: &[u8]) -> Result<quickexif::ParsedInfo, RawFileReadingError> {
+    let buffer = fuji_buffer_slice_fix(buffer);
+    Ok(quickexif::parse(buffer, &utility::BASIC_INFO_RULE)?)
+}
+
 pub(super) fn get_exif_info(buffer: &[u8]) -> Result<quickexif::ParsedInfo, RawFileReadingError> {
     let buffer = fuji_buffer_slice_fix(buffer);
     let rule = &utility::BASIC_INFO_RULE;
@@ -99,6 +307,36 @@ pub(super) fn get_exif_info(buffer: &[u8]) -> Result<quickexif::ParsedInfo, RawF
     Ok(result)
 }
 
+pub(super) fn get_metadata(buffer: &[u8]) -> Result<RawMetadata, RawFileReadingError> {
+    let buffer = fuji_buffer_slice_fix(buffer);
+    let basic_info = quickexif::parse(buffer, &utility::BASIC_INFO_RULE)?;
+    let mut metadata = maker::selector::select_and_decode_metadata(buffer, basic_info)?;
+    if let Ok(info) = quickexif::parse(buffer, &utility::SHOOTING_INFO_RULE) {
+        metadata.apply_shooting_info(&info);
+    }
+    Ok(metadata)
+}
+
+pub(super) fn get_shooting_info(buffer: &[u8]) -> Result<quickexif::ParsedInfo, RawFileReadingError> {
+    let buffer = fuji_buffer_slice_fix(buffer);
+    let rule = &utility::SHOOTING_INFO_RULE;
+    Ok(quickexif::parse(buffer, rule)?)
+}
+
+/// The bytes of the embedded preview in `buffer`, which may still be missing the preview itself.
+pub(super) fn get_thumbnail_range(buffer: &[u8]) -> Result<Range<usize>, RawFileReadingError> {
+    let fixed_buffer = fuji_buffer_slice_fix(buffer);
+    let shift = buffer.len() - fixed_buffer.len();
+    let basic_info = quickexif::parse(fixed_buffer, &utility::BASIC_INFO_RULE)?;
+    let range = maker::selector::select_thumbnail_range(fixed_buffer, basic_info)?;
+    let range = range.start.saturating_add(shift)..range.end.saturating_add(shift);
+    // the preview of RAF files goes on after its EXIF block up to the end of the embedded JPEG
+    match raf_jpeg_range(buffer) {
+        Some(jpeg) => Ok(range.start..range.end.max(jpeg.end)),
+        None => Ok(range),
+    }
+}
+
 pub(super) fn get_thumbnail(buffer: &[u8]) -> Result<(&[u8], Orientation), RawFileReadingError> {
     let buffer = fuji_buffer_slice_fix(buffer);
     let rule = &utility::BASIC_INFO_RULE;
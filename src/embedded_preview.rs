@@ -0,0 +1,176 @@
+//! The previews embedded in raw files by the cameras, like a tiny thumbnail, a medium preview and
+//! a full-size JPEG.
+
+use super::*;
+use ifd_walker::Ifd;
+use std::ops::Range;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PreviewFormat {
+    Jpeg,
+    /// Uncompressed 8-bit RGB, in rows of `width` pixels.
+    Rgb8,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EmbeddedPreview {
+    pub format: PreviewFormat,
+    pub width: u32,
+    pub height: u32,
+    /// The bytes of the preview in the raw file.
+    pub range: Range<usize>,
+}
+
+/// Chooses an embedded preview by its size.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PreviewSize {
+    Smallest,
+    Largest,
+    /// The one with its long side closest to the given number of pixels, the larger one of a tie.
+    Closest(u32),
+}
+
+impl PreviewSize {
+    pub fn select<'a>(&self, previews: &'a [EmbeddedPreview]) -> Option<&'a EmbeddedPreview> {
+        let size = |x: &EmbeddedPreview| (x.width as u64 * x.height as u64, x.range.len());
+        match *self {
+            PreviewSize::Smallest => previews.iter().min_by_key(|x| size(x)),
+            PreviewSize::Largest => previews.iter().max_by_key(|x| size(x)),
+            PreviewSize::Closest(long_side) => previews.iter().min_by_key(|x| {
+                let distance = x.width.max(x.height).abs_diff(long_side);
+                (distance, std::cmp::Reverse(size(x)))
+            }),
+        }
+    }
+}
+
+/// Finds the first segment of a JPEG stream before its scan for which `f` returns a value.
+fn find_segment<T>(data: &[u8], f: impl Fn(usize, usize) -> Option<T>) -> Option<T> {
+    let be16 = |i: usize| Some(u16::from_be_bytes([*data.get(i)?, *data.get(i + 1)?]) as usize);
+    if be16(0)? != 0xffd8 {
+        return None;
+    }
+    let mut i = 2;
+    loop {
+        let marker = be16(i)?;
+        match marker {
+            0xff01 | 0xffd0..=0xffd7 => i += 2,
+            0xffd9 | 0xffda => return None,
+            _ => match f(marker, i) {
+                Some(result) => return Some(result),
+                None => i += 2 + be16(i + 2)?,
+            },
+        }
+    }
+}
+
+/// Reads the size from the SOF segment of a JPEG stream.
+pub(crate) fn jpeg_size(data: &[u8]) -> Option<(u32, u32)> {
+    let be16 = |i: usize| Some(u16::from_be_bytes([*data.get(i)?, *data.get(i + 1)?]) as u32);
+    find_segment(data, |marker, i| match marker {
+        0xffc0..=0xffc3 | 0xffc5..=0xffc7 | 0xffc9..=0xffcb | 0xffcd..=0xffcf => {
+            Some((be16(i + 7)?, be16(i + 5)?))
+        }
+        _ => None,
+    })
+}
+
+/// The offset of the TIFF block in the EXIF segment of a JPEG stream.
+fn jpeg_exif(data: &[u8]) -> Option<usize> {
+    find_segment(data, |marker, i| {
+        (marker == 0xffe1 && data.get(i + 4..i + 10)? == b"Exif\0\0").then_some(i + 10)
+    })
+}
+
+/// Lists the previews of a raw file from the smallest to the largest.
+pub(super) fn list(buffer: &[u8]) -> Result<Vec<EmbeddedPreview>, RawFileReadingError> {
+    let mut source = buffer;
+    let mut candidates = vec![];
+    let ifds = match decode::raf_jpeg_range(buffer) {
+        // the EXIF block of the embedded JPEG of RAF files holds the thumbnail
+        Some(jpeg) => {
+            candidates.push((PreviewFormat::Jpeg, jpeg.clone(), None));
+            ifd_walker::walk_tiff(&mut source, jpeg.start + 12)?
+        }
+        None => ifd_walker::walk_tiff(&mut source, 0)?,
+    };
+    ifds.iter().for_each(|ifd| collect(ifd, &mut candidates));
+
+    // like the thumbnail in the `JpgFromRaw` of Panasonic
+    let mut nested = vec![];
+    for (_, range, _) in candidates.iter().filter(|x| x.0 == PreviewFormat::Jpeg) {
+        if let Some(exif) = buffer.get(range.clone()).and_then(jpeg_exif) {
+            for ifd in ifd_walker::walk_tiff(&mut source, range.start + exif)? {
+                collect(&ifd, &mut nested);
+            }
+        }
+    }
+    candidates.extend(nested);
+
+    let mut previews: Vec<EmbeddedPreview> = vec![];
+    for (format, range, size) in candidates {
+        let Some(data) = buffer.get(range.clone()).filter(|x| !x.is_empty()) else {
+            continue;
+        };
+        let size = match format {
+            PreviewFormat::Jpeg => jpeg_size(data),
+            PreviewFormat::Rgb8 => {
+                size.filter(|&(w, h)| w as u64 * h as u64 * 3 == data.len() as u64)
+            }
+        };
+        if let Some((width, height)) = size.filter(|&(w, h)| w > 0 && h > 0) {
+            if previews.iter().all(|x| x.range != range) {
+                previews.push(EmbeddedPreview {
+                    format,
+                    width,
+                    height,
+                    range,
+                });
+            }
+        }
+    }
+    previews.sort_by_key(|x| (x.width as u64 * x.height as u64, x.range.len()));
+    Ok(previews)
+}
+
+type Candidate = (PreviewFormat, Range<usize>, Option<(u32, u32)>);
+
+fn collect(ifd: &Ifd, candidates: &mut Vec<Candidate>) {
+    let range = |offset: u32, len: u32| {
+        let start = ifd.base.saturating_add(offset as usize);
+        start..start.saturating_add(len as usize)
+    };
+    let jpeg = |offset: u32, len: u32| (PreviewFormat::Jpeg, range(offset, len), None);
+
+    // the preview in the `CameraSettings` of the maker notes of Olympus
+    if ifd.parent_tag == Some(0x2020) {
+        if let (Some(offset), Some(len)) = (ifd.number(0x0101), ifd.number(0x0102)) {
+            candidates.push(jpeg(offset, len));
+        }
+        return;
+    }
+
+    if let (Some(offset), Some(len)) = (ifd.number(0x0201), ifd.number(0x0202)) {
+        candidates.push(jpeg(offset, len));
+    }
+    // `JpgFromRaw` of Panasonic
+    if let Some(range) = ifd.value_range(0x002e) {
+        candidates.push((PreviewFormat::Jpeg, range, None));
+    }
+
+    // a single strip of a reduced-resolution image, the sensor data has other photometrics
+    let (Some(offset), Some(len)) = (ifd.number(0x0111), ifd.number(0x0117)) else {
+        return;
+    };
+    match (ifd.number(0x0103), ifd.number(0x0106)) {
+        (Some(6 | 7), Some(2 | 6) | None) => candidates.push(jpeg(offset, len)),
+        (Some(1), Some(2)) if ifd.number(0x0115) == Some(3) => {
+            let size = ifd.number(0x0100).zip(ifd.number(0x0101));
+            candidates.push((PreviewFormat::Rgb8, range(offset, len), size));
+        }
+        _ => {}
+    }
+}
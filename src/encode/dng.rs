@@ -0,0 +1,422 @@
+//! A DNG encoder which stores the sensor samples along with their levels and the color calibration.
+//!
+//! Bayer and X-Trans data are written as CFA images of their 2x2 and 6x6 patterns, the data which
+//! is already RGB as a linear raw image. The samples are kept as the camera recorded them, the
+//! black level, the white level and the linearization table go to their tags.
+
+use super::ifd::*;
+use super::*;
+use crate::decode::{cfa_color, CFAPattern, Crop, SensorData};
+use crate::embedded_preview::jpeg_size;
+use crate::maker::utility::matrix3_inverse;
+
+/// Compression schemes for the raw data.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DngCompression {
+    None,
+    LosslessJpeg,
+}
+
+#[derive(Clone, Debug)]
+pub struct DngOptions {
+    pub compression: DngCompression,
+    /// Embeds the JPEG preview of the source file, a small thumbnail is rendered otherwise.
+    pub embed_preview: bool,
+}
+
+impl Default for DngOptions {
+    fn default() -> Self {
+        DngOptions {
+            compression: DngCompression::LosslessJpeg,
+            embed_preview: true,
+        }
+    }
+}
+
+const TILE_SIZE: usize = 256;
+const THUMBNAIL_SIZE: usize = 256;
+const ILLUMINANT_D65: u16 = 21;
+const WHITE_D65: [f32; 3] = [0.9504559, 1.0, 1.0890578];
+
+/// The sensor data with its levels and calibration, usually borrowed from a `SensorData`.
+pub struct RawImage<'a> {
+    /// Either a CFA mosaic or RGB data with 3 samples per pixel.
+    pub image: &'a [u16],
+    pub width: usize,
+    pub height: usize,
+    pub cfa_pattern: CFAPattern,
+    /// Of the 2x2 block at the top left of the CFA pattern in row-major order, in the linearized values.
+    pub black_level: [u16; 4],
+    /// In the linearized values.
+    pub white_level: u16,
+    /// Of the samples before the linearization, up to 16.
+    pub bits_per_sample: u16,
+    /// A sample `x` is linearized as `table[x]`.
+    pub linearization_table: Option<&'a [u16]>,
+    pub crop: Option<Crop>,
+    pub white_balance: [i32; 3],
+    pub cam_matrix: [f32; 9],
+}
+
+impl<'a> RawImage<'a> {
+    /// Borrows the samples and the levels of `sensor_data`, the calibration is read apart from it.
+    ///
+    /// Missing levels are taken as the full range of the bit depth.
+    pub fn from_sensor_data(
+        sensor_data: &'a SensorData,
+        white_balance: [i32; 3],
+        cam_matrix: [f32; 9],
+    ) -> Self {
+        let bits_per_sample = sensor_data.bit_depth.unwrap_or(16).clamp(1, 16);
+        RawImage {
+            image: &sensor_data.image,
+            width: sensor_data.width,
+            height: sensor_data.height,
+            cfa_pattern: sensor_data.cfa_pattern,
+            black_level: sensor_data.black_level.unwrap_or([0; 4]),
+            white_level: sensor_data
+                .white_level
+                .unwrap_or(u16::MAX >> (16 - bits_per_sample)),
+            bits_per_sample,
+            linearization_table: sensor_data.linearization_table.as_deref(),
+            crop: sensor_data.crop,
+            white_balance,
+            cam_matrix,
+        }
+    }
+}
+
+/// The layout of a raw image as DNG describes it.
+struct RawData<'a> {
+    image: &'a RawImage<'a>,
+    samples_per_pixel: usize,
+    /// The side of the repeat pattern of the colors, `1` for linear raw data.
+    pattern_size: usize,
+    /// The colors of the repeat pattern in rows, empty for linear raw data.
+    colors: Vec<u8>,
+}
+
+impl<'a> RawData<'a> {
+    fn new(image: &'a RawImage<'a>) -> Self {
+        if image.image.len() == image.width * image.height * 3 {
+            return RawData {
+                image,
+                samples_per_pixel: 3,
+                pattern_size: 1,
+                colors: vec![],
+            };
+        }
+        let pattern_size = match image.cfa_pattern {
+            CFAPattern::XTrans0 | CFAPattern::XTrans1 => 6,
+            _ => 2,
+        };
+        let colors = (0..pattern_size * pattern_size)
+            .map(|i| cfa_color(image.cfa_pattern, i % pattern_size, i / pattern_size) as u8)
+            .collect();
+        RawData {
+            image,
+            samples_per_pixel: 1,
+            pattern_size,
+            colors,
+        }
+    }
+
+    fn is_cfa(&self) -> bool {
+        self.samples_per_pixel == 1
+    }
+
+    /// The samples range up to the larger of `bits_per_sample` and the bits of the largest sample.
+    fn bits_per_sample(&self) -> u16 {
+        let largest = self.image.image.iter().copied().max().unwrap_or(0);
+        let bits = (u16::BITS - largest.leading_zeros()) as u16;
+        self.image.bits_per_sample.clamp(1, 16).max(bits)
+    }
+
+    /// Copies a tile, the area outside of the image repeats the edge pixels.
+    fn tile(&self, x: usize, y: usize) -> Vec<u16> {
+        let (width, height) = (self.image.width, self.image.height);
+        let (spp, size) = (self.samples_per_pixel, self.pattern_size);
+        let mut result = Vec::with_capacity(TILE_SIZE * TILE_SIZE * spp);
+        for row in y..y + TILE_SIZE {
+            // keeps the phase of the pattern so the CFA colors stay in place
+            let row = if row < height {
+                row
+            } else {
+                height - size + (row - height) % size
+            };
+            for col in x..x + TILE_SIZE {
+                let col = if col < width {
+                    col
+                } else {
+                    width - size + (col - width) % size
+                };
+                let i = (row * width + col) * spp;
+                result.extend_from_slice(&self.image.image[i..i + spp]);
+            }
+        }
+        result
+    }
+
+    /// Renders a small sRGB thumbnail without color calibration.
+    fn thumbnail(&self) -> (Vec<u8>, usize, usize) {
+        let image = self.image;
+        let size = self.pattern_size;
+        let step = image
+            .width
+            .max(image.height)
+            .div_ceil(THUMBNAIL_SIZE)
+            .next_multiple_of(size);
+        let (width, height) = (image.width / step, image.height / step);
+        let wb = image
+            .white_balance
+            .map(|x| x as f32 / image.white_balance[1] as f32);
+
+        // linear raw data takes the first black level for all its samples
+        let levels = if self.is_cfa() {
+            image.black_level
+        } else {
+            [image.black_level[0]; 4]
+        };
+        let ranges = levels.map(|x| (image.white_level.saturating_sub(x) as f32).max(1.));
+        let normalize = |v: u16, x: usize, y: usize| {
+            let v = match image.linearization_table {
+                Some(table) => table.get(v as usize).or(table.last()).copied().unwrap_or(v),
+                None => v,
+            };
+            let position = y % 2 * 2 + x % 2;
+            (v as f32 - levels[position] as f32) / ranges[position]
+        };
+
+        let mut result = Vec::with_capacity(width * height * 3);
+        for y in (0..height).map(|y| y * step) {
+            for x in (0..width).map(|x| x * step) {
+                let (mut sums, mut counts) = ([0f32; 3], [0f32; 3]);
+                if self.is_cfa() {
+                    for (i, &color) in self.colors.iter().enumerate() {
+                        let (x, y) = (x + i % size, y + i / size);
+                        sums[color as usize] += normalize(image.image[y * image.width + x], x, y);
+                        counts[color as usize] += 1.;
+                    }
+                } else {
+                    let i = (y * image.width + x) * 3;
+                    for c in 0..3 {
+                        sums[c] = normalize(image.image[i + c], x, y);
+                        counts[c] = 1.;
+                    }
+                }
+                for c in 0..3 {
+                    let v = sums[c] / counts[c].max(1.) * wb[c];
+                    let v = TransferFunction::Srgb.encode(v.clamp(0., 1.));
+                    result.push((v * 255.).round() as u8);
+                }
+            }
+        }
+        (result, width, height)
+    }
+}
+
+fn to_srational(v: f32) -> [i32; 2] {
+    [(v * 10000.).round() as i32, 10000]
+}
+
+fn to_rational(v: f32) -> [u32; 2] {
+    [(v.max(0.) * 1000000.).round() as u32, 1000000]
+}
+
+/// Converts a size or a position into the type of its tag.
+fn checked<T: TryFrom<usize>>(name: &'static str, value: usize) -> Result<T, EncodingError> {
+    T::try_from(value).map_err(|_| EncodingError::ValueOutOfRange(name, value))
+}
+
+fn write_raw_ifd(
+    writer: &mut TiffWriter,
+    raw: &RawData,
+    options: &DngOptions,
+) -> Result<u32, EncodingError> {
+    let image = raw.image;
+    let spp = raw.samples_per_pixel;
+    // uncompressed rows keep 16-bit samples, the readers differ in the bit order of packed ones
+    let bits_per_sample = match options.compression {
+        DngCompression::None => 16,
+        DngCompression::LosslessJpeg => raw.bits_per_sample(),
+    };
+
+    let mut ifd = Ifd::default();
+    ifd.insert(0x00fe, Entry::Long(vec![0]));
+    ifd.insert(0x0100, Entry::Long(vec![checked("width", image.width)?]));
+    ifd.insert(0x0101, Entry::Long(vec![checked("height", image.height)?]));
+    ifd.insert(0x0102, Entry::Short(vec![bits_per_sample; spp]));
+    ifd.insert(0x0115, Entry::Short(vec![spp as u16]));
+    ifd.insert(0x011c, Entry::Short(vec![1]));
+
+    let black_level = |x: u16| [x as u32, 1];
+    if raw.is_cfa() {
+        let size = raw.pattern_size as u16;
+        ifd.insert(0x0106, Entry::Short(vec![32803])); // CFA
+        ifd.insert(0x828d, Entry::Short(vec![size, size]));
+        ifd.insert(0x828e, Entry::Byte(raw.colors.clone()));
+        // the levels of the 2x2 block repeat over the larger pattern of X-Trans as well
+        ifd.insert(0xc619, Entry::Short(vec![2, 2]));
+        ifd.insert(
+            0xc61a,
+            Entry::Rational(image.black_level.map(black_level).to_vec()),
+        );
+        ifd.insert(0xc61d, Entry::Short(vec![image.white_level]));
+    } else {
+        ifd.insert(0x0106, Entry::Short(vec![34892])); // LinearRaw
+        ifd.insert(
+            0xc61a,
+            Entry::Rational(vec![black_level(image.black_level[0]); 3]),
+        );
+        ifd.insert(0xc61d, Entry::Short(vec![image.white_level; 3]));
+    }
+    if let Some(table) = image.linearization_table {
+        ifd.insert(0xc618, Entry::Short(table.to_vec()));
+    }
+    if let Some(crop) = image.crop {
+        let short = |name, value: u32| checked::<u16>(name, value as usize);
+        ifd.insert(
+            0xc61f,
+            Entry::Short(vec![short("crop x", crop.x)?, short("crop y", crop.y)?]),
+        );
+        ifd.insert(
+            0xc620,
+            Entry::Short(vec![
+                short("crop width", crop.width)?,
+                short("crop height", crop.height)?,
+            ]),
+        );
+    }
+
+    match options.compression {
+        DngCompression::None => {
+            let bytes = image
+                .image
+                .iter()
+                .flat_map(|x| x.to_le_bytes())
+                .collect::<Vec<_>>();
+            let offset = writer.write_data(&bytes);
+            ifd.insert(0x0103, Entry::Short(vec![1]));
+            ifd.insert(0x0111, Entry::Long(vec![offset]));
+            ifd.insert(0x0116, Entry::Long(vec![checked("height", image.height)?]));
+            ifd.insert(0x0117, Entry::Long(vec![checked("strip length", bytes.len())?]));
+        }
+        DngCompression::LosslessJpeg => {
+            // two components for CFA data so that the same colors predict each other
+            let (frame_width, components) = if raw.is_cfa() {
+                (TILE_SIZE / 2, 2)
+            } else {
+                (TILE_SIZE, 3)
+            };
+            let mut offsets = vec![];
+            let mut byte_counts = vec![];
+            for y in (0..image.height).step_by(TILE_SIZE) {
+                for x in (0..image.width).step_by(TILE_SIZE) {
+                    let tile = ljpeg::encode(
+                        &raw.tile(x, y),
+                        frame_width,
+                        TILE_SIZE,
+                        components,
+                        bits_per_sample as u8,
+                    );
+                    offsets.push(writer.write_data(&tile));
+                    byte_counts.push(checked("tile length", tile.len())?);
+                }
+            }
+            ifd.insert(0x0103, Entry::Short(vec![7]));
+            ifd.insert(0x0142, Entry::Long(vec![TILE_SIZE as u32]));
+            ifd.insert(0x0143, Entry::Long(vec![TILE_SIZE as u32]));
+            ifd.insert(0x0144, Entry::Long(offsets));
+            ifd.insert(0x0145, Entry::Long(byte_counts));
+        }
+    }
+
+    Ok(writer.write_ifd(&ifd))
+}
+
+/// Encodes a raw image into a DNG file, IFD0 holds the `preview` when it is a JPEG stream,
+/// otherwise a rendered thumbnail.
+pub fn encode(
+    raw_image: &RawImage,
+    metadata: &Metadata,
+    preview: Option<&[u8]>,
+    options: &DngOptions,
+) -> Result<Vec<u8>, EncodingError> {
+    let raw = RawData::new(raw_image);
+    // the edges of the tiles repeat the last pattern of the image
+    let min_size = raw.pattern_size.max(2);
+    let len = raw_image.width * raw_image.height * raw.samples_per_pixel;
+    if raw_image.width < min_size || raw_image.height < min_size || raw_image.image.len() != len {
+        return Err(EncodingError::InvalidImageSize(raw_image.image.len(), len));
+    }
+
+    let mut writer = TiffWriter::new();
+    let raw_offset = write_raw_ifd(&mut writer, &raw, options)?;
+
+    let mut ifd0 = Ifd::default();
+    ifd0.insert(0x00fe, Entry::Long(vec![1]));
+    let preview = preview
+        .filter(|_| options.embed_preview)
+        .and_then(|x| Some((x, jpeg_size(x)?)));
+    match preview {
+        Some((jpeg, (width, height))) => {
+            let offset = writer.write_data(jpeg);
+            ifd0.insert(0x0100, Entry::Long(vec![width]));
+            ifd0.insert(0x0101, Entry::Long(vec![height]));
+            ifd0.insert(0x0103, Entry::Short(vec![7]));
+            ifd0.insert(0x0106, Entry::Short(vec![6])); // YCbCr
+            ifd0.insert(0x0111, Entry::Long(vec![offset]));
+            ifd0.insert(0x0116, Entry::Long(vec![height]));
+            ifd0.insert(0x0117, Entry::Long(vec![jpeg.len() as u32]));
+        }
+        None => {
+            let (thumbnail, width, height) = raw.thumbnail();
+            let offset = writer.write_data(&thumbnail);
+            ifd0.insert(0x0100, Entry::Long(vec![width as u32]));
+            ifd0.insert(0x0101, Entry::Long(vec![height as u32]));
+            ifd0.insert(0x0103, Entry::Short(vec![1]));
+            ifd0.insert(0x0106, Entry::Short(vec![2])); // RGB
+            ifd0.insert(0x0111, Entry::Long(vec![offset]));
+            ifd0.insert(0x0116, Entry::Long(vec![height as u32]));
+            ifd0.insert(0x0117, Entry::Long(vec![thumbnail.len() as u32]));
+        }
+    }
+    ifd0.insert(0x0102, Entry::Short(vec![8; 3]));
+    ifd0.insert(0x0115, Entry::Short(vec![3]));
+    ifd0.insert(0x011c, Entry::Short(vec![1]));
+    ifd0.insert(0x014a, Entry::Long(vec![raw_offset]));
+
+    // `cam_matrix` works on the XYZ values normalized by the D65 white, and the reader
+    // inverts the color matrix and normalizes its rows, so the rows are scaled back first
+    let mut color_matrix = raw_image.cam_matrix;
+    for (i, v) in color_matrix.iter_mut().enumerate() {
+        *v *= WHITE_D65[i / 3];
+    }
+    matrix3_inverse(&mut color_matrix);
+    let [r, g, b] = raw_image.white_balance.map(|x| x as f32);
+    let make_model = [metadata.make.as_deref(), metadata.model.as_deref()]
+        .into_iter()
+        .flatten()
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    ifd0.insert(0xc612, Entry::Byte(vec![1, 4, 0, 0]));
+    ifd0.insert(0xc613, Entry::Byte(vec![1, 1, 0, 0]));
+    ifd0.insert(0xc614, Entry::Ascii(make_model));
+    ifd0.insert(
+        0xc621,
+        Entry::SRational(color_matrix.iter().map(|&x| to_srational(x)).collect()),
+    );
+    ifd0.insert(
+        0xc628,
+        Entry::Rational(vec![to_rational(g / r), [1, 1], to_rational(g / b)]),
+    );
+    ifd0.insert(0xc65a, Entry::Short(vec![ILLUMINANT_D65]));
+    metadata.write_tags(&mut writer, &mut ifd0);
+
+    let ifd0_offset = writer.write_ifd(&ifd0);
+    let data = writer.finish(ifd0_offset);
+    // every offset is below the length of the file
+    checked::<u32>("file length", data.len())?;
+    Ok(data)
+}
@@ -0,0 +1,234 @@
+//! An OpenEXR encoder for scene-linear RGB(A) images, written as single-part scanline files.
+
+use super::piz;
+use super::*;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExrCompression {
+    None,
+    /// Deflate over blocks of 16 scanlines.
+    Zip,
+    /// Wavelet and Huffman over blocks of 32 scanlines, usually the best for noisy plates.
+    Piz,
+}
+
+impl ExrCompression {
+    fn code(&self) -> u8 {
+        match self {
+            ExrCompression::None => 0,
+            ExrCompression::Zip => 3,
+            ExrCompression::Piz => 4,
+        }
+    }
+
+    fn lines_per_chunk(&self) -> usize {
+        match self {
+            ExrCompression::None => 1,
+            ExrCompression::Zip => 16,
+            ExrCompression::Piz => 32,
+        }
+    }
+}
+
+/// The pixel type of the channels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExrSampleType {
+    Half,
+    Float,
+}
+
+impl ExrSampleType {
+    fn code(&self) -> i32 {
+        match self {
+            ExrSampleType::Half => 1,
+            ExrSampleType::Float => 2,
+        }
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            ExrSampleType::Half => 2,
+            ExrSampleType::Float => 4,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ExrOptions {
+    pub compression: ExrCompression,
+    pub sample_type: ExrSampleType,
+}
+
+impl Default for ExrOptions {
+    fn default() -> Self {
+        ExrOptions {
+            compression: ExrCompression::Zip,
+            sample_type: ExrSampleType::Half,
+        }
+    }
+}
+
+const MAGIC: [u8; 4] = [0x76, 0x2f, 0x31, 0x01];
+
+fn write_attribute(output: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    output.extend(name.as_bytes());
+    output.push(0);
+    output.extend(kind.as_bytes());
+    output.push(0);
+    output.extend((value.len() as i32).to_le_bytes());
+    output.extend(value);
+}
+
+fn floats(values: &[f32]) -> Vec<u8> {
+    values.iter().flat_map(|x| x.to_le_bytes()).collect()
+}
+
+/// The primaries of the pixels, the raw space is XYZ normalized by the D65 white.
+fn chromaticities(color_space: &ColorSpace) -> Option<[[f32; 2]; 4]> {
+    match color_space {
+        ColorSpace::Raw => ColorSpace::Xyz.chromaticities(),
+        x => x.chromaticities(),
+    }
+}
+
+/// The predictor and byte interleaving applied before deflate.
+fn zip_predict(data: &[u8]) -> Vec<u8> {
+    let mut result = data.iter().step_by(2).copied().collect::<Vec<_>>();
+    result.extend(data.iter().skip(1).step_by(2));
+    let mut prev = result.first().copied().unwrap_or(0);
+    for v in result.iter_mut().skip(1) {
+        let current = *v;
+        *v = current.wrapping_sub(prev).wrapping_add(128);
+        prev = current;
+    }
+    result
+}
+
+pub fn encode(
+    image: &Image,
+    metadata: &Metadata,
+    options: &ExrOptions,
+) -> Result<Vec<u8>, EncodingError> {
+    let pixels = match image.pixels {
+        Pixels::F32(x) => x,
+        Pixels::U8(_) => return Err(EncodingError::UnsupportedSampleFormat("OpenEXR", "u8")),
+        Pixels::U16(_) => return Err(EncodingError::UnsupportedSampleFormat("OpenEXR", "u16")),
+    };
+    let (width, height, channels) = (image.width, image.height, image.channels);
+    let sample_type = options.sample_type;
+
+    // channels are stored in the alphabetical order of names
+    let names: &[(&str, usize)] = if channels == 4 {
+        &[("A", 3), ("B", 2), ("G", 1), ("R", 0)]
+    } else {
+        &[("B", 2), ("G", 1), ("R", 0)]
+    };
+
+    let mut output = MAGIC.to_vec();
+    output.extend(2u32.to_le_bytes());
+
+    let mut channel_list = vec![];
+    for (name, _) in names {
+        channel_list.extend(name.as_bytes());
+        channel_list.push(0);
+        channel_list.extend(sample_type.code().to_le_bytes());
+        channel_list.extend([0, 0, 0, 0]); // pLinear and reserved
+        channel_list.extend(1i32.to_le_bytes());
+        channel_list.extend(1i32.to_le_bytes());
+    }
+    channel_list.push(0);
+    let window = [0, 0, width as i32 - 1, height as i32 - 1]
+        .iter()
+        .flat_map(|x| x.to_le_bytes())
+        .collect::<Vec<_>>();
+
+    write_attribute(&mut output, "channels", "chlist", &channel_list);
+    write_attribute(
+        &mut output,
+        "compression",
+        "compression",
+        &[options.compression.code()],
+    );
+    write_attribute(&mut output, "dataWindow", "box2i", &window);
+    write_attribute(&mut output, "displayWindow", "box2i", &window);
+    write_attribute(&mut output, "lineOrder", "lineOrder", &[0]);
+    write_attribute(&mut output, "pixelAspectRatio", "float", &floats(&[1.]));
+    write_attribute(&mut output, "screenWindowCenter", "v2f", &floats(&[0., 0.]));
+    write_attribute(&mut output, "screenWindowWidth", "float", &floats(&[1.]));
+    if let Some(xy) = chromaticities(&metadata.color_space) {
+        write_attribute(
+            &mut output,
+            "chromaticities",
+            "chromaticities",
+            &floats(xy.as_flattened()),
+        );
+    }
+    let exif = &metadata.exif;
+    if let Some(v) = &exif.date_time_original {
+        write_attribute(&mut output, "capDate", "string", v.as_bytes());
+    }
+    if let Some(v) = exif.exposure_time {
+        write_attribute(&mut output, "expTime", "float", &floats(&[v as f32]));
+    }
+    if let Some(v) = exif.f_number {
+        write_attribute(&mut output, "aperture", "float", &floats(&[v as f32]));
+    }
+    if let Some(v) = exif.iso {
+        write_attribute(&mut output, "isoSpeed", "float", &floats(&[v as f32]));
+    }
+    output.push(0);
+
+    let lines_per_chunk = options.compression.lines_per_chunk();
+    let chunk_count = height.div_ceil(lines_per_chunk);
+    let table_start = output.len();
+    output.resize(table_start + chunk_count * 8, 0);
+
+    let piz_channels = names
+        .iter()
+        .map(|_| piz::Channel {
+            size: sample_type.size() / 2,
+        })
+        .collect::<Vec<_>>();
+
+    for (chunk_index, rows) in pixels
+        .chunks(width * channels * lines_per_chunk)
+        .enumerate()
+    {
+        let lines = rows.len() / (width * channels);
+        let mut data = Vec::with_capacity(rows.len() * sample_type.size());
+        for row in rows.chunks_exact(width * channels) {
+            for &(_, channel) in names {
+                let samples = row.iter().skip(channel).step_by(channels);
+                match sample_type {
+                    ExrSampleType::Half => {
+                        data.extend(samples.flat_map(|&x| half::f16::from_f32(x).to_le_bytes()))
+                    }
+                    ExrSampleType::Float => data.extend(samples.flat_map(|x| x.to_le_bytes())),
+                }
+            }
+        }
+
+        let compressed = match options.compression {
+            ExrCompression::None => None,
+            ExrCompression::Zip => Some(miniz_oxide::deflate::compress_to_vec_zlib(
+                &zip_predict(&data),
+                6,
+            )),
+            ExrCompression::Piz => Some(piz::compress(&data, width, lines, &piz_channels)),
+        };
+        // a chunk is stored raw when the compression does not help
+        let data = match compressed {
+            Some(x) if x.len() < data.len() => x,
+            _ => data,
+        };
+
+        let offset = output.len() as u64;
+        let entry = table_start + chunk_index * 8;
+        output[entry..entry + 8].copy_from_slice(&offset.to_le_bytes());
+        output.extend(((chunk_index * lines_per_chunk) as i32).to_le_bytes());
+        output.extend((data.len() as i32).to_le_bytes());
+        output.extend(data);
+    }
+
+    Ok(output)
+}
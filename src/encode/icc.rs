@@ -0,0 +1,194 @@
+use super::*;
+use crate::{maker::utility::matrix3_inverse, transfer::PQ_REFERENCE_WHITE, utility::matrix3_mul};
+
+const WHITE_D50: [f32; 3] = [0.9642, 1.0, 0.8249];
+const WHITE_D65: [f32; 3] = [0.9504559, 1.0, 1.0890578];
+const BRADFORD: [f32; 9] = [
+    0.8951, 0.2664, -0.1614, -0.7502, 1.7135, 0.0367, 0.0389, -0.0685, 1.0296,
+];
+const CURVE_SIZE: usize = 1024;
+
+fn xy_to_xyz([x, y]: [f32; 2]) -> [f32; 3] {
+    [x / y, 1., (1. - x - y) / y]
+}
+
+fn inverse(m: &[f32; 9]) -> [f32; 9] {
+    // `matrix3_inverse` gives the transposed inverse
+    let mut result = *m;
+    matrix3_inverse(&mut result);
+    [
+        result[0], result[3], result[6], result[1], result[4], result[7], result[2], result[5],
+        result[8],
+    ]
+}
+
+fn mul_vec(m: &[f32; 9], v: &[f32; 3]) -> [f32; 3] {
+    [
+        m[0] * v[0] + m[1] * v[1] + m[2] * v[2],
+        m[3] * v[0] + m[4] * v[1] + m[5] * v[2],
+        m[6] * v[0] + m[7] * v[1] + m[8] * v[2],
+    ]
+}
+
+fn bradford(from: &[f32; 3], to: &[f32; 3]) -> [f32; 9] {
+    let s = mul_vec(&BRADFORD, from);
+    let d = mul_vec(&BRADFORD, to);
+    let scale = [d[0] / s[0], 0., 0., 0., d[1] / s[1], 0., 0., 0., d[2] / s[2]];
+    matrix3_mul(&inverse(&BRADFORD), &matrix3_mul(&scale, &BRADFORD))
+}
+
+/// Returns the RGB to XYZ matrix and the white point of the color space.
+fn rgb_to_xyz(color_space: &ColorSpace) -> Option<([f32; 9], [f32; 3])> {
+    match color_space {
+        ColorSpace::Raw => None,
+        ColorSpace::Xyz | ColorSpace::Custom(_) => {
+            let scale = [WHITE_D65[0], 0., 0., 0., 1., 0., 0., 0., WHITE_D65[2]];
+            Some((matrix3_mul(&scale, &inverse(&color_space.matrix())), WHITE_D65))
+        }
+        _ => {
+            let [r, g, b, w] = color_space.chromaticities()?;
+            let [r, g, b, w] = [r, g, b, w].map(xy_to_xyz);
+            let primaries = [r[0], g[0], b[0], r[1], g[1], b[1], r[2], g[2], b[2]];
+            let s = mul_vec(&inverse(&primaries), &w);
+            let scale = [s[0], 0., 0., 0., s[1], 0., 0., 0., s[2]];
+            Some((matrix3_mul(&primaries, &scale), w))
+        }
+    }
+}
+
+fn s15_fixed16(v: f32) -> [u8; 4] {
+    ((v as f64 * 65536.).round() as i32).to_be_bytes()
+}
+
+fn tag_mluc(text: &str) -> Vec<u8> {
+    let text = text.encode_utf16().flat_map(|x| x.to_be_bytes()).collect::<Vec<_>>();
+    let mut result = b"mluc\0\0\0\0".to_vec();
+    result.extend(1u32.to_be_bytes());
+    result.extend(12u32.to_be_bytes());
+    result.extend(b"enUS");
+    result.extend((text.len() as u32).to_be_bytes());
+    result.extend(28u32.to_be_bytes());
+    result.extend(text);
+    result
+}
+
+fn tag_xyz(v: &[f32; 3]) -> Vec<u8> {
+    let mut result = b"XYZ \0\0\0\0".to_vec();
+    result.extend(v.iter().flat_map(|&x| s15_fixed16(x)));
+    result
+}
+
+fn tag_sf32(m: &[f32; 9]) -> Vec<u8> {
+    let mut result = b"sf32\0\0\0\0".to_vec();
+    result.extend(m.iter().flat_map(|&x| s15_fixed16(x)));
+    result
+}
+
+fn tag_curv(transfer: TransferFunction) -> Vec<u8> {
+    let mut result = b"curv\0\0\0\0".to_vec();
+    match transfer {
+        TransferFunction::Linear => result.extend(0u32.to_be_bytes()),
+        TransferFunction::Gamma(gamma) => {
+            result.extend(1u32.to_be_bytes());
+            result.extend(((256. / gamma).round() as u16).to_be_bytes());
+        }
+        _ => {
+            // the curve ends at 1.0, so PQ is relative to its peak of 10000 cd/m² and not to the
+            // reference white
+            let scale = match transfer {
+                TransferFunction::Pq => PQ_REFERENCE_WHITE / 10000.,
+                _ => 1.,
+            };
+            result.extend((CURVE_SIZE as u32).to_be_bytes());
+            for i in 0..CURVE_SIZE {
+                let v = transfer.decode(i as f32 / (CURVE_SIZE - 1) as f32) * scale;
+                result.extend(((v.clamp(0., 1.) * 65535.).round() as u16).to_be_bytes());
+            }
+        }
+    }
+    result
+}
+
+fn description(color_space: &ColorSpace, transfer: TransferFunction) -> String {
+    let space = match color_space {
+        ColorSpace::Srgb => "sRGB",
+        ColorSpace::AdobeRgb => "Adobe RGB (1998)",
+        ColorSpace::ProPhotoRgb => "ProPhoto RGB",
+        ColorSpace::Rec2020 => "Rec. 2020",
+        ColorSpace::DisplayP3 => "Display P3",
+        ColorSpace::DciP3 => "DCI-P3",
+        ColorSpace::AcesAp0 => "ACES2065-1",
+        ColorSpace::AcesAp1 => "ACEScg",
+        ColorSpace::Xyz => "CIE XYZ",
+        ColorSpace::Raw | ColorSpace::Custom(_) => "Custom RGB",
+    };
+    if transfer == color_space.default_transfer() {
+        space.to_owned()
+    } else {
+        format!("{} ({:?})", space, transfer)
+    }
+}
+
+/// Generates an ICC v4 matrix/TRC display profile for the color space and transfer function.
+///
+/// Returns `None` for `ColorSpace::Raw` since it has no colorimetric meaning.
+pub fn icc_profile(color_space: &ColorSpace, transfer: TransferFunction) -> Option<Vec<u8>> {
+    let (matrix, white) = rgb_to_xyz(color_space)?;
+    let chad = bradford(&white, &WHITE_D50);
+    let colorants = matrix3_mul(&chad, &matrix);
+    let curve = tag_curv(transfer);
+
+    let tags: [(&[u8; 4], Vec<u8>); 7] = [
+        (b"desc", tag_mluc(&description(color_space, transfer))),
+        (b"cprt", tag_mluc("No copyright, use freely")),
+        (b"wtpt", tag_xyz(&WHITE_D50)),
+        (b"chad", tag_sf32(&chad)),
+        (b"rXYZ", tag_xyz(&[colorants[0], colorants[3], colorants[6]])),
+        (b"gXYZ", tag_xyz(&[colorants[1], colorants[4], colorants[7]])),
+        (b"bXYZ", tag_xyz(&[colorants[2], colorants[5], colorants[8]])),
+    ];
+    let trc_tags = [b"rTRC", b"gTRC", b"bTRC"];
+
+    let tag_count = tags.len() + trc_tags.len();
+    let mut table = (tag_count as u32).to_be_bytes().to_vec();
+    let mut data = vec![];
+    let data_start = 128 + 4 + tag_count * 12;
+    let mut push_data = |bytes: &[u8]| {
+        let offset = data_start + data.len();
+        data.extend_from_slice(bytes);
+        while data.len() % 4 != 0 {
+            data.push(0);
+        }
+        offset as u32
+    };
+
+    for (signature, bytes) in tags.iter() {
+        let offset = push_data(bytes);
+        table.extend(*signature);
+        table.extend(offset.to_be_bytes());
+        table.extend((bytes.len() as u32).to_be_bytes());
+    }
+    // the three curves share the same data
+    let offset = push_data(&curve);
+    for signature in trc_tags {
+        table.extend(signature);
+        table.extend(offset.to_be_bytes());
+        table.extend((curve.len() as u32).to_be_bytes());
+    }
+
+    let size = data_start + data.len();
+    let mut header = vec![0u8; 128];
+    header[0..4].copy_from_slice(&(size as u32).to_be_bytes());
+    header[8..12].copy_from_slice(&[4, 0x30, 0, 0]);
+    header[12..16].copy_from_slice(b"mntr");
+    header[16..20].copy_from_slice(b"RGB ");
+    header[20..24].copy_from_slice(b"XYZ ");
+    header[36..40].copy_from_slice(b"acsp");
+    for (i, &v) in WHITE_D50.iter().enumerate() {
+        header[68 + i * 4..72 + i * 4].copy_from_slice(&s15_fixed16(v));
+    }
+
+    header.extend(table);
+    header.extend(data);
+    Some(header)
+}
@@ -0,0 +1,137 @@
+use std::collections::BTreeMap;
+
+/// A value of a TIFF IFD entry, always written in little-endian.
+pub(crate) enum Entry {
+    Byte(Vec<u8>),
+    Ascii(String),
+    Short(Vec<u16>),
+    Long(Vec<u32>),
+    Rational(Vec<[u32; 2]>),
+    Undefined(Vec<u8>),
+    SRational(Vec<[i32; 2]>),
+}
+
+impl Entry {
+    fn field_type(&self) -> u16 {
+        match self {
+            Entry::Byte(_) => 1,
+            Entry::Ascii(_) => 2,
+            Entry::Short(_) => 3,
+            Entry::Long(_) => 4,
+            Entry::Rational(_) => 5,
+            Entry::Undefined(_) => 7,
+            Entry::SRational(_) => 10,
+        }
+    }
+
+    fn count(&self) -> u32 {
+        let count = match self {
+            Entry::Byte(x) => x.len(),
+            Entry::Ascii(x) => x.len() + 1,
+            Entry::Short(x) => x.len(),
+            Entry::Long(x) => x.len(),
+            Entry::Rational(x) => x.len(),
+            Entry::Undefined(x) => x.len(),
+            Entry::SRational(x) => x.len(),
+        };
+        count as u32
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Entry::Byte(x) => x.clone(),
+            Entry::Ascii(x) => x.bytes().chain([0]).collect(),
+            Entry::Short(x) => x.iter().flat_map(|v| v.to_le_bytes()).collect(),
+            Entry::Long(x) => x.iter().flat_map(|v| v.to_le_bytes()).collect(),
+            Entry::Rational(x) => x.iter().flatten().flat_map(|v| v.to_le_bytes()).collect(),
+            Entry::Undefined(x) => x.clone(),
+            Entry::SRational(x) => x.iter().flatten().flat_map(|v| v.to_le_bytes()).collect(),
+        }
+    }
+}
+
+/// An image file directory. Entries are kept sorted by tag as the spec requires.
+#[derive(Default)]
+pub(crate) struct Ifd {
+    entries: BTreeMap<u16, Entry>,
+}
+
+impl Ifd {
+    pub(crate) fn insert(&mut self, tag: u16, entry: Entry) {
+        self.entries.insert(tag, entry);
+    }
+}
+
+/// Writes a little-endian TIFF stream.
+///
+/// Data blocks and IFDs are appended in the order they are written, so child IFDs and
+/// image data must be written before the IFD that points to them.
+pub(crate) struct TiffWriter {
+    buffer: Vec<u8>,
+}
+
+impl TiffWriter {
+    pub(crate) fn new() -> Self {
+        TiffWriter {
+            buffer: vec![0x49, 0x49, 0x2a, 0x00, 0, 0, 0, 0],
+        }
+    }
+
+    fn align(&mut self) {
+        if !self.buffer.len().is_multiple_of(2) {
+            self.buffer.push(0);
+        }
+    }
+
+    /// Appends a data block and returns its offset.
+    pub(crate) fn write_data(&mut self, data: &[u8]) -> u32 {
+        self.align();
+        let offset = self.buffer.len() as u32;
+        self.buffer.extend_from_slice(data);
+        offset
+    }
+
+    /// Appends an IFD with its out-of-line values and returns its offset.
+    pub(crate) fn write_ifd(&mut self, ifd: &Ifd) -> u32 {
+        self.write_ifd_with_next(ifd, 0)
+    }
+
+    /// Same as `write_ifd`, but links the IFD to the next one like IFD0 to IFD1 in EXIF.
+    pub(crate) fn write_ifd_with_next(&mut self, ifd: &Ifd, next_ifd: u32) -> u32 {
+        self.align();
+        let offset = self.buffer.len();
+        let mut value_offset = offset + 2 + ifd.entries.len() * 12 + 4;
+
+        let mut table = Vec::with_capacity(value_offset - offset);
+        let mut values = vec![];
+        table.extend((ifd.entries.len() as u16).to_le_bytes());
+        for (&tag, entry) in ifd.entries.iter() {
+            let mut bytes = entry.to_bytes();
+            table.extend(tag.to_le_bytes());
+            table.extend(entry.field_type().to_le_bytes());
+            table.extend(entry.count().to_le_bytes());
+            if bytes.len() <= 4 {
+                bytes.resize(4, 0);
+                table.extend(bytes);
+            } else {
+                if !bytes.len().is_multiple_of(2) {
+                    bytes.push(0);
+                }
+                table.extend((value_offset as u32).to_le_bytes());
+                value_offset += bytes.len();
+                values.extend(bytes);
+            }
+        }
+        table.extend(next_ifd.to_le_bytes());
+
+        self.buffer.extend(table);
+        self.buffer.extend(values);
+        offset as u32
+    }
+
+    /// Points the header to the first IFD and returns the whole stream.
+    pub(crate) fn finish(mut self, first_ifd: u32) -> Vec<u8> {
+        self.buffer[4..8].copy_from_slice(&first_ifd.to_le_bytes());
+        self.buffer
+    }
+}
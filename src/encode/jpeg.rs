@@ -0,0 +1,152 @@
+//! A JPEG encoder for 8/16-bit RGB(A) images with EXIF, ICC profile and an optional thumbnail.
+
+use super::ifd::*;
+use super::*;
+use jpeg_encoder::{ColorType, Encoder, SamplingFactor};
+
+/// The chroma subsampling of the YCbCr planes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChromaSubsampling {
+    Yuv444,
+    Yuv422,
+    Yuv420,
+}
+
+impl ChromaSubsampling {
+    fn sampling_factor(&self) -> SamplingFactor {
+        match self {
+            ChromaSubsampling::Yuv444 => SamplingFactor::R_4_4_4,
+            ChromaSubsampling::Yuv422 => SamplingFactor::R_4_2_2,
+            ChromaSubsampling::Yuv420 => SamplingFactor::R_4_2_0,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct JpegOptions {
+    /// From `1` to `100`.
+    pub quality: u8,
+    pub subsampling: ChromaSubsampling,
+    pub progressive: bool,
+    /// Embeds a small preview in the IFD1 of the EXIF block.
+    pub embed_thumbnail: bool,
+}
+
+impl Default for JpegOptions {
+    fn default() -> Self {
+        JpegOptions {
+            quality: 92,
+            subsampling: ChromaSubsampling::Yuv420,
+            progressive: false,
+            embed_thumbnail: false,
+        }
+    }
+}
+
+const MAX_SIZE: usize = u16::MAX as usize;
+const THUMBNAIL_SIZE: usize = 160;
+const THUMBNAIL_QUALITY: u8 = 75;
+
+/// Box filters the image to fit in `THUMBNAIL_SIZE`, the alpha channel is dropped.
+fn thumbnail(
+    pixels: &[u8],
+    width: usize,
+    height: usize,
+    channels: usize,
+) -> (Vec<u8>, usize, usize) {
+    let scale = width.max(height).div_ceil(THUMBNAIL_SIZE).max(1);
+    let (thumb_width, thumb_height) = ((width / scale).max(1), (height / scale).max(1));
+
+    let mut result = Vec::with_capacity(thumb_width * thumb_height * 3);
+    for y in 0..thumb_height {
+        for x in 0..thumb_width {
+            let mut sum = [0u32; 3];
+            for row in y * scale..(y + 1) * scale {
+                for col in x * scale..(x + 1) * scale {
+                    // very narrow images repeat their last row or column
+                    let i = (row.min(height - 1) * width + col.min(width - 1)) * channels;
+                    sum.iter_mut()
+                        .zip(&pixels[i..i + 3])
+                        .for_each(|(s, &v)| *s += v as u32);
+                }
+            }
+            let count = (scale * scale) as u32;
+            result.extend(sum.map(|s| ((s + count / 2) / count) as u8));
+        }
+    }
+    (result, thumb_width, thumb_height)
+}
+
+fn exif_segment(metadata: &Metadata, thumbnail: Option<&[u8]>) -> Vec<u8> {
+    let mut writer = TiffWriter::new();
+    let ifd1 = thumbnail.map(|data| {
+        let offset = writer.write_data(data);
+        let mut ifd1 = Ifd::default();
+        ifd1.insert(0x0103, Entry::Short(vec![6])); // JPEG
+        ifd1.insert(0x0201, Entry::Long(vec![offset]));
+        ifd1.insert(0x0202, Entry::Long(vec![data.len() as u32]));
+        writer.write_ifd(&ifd1)
+    });
+
+    let mut ifd0 = Ifd::default();
+    metadata.write_tags(&mut writer, &mut ifd0);
+    let offset = writer.write_ifd_with_next(&ifd0, ifd1.unwrap_or(0));
+
+    let mut segment = b"Exif\0\0".to_vec();
+    segment.extend(writer.finish(offset));
+    segment
+}
+
+/// Encodes the image into a baseline or progressive JFIF file.
+///
+/// 16-bit samples are reduced to 8 bits. The EXIF block carries the orientation of the metadata
+/// along with the shooting parameters, and an ICC profile is embedded when there is one.
+pub fn encode(
+    image: &Image,
+    metadata: &Metadata,
+    options: &JpegOptions,
+) -> Result<Vec<u8>, EncodingError> {
+    let pixels = match image.pixels {
+        Pixels::U8(x) => x.to_vec(),
+        Pixels::U16(x) => x
+            .iter()
+            .map(|&v| ((v as u32 * 255 + 32767) / 65535) as u8)
+            .collect(),
+        Pixels::F32(_) => return Err(EncodingError::UnsupportedSampleFormat("JPEG", "f32")),
+    };
+    let (width, height, channels) = (image.width, image.height, image.channels);
+    if width > MAX_SIZE || height > MAX_SIZE {
+        return Err(EncodingError::ImageTooLarge("JPEG", MAX_SIZE));
+    }
+    let color_type = if channels == 4 {
+        ColorType::Rgba
+    } else {
+        ColorType::Rgb
+    };
+
+    let thumbnail = if options.embed_thumbnail {
+        let (data, thumb_width, thumb_height) = thumbnail(&pixels, width, height, channels);
+        let mut output = vec![];
+        Encoder::new(&mut output, THUMBNAIL_QUALITY).encode(
+            &data,
+            thumb_width as u16,
+            thumb_height as u16,
+            ColorType::Rgb,
+        )?;
+        Some(output)
+    } else {
+        None
+    };
+
+    let mut output = vec![];
+    let mut encoder = Encoder::new(&mut output, options.quality.clamp(1, 100));
+    encoder.set_sampling_factor(options.subsampling.sampling_factor());
+    encoder.set_progressive(options.progressive);
+    encoder.add_app_segment(1, &exif_segment(metadata, thumbnail.as_deref()))?;
+    if let Some(profile) = icc_profile(&metadata.color_space, metadata.transfer) {
+        encoder.add_icc_profile(&profile)?;
+    }
+    encoder.encode(&pixels, width as u16, height as u16, color_type)?;
+
+    Ok(output)
+}
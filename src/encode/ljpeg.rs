@@ -0,0 +1,206 @@
+//! A lossless JPEG (ITU T.81 process 14) encoder with the predictor 1 and up to 16-bit precision.
+
+const CATEGORIES: usize = 17;
+
+/// The category(SSSS) and the extra bits of a prediction difference.
+fn categorize(diff: i16) -> (usize, u16) {
+    if diff == i16::MIN {
+        // no extra bits are stored for the category 16
+        return (16, 0);
+    }
+    let category = 16 - diff.unsigned_abs().leading_zeros() as usize;
+    let bits = if diff < 0 {
+        (diff as i32 + (1 << category) - 1) as u16
+    } else {
+        diff as u16
+    };
+    (category, bits)
+}
+
+/// Generates the `BITS` and `HUFFVAL` lists with the procedure in Annex K.2.
+fn huffman_table(freq: &[u32; CATEGORIES]) -> ([u8; 17], Vec<u8>) {
+    // the extra symbol reserves one code point so that no code is all 1 bits
+    let mut freq = freq.iter().copied().chain([1]).collect::<Vec<_>>();
+    let mut code_size = vec![0usize; freq.len()];
+    let mut others = vec![None; freq.len()];
+
+    loop {
+        let least = |exclude: Option<usize>| {
+            (0..freq.len())
+                .filter(|&i| freq[i] > 0 && Some(i) != exclude)
+                .min_by_key(|&i| (freq[i], usize::MAX - i))
+        };
+        let (Some(v1), Some(v2)) = (least(None), least(least(None))) else {
+            break;
+        };
+
+        freq[v1] += freq[v2];
+        freq[v2] = 0;
+        for start in [v1, v2] {
+            let mut v = start;
+            code_size[v] += 1;
+            while let Some(next) = others[v] {
+                v = next;
+                code_size[v] += 1;
+            }
+        }
+        let mut v = v1;
+        while let Some(next) = others[v] {
+            v = next;
+        }
+        others[v] = Some(v2);
+    }
+
+    let mut bits = [0usize; 33];
+    for &size in code_size.iter().filter(|&&x| x > 0) {
+        bits[size] += 1;
+    }
+    for i in (17..=32).rev() {
+        while bits[i] > 0 {
+            let mut j = i - 2;
+            while bits[j] == 0 {
+                j -= 1;
+            }
+            bits[i] -= 2;
+            bits[i - 1] += 1;
+            bits[j + 1] += 2;
+            bits[j] -= 1;
+        }
+    }
+    // removes the reserved code point
+    let longest = (1..=16).rev().find(|&i| bits[i] > 0).unwrap_or(1);
+    bits[longest] -= 1;
+
+    let mut values = vec![];
+    for size in 1..=32 {
+        values.extend(
+            (0..CATEGORIES)
+                .filter(|&i| code_size[i] == size)
+                .map(|i| i as u8),
+        );
+    }
+    let mut result = [0u8; 17];
+    for (r, &b) in result.iter_mut().zip(bits.iter()) {
+        *r = b as u8;
+    }
+    (result, values)
+}
+
+struct BitWriter {
+    output: Vec<u8>,
+    acc: u32,
+    len: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, bits: u32, len: u32) {
+        if len == 0 {
+            return;
+        }
+        self.acc = (self.acc << len) | (bits & ((1 << len) - 1));
+        self.len += len;
+        while self.len >= 8 {
+            self.len -= 8;
+            let byte = (self.acc >> self.len) as u8;
+            self.output.push(byte);
+            if byte == 0xff {
+                self.output.push(0);
+            }
+        }
+        self.acc &= (1 << self.len) - 1;
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.len > 0 {
+            let pad = 8 - self.len;
+            self.write((1 << pad) - 1, pad);
+        }
+        self.output
+    }
+}
+
+/// Encodes interleaved samples into a lossless JPEG stream.
+///
+/// `width` is the frame width in pixels, so every row has `width * components` samples, which
+/// are below `1 << precision`.
+pub(crate) fn encode(
+    samples: &[u16],
+    width: usize,
+    height: usize,
+    components: usize,
+    precision: u8,
+) -> Vec<u8> {
+    let stride = width * components;
+    let diffs = (0..height)
+        .flat_map(|row| (0..stride).map(move |col| (row, col)))
+        .map(|(row, col)| {
+            let i = row * stride + col;
+            let prediction = match (row, col) {
+                (0, col) if col < components => 1 << (precision - 1),
+                (_, col) if col < components => samples[i - stride],
+                _ => samples[i - components],
+            };
+            categorize(samples[i].wrapping_sub(prediction) as i16)
+        })
+        .collect::<Vec<_>>();
+
+    let mut freq = [0u32; CATEGORIES];
+    for &(category, _) in diffs.iter() {
+        freq[category] += 1;
+    }
+    let (bits, values) = huffman_table(&freq);
+
+    let mut codes = [(0u32, 0u32); CATEGORIES];
+    let mut code = 0u32;
+    let mut k = 0;
+    for (size, &count) in bits.iter().enumerate().skip(1) {
+        for _ in 0..count {
+            codes[values[k] as usize] = (code, size as u32);
+            code += 1;
+            k += 1;
+        }
+        code <<= 1;
+    }
+
+    let mut output = vec![0xff, 0xd8];
+
+    output.extend([0xff, 0xc4]);
+    output.extend(((2 + 1 + 16 + values.len()) as u16).to_be_bytes());
+    output.push(0);
+    output.extend(&bits[1..]);
+    output.extend(&values);
+
+    output.extend([0xff, 0xc3]);
+    output.extend(((8 + 3 * components) as u16).to_be_bytes());
+    output.push(precision);
+    output.extend((height as u16).to_be_bytes());
+    output.extend((width as u16).to_be_bytes());
+    output.push(components as u8);
+    for id in 1..=components as u8 {
+        output.extend([id, 0x11, 0]);
+    }
+
+    output.extend([0xff, 0xda]);
+    output.extend(((6 + 2 * components) as u16).to_be_bytes());
+    output.push(components as u8);
+    for id in 1..=components as u8 {
+        output.extend([id, 0]);
+    }
+    output.extend([1, 0, 0]); // predictor 1, no point transform
+
+    let mut writer = BitWriter {
+        output,
+        acc: 0,
+        len: 0,
+    };
+    for (category, extra) in diffs {
+        let (code, size) = codes[category];
+        writer.write(code, size);
+        if category < 16 {
+            writer.write(extra as u32, category as u32);
+        }
+    }
+    let mut output = writer.finish();
+    output.extend([0xff, 0xd9]);
+    output
+}
@@ -0,0 +1,292 @@
+//! Encoders to write rendered images into common file formats.
+
+use super::*;
+use crate::decode::{DecodedImage, Orientation};
+use crate::metadata::RawMetadata;
+
+pub mod dng;
+pub mod exr;
+mod icc;
+mod ifd;
+pub mod jpeg;
+mod ljpeg;
+pub mod pfm;
+mod piz;
+pub mod png;
+pub mod tiff;
+
+pub use icc::icc_profile;
+
+/// Errors of image encoding.
+#[derive(Error, Debug)]
+pub enum EncodingError {
+    #[error("The image buffer size({0}) is invalid due to the width x height x channels = {1}.")]
+    InvalidImageSize(usize, usize),
+    #[error("The image has {0} channels, only 3(RGB) or 4(RGBA) are supported.")]
+    UnsupportedChannels(usize),
+    #[error("The tile size {0} is not a positive multiple of 16.")]
+    InvalidTileSize(u32),
+    #[error("The {0} format does not support {1} samples.")]
+    UnsupportedSampleFormat(&'static str, &'static str),
+    #[error("The output format of '{0}' is not supported.")]
+    UnsupportedFormat(String),
+    #[error("The {0} format is limited to {1} pixels per side.")]
+    ImageTooLarge(&'static str, usize),
+    #[error("The {0} of {1} does not fit in its TIFF tag.")]
+    ValueOutOfRange(&'static str, usize),
+    #[error("Cannot encode the JPEG stream: {0}")]
+    JpegError(#[from] jpeg_encoder::EncodingError),
+    #[error("Cannot compress the LZW stream: {0}")]
+    LzwError(#[from] weezl::LzwError),
+}
+
+/// Interleaved samples of an image.
+#[derive(Clone, Copy)]
+pub enum Pixels<'a> {
+    U8(&'a [u8]),
+    U16(&'a [u16]),
+    F32(&'a [f32]),
+}
+
+impl<'a> Pixels<'a> {
+    fn len(&self) -> usize {
+        match self {
+            Pixels::U8(x) => x.len(),
+            Pixels::U16(x) => x.len(),
+            Pixels::F32(x) => x.len(),
+        }
+    }
+}
+
+/// An interleaved RGB or RGBA image to be encoded.
+#[derive(Clone, Copy)]
+pub struct Image<'a> {
+    pub pixels: Pixels<'a>,
+    pub width: usize,
+    pub height: usize,
+    pub channels: usize,
+}
+
+impl<'a> Image<'a> {
+    pub fn new(
+        pixels: Pixels<'a>,
+        width: usize,
+        height: usize,
+        channels: usize,
+    ) -> Result<Self, EncodingError> {
+        if channels != 3 && channels != 4 {
+            return Err(EncodingError::UnsupportedChannels(channels));
+        }
+        if pixels.len() != width * height * channels {
+            return Err(EncodingError::InvalidImageSize(
+                pixels.len(),
+                width * height * channels,
+            ));
+        }
+        Ok(Image {
+            pixels,
+            width,
+            height,
+            channels,
+        })
+    }
+}
+
+/// The sample type of the encoded output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BitDepth {
+    Eight,
+    Sixteen,
+    Float,
+}
+
+/// Shooting parameters copied into the EXIF block of the output.
+#[derive(Clone, Debug, Default)]
+pub struct Exif {
+    /// In seconds.
+    pub exposure_time: Option<f64>,
+    pub f_number: Option<f64>,
+    pub iso: Option<u16>,
+    /// In millimeters.
+    pub focal_length: Option<f64>,
+    /// In the EXIF format of `YYYY:MM:DD HH:MM:SS`.
+    pub date_time_original: Option<String>,
+}
+
+impl Exif {
+    pub(crate) fn from_parsed_info(info: &quickexif::ParsedInfo) -> Self {
+        Exif {
+            exposure_time: info.f64("exposure_time").ok(),
+            f_number: info.f64("f_number").ok(),
+            iso: info.u16("iso").ok(),
+            focal_length: info.f64("focal_length").ok(),
+            date_time_original: info.str("date_time_original").ok().map(str::to_owned),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.exposure_time.is_none()
+            && self.f_number.is_none()
+            && self.iso.is_none()
+            && self.focal_length.is_none()
+            && self.date_time_original.is_none()
+    }
+}
+
+/// Converts a positive value to a rational, exposure times like `1/250` are kept exact.
+fn to_rational(v: f64) -> [u32; 2] {
+    if v > 0. && v < 1. && ((1. / v) - (1. / v).round()).abs() < 1e-3 {
+        [1, (1. / v).round() as u32]
+    } else {
+        [(v.max(0.) * 1000.).round() as u32, 1000]
+    }
+}
+
+/// Information written along with the pixels.
+#[derive(Clone, Debug)]
+pub struct Metadata {
+    pub make: Option<String>,
+    pub model: Option<String>,
+    /// The orientation in EXIF convention, `1` means horizontal.
+    pub orientation: u16,
+    pub exif: Exif,
+    pub color_space: ColorSpace,
+    pub transfer: TransferFunction,
+}
+
+impl Default for Metadata {
+    fn default() -> Self {
+        Metadata {
+            make: None,
+            model: None,
+            orientation: 1,
+            exif: Exif::default(),
+            color_space: ColorSpace::Srgb,
+            transfer: TransferFunction::Srgb,
+        }
+    }
+}
+
+fn exif_orientation(orientation: Orientation) -> u16 {
+    match orientation {
+        Orientation::Horizontal => 1,
+        Orientation::Rotate180 => 3,
+        Orientation::Rotate90 => 6,
+        Orientation::Rotate270 => 8,
+    }
+}
+
+impl Metadata {
+    /// Collects the camera info from a decoded image, the pixels are tagged with the given color.
+    pub fn from_decoded_image<T>(
+        decoded_image: &DecodedImage<T>,
+        color_space: ColorSpace,
+        transfer: TransferFunction,
+    ) -> Self {
+        let info = &decoded_image.parsed_info;
+        Metadata {
+            make: info.str("make").ok().map(str::to_owned),
+            model: info.str("model").ok().map(str::to_owned),
+            orientation: exif_orientation(decoded_image.orientation),
+            exif: Exif::default(),
+            color_space,
+            transfer,
+        }
+    }
+
+    /// Collects the camera info from the metadata of a raw file, the pixels are tagged with the given color.
+    pub fn from_raw_metadata(
+        metadata: &RawMetadata,
+        color_space: ColorSpace,
+        transfer: TransferFunction,
+    ) -> Self {
+        Metadata {
+            make: Some(metadata.make.clone()),
+            model: Some(metadata.model.clone()),
+            orientation: exif_orientation(metadata.orientation),
+            exif: Exif::default(),
+            color_space,
+            transfer,
+        }
+    }
+
+    pub(crate) fn software() -> String {
+        format!("quickraw {}", env!("CARGO_PKG_VERSION"))
+    }
+
+    /// Fills the camera tags of IFD0, the EXIF IFD is written first when there is one.
+    pub(crate) fn write_tags(&self, writer: &mut ifd::TiffWriter, ifd0: &mut ifd::Ifd) {
+        use ifd::Entry;
+
+        ifd0.insert(0x0112, Entry::Short(vec![self.orientation]));
+        ifd0.insert(0x0131, Entry::Ascii(Metadata::software()));
+        if let Some(make) = &self.make {
+            ifd0.insert(0x010f, Entry::Ascii(make.clone()));
+        }
+        if let Some(model) = &self.model {
+            ifd0.insert(0x0110, Entry::Ascii(model.clone()));
+        }
+
+        let exif = &self.exif;
+        if exif.is_empty() {
+            return;
+        }
+        let mut exif_ifd = ifd::Ifd::default();
+        exif_ifd.insert(0x9000, Entry::Undefined(b"0232".to_vec()));
+        if let Some(v) = exif.exposure_time {
+            exif_ifd.insert(0x829a, Entry::Rational(vec![to_rational(v)]));
+        }
+        if let Some(v) = exif.f_number {
+            exif_ifd.insert(0x829d, Entry::Rational(vec![to_rational(v)]));
+        }
+        if let Some(v) = exif.iso {
+            exif_ifd.insert(0x8827, Entry::Short(vec![v]));
+        }
+        if let Some(v) = &exif.date_time_original {
+            exif_ifd.insert(0x9003, Entry::Ascii(v.clone()));
+        }
+        if let Some(v) = exif.focal_length {
+            exif_ifd.insert(0x920a, Entry::Rational(vec![to_rational(v)]));
+        }
+        let offset = writer.write_ifd(&exif_ifd);
+        ifd0.insert(0x8769, Entry::Long(vec![offset]));
+    }
+}
+
+/// The supported output formats along with their encoding options.
+#[derive(Clone, Debug)]
+pub enum ImageFormat {
+    Tiff(tiff::TiffOptions),
+    Png(png::PngOptions),
+    Jpeg(jpeg::JpegOptions),
+    Exr(exr::ExrOptions),
+    Pfm,
+}
+
+impl ImageFormat {
+    /// Picks the format with default options according to the extension of a path.
+    pub fn from_path(path: &str) -> Result<Self, EncodingError> {
+        let extension = path
+            .rsplit_once('.')
+            .map(|(_, x)| x.to_ascii_lowercase())
+            .unwrap_or_default();
+        match extension.as_str() {
+            "tif" | "tiff" => Ok(ImageFormat::Tiff(Default::default())),
+            "png" => Ok(ImageFormat::Png(Default::default())),
+            "jpg" | "jpeg" => Ok(ImageFormat::Jpeg(Default::default())),
+            "exr" => Ok(ImageFormat::Exr(Default::default())),
+            "pfm" => Ok(ImageFormat::Pfm),
+            _ => Err(EncodingError::UnsupportedFormat(path.to_owned())),
+        }
+    }
+
+    pub fn encode(&self, image: &Image, metadata: &Metadata) -> Result<Vec<u8>, EncodingError> {
+        match self {
+            ImageFormat::Tiff(options) => tiff::encode(image, metadata, options),
+            ImageFormat::Png(options) => png::encode(image, metadata, options),
+            ImageFormat::Jpeg(options) => jpeg::encode(image, metadata, options),
+            ImageFormat::Exr(options) => exr::encode(image, metadata, options),
+            ImageFormat::Pfm => pfm::encode(image),
+        }
+    }
+}
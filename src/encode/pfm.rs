@@ -0,0 +1,25 @@
+//! A Portable FloatMap encoder, the simplest way to hand linear RGB to HDR tools.
+
+use super::*;
+
+/// Writes the RGB channels as little-endian floats, rows go from bottom to top.
+///
+/// PFM carries no color information, so the alpha channel and the metadata are dropped.
+pub fn encode(image: &Image) -> Result<Vec<u8>, EncodingError> {
+    let pixels = match image.pixels {
+        Pixels::F32(x) => x,
+        Pixels::U8(_) => return Err(EncodingError::UnsupportedSampleFormat("PFM", "u8")),
+        Pixels::U16(_) => return Err(EncodingError::UnsupportedSampleFormat("PFM", "u16")),
+    };
+    let (width, channels) = (image.width, image.channels);
+
+    // a negative scale marks little-endian data
+    let mut output = format!("PF\n{} {}\n-1.0\n", width, image.height).into_bytes();
+    output.reserve(width * image.height * 12);
+    for row in pixels.chunks_exact(width * channels).rev() {
+        for pixel in row.chunks_exact(channels) {
+            output.extend(pixel[..3].iter().flat_map(|x| x.to_le_bytes()));
+        }
+    }
+    Ok(output)
+}
@@ -0,0 +1,335 @@
+//! The PIZ compression of OpenEXR: a wavelet transform followed by a Huffman coder.
+
+const USHORT_RANGE: usize = 1 << 16;
+const BITMAP_SIZE: usize = USHORT_RANGE >> 3;
+
+const HUF_ENCSIZE: usize = (1 << 16) + 1;
+const SHORT_ZEROCODE_RUN: u64 = 59;
+const LONG_ZEROCODE_RUN: u64 = 63;
+const SHORTEST_LONG_RUN: usize = 2 + LONG_ZEROCODE_RUN as usize - SHORT_ZEROCODE_RUN as usize;
+const LONGEST_LONG_RUN: usize = 255 + SHORTEST_LONG_RUN;
+
+/// A channel of the chunk, `size` is the count of 16-bit words per sample.
+pub(crate) struct Channel {
+    pub(crate) size: usize,
+}
+
+/// Compresses a chunk of `lines` scanlines in the pixel layout of OpenEXR.
+pub(crate) fn compress(data: &[u8], width: usize, lines: usize, channels: &[Channel]) -> Vec<u8> {
+    // splits the scanlines into one plane per channel
+    let words = data
+        .chunks_exact(2)
+        .map(|x| u16::from_le_bytes([x[0], x[1]]));
+    let mut planes = channels
+        .iter()
+        .map(|c| Vec::with_capacity(width * lines * c.size))
+        .collect::<Vec<_>>();
+    let mut words = words.peekable();
+    while words.peek().is_some() {
+        for (plane, channel) in planes.iter_mut().zip(channels) {
+            plane.extend(words.by_ref().take(width * channel.size));
+        }
+    }
+
+    let mut bitmap = vec![0u8; BITMAP_SIZE];
+    for &v in planes.iter().flatten() {
+        bitmap[v as usize >> 3] |= 1 << (v & 7);
+    }
+    bitmap[0] &= !1; // zero is never stored
+    let non_zero = bitmap.iter().position(|&x| x != 0).map(|min| {
+        let max = bitmap.iter().rposition(|&x| x != 0).unwrap_or(min);
+        (min, max)
+    });
+
+    let mut lut = vec![0u16; USHORT_RANGE];
+    let mut k = 0u16;
+    for (i, v) in lut.iter_mut().enumerate() {
+        if i == 0 || bitmap[i >> 3] & (1 << (i & 7)) != 0 {
+            *v = k;
+            k = k.wrapping_add(1);
+        }
+    }
+    let max_value = k.wrapping_sub(1);
+
+    let mut output = vec![];
+    match non_zero {
+        Some((min, max)) => {
+            output.extend((min as u16).to_le_bytes());
+            output.extend((max as u16).to_le_bytes());
+            output.extend(&bitmap[min..=max]);
+        }
+        None => {
+            output.extend((BITMAP_SIZE as u16 - 1).to_le_bytes());
+            output.extend(0u16.to_le_bytes());
+        }
+    }
+
+    let mut words = vec![];
+    for (plane, channel) in planes.iter_mut().zip(channels) {
+        plane.iter_mut().for_each(|x| *x = lut[*x as usize]);
+        for offset in 0..channel.size {
+            wavelet_encode(
+                &mut plane[offset..],
+                (width, channel.size),
+                (lines, width * channel.size),
+                max_value,
+            );
+        }
+        words.extend_from_slice(plane);
+    }
+
+    let compressed = huffman_compress(&words);
+    output.extend((compressed.len() as u32).to_le_bytes());
+    output.extend(compressed);
+    output
+}
+
+fn wenc14(a: u16, b: u16) -> (u16, u16) {
+    let (a, b) = (a as i16 as i32, b as i16 as i32);
+    (((a + b) >> 1) as u16, (a - b) as u16)
+}
+
+fn wenc16(a: u16, b: u16) -> (u16, u16) {
+    const OFFSET: i32 = 1 << 15;
+    const MASK: i32 = 0xffff;
+    let ao = (a as i32 + OFFSET) & MASK;
+    let mut m = (ao + b as i32) >> 1;
+    let d = ao - b as i32;
+    if d < 0 {
+        m = (m + OFFSET) & MASK;
+    }
+    (m as u16, (d & MASK) as u16)
+}
+
+/// The 2D Haar wavelet transform in place, `(n, stride)` of x and y.
+fn wavelet_encode(
+    data: &mut [u16],
+    (nx, ox): (usize, usize),
+    (ny, oy): (usize, usize),
+    max_value: u16,
+) {
+    let wenc = if max_value < (1 << 14) {
+        wenc14
+    } else {
+        wenc16
+    };
+    let n = nx.min(ny);
+    let mut p = 1;
+    let mut p2 = 2;
+
+    while p2 <= n {
+        let (ox1, oy1) = (ox * p, oy * p);
+
+        let mut py = 0;
+        while py + p2 <= ny {
+            let y = py * oy;
+            let mut px = 0;
+            while px + p2 <= nx {
+                let i = y + px * ox;
+                let (p01, p10) = (i + ox1, i + oy1);
+                let p11 = p10 + ox1;
+                let (i00, i01) = wenc(data[i], data[p01]);
+                let (i10, i11) = wenc(data[p10], data[p11]);
+                (data[i], data[p10]) = wenc(i00, i10);
+                (data[p01], data[p11]) = wenc(i01, i11);
+                px += p2;
+            }
+            if nx & p != 0 {
+                let i = y + px * ox;
+                (data[i], data[i + oy1]) = wenc(data[i], data[i + oy1]);
+            }
+            py += p2;
+        }
+        if ny & p != 0 {
+            let y = py * oy;
+            let mut px = 0;
+            while px + p2 <= nx {
+                let i = y + px * ox;
+                (data[i], data[i + ox1]) = wenc(data[i], data[i + ox1]);
+                px += p2;
+            }
+        }
+
+        p = p2;
+        p2 <<= 1;
+    }
+}
+
+struct BitWriter {
+    output: Vec<u8>,
+    acc: u64,
+    len: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, len: u32, bits: u64) {
+        self.acc = (self.acc << len) | bits;
+        self.len += len;
+        while self.len >= 8 {
+            self.len -= 8;
+            self.output.push((self.acc >> self.len) as u8);
+        }
+    }
+
+    fn write_code(&mut self, code: u64) {
+        self.write((code & 63) as u32, code >> 6);
+    }
+
+    /// Returns the bytes and the count of valid bits.
+    fn finish(mut self) -> (Vec<u8>, usize) {
+        let bits = self.output.len() * 8 + self.len as usize;
+        if self.len > 0 {
+            self.output.push((self.acc << (8 - self.len)) as u8);
+        }
+        (self.output, bits)
+    }
+}
+
+/// Builds the canonical code table, each entry is `code << 6 | length`.
+fn build_code_table(freq: &mut [u64]) -> (usize, usize) {
+    use std::cmp::Reverse;
+    use std::collections::BinaryHeap;
+
+    let min = freq.iter().position(|&x| x > 0).unwrap_or(0);
+    let mut max = freq.iter().rposition(|&x| x > 0).unwrap_or(0);
+    // the pseudo symbol for the run length encoding
+    max += 1;
+    freq[max] = 1;
+
+    let mut link = (0..freq.len()).collect::<Vec<_>>();
+    let mut heap = (min..=max)
+        .filter(|&i| freq[i] > 0)
+        .map(|i| Reverse((freq[i], i)))
+        .collect::<BinaryHeap<_>>();
+    let mut lengths = vec![0u64; freq.len()];
+
+    while heap.len() > 1 {
+        let Reverse((f1, mm)) = heap.pop().unwrap();
+        let Reverse((f2, m)) = heap.pop().unwrap();
+        heap.push(Reverse((f1 + f2, m)));
+
+        let mut j = m;
+        loop {
+            lengths[j] += 1;
+            if link[j] == j {
+                link[j] = mm;
+                break;
+            }
+            j = link[j];
+        }
+        let mut j = mm;
+        loop {
+            lengths[j] += 1;
+            if link[j] == j {
+                break;
+            }
+            j = link[j];
+        }
+    }
+
+    let mut counts = [0u64; 59];
+    for &l in lengths.iter() {
+        counts[l as usize] += 1;
+    }
+    let mut code = 0;
+    for i in (1..=58).rev() {
+        let next = (code + counts[i]) >> 1;
+        counts[i] = code;
+        code = next;
+    }
+    for (f, &l) in freq.iter_mut().zip(lengths.iter()) {
+        *f = if l > 0 {
+            let code = counts[l as usize];
+            counts[l as usize] += 1;
+            l | (code << 6)
+        } else {
+            0
+        };
+    }
+    (min, max)
+}
+
+fn pack_code_table(table: &[u64], min: usize, max: usize) -> Vec<u8> {
+    let mut writer = BitWriter {
+        output: vec![],
+        acc: 0,
+        len: 0,
+    };
+    let mut i = min;
+    while i <= max {
+        let length = table[i] & 63;
+        if length == 0 {
+            let mut zero_run = 1;
+            while i < max && zero_run < LONGEST_LONG_RUN && table[i + 1] & 63 == 0 {
+                i += 1;
+                zero_run += 1;
+            }
+            if zero_run >= SHORTEST_LONG_RUN {
+                writer.write(6, LONG_ZEROCODE_RUN);
+                writer.write(8, (zero_run - SHORTEST_LONG_RUN) as u64);
+                i += 1;
+                continue;
+            } else if zero_run >= 2 {
+                writer.write(6, SHORT_ZEROCODE_RUN + zero_run as u64 - 2);
+                i += 1;
+                continue;
+            }
+        }
+        writer.write(6, length);
+        i += 1;
+    }
+    writer.finish().0
+}
+
+fn huffman_compress(words: &[u16]) -> Vec<u8> {
+    if words.is_empty() {
+        return vec![];
+    }
+    let mut table = vec![0u64; HUF_ENCSIZE];
+    for &v in words {
+        table[v as usize] += 1;
+    }
+    let (min, max) = build_code_table(&mut table);
+    let packed_table = pack_code_table(&table, min, max);
+
+    let mut writer = BitWriter {
+        output: vec![],
+        acc: 0,
+        len: 0,
+    };
+    let run_code = table[max];
+    let send = |writer: &mut BitWriter, code: u64, run: u64| {
+        if (code & 63) + (run_code & 63) + 8 < (code & 63) * run {
+            writer.write_code(code);
+            writer.write_code(run_code);
+            writer.write(8, run);
+        } else {
+            for _ in 0..=run {
+                writer.write_code(code);
+            }
+        }
+    };
+    let mut current = words[0];
+    let mut run = 0;
+    for &v in &words[1..] {
+        if v == current && run < 255 {
+            run += 1;
+        } else {
+            send(&mut writer, table[current as usize], run);
+            run = 0;
+        }
+        current = v;
+    }
+    send(&mut writer, table[current as usize], run);
+    let (data, bits) = writer.finish();
+
+    let mut output = vec![];
+    output.extend((min as u32).to_le_bytes());
+    output.extend((max as u32).to_le_bytes());
+    output.extend((packed_table.len() as u32).to_le_bytes());
+    output.extend((bits as u32).to_le_bytes());
+    output.extend(0u32.to_le_bytes());
+    output.extend(packed_table);
+    output.extend(data);
+    output
+}
@@ -0,0 +1,163 @@
+//! A PNG encoder for 8/16-bit RGB(A) images with color and EXIF chunks.
+
+use super::ifd::*;
+use super::*;
+use once_cell::sync::Lazy;
+
+#[derive(Clone, Debug)]
+pub struct PngOptions {
+    /// The zlib level from `0` to `10`.
+    pub compression_level: u8,
+}
+
+impl Default for PngOptions {
+    fn default() -> Self {
+        PngOptions {
+            compression_level: 6,
+        }
+    }
+}
+
+const SIGNATURE: [u8; 8] = [0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a];
+
+static CRC_TABLE: Lazy<[u32; 256]> = Lazy::new(|| {
+    let mut table = [0u32; 256];
+    for (n, v) in table.iter_mut().enumerate() {
+        let mut c = n as u32;
+        for _ in 0..8 {
+            c = if c & 1 == 1 { 0xedb88320 ^ (c >> 1) } else { c >> 1 };
+        }
+        *v = c;
+    }
+    table
+});
+
+fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0u32, |c, &b| {
+        CRC_TABLE[((c ^ b as u32) & 0xff) as usize] ^ (c >> 8)
+    })
+}
+
+fn write_chunk(output: &mut Vec<u8>, name: &[u8; 4], body: &[u8]) {
+    output.extend((body.len() as u32).to_be_bytes());
+    let start = output.len();
+    output.extend(name);
+    output.extend(body);
+    let crc = crc32(&output[start..]);
+    output.extend(crc.to_be_bytes());
+}
+
+/// cICP is preferred over iCCP where an ICC curve cannot describe the signal well.
+fn cicp(metadata: &Metadata) -> Option<[u8; 4]> {
+    if !(metadata.transfer.is_hdr() || metadata.color_space == ColorSpace::Rec2020) {
+        return None;
+    }
+    let primaries = metadata.color_space.cicp_code()?;
+    let transfer = metadata.transfer.cicp_code()?;
+    Some([primaries, transfer, 0, 1])
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = ((p - a as i16).abs(), (p - b as i16).abs(), (p - c as i16).abs());
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+/// Filters every row with the type of the smallest sum of absolute differences.
+fn filter_rows(raw: &[u8], row_bytes: usize, bpp: usize) -> Vec<u8> {
+    let zero_row = vec![0u8; row_bytes];
+    let mut result = Vec::with_capacity(raw.len() + raw.len() / row_bytes.max(1));
+    let mut candidate = vec![0u8; row_bytes];
+    let mut best = vec![0u8; row_bytes];
+
+    for (y, row) in raw.chunks_exact(row_bytes).enumerate() {
+        let prev = if y == 0 {
+            &zero_row[..]
+        } else {
+            &raw[(y - 1) * row_bytes..y * row_bytes]
+        };
+        let mut best_type = 0u8;
+        let mut best_score = u64::MAX;
+        for filter_type in 0..5u8 {
+            for i in 0..row_bytes {
+                let a = if i >= bpp { row[i - bpp] } else { 0 };
+                let b = prev[i];
+                let c = if i >= bpp { prev[i - bpp] } else { 0 };
+                let predicted = match filter_type {
+                    0 => 0,
+                    1 => a,
+                    2 => b,
+                    3 => ((a as u16 + b as u16) / 2) as u8,
+                    _ => paeth(a, b, c),
+                };
+                candidate[i] = row[i].wrapping_sub(predicted);
+            }
+            let score = candidate.iter().map(|&x| (x as i8).unsigned_abs() as u64).sum();
+            if score < best_score {
+                best_score = score;
+                best_type = filter_type;
+                std::mem::swap(&mut best, &mut candidate);
+            }
+        }
+        result.push(best_type);
+        result.extend_from_slice(&best);
+    }
+    result
+}
+
+fn exif_chunk(metadata: &Metadata) -> Vec<u8> {
+    let mut writer = TiffWriter::new();
+    let mut ifd0 = Ifd::default();
+    metadata.write_tags(&mut writer, &mut ifd0);
+    let offset = writer.write_ifd(&ifd0);
+    writer.finish(offset)
+}
+
+/// Encodes the image into a PNG file.
+///
+/// A cICP chunk is written for Rec. 2020 and HDR transfer functions, otherwise an iCCP chunk
+/// is embedded when the color space has an ICC profile.
+pub fn encode(
+    image: &Image,
+    metadata: &Metadata,
+    options: &PngOptions,
+) -> Result<Vec<u8>, EncodingError> {
+    let (bit_depth, raw) = match image.pixels {
+        Pixels::U8(samples) => (8u8, samples.to_vec()),
+        Pixels::U16(samples) => (16, samples.iter().flat_map(|x| x.to_be_bytes()).collect()),
+        Pixels::F32(_) => return Err(EncodingError::UnsupportedSampleFormat("PNG", "f32")),
+    };
+    let bpp = image.channels * bit_depth as usize / 8;
+    let color_type = if image.channels == 4 { 6u8 } else { 2 };
+
+    let mut output = SIGNATURE.to_vec();
+
+    let mut header = vec![];
+    header.extend((image.width as u32).to_be_bytes());
+    header.extend((image.height as u32).to_be_bytes());
+    header.extend([bit_depth, color_type, 0, 0, 0]);
+    write_chunk(&mut output, b"IHDR", &header);
+
+    if let Some(cicp) = cicp(metadata) {
+        write_chunk(&mut output, b"cICP", &cicp);
+    } else if let Some(profile) = icc_profile(&metadata.color_space, metadata.transfer) {
+        let mut body = b"ICC profile\0\0".to_vec();
+        body.extend(miniz_oxide::deflate::compress_to_vec_zlib(&profile, 9));
+        write_chunk(&mut output, b"iCCP", &body);
+    }
+
+    write_chunk(&mut output, b"eXIf", &exif_chunk(metadata));
+
+    let filtered = filter_rows(&raw, image.width * bpp, bpp);
+    let compressed = miniz_oxide::deflate::compress_to_vec_zlib(&filtered, options.compression_level);
+    write_chunk(&mut output, b"IDAT", &compressed);
+    write_chunk(&mut output, b"IEND", &[]);
+
+    Ok(output)
+}
@@ -0,0 +1,198 @@
+//! A baseline TIFF encoder for 8/16-bit integer and 32-bit float RGB(A) images.
+
+use super::ifd::*;
+use super::*;
+
+/// Compression schemes for TIFF output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TiffCompression {
+    None,
+    Lzw,
+    Deflate,
+}
+
+/// The way image data is split inside the file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TiffLayout {
+    Strips,
+    /// Square tiles of the given size, which must be a multiple of 16.
+    Tiles(u32),
+}
+
+#[derive(Clone, Debug)]
+pub struct TiffOptions {
+    pub compression: TiffCompression,
+    pub layout: TiffLayout,
+}
+
+impl Default for TiffOptions {
+    fn default() -> Self {
+        TiffOptions {
+            compression: TiffCompression::Deflate,
+            layout: TiffLayout::Strips,
+        }
+    }
+}
+
+const STRIP_SIZE: usize = 1 << 18;
+
+impl TiffCompression {
+    fn code(&self) -> u16 {
+        match self {
+            TiffCompression::None => 1,
+            TiffCompression::Lzw => 5,
+            TiffCompression::Deflate => 8,
+        }
+    }
+
+    fn compress(&self, data: Vec<u8>) -> Result<Vec<u8>, EncodingError> {
+        let result = match self {
+            TiffCompression::None => data,
+            TiffCompression::Lzw => {
+                weezl::encode::Encoder::with_tiff_size_switch(weezl::BitOrder::Msb, 8).encode(&data)?
+            }
+            TiffCompression::Deflate => miniz_oxide::deflate::compress_to_vec_zlib(&data, 6),
+        };
+        Ok(result)
+    }
+}
+
+/// Copies a block of rows into little-endian bytes, applying the horizontal predictor if needed.
+fn block_to_bytes(
+    image: &Image,
+    (x, y, block_width, block_height): (usize, usize, usize, usize),
+    use_predictor: bool,
+) -> Vec<u8> {
+    let c = image.channels;
+    let row_range = |row: usize| {
+        let start = ((y + row) * image.width + x) * c;
+        start..start + block_width.min(image.width.saturating_sub(x)) * c
+    };
+
+    macro_rules! collect {
+        ($samples:expr, $t:ty) => {{
+            let mut block = vec![<$t>::default(); block_width * block_height * c];
+            for (row, out) in block.chunks_exact_mut(block_width * c).enumerate() {
+                if y + row >= image.height {
+                    break;
+                }
+                let src = &$samples[row_range(row)];
+                out[..src.len()].copy_from_slice(src);
+            }
+            block
+        }};
+    }
+    macro_rules! predict {
+        ($block:expr) => {
+            if use_predictor {
+                for row in $block.chunks_exact_mut(block_width * c) {
+                    for i in (c..row.len()).rev() {
+                        row[i] = row[i].wrapping_sub(row[i - c]);
+                    }
+                }
+            }
+        };
+    }
+
+    match image.pixels {
+        Pixels::U8(samples) => {
+            let mut block = collect!(samples, u8);
+            predict!(block);
+            block
+        }
+        Pixels::U16(samples) => {
+            let mut block = collect!(samples, u16);
+            predict!(block);
+            block.iter().flat_map(|x| x.to_le_bytes()).collect()
+        }
+        Pixels::F32(samples) => collect!(samples, f32)
+            .iter()
+            .flat_map(|x| x.to_le_bytes())
+            .collect(),
+    }
+}
+
+/// Encodes the image into a TIFF file, the ICC profile is embedded when the color space has one.
+pub fn encode(
+    image: &Image,
+    metadata: &Metadata,
+    options: &TiffOptions,
+) -> Result<Vec<u8>, EncodingError> {
+    let (bits, sample_format) = match image.pixels {
+        Pixels::U8(_) => (8u16, 1u16),
+        Pixels::U16(_) => (16, 1),
+        Pixels::F32(_) => (32, 3),
+    };
+    let use_predictor = options.compression != TiffCompression::None && sample_format == 1;
+    let bytes_per_pixel = image.channels * bits as usize / 8;
+
+    let (block_width, block_height) = match options.layout {
+        TiffLayout::Strips => {
+            let rows = (STRIP_SIZE / (image.width * bytes_per_pixel).max(1)).max(1);
+            (image.width, rows.min(image.height.max(1)))
+        }
+        TiffLayout::Tiles(size) => {
+            if size == 0 || !size.is_multiple_of(16) {
+                return Err(EncodingError::InvalidTileSize(size));
+            }
+            (size as usize, size as usize)
+        }
+    };
+
+    let mut writer = TiffWriter::new();
+    let mut offsets = vec![];
+    let mut byte_counts = vec![];
+    for y in (0..image.height).step_by(block_height) {
+        for x in (0..image.width).step_by(block_width) {
+            let rows = match options.layout {
+                TiffLayout::Strips => block_height.min(image.height - y),
+                TiffLayout::Tiles(_) => block_height,
+            };
+            let block = block_to_bytes(image, (x, y, block_width, rows), use_predictor);
+            let block = options.compression.compress(block)?;
+            offsets.push(writer.write_data(&block));
+            byte_counts.push(block.len() as u32);
+        }
+    }
+
+    let mut ifd = Ifd::default();
+    ifd.insert(0x00fe, Entry::Long(vec![0]));
+    ifd.insert(0x0100, Entry::Long(vec![image.width as u32]));
+    ifd.insert(0x0101, Entry::Long(vec![image.height as u32]));
+    ifd.insert(0x0102, Entry::Short(vec![bits; image.channels]));
+    ifd.insert(0x0103, Entry::Short(vec![options.compression.code()]));
+    ifd.insert(0x0106, Entry::Short(vec![2])); // RGB
+    ifd.insert(0x0115, Entry::Short(vec![image.channels as u16]));
+    ifd.insert(0x011a, Entry::Rational(vec![[72, 1]]));
+    ifd.insert(0x011b, Entry::Rational(vec![[72, 1]]));
+    ifd.insert(0x011c, Entry::Short(vec![1])); // chunky
+    ifd.insert(0x0128, Entry::Short(vec![2])); // inch
+    ifd.insert(0x0153, Entry::Short(vec![sample_format; image.channels]));
+    if use_predictor {
+        ifd.insert(0x013d, Entry::Short(vec![2]));
+    }
+    if image.channels == 4 {
+        ifd.insert(0x0152, Entry::Short(vec![2])); // unassociated alpha
+    }
+    metadata.write_tags(&mut writer, &mut ifd);
+    if let Some(profile) = icc_profile(&metadata.color_space, metadata.transfer) {
+        ifd.insert(0x8773, Entry::Undefined(profile));
+    }
+
+    match options.layout {
+        TiffLayout::Strips => {
+            ifd.insert(0x0111, Entry::Long(offsets));
+            ifd.insert(0x0116, Entry::Long(vec![block_height as u32]));
+            ifd.insert(0x0117, Entry::Long(byte_counts));
+        }
+        TiffLayout::Tiles(size) => {
+            ifd.insert(0x0142, Entry::Long(vec![size]));
+            ifd.insert(0x0143, Entry::Long(vec![size]));
+            ifd.insert(0x0144, Entry::Long(offsets));
+            ifd.insert(0x0145, Entry::Long(byte_counts));
+        }
+    }
+
+    let ifd_offset = writer.write_ifd(&ifd);
+    Ok(writer.finish(ifd_offset))
+}
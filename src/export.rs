@@ -3,26 +3,76 @@ use crate::{decode::CFAPattern, utility::ArrayMulNum};
 use super::*;
 use pass::*;
 
-pub struct Options<'a> {
-    gamma: f32,
-    color_space: &'a [f32; 9],
-    no_demosaicing: bool,
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Options {
+    gamma: TransferFunction,
+    color_space: ColorSpace,
+    demosaicing: DemosaicingMethod,
+    white_balance: Option<[f32; 3]>,
+    crop: bool,
+    rotate: bool,
 }
-impl<'a> Options<'a> {
-    pub fn new(gamma: f32, color_space: &'a [f32; 9], no_demosaicing: bool) -> Self {
+impl Options {
+    /// `gamma` accepts either a plain exponent like `data::GAMMA_SRGB` or a `TransferFunction` like `TransferFunction::Srgb`.
+    /// `color_space` accepts either a matrix like `&data::XYZ2SRGB` or a `ColorSpace`.
+    /// `no_demosaicing` picks `DemosaicingMethod::None` over `DemosaicingMethod::Linear`.
+    pub fn new(
+        gamma: impl Into<TransferFunction>,
+        color_space: impl Into<ColorSpace>,
+        no_demosaicing: bool,
+    ) -> Self {
         Options {
-            gamma,
-            color_space,
-            no_demosaicing,
+            gamma: gamma.into(),
+            color_space: color_space.into(),
+            demosaicing: if no_demosaicing {
+                DemosaicingMethod::None
+            } else {
+                DemosaicingMethod::Linear
+            },
+            white_balance: None,
+            crop: false,
+            rotate: false,
         }
     }
+
+    /// Replaces the demosaicing method picked by `no_demosaicing`.
+    pub fn with_demosaicing(mut self, demosaicing: DemosaicingMethod) -> Self {
+        self.demosaicing = demosaicing;
+        self
+    }
+
+    /// Replaces the camera white balance with `[r, g, b]` multipliers, green is usually `1.0`.
+    pub fn with_white_balance(mut self, white_balance: [f32; 3]) -> Self {
+        self.white_balance = Some(white_balance);
+        self
+    }
+
+    /// Crops encoded images to the default crop area of the raw file.
+    pub fn with_crop(mut self, crop: bool) -> Self {
+        self.crop = crop;
+        self
+    }
+
+    /// Rotates the pixels of encoded images upright, the written orientation becomes horizontal.
+    pub fn with_rotation(mut self, rotate: bool) -> Self {
+        self.rotate = rotate;
+        self
+    }
+
+    /// The scene-linear path for EXR and PFM output, no transfer function is applied.
+    ///
+    /// Use `&data::XYZ2RAW` to keep the camera-independent normalized XYZ or `ColorSpace::AcesAp0`
+    /// for ACES plates.
+    pub fn linear(color_space: impl Into<ColorSpace>) -> Self {
+        Options::new(TransferFunction::Linear, color_space, false)
+    }
 }
 
 pub fn load_image_from_file(
     path: &str,
     options: Options,
 ) -> Result<(Vec<u16>, usize, usize), RawFileReadingError> {
-    let buffer = decode::get_buffer_from_file(path)?;
+    let buffer = RawBuffer::from_file(path)?;
     load_image_from_buffer(buffer, options)
 }
 
@@ -31,12 +81,12 @@ pub fn load_origin_image_from_file(
     path: &str,
     options: Options,
 ) -> Result<(Vec<u16>, usize, usize), RawFileReadingError> {
-    let buffer = decode::get_buffer_from_file(path)?;
+    let buffer = RawBuffer::from_file(path)?;
     load_origin_image_from_buffer(buffer, options)
 }
 
 pub fn load_origin_image_from_buffer(
-    buffer: Vec<u8>,
+    buffer: impl AsRef<[u8]>,
     options: Options,
 ) -> Result<(Vec<u16>, usize, usize), RawFileReadingError> {
     let decoded_image = decode::decode_buffer(buffer)?;
@@ -45,33 +95,35 @@ pub fn load_origin_image_from_buffer(
     let width = decoded_image.width;
     let height = decoded_image.height;
 
-    let iter = image.iter().copied();
-    let data = pass::iters_to_vec! (
-        iter
-            ..enumerate()
-            [(options.no_demosaicing, decoded_image.cfa_pattern)] {
-                (true, _) => .none(),
-                (false, CFAPattern::RGGB) => .linear_rggb(&image, width, height),
-                (false, CFAPattern::GRBG) => .linear_grbg(&image, width, height),
-                (false, CFAPattern::GBRG) => .linear_gbrg(&image, width, height),
-                (false, CFAPattern::BGGR) => .linear_bggr(&image, width, height),
-                (false, CFAPattern::XTrans0) => .linear_xtrans0(&image, width, height),
-                (false, CFAPattern::XTrans1) => .linear_xtrans1(&image, width, height)
-            }
-            .u16rgb_to_u16rgba()
-            ..flatten()
-    );
+    let data = parallel::map_ranges(image.len(), |pixels| {
+        let iter = pixels.clone().zip(image[pixels].iter().copied());
+        pass::iters_to_vec! (
+            iter
+                [(options.demosaicing, decoded_image.cfa_pattern)] {
+                    (DemosaicingMethod::None, _) => .none(),
+                    (DemosaicingMethod::SuperPixel, cfa_pattern) => .super_pixel(&image, width, height, cfa_pattern),
+                    (DemosaicingMethod::Linear, CFAPattern::RGGB) => .linear_rggb(&image, width, height),
+                    (DemosaicingMethod::Linear, CFAPattern::GRBG) => .linear_grbg(&image, width, height),
+                    (DemosaicingMethod::Linear, CFAPattern::GBRG) => .linear_gbrg(&image, width, height),
+                    (DemosaicingMethod::Linear, CFAPattern::BGGR) => .linear_bggr(&image, width, height),
+                    (DemosaicingMethod::Linear, CFAPattern::XTrans0) => .linear_xtrans0(&image, width, height),
+                    (DemosaicingMethod::Linear, CFAPattern::XTrans1) => .linear_xtrans1(&image, width, height)
+                }
+                .u16rgb_to_u16rgba()
+                ..flatten()
+        )
+    });
 
     Ok((data, width, height))
 }
 
 pub fn load_image_from_buffer(
-    buffer: Vec<u8>,
+    buffer: impl AsRef<[u8]>,
     options: Options,
 ) -> Result<(Vec<u16>, usize, usize), RawFileReadingError> {
     let decoded_image = decode::decode_buffer(buffer)?;
 
-    let color_matrix = utility::matrix3_mul(options.color_space, &decoded_image.cam_matrix);
+    let color_matrix = utility::matrix3_mul(&options.color_space.matrix(), &decoded_image.cam_matrix);
     let color_matrix = color_matrix.mul(1 << BIT_SHIFT);
 
     let white_balance = decoded_image
@@ -88,25 +140,524 @@ pub fn load_image_from_buffer(
         return Ok((image, width, height));
     }
 
-    let iter = image.iter().copied();
-    let data = pass::iters_to_vec! (
-        iter
-            ..enumerate()
-            [(options.no_demosaicing, decoded_image.cfa_pattern)] {
-                (true, _) => .none(),
-                (false, CFAPattern::RGGB) => .linear_rggb(&image, width, height),
-                (false, CFAPattern::GRBG) => .linear_grbg(&image, width, height),
-                (false, CFAPattern::GBRG) => .linear_gbrg(&image, width, height),
-                (false, CFAPattern::BGGR) => .linear_bggr(&image, width, height),
-                (false, CFAPattern::XTrans0) => .linear_xtrans0(&image, width, height),
-                (false, CFAPattern::XTrans1) => .linear_xtrans1(&image, width, height)
-            }
-            .gamma_correct(&gamma_lut)
-            .u16rgb_to_i32rgb()
-            .white_balance_fix(&white_balance)
-            .color_convert(&color_matrix)
-            ..flatten()
-    );
+    let data = parallel::map_ranges(image.len(), |pixels| {
+        let iter = pixels.clone().zip(image[pixels].iter().copied());
+        pass::iters_to_vec! (
+            iter
+                [(options.demosaicing, decoded_image.cfa_pattern)] {
+                    (DemosaicingMethod::None, _) => .none(),
+                    (DemosaicingMethod::SuperPixel, cfa_pattern) => .super_pixel(&image, width, height, cfa_pattern),
+                    (DemosaicingMethod::Linear, CFAPattern::RGGB) => .linear_rggb(&image, width, height),
+                    (DemosaicingMethod::Linear, CFAPattern::GRBG) => .linear_grbg(&image, width, height),
+                    (DemosaicingMethod::Linear, CFAPattern::GBRG) => .linear_gbrg(&image, width, height),
+                    (DemosaicingMethod::Linear, CFAPattern::BGGR) => .linear_bggr(&image, width, height),
+                    (DemosaicingMethod::Linear, CFAPattern::XTrans0) => .linear_xtrans0(&image, width, height),
+                    (DemosaicingMethod::Linear, CFAPattern::XTrans1) => .linear_xtrans1(&image, width, height)
+                }
+                .gamma_correct(&gamma_lut)
+                .white_balance_and_convert(&white_balance, &color_matrix)
+                ..flatten()
+        )
+    });
+
+    Ok((data, width, height))
+}
+
+/// Loads a linear, scene-referred `f32` RGB image from a file.
+///
+/// Unlike `load_image_from_file`, values are neither clipped nor quantized, so they may
+/// exceed `1.0` or go negative for out-of-gamut colors.
+pub fn load_float_image_from_file(
+    path: &str,
+    options: Options,
+) -> Result<(Vec<f32>, usize, usize), RawFileReadingError> {
+    let buffer = RawBuffer::from_file(path)?;
+    load_float_image_from_buffer(buffer, options)
+}
+
+/// Loads a linear, scene-referred `f32` RGB image from a buffer.
+///
+/// The sensor values are converted to `f32` before the black level is subtracted, then the pipeline
+/// runs demosaicing, white balance, color conversion and finally the tone stage, which is skipped
+/// when the transfer function is `TransferFunction::Linear`.
+pub fn load_float_image_from_buffer(
+    buffer: impl AsRef<[u8]>,
+    options: Options,
+) -> Result<(Vec<f32>, usize, usize), RawFileReadingError> {
+    let decoded_image = decode::decode_float(buffer.as_ref())?;
+    let data = render_float_image(&decoded_image, &options);
+    Ok((data, decoded_image.width, decoded_image.height))
+}
+
+/// Renders a preview of a raw file at a fraction of the sensor resolution.
+pub fn load_preview_from_file(
+    path: &str,
+    scale: PreviewScale,
+    options: Options,
+) -> Result<(Vec<f32>, usize, usize), RawFileReadingError> {
+    let buffer = RawBuffer::from_file(path)?;
+    load_preview_from_buffer(buffer, scale, options)
+}
+
+/// Renders a preview of a raw buffer at a fraction of the sensor resolution, for culling.
+///
+/// Every block of the CFA is binned into an RGB pixel instead of being demosaiced, and the rows
+/// out of the binned ones are skipped in the bit stream of uncompressed formats.
+/// The crop and the rotation of `options` are applied as in `encode_image_from_buffer`.
+pub fn load_preview_from_buffer(
+    buffer: impl AsRef<[u8]>,
+    scale: PreviewScale,
+    options: Options,
+) -> Result<(Vec<f32>, usize, usize), RawFileReadingError> {
+    let (preview, is_cfa) = decode::decode_preview(buffer.as_ref(), scale)?;
+    let (color_matrix, white_balance) = render_params(&preview, &options);
+    let gamma = options.gamma;
+
+    let data = parallel::map_ranges(preview.width * preview.height, |pixels| {
+        let iter = preview.image[pixels.start * 3..pixels.end * 3].chunks_exact(3).map(|x| [x[0], x[1], x[2]]);
+        pass::iters_to_vec! (
+            iter
+                [is_cfa] {
+                    true => .white_balance_and_convert_f32(&white_balance, &color_matrix),
+                    false => .u16rgb_to_f32rgb()
+                }
+                [.gamma_correct_f32(gamma) gamma != TransferFunction::Linear]
+                ..flatten()
+        )
+    });
+    let (mut width, mut height) = (preview.width, preview.height);
 
+    let data = match (&preview.crop, options.crop) {
+        (Some(crop), true) => {
+            let data = crop_image(&data, width, height, crop);
+            (width, height) = (crop.width as usize, crop.height as usize);
+            data
+        }
+        _ => data,
+    };
+    let data = if options.rotate {
+        let (data, rotated_width, rotated_height) =
+            rotate_image(&data, width, height, &preview.orientation);
+        (width, height) = (rotated_width, rotated_height);
+        data
+    } else {
+        data
+    };
     Ok((data, width, height))
 }
+
+/// Renders a rectangle of a raw file, see `load_region_from_buffer`.
+pub fn load_region_from_file(
+    path: &str,
+    region: Crop,
+    options: Options,
+) -> Result<(Vec<f32>, usize, usize), RawFileReadingError> {
+    let buffer = RawBuffer::from_file(path)?;
+    load_region_from_buffer(buffer, region, options)
+}
+
+/// Renders only a rectangle of a raw buffer, like the visible part of a zoomed-in viewer.
+///
+/// The region is in the coordinates of the sensor data and is clamped into the image, its pixels
+/// are the same as in a full render. Tiled DNGs and uncompressed formats only decode the tiles and
+/// rows intersecting the region along with the border needed by demosaicing.
+/// The crop and the rotation of `options` are not applied.
+pub fn load_region_from_buffer(
+    buffer: impl AsRef<[u8]>,
+    region: Crop,
+    options: Options,
+) -> Result<(Vec<f32>, usize, usize), RawFileReadingError> {
+    // the pixels around the region read by demosaicing
+    const MARGIN: usize = 2;
+    // the cut starts at a multiple of both Bayer and X-Trans patterns to keep the CFA pattern
+    const PERIOD: usize = 6;
+
+    let (x, y) = (region.x as usize, region.y as usize);
+    let (x_end, y_end) = (x + region.width as usize, y + region.height as usize);
+    let columns = x.saturating_sub(MARGIN) / PERIOD * PERIOD..x_end + MARGIN;
+    let rows = y.saturating_sub(MARGIN) / PERIOD * PERIOD..y_end + MARGIN;
+
+    let decoded_image = decode::decode_region(buffer.as_ref(), columns.clone(), rows.clone())?;
+    let (width, height) = (decoded_image.width, decoded_image.height);
+    let (x_end, y_end) = (x_end.min(width), y_end.min(height));
+    if x >= x_end || y >= y_end {
+        return Err(RawFileReadingError::RegionIsOutOfImage);
+    }
+
+    let columns = columns.start..columns.end.min(width);
+    let rows = rows.start..rows.end.min(height);
+    let samples_per_pixel = if decoded_image.image.len() == width * height * 3 { 3 } else { 1 };
+    let image = decoded_image
+        .image
+        .chunks_exact(width * samples_per_pixel)
+        .skip(rows.start)
+        .take(rows.len())
+        .flat_map(|row| &row[columns.start * samples_per_pixel..columns.end * samples_per_pixel])
+        .copied()
+        .collect();
+    let cut_image = decode::DecodedImage {
+        image,
+        width: columns.len(),
+        height: rows.len(),
+        crop: None,
+        ..decoded_image
+    };
+
+    let data = render_float_image(&cut_image, &options);
+    let region = Crop {
+        x: (x - columns.start) as u32,
+        y: (y - rows.start) as u32,
+        width: (x_end - x) as u32,
+        height: (y_end - y) as u32,
+    };
+    let data = crop_image(&data, cut_image.width, cut_image.height, &region);
+    Ok((data, region.width as usize, region.height as usize))
+}
+
+/// The color matrix into the output space and the white balance of a render.
+fn render_params<T>(decoded_image: &decode::DecodedImage<T>, options: &Options) -> ([f32; 9], [f32; 3]) {
+    let color_matrix = utility::matrix3_mul(&options.color_space.matrix(), &decoded_image.cam_matrix);
+    let white_balance = options.white_balance.unwrap_or_else(|| {
+        let [r, g, b] = decoded_image.white_balance;
+        [r as f32 / g as f32, 1f32, b as f32 / g as f32]
+    });
+    (color_matrix, white_balance)
+}
+
+/// Renders the normalized samples of `decode::decode_float`, demosaiced in `f32`.
+fn render_float_image(decoded_image: &decode::DecodedImage<f32>, options: &Options) -> Vec<f32> {
+    let (color_matrix, white_balance) = render_params(decoded_image, options);
+    let gamma = options.gamma;
+
+    let image = &decoded_image.image;
+    let width = decoded_image.width;
+    let height = decoded_image.height;
+
+    if image.len() == width * height * 3 {
+        return parallel::map_ranges(width * height, |pixels| {
+            let iter = image[pixels.start * 3..pixels.end * 3].chunks_exact(3).map(|x| [x[0], x[1], x[2]]);
+            pass::iters_to_vec! (
+                iter
+                    [.gamma_correct_f32(gamma) gamma != TransferFunction::Linear]
+                    ..flatten()
+            )
+        });
+    }
+
+    // the demosaicing reads the neighbours from the whole image, so any range of pixels can be rendered apart
+    parallel::map_ranges(image.len(), |pixels| {
+        let iter = pixels.clone().zip(image[pixels].iter().copied());
+        pass::iters_to_vec! (
+            iter
+                [(options.demosaicing, decoded_image.cfa_pattern)] {
+                    (DemosaicingMethod::None, _) => .none(),
+                    (DemosaicingMethod::SuperPixel, cfa_pattern) => .super_pixel(image, width, height, cfa_pattern),
+                    (DemosaicingMethod::Linear, CFAPattern::RGGB) => .linear_rggb(image, width, height),
+                    (DemosaicingMethod::Linear, CFAPattern::GRBG) => .linear_grbg(image, width, height),
+                    (DemosaicingMethod::Linear, CFAPattern::GBRG) => .linear_gbrg(image, width, height),
+                    (DemosaicingMethod::Linear, CFAPattern::BGGR) => .linear_bggr(image, width, height),
+                    (DemosaicingMethod::Linear, CFAPattern::XTrans0) => .linear_xtrans0(image, width, height),
+                    (DemosaicingMethod::Linear, CFAPattern::XTrans1) => .linear_xtrans1(image, width, height)
+                }
+                .white_balance_fix_f32(&white_balance)
+                .color_convert_f32(&color_matrix)
+                [.gamma_correct_f32(gamma) gamma != TransferFunction::Linear]
+                ..flatten()
+        )
+    })
+}
+
+/// Renders a raw file and writes it to the path of an `OutputType::Image8`, `Image16` or `ImageF32`.
+///
+/// The file format is picked by the extension of the output path.
+pub fn export_image_from_file(
+    path: &str,
+    options: Options,
+    output_type: &OutputType,
+) -> Result<(), RawFileReadingError> {
+    let buffer = RawBuffer::from_file(path)?;
+    export_image_from_buffer(buffer, options, output_type)
+}
+
+/// Renders a raw buffer and writes it to the path of an `OutputType::Image8`, `Image16` or `ImageF32`.
+pub fn export_image_from_buffer(
+    buffer: impl AsRef<[u8]>,
+    options: Options,
+    output_type: &OutputType,
+) -> Result<(), RawFileReadingError> {
+    let (path, depth) = match output_type {
+        OutputType::Image8(path) => (path, encode::BitDepth::Eight),
+        OutputType::Image16(path) => (path, encode::BitDepth::Sixteen),
+        OutputType::ImageF32(path) => (path, encode::BitDepth::Float),
+        OutputType::Raw8 | OutputType::Raw16 => return Err(RawFileReadingError::OutputTypeIsNotImage),
+    };
+    let format = encode::ImageFormat::from_path(path)?;
+    let data = encode_image_from_buffer(buffer, options, &format, depth)?;
+    std::fs::write(path, data).map_err(|_| RawFileReadingError::FileWritingError(path.to_owned()))
+}
+
+/// Renders a raw buffer and encodes it into the given format, returns the encoded bytes.
+///
+/// Integer depths clip the rendered values into `0..=1` before quantization.
+pub fn encode_image_from_buffer(
+    buffer: impl AsRef<[u8]>,
+    options: Options,
+    format: &encode::ImageFormat,
+    depth: encode::BitDepth,
+) -> Result<Vec<u8>, RawFileReadingError> {
+    let buffer = buffer.as_ref();
+    let exif = decode::get_shooting_info(buffer)
+        .map(|info| encode::Exif::from_parsed_info(&info))
+        .unwrap_or_default();
+    let decoded_image = decode::decode_float(buffer)?;
+    let data = render_float_image(&decoded_image, &options);
+    let (mut width, mut height) = (decoded_image.width, decoded_image.height);
+
+    let mut metadata = encode::Metadata {
+        exif,
+        ..encode::Metadata::from_decoded_image(&decoded_image, options.color_space, options.gamma)
+    };
+
+    let data = match (&decoded_image.crop, options.crop) {
+        (Some(crop), true) => {
+            let data = crop_image(&data, width, height, crop);
+            (width, height) = (crop.width as usize, crop.height as usize);
+            data
+        }
+        _ => data,
+    };
+    let data = if options.rotate {
+        metadata.orientation = 1;
+        let (data, rotated_width, rotated_height) =
+            rotate_image(&data, width, height, &decoded_image.orientation);
+        (width, height) = (rotated_width, rotated_height);
+        data
+    } else {
+        data
+    };
+
+    let quantize = |max: f32| data.iter().map(move |x| (x.clamp(0., 1.) * max).round());
+    let result = match depth {
+        encode::BitDepth::Eight => {
+            let data = quantize(u8::MAX as f32).map(|x| x as u8).collect::<Vec<_>>();
+            let image = encode::Image::new(encode::Pixels::U8(&data), width, height, 3)?;
+            format.encode(&image, &metadata)?
+        }
+        encode::BitDepth::Sixteen => {
+            let data = quantize(u16::MAX as f32).map(|x| x as u16).collect::<Vec<_>>();
+            let image = encode::Image::new(encode::Pixels::U16(&data), width, height, 3)?;
+            format.encode(&image, &metadata)?
+        }
+        encode::BitDepth::Float => {
+            let image = encode::Image::new(encode::Pixels::F32(&data), width, height, 3)?;
+            format.encode(&image, &metadata)?
+        }
+    };
+    Ok(result)
+}
+
+/// Cuts the crop area out of an interleaved RGB image, the area is clamped into the image.
+fn crop_image(data: &[f32], width: usize, height: usize, crop: &decode::Crop) -> Vec<f32> {
+    let x = (crop.x as usize).min(width);
+    let y = (crop.y as usize).min(height);
+    let crop_width = (crop.width as usize).min(width - x);
+    let crop_height = (crop.height as usize).min(height - y);
+
+    data.chunks_exact(width * 3)
+        .skip(y)
+        .take(crop_height)
+        .flat_map(|row| &row[x * 3..(x + crop_width) * 3])
+        .copied()
+        .collect()
+}
+
+/// Rotates an interleaved RGB image clockwise by the orientation, returns the new size.
+fn rotate_image(
+    data: &[f32],
+    width: usize,
+    height: usize,
+    orientation: &decode::Orientation,
+) -> (Vec<f32>, usize, usize) {
+    let pixel = |x: usize, y: usize| &data[(y * width + x) * 3..(y * width + x) * 3 + 3];
+    match orientation {
+        decode::Orientation::Horizontal => (data.to_vec(), width, height),
+        decode::Orientation::Rotate180 => {
+            let result = data.chunks_exact(3).rev().flatten().copied().collect();
+            (result, width, height)
+        }
+        decode::Orientation::Rotate90 => {
+            let result = (0..width)
+                .flat_map(|x| (0..height).rev().map(move |y| (x, y)))
+                .flat_map(|(x, y)| pixel(x, y))
+                .copied()
+                .collect();
+            (result, height, width)
+        }
+        decode::Orientation::Rotate270 => {
+            let result = (0..width)
+                .rev()
+                .flat_map(|x| (0..height).map(move |y| (x, y)))
+                .flat_map(|(x, y)| pixel(x, y))
+                .copied()
+                .collect();
+            (result, height, width)
+        }
+    }
+}
+
+/// Extracts the embedded JPEG preview of a raw buffer along with its orientation.
+pub fn load_thumbnail_from_buffer(
+    buffer: &[u8],
+) -> Result<(Vec<u8>, decode::Orientation), RawFileReadingError> {
+    let (thumbnail, orientation) = decode::get_thumbnail(buffer)?;
+    Ok((thumbnail.to_vec(), orientation))
+}
+
+/// Lists the previews embedded in a raw buffer, from the smallest to the largest.
+pub fn list_embedded_previews(buffer: &[u8]) -> Result<Vec<EmbeddedPreview>, RawFileReadingError> {
+    embedded_preview::list(buffer)
+}
+
+/// Extracts the embedded preview of the chosen size along with its description.
+///
+/// Unlike `load_thumbnail_from_buffer`, any of the previews can be chosen, including the
+/// uncompressed ones.
+pub fn load_embedded_preview_from_buffer(
+    buffer: &[u8],
+    size: PreviewSize,
+) -> Result<(&[u8], EmbeddedPreview), RawFileReadingError> {
+    let previews = embedded_preview::list(buffer)?;
+    let preview = size
+        .select(&previews)
+        .ok_or(RawFileReadingError::NoEmbeddedPreview)?;
+    Ok((&buffer[preview.range.clone()], preview.clone()))
+}
+
+/// Parses the metadata of a raw buffer with the rule of its maker.
+pub fn load_exif_info_from_buffer(buffer: &[u8]) -> Result<quickexif::ParsedInfo, RawFileReadingError> {
+    decode::get_exif_info(buffer)
+}
+
+/// Reads the typed metadata of a raw file without decoding the image.
+pub fn load_metadata_from_file(path: &str) -> Result<RawMetadata, RawFileReadingError> {
+    let buffer = RawBuffer::from_file(path)?;
+    load_metadata_from_buffer(&buffer)
+}
+
+/// Reads the typed metadata of a raw buffer without decoding the image.
+pub fn load_metadata_from_buffer(buffer: &[u8]) -> Result<RawMetadata, RawFileReadingError> {
+    decode::get_metadata(buffer)
+}
+
+/// Reads the unscaled sensor values of a raw file along with their levels and CFA layout.
+pub fn load_sensor_data_from_file(path: &str) -> Result<SensorData, RawFileReadingError> {
+    let buffer = RawBuffer::from_file(path)?;
+    load_sensor_data_from_buffer(buffer)
+}
+
+/// Reads the unscaled sensor values of a raw buffer along with their levels and CFA layout.
+pub fn load_sensor_data_from_buffer(
+    buffer: impl AsRef<[u8]>,
+) -> Result<SensorData, RawFileReadingError> {
+    decode::get_sensor_data(buffer.as_ref())
+}
+
+/// The camera of a raw file and whether its image can be decoded.
+pub struct Identity {
+    pub make: String,
+    pub model: String,
+    pub is_dng: bool,
+    /// The reason why the image cannot be decoded, or `Ok` when it is supported.
+    pub support: Result<(), RawFileReadingError>,
+}
+
+/// Identifies the camera of a raw buffer without decoding the image.
+pub fn identify_buffer(buffer: &[u8]) -> Result<Identity, RawFileReadingError> {
+    let basic_info = decode::get_basic_info(buffer)?;
+    let make = basic_info
+        .str("make")
+        .map_err(|_| RawFileReadingError::CannotReadMake)?
+        .to_owned();
+    let model = basic_info
+        .str("model")
+        .map_err(|_| RawFileReadingError::CannotReadModel)?
+        .to_owned();
+    let is_dng = basic_info.u16("dng_version").is_ok();
+    let support = maker::selector::check_support(&basic_info);
+
+    Ok(Identity {
+        make,
+        model,
+        is_dng,
+        support,
+    })
+}
+
+/// Converts a raw file into a DNG file.
+pub fn export_dng_from_file(
+    path: &str,
+    output_path: &str,
+    options: &encode::dng::DngOptions,
+) -> Result<(), RawFileReadingError> {
+    let buffer = RawBuffer::from_file(path)?;
+    let data = encode_dng_from_buffer(buffer, options)?;
+    std::fs::write(output_path, data)
+        .map_err(|_| RawFileReadingError::FileWritingError(output_path.to_owned()))
+}
+
+/// Converts a raw buffer into DNG bytes, the embedded preview and EXIF are carried over.
+///
+/// The sensor samples are stored as they are, along with their levels. Linear data like Nikon
+/// sRAW has no CFA samples and is stored as the decoder scales it.
+pub fn encode_dng_from_buffer(
+    buffer: impl AsRef<[u8]>,
+    options: &encode::dng::DngOptions,
+) -> Result<Vec<u8>, RawFileReadingError> {
+    let buffer = buffer.as_ref();
+    let exif = decode::get_shooting_info(buffer)
+        .map(|info| encode::Exif::from_parsed_info(&info))
+        .unwrap_or_default();
+    let preview = decode::get_thumbnail(buffer)
+        .map(|(preview, _)| preview.to_vec())
+        .ok();
+    let metadata = encode::Metadata {
+        exif,
+        ..encode::Metadata::from_raw_metadata(
+            &decode::get_metadata(buffer)?,
+            ColorSpace::Raw,
+            TransferFunction::Linear,
+        )
+    };
+    let encode = |raw_image| {
+        encode::dng::encode(&raw_image, &metadata, preview.as_deref(), options)
+            .map_err(RawFileReadingError::from)
+    };
+
+    match decode::get_sensor_data(buffer) {
+        Ok(sensor_data) => {
+            let (white_balance, cam_matrix) = decode::get_calibration(buffer)?;
+            encode(encode::dng::RawImage::from_sensor_data(
+                &sensor_data,
+                white_balance,
+                cam_matrix,
+            ))
+        }
+        Err(RawFileReadingError::DecodingError(DecodingError::InvalidDecodedImageSize(..))) => {
+            let decoded_image = decode::decode_buffer(buffer)?;
+            encode(encode::dng::RawImage {
+                image: &decoded_image.image,
+                width: decoded_image.width,
+                height: decoded_image.height,
+                cfa_pattern: decoded_image.cfa_pattern,
+                black_level: [0; 4],
+                white_level: u16::MAX,
+                bits_per_sample: 16,
+                linearization_table: None,
+                crop: decoded_image.crop,
+                white_balance: decoded_image.white_balance,
+                cam_matrix: decoded_image.cam_matrix,
+            })
+        }
+        Err(e) => Err(e),
+    }
+}
@@ -0,0 +1,267 @@
+//! Walks the IFDs of TIFF based raw files, along with the IFDs they point to and the maker notes.
+//!
+//! Unlike the rules of the makers, nothing is known of the tags beforehand, which lets the
+//! headers of a file be found before reading the rest of it, and the previews be listed.
+
+use super::RawFileReadingError;
+use std::ops::Range;
+
+/// Values larger than this are not loaded, like strips of image data.
+const MAX_VALUE_LEN: usize = 1024 * 1024;
+/// Of nested IFDs, and of IFDs walked in total, for files with IFDs pointing to each other.
+const MAX_DEPTH: u32 = 4;
+const MAX_IFDS: usize = 256;
+
+const SUB_IFD_TAGS: [u16; 5] = [0x014a, 0x8769, 0x8825, 0xa005, 0xc634];
+/// The IFDs in the maker notes of Olympus, like `ImageProcessing`.
+const OLYMPUS_IFD_TAGS: [u16; 6] = [0x2010, 0x2020, 0x2030, 0x2031, 0x2040, 0x2050];
+const MAKER_NOTES_TAG: u16 = 0x927c;
+
+/// The bytes of a file, of which a part may be missing until it is loaded.
+pub(crate) trait Source {
+    fn bytes(&self) -> &[u8];
+    fn load(&mut self, range: Range<usize>) -> Result<(), RawFileReadingError>;
+}
+
+impl Source for &[u8] {
+    fn bytes(&self) -> &[u8] {
+        self
+    }
+    fn load(&mut self, _range: Range<usize>) -> Result<(), RawFileReadingError> {
+        Ok(())
+    }
+}
+
+pub(crate) struct Entry {
+    pub(crate) tag: u16,
+    pub(crate) value_type: u16,
+    pub(crate) count: u32,
+    /// The value itself when it fits, its offset otherwise.
+    value: [u8; 4],
+}
+
+impl Entry {
+    pub(crate) fn value_len(&self) -> usize {
+        let type_len = match self.value_type {
+            1 | 2 | 6 | 7 => 1,
+            3 | 8 => 2,
+            4 | 9 | 11 | 13 => 4,
+            5 | 10 | 12 => 8,
+            _ => 0,
+        };
+        type_len * self.count as usize
+    }
+}
+
+/// An IFD with its entries, the offsets in it are relative to `base`.
+pub(crate) struct Ifd {
+    pub(crate) base: usize,
+    pub(crate) is_le: bool,
+    /// The tag of the entry pointing to the IFD, `None` for the IFDs of a TIFF header.
+    pub(crate) parent_tag: Option<u16>,
+    pub(crate) entries: Vec<Entry>,
+}
+
+impl Ifd {
+    fn get(&self, tag: u16) -> Option<&Entry> {
+        self.entries.iter().find(|x| x.tag == tag)
+    }
+
+    /// A single `SHORT` or `LONG` value.
+    pub(crate) fn number(&self, tag: u16) -> Option<u32> {
+        let entry = self.get(tag).filter(|x| x.count == 1)?;
+        match entry.value_type {
+            3 => read_u16(&entry.value, 0, self.is_le).map(|x| x as u32),
+            4 | 13 => read_u32(&entry.value, 0, self.is_le),
+            _ => None,
+        }
+    }
+
+    /// The position of a value out of the entry in the file.
+    pub(crate) fn value_range(&self, tag: u16) -> Option<Range<usize>> {
+        let entry = self.get(tag).filter(|x| x.value_len() > 4)?;
+        let start = self.offset(entry)?;
+        Some(start..start.checked_add(entry.value_len())?)
+    }
+
+    fn offset(&self, entry: &Entry) -> Option<usize> {
+        let offset = read_u32(&entry.value, 0, self.is_le)?;
+        self.base.checked_add(offset as usize)
+    }
+}
+
+pub(crate) fn read_u16(bytes: &[u8], offset: usize, is_le: bool) -> Option<u16> {
+    let bytes = bytes.get(offset..offset.checked_add(2)?)?.try_into().ok()?;
+    Some(if is_le {
+        u16::from_le_bytes(bytes)
+    } else {
+        u16::from_be_bytes(bytes)
+    })
+}
+
+pub(crate) fn read_u32(bytes: &[u8], offset: usize, is_le: bool) -> Option<u32> {
+    let bytes = bytes.get(offset..offset.checked_add(4)?)?.try_into().ok()?;
+    Some(if is_le {
+        u32::from_le_bytes(bytes)
+    } else {
+        u32::from_be_bytes(bytes)
+    })
+}
+
+/// Walks the IFDs of the TIFF block at `base` and loads them along with the values they point to.
+///
+/// Broken offsets end the walk of their IFD, only the errors of loading are returned.
+pub(crate) fn walk_tiff(
+    source: &mut impl Source,
+    base: usize,
+) -> Result<Vec<Ifd>, RawFileReadingError> {
+    let mut walker = Walker {
+        source,
+        walked: vec![],
+        ifds: vec![],
+    };
+    walker.walk_tiff(base, 0)?;
+    Ok(walker.ifds)
+}
+
+struct Walker<'a, S> {
+    source: &'a mut S,
+    walked: Vec<usize>,
+    ifds: Vec<Ifd>,
+}
+
+impl<S: Source> Walker<'_, S> {
+    fn walk_tiff(&mut self, base: usize, depth: u32) -> Result<(), RawFileReadingError> {
+        self.source.load(base..base.saturating_add(8))?;
+        let bytes = self.source.bytes();
+        let is_le = match bytes.get(base..base.saturating_add(2)) {
+            Some(b"II") => true,
+            Some(b"MM") => false,
+            _ => return Ok(()),
+        };
+        match read_u32(bytes, base + 4, is_le) {
+            Some(offset) => self.walk_ifd(base, offset as usize, is_le, None, depth),
+            None => Ok(()),
+        }
+    }
+
+    fn walk_ifd(
+        &mut self,
+        base: usize,
+        offset: usize,
+        is_le: bool,
+        parent_tag: Option<u16>,
+        depth: u32,
+    ) -> Result<(), RawFileReadingError> {
+        let start = base.saturating_add(offset);
+        if depth > MAX_DEPTH || self.walked.len() >= MAX_IFDS || self.walked.contains(&start) {
+            return Ok(());
+        }
+        self.walked.push(start);
+
+        self.source.load(start..start.saturating_add(2))?;
+        let Some(count) = read_u16(self.source.bytes(), start, is_le) else {
+            return Ok(());
+        };
+        let entries_start = start + 2;
+        let next = entries_start + count as usize * 12;
+        self.source.load(entries_start..next + 4)?;
+
+        let bytes = self.source.bytes();
+        let entries = (entries_start..next)
+            .step_by(12)
+            .map_while(|entry| {
+                Some(Entry {
+                    tag: read_u16(bytes, entry, is_le)?,
+                    value_type: read_u16(bytes, entry + 2, is_le)?,
+                    count: read_u32(bytes, entry + 4, is_le)?,
+                    value: bytes.get(entry + 8..entry + 12)?.try_into().ok()?,
+                })
+            })
+            .collect();
+        let next = read_u32(bytes, next, is_le);
+        let ifd = Ifd {
+            base,
+            is_le,
+            parent_tag,
+            entries,
+        };
+
+        for entry in ifd.entries.iter() {
+            let value_len = entry.value_len();
+            let Some(value_start) = ifd.offset(entry) else {
+                continue;
+            };
+            if value_len > 4 && value_len <= MAX_VALUE_LEN {
+                self.source
+                    .load(value_start..value_start.saturating_add(value_len))?;
+            }
+
+            let value = value_start - base;
+            let is_pointer = entry.count == 1 && matches!(entry.value_type, 4 | 13);
+            let tag = Some(entry.tag);
+            match entry.tag {
+                MAKER_NOTES_TAG if value_len > 4 => {
+                    self.walk_maker_notes(base, value_start, is_le, depth + 1)?
+                }
+                x if SUB_IFD_TAGS.contains(&x) && value_len <= 4 => {
+                    self.walk_ifd(base, value, is_le, tag, depth + 1)?
+                }
+                0x014a => {
+                    for i in 0..entry.count as usize {
+                        if let Some(offset) =
+                            read_u32(self.source.bytes(), value_start + i * 4, is_le)
+                        {
+                            self.walk_ifd(base, offset as usize, is_le, tag, depth + 1)?;
+                        }
+                    }
+                }
+                x if is_pointer && (entry.value_type == 13 || OLYMPUS_IFD_TAGS.contains(&x)) => {
+                    self.walk_ifd(base, value, is_le, tag, depth + 1)?
+                }
+                _ => {}
+            }
+        }
+        self.ifds.push(ifd);
+
+        match next {
+            Some(0) | None => Ok(()),
+            // the IFDs of a chain are at the same depth
+            Some(offset) => self.walk_ifd(base, offset as usize, is_le, parent_tag, depth),
+        }
+    }
+
+    /// Walks the IFD of the maker notes at `start`, after the header of the maker if any.
+    fn walk_maker_notes(
+        &mut self,
+        base: usize,
+        start: usize,
+        is_le: bool,
+        depth: u32,
+    ) -> Result<(), RawFileReadingError> {
+        let tag = Some(MAKER_NOTES_TAG);
+        let bytes = self.source.bytes();
+        let header = bytes
+            .get(start..start.saturating_add(12))
+            .unwrap_or_default();
+        if header.starts_with(b"Nikon\0") {
+            // a TIFF block of its own
+            self.walk_tiff(start + 10, depth)
+        } else if header.starts_with(b"OLYMPUS\0") {
+            // the offsets are relative to the maker notes, which have a byte order of their own
+            let is_le = header.get(8..10) == Some(b"II".as_slice());
+            self.walk_ifd(start, 12, is_le, tag, depth)
+        } else if header.starts_with(b"OLYMP\0") {
+            self.walk_ifd(base, start + 8 - base, is_le, tag, depth)
+        } else if header.starts_with(b"FUJIFILM") {
+            match read_u32(bytes, start + 8, true) {
+                Some(offset) => self.walk_ifd(start, offset as usize, true, tag, depth),
+                None => Ok(()),
+            }
+        } else if header.starts_with(b"Panasonic\0") || header.starts_with(b"SONY") {
+            self.walk_ifd(base, start + 12 - base, is_le, tag, depth)
+        } else {
+            self.walk_ifd(base, start - base, is_le, tag, depth)
+        }
+    }
+}
@@ -0,0 +1,170 @@
+//! Reads the metadata and the embedded preview of a raw file by fetching only the bytes they are
+//! stored in, like a grid view listing files on a network share.
+//!
+//! The head of the file is fetched first, the IFDs are walked from it and the values and the IFDs
+//! they point to are fetched along by `ifd_walker`. The fetched bytes are kept at their offsets in a buffer of the
+//! size of the file, so the parsers of the makers read it as a whole file. The buffer is allocated
+//! zeroed, the pages of it never written are not backed by memory on most systems.
+
+use super::*;
+use metadata::RawMetadata;
+use std::{
+    alloc::{self, Layout},
+    io::{Read, Seek, SeekFrom},
+    ops::Range,
+};
+
+/// The unit of fetching.
+const PAGE: usize = 4096;
+/// Larger than any raw file, a longer length is taken for a bogus one.
+const MAX_LEN: u64 = 4 << 30;
+/// Fetched from the start of the file, and of the sensor data of RAF files, where the headers are.
+const HEAD_LEN: usize = 64 * 1024;
+
+type Fetch<'a> = Box<dyn FnMut(u64, &mut [u8]) -> std::io::Result<()> + 'a>;
+
+/// A raw file of which only the parts needed so far are fetched.
+pub struct LazyRawFile<'a> {
+    fetch: Fetch<'a>,
+    buffer: Vec<u8>,
+    pages: Vec<bool>,
+    fetched_len: usize,
+    headers_fetched: bool,
+}
+
+impl<'a> LazyRawFile<'a> {
+    /// Fetches the bytes by seeking and reading, the length of the file is found by seeking to its end.
+    pub fn from_reader(
+        mut reader: impl Read + Seek + 'a,
+    ) -> Result<LazyRawFile<'a>, RawFileReadingError> {
+        let len = reader
+            .seek(SeekFrom::End(0))
+            .map_err(RawFileReadingError::StreamReadingError)?;
+        Self::from_fetch(len, move |offset, data| {
+            reader.seek(SeekFrom::Start(offset))?;
+            reader.read_exact(data)
+        })
+    }
+
+    /// Fetches the bytes by a callback filling its slice with the bytes from the offset, like a
+    /// ranged HTTP request.
+    ///
+    /// Fails with `RawFileReadingError::FileTooLarge` when `len` is over 4 GiB or the buffer of it
+    /// cannot be allocated, like a bogus length on wasm32.
+    pub fn from_fetch(
+        len: u64,
+        fetch: impl FnMut(u64, &mut [u8]) -> std::io::Result<()> + 'a,
+    ) -> Result<LazyRawFile<'a>, RawFileReadingError> {
+        let buffer = zeroed_buffer(len).ok_or(RawFileReadingError::FileTooLarge(len))?;
+        let pages = vec![false; buffer.len().div_ceil(PAGE)];
+        Ok(LazyRawFile {
+            fetch: Box::new(fetch),
+            buffer,
+            pages,
+            fetched_len: 0,
+            headers_fetched: false,
+        })
+    }
+
+    /// The number of bytes fetched so far.
+    pub fn fetched_len(&self) -> usize {
+        self.fetched_len
+    }
+
+    /// Reads the typed metadata, see `export::load_metadata_from_buffer`.
+    pub fn metadata(&mut self) -> Result<RawMetadata, RawFileReadingError> {
+        self.fetch_headers()?;
+        decode::get_metadata(&self.buffer)
+    }
+
+    /// Parses the metadata with the rule of the maker, see `export::load_exif_info_from_buffer`.
+    pub fn exif_info(&mut self) -> Result<quickexif::ParsedInfo, RawFileReadingError> {
+        self.fetch_headers()?;
+        decode::get_exif_info(&self.buffer)
+    }
+
+    /// Extracts the embedded JPEG preview, only its bytes are fetched besides the headers.
+    pub fn thumbnail(&mut self) -> Result<(&[u8], Orientation), RawFileReadingError> {
+        self.fetch_headers()?;
+        let range = decode::get_thumbnail_range(&self.buffer)?;
+        self.fetch_range(range)?;
+        decode::get_thumbnail(&self.buffer)
+    }
+
+    fn fetch_range(&mut self, range: Range<usize>) -> Result<(), RawFileReadingError> {
+        let len = self.buffer.len();
+        let (start, end) = (range.start.min(len), range.end.min(len));
+        if start >= end {
+            return Ok(());
+        }
+
+        // the missing pages are fetched in runs
+        let mut page = start / PAGE;
+        while page < end.div_ceil(PAGE) {
+            if self.pages[page] {
+                page += 1;
+                continue;
+            }
+            let first = page;
+            while page < end.div_ceil(PAGE) && !self.pages[page] {
+                page += 1;
+            }
+            let bytes = first * PAGE..(page * PAGE).min(len);
+            (self.fetch)(bytes.start as u64, &mut self.buffer[bytes.clone()])
+                .map_err(RawFileReadingError::StreamReadingError)?;
+            self.pages[first..page].fill(true);
+            self.fetched_len += bytes.len();
+        }
+        Ok(())
+    }
+
+    fn fetch_headers(&mut self) -> Result<(), RawFileReadingError> {
+        if self.headers_fetched {
+            return Ok(());
+        }
+        self.fetch_range(0..HEAD_LEN)?;
+
+        match decode::raf_jpeg_range(&self.buffer) {
+            Some(jpeg) => {
+                // the EXIF block of the embedded JPEG, and the TIFF block of the sensor data
+                ifd_walker::walk_tiff(self, jpeg.start + 12)?;
+                let sensor_data = ifd_walker::read_u32(&self.buffer, 100, false).unwrap_or(0);
+                let sensor_data = sensor_data as usize;
+                self.fetch_range(sensor_data..sensor_data.saturating_add(HEAD_LEN))?;
+            }
+            None => {
+                ifd_walker::walk_tiff(self, 0)?;
+            }
+        }
+        self.headers_fetched = true;
+        Ok(())
+    }
+}
+
+/// A zeroed buffer of `len` bytes, `None` instead of aborting when it cannot be allocated.
+fn zeroed_buffer(len: u64) -> Option<Vec<u8>> {
+    if len > MAX_LEN {
+        return None;
+    }
+    let len = usize::try_from(len).ok()?;
+    if len == 0 {
+        return Some(Vec::new());
+    }
+    let layout = Layout::array::<u8>(len).ok()?;
+    // zeroed by the allocator, the pages are not touched
+    let ptr = unsafe { alloc::alloc_zeroed(layout) };
+    if ptr.is_null() {
+        return None;
+    }
+    // allocated by the global allocator with the layout of `len` bytes
+    Some(unsafe { Vec::from_raw_parts(ptr, len, len) })
+}
+
+impl ifd_walker::Source for LazyRawFile<'_> {
+    fn bytes(&self) -> &[u8] {
+        &self.buffer
+    }
+    fn load(&mut self, range: Range<usize>) -> Result<(), RawFileReadingError> {
+        self.fetch_range(range)
+    }
+}
@@ -63,11 +63,39 @@ pub mod data;
 
 mod utility;
 
+mod parallel;
+#[cfg(feature = "parallel")]
+pub use parallel::set_max_threads;
+
 mod pass;
+pub use pass::{set_simd_enabled, set_tiled_demosaicing_enabled};
 mod maker;
+pub use maker::DecodingError;
+#[cfg(feature = "fuzzing")]
+#[doc(hidden)]
+pub use maker::fuzzing;
+mod raw_buffer;
+pub use raw_buffer::RawBuffer;
 mod decode;
+mod ifd_walker;
+mod embedded_preview;
+pub use embedded_preview::{EmbeddedPreview, PreviewFormat, PreviewSize};
+mod lazy_file;
+pub use lazy_file::LazyRawFile;
 pub use decode::decode_file;
 pub use decode::decode_buffer;
+pub use decode::{CFAPattern, Crop, DecodedImage, Orientation, PreviewScale, SensorData};
+
+mod metadata;
+pub use metadata::{DateTime, DriveMode, Gps, RawMetadata};
+
+mod color_space;
+pub use color_space::ColorSpace;
+
+mod transfer;
+pub use transfer::{TransferFunction, PQ_REFERENCE_WHITE};
+
+pub mod encode;
 
 #[cfg(feature = "wasm-bindgen")]
 mod lib_wasm;
@@ -79,20 +107,25 @@ pub mod export;
 const BIT_SHIFT: u32 = 13u32;
 
 /// All the demosaicing method currently supported.
-#[derive(Clone)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum DemosaicingMethod {
+    /// Every pixel keeps its own sample in all three channels.
     None,
+    /// Every pixel takes the averaged colors of its 2x2 Bayer or 3x3 X-Trans block.
     SuperPixel,
     Linear,
 }
 
 /// Decides if the output should be 8bit or 16bit.
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum OutputType {
     Raw8,
     Raw16,
     Image8(String),
     Image16(String),
+    ImageF32(String),
 }
 
 /// Chooses the input from a file or a buffer.
@@ -104,6 +137,7 @@ pub enum Input<'a> {
 /// Contains options for image rendering.
 #[allow(dead_code)]
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Output {
     demosaicing_method: DemosaicingMethod,
     color_space: [f32; 9],
@@ -141,12 +175,31 @@ pub enum RawFileReadingError {
     ExifParseInfoError(#[from] quickexif::parsed_info::Error),
     #[error("Cannot read the raw file.")]
     DecodingError(#[from] maker::DecodingError),
+    #[error("Cannot encode the output image.")]
+    EncodingError(#[from] encode::EncodingError),
     #[error("The file '{0}' is not existed.")]
     FileNotExisted(String),
+    #[error("The path is null or not valid UTF-8.")]
+    InvalidPath,
     #[error("The metadata of file '{0}' cannot be read.")]
     FileMetadataReadingError(String),
     #[error("The content of file '{0}' cannot be read.")]
     FileContentReadingError(String),
+    #[error("The raw data cannot be read from the stream.")]
+    StreamReadingError(#[source] std::io::Error),
+    #[error("The file '{0}' cannot be written.")]
+    FileWritingError(String),
+    #[error("The output type is not an image file.")]
+    OutputTypeIsNotImage,
+    #[error("The raw file has no embedded preview.")]
+    NoEmbeddedPreview,
+    #[error("The region is out of the image.")]
+    RegionIsOutOfImage,
+    #[error("A file of {0} bytes cannot be read lazily.")]
+    FileTooLarge(u64),
+    #[cfg(feature = "parallel")]
+    #[error("Cannot build the thread pool.")]
+    ThreadPoolBuildError(#[from] rayon::ThreadPoolBuildError),
     #[error("Cannot read Make info from this raw file.")]
     CannotReadMake,
     #[error("Cannot read Model info from this raw file.")]
@@ -3,18 +3,22 @@ use anyhow::Result;
 use std::ffi::{CStr, CString};
 use std::os::raw::*;
 
-fn str_from_cchar<'a>(ptr: *mut c_char) -> &'a str {
+fn str_from_cchar<'a>(ptr: *mut c_char) -> Result<&'a str, RawFileReadingError> {
+    if ptr.is_null() {
+        return Err(RawFileReadingError::InvalidPath);
+    }
     let s = unsafe { CStr::from_ptr(ptr) };
-    s.to_str().unwrap()
+    s.to_str().map_err(|_| RawFileReadingError::InvalidPath)
 }
 fn free_cstring(ptr: *mut c_char) {
     unsafe { CString::from_raw(ptr) };
 }
+/// A string with a nul byte in it, which cannot be passed to C, becomes an empty one.
 fn gen_cstring(s: String) -> *mut c_char {
-    CString::new(s).unwrap().into_raw()
+    CString::new(s).unwrap_or_default().into_raw()
 }
 fn gen_empty_cstring() -> *mut c_char {
-    CString::new("").unwrap().into_raw()
+    CString::default().into_raw()
 }
 
 pub trait Free {
@@ -52,6 +56,16 @@ impl RustVec {
         unsafe { Vec::from_raw_parts(self.ptr, self.len as usize, self.capacity as usize) };
     }
 }
+impl Free for RustVec {
+    fn free(&mut self) {
+        RustVec::free(self);
+    }
+}
+impl Default for RustVec {
+    fn default() -> Self {
+        RustVec::new_empty()
+    }
+}
 
 #[repr(C)]
 pub struct QuickrawResponse<T> {
@@ -85,6 +99,8 @@ pub struct BasicInfo {
     exif: *mut c_char,
     thumbnail: RustVec,
     orientation: c_uchar,
+    /// The `RawMetadata` in JSON, empty without the `serde` feature.
+    metadata: *mut c_char,
 }
 impl Default for BasicInfo {
     fn default() -> Self {
@@ -92,15 +108,17 @@ impl Default for BasicInfo {
             exif: gen_empty_cstring(),
             thumbnail: RustVec::new_empty(),
             orientation: 0,
+            metadata: gen_empty_cstring(),
         }
     }
 }
 impl BasicInfo {
-    fn new(exif: String, thumbnail: RustVec, orientation: c_uchar) -> Self {
+    fn new(exif: String, thumbnail: RustVec, orientation: c_uchar, metadata: String) -> Self {
         BasicInfo {
             exif: gen_cstring(exif),
             thumbnail,
             orientation,
+            metadata: gen_cstring(metadata),
         }
     }
 }
@@ -108,16 +126,28 @@ impl Free for BasicInfo {
     fn free(&mut self) {
         free_cstring(self.exif);
         self.thumbnail.free();
+        free_cstring(self.metadata);
     }
 }
 
+#[cfg(feature = "serde")]
+fn metadata_string(buffer: &[u8]) -> Result<String> {
+    let metadata = decode::get_metadata(buffer)?;
+    Ok(serde_json::to_string(&metadata)?)
+}
+/// The metadata is only serialized with the `serde` feature.
+#[cfg(not(feature = "serde"))]
+fn metadata_string(_: &[u8]) -> Result<String> {
+    Ok(String::new())
+}
+
 fn load_basicinfo(cpath: *mut c_char) -> Result<BasicInfo> {
-    let path = str_from_cchar(cpath);
-    let buffer = decode::get_buffer_from_file(path)?;
+    let path = str_from_cchar(cpath)?;
+    let buffer = RawBuffer::from_file(path)?;
     let exif = decode::get_exif_info(&buffer)?;
     let s = exif.stringify_all()?;
     let thumbnail = RustVec::new_empty();
-    Ok(BasicInfo::new(s, thumbnail, 0))
+    Ok(BasicInfo::new(s, thumbnail, 0, metadata_string(&buffer)?))
 }
 #[no_mangle]
 pub extern "C" fn quickraw_load_basicinfo(cpath: *mut c_char) -> QuickrawResponse<BasicInfo> {
@@ -129,6 +159,7 @@ pub extern "C" fn quickraw_free_basicinfo(mut response: QuickrawResponse<BasicIn
 }
 
 #[repr(C)]
+#[derive(Default)]
 pub struct Image {
     data: RustVec,
     width: c_uint,
@@ -148,18 +179,9 @@ impl Free for Image {
         self.data.free();
     }
 }
-impl Default for Image {
-    fn default() -> Self {
-        Image {
-            data: RustVec::new_empty(),
-            width: 0,
-            height: 0,
-        }
-    }
-}
 
 fn load_image(cpath: *mut c_char) -> Result<Image> {
-    let path = str_from_cchar(cpath);
+    let path = str_from_cchar(cpath)?;
     let options = export::Options::new(data::GAMMA_SRGB, &data::XYZ2SRGB, false);
 
     let (img, width, height) = export::load_image_from_file(path, options)?;
@@ -174,3 +196,45 @@ pub extern "C" fn quickraw_load_image(cpath: *mut c_char) -> QuickrawResponse<Im
 pub extern "C" fn quickraw_free_image(mut response: QuickrawResponse<Image>) {
     response.free();
 }
+
+fn encode_jpeg(
+    cpath: *mut c_char,
+    quality: c_uchar,
+    subsampling: c_ushort,
+    progressive: bool,
+    embed_thumbnail: bool,
+) -> Result<RustVec> {
+    let path = str_from_cchar(cpath)?;
+    let buffer = RawBuffer::from_file(path)?;
+    let options = export::Options::new(TransferFunction::Srgb, ColorSpace::Srgb, false);
+
+    let subsampling = match subsampling {
+        444 => encode::jpeg::ChromaSubsampling::Yuv444,
+        422 => encode::jpeg::ChromaSubsampling::Yuv422,
+        420 => encode::jpeg::ChromaSubsampling::Yuv420,
+        _ => anyhow::bail!("The chroma subsampling {} is not supported.", subsampling),
+    };
+    let format = encode::ImageFormat::Jpeg(encode::jpeg::JpegOptions {
+        quality,
+        subsampling,
+        progressive,
+        embed_thumbnail,
+    });
+    let data = export::encode_image_from_buffer(buffer, options, &format, encode::BitDepth::Eight)?;
+    Ok(RustVec::new(data))
+}
+/// Renders a raw file into JPEG bytes, `subsampling` is one of `444`, `422` or `420`.
+#[no_mangle]
+pub extern "C" fn quickraw_encode_jpeg(
+    cpath: *mut c_char,
+    quality: c_uchar,
+    subsampling: c_ushort,
+    progressive: bool,
+    embed_thumbnail: bool,
+) -> QuickrawResponse<RustVec> {
+    QuickrawResponse::new(encode_jpeg(cpath, quality, subsampling, progressive, embed_thumbnail))
+}
+#[no_mangle]
+pub extern "C" fn quickraw_free_jpeg(mut response: QuickrawResponse<RustVec>) {
+    response.free();
+}
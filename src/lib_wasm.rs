@@ -170,6 +170,7 @@ pub fn encode_to_jpeg(
 pub struct ExifWithThumbnail {
     pub orientation: isize,
     exif: String,
+    metadata: String,
     thumbnail: Vec<u8>,
 }
 
@@ -183,67 +184,54 @@ impl ExifWithThumbnail {
     pub fn exif(&self) -> String {
         self.exif.clone()
     }
+    /// The `RawMetadata` in JSON.
+    #[wasm_bindgen(getter)]
+    pub fn metadata(&self) -> String {
+        self.metadata.clone()
+    }
 }
 
 fn quick_image_load(
     input: Vec<u8>,
 ) -> Result<(Vec<u8>, u32, u32, Orientation), RawFileReadingError> {
-    let decoded_image = decode::decode_buffer(input)?;
-
-    let width = decoded_image.width;
-    let image = decoded_image
-        .image
-        .chunks(width * 4)
-        .enumerate()
-        .map(|(row_index, row)| {
-            let left_bound = row_index % 4 * width;
-            row[left_bound..left_bound + width]
-                .chunks(4)
-                .enumerate()
-                .map(|(column_index, values)| values[column_index % 4])
-                .collect::<Vec<_>>()
-        })
-        .flatten()
-        .collect::<Vec<_>>();
-
-    let orientation = decoded_image.orientation;
-    let width = decoded_image.width / 4;
-    let height = decoded_image.height / 4;
-
-    let gamma_lut = gen_gamma_lut(0.45);
-    let color_matrix = utility::matrix3_mul(&data::XYZ2SRGB, &decoded_image.cam_matrix);
+    let (preview, is_cfa) = decode::decode_preview(&input, decode::PreviewScale::Quarter)?;
+    let width = preview.width;
+    let height = preview.height;
+
+    let gamma_lut = gen_gamma_lut(data::GAMMA_SRGB);
+    let color_matrix = utility::matrix3_mul(&data::XYZ2SRGB, &preview.cam_matrix);
     let color_matrix = color_matrix.mul(1 << BIT_SHIFT);
-    let white_balance = decoded_image
+    let white_balance = preview
         .white_balance
-        .mul(1 << (BIT_SHIFT - log2(decoded_image.white_balance[1])));
-
-    let iter = image.iter().copied();
-    let data = pass::iters_to_vec!(
-        iter
-            ..enumerate()
-            [decoded_image.cfa_pattern] {
-                CFAPattern::RGGB => .linear_rggb(&image, width, height),
-                CFAPattern::GRBG => .linear_grbg(&image, width, height),
-                CFAPattern::GBRG => .linear_gbrg(&image, width, height),
-                CFAPattern::BGGR => .linear_bggr(&image, width, height),
-                CFAPattern::XTrans0 => .linear_xtrans0(&image, width, height),
-                CFAPattern::XTrans1 => .linear_xtrans1(&image, width, height)
-            }
-            .u16rgb_to_i32rgb()
-            .white_balance_fix(&white_balance)
-            .color_convert(&color_matrix)
-            .gamma_correct(&gamma_lut)
-            .u16rgb_to_u8rgb()
-            ..flatten()
-    );
-
-    Ok((data, width as u32, height as u32, orientation))
+        .mul(1 << (BIT_SHIFT - log2(preview.white_balance[1])));
+
+    let iter = preview.image.chunks_exact(3).map(|x| [x[0], x[1], x[2]]);
+    let data = if is_cfa {
+        pass::iters_to_vec!(
+            iter
+                .white_balance_and_convert(&white_balance, &color_matrix)
+                .gamma_correct(&gamma_lut)
+                .u16rgb_to_u8rgb()
+                ..flatten()
+        )
+    } else {
+        pass::iters_to_vec!(
+            iter
+                .gamma_correct(&gamma_lut)
+                .u16rgb_to_u8rgb()
+                ..flatten()
+        )
+    };
+
+    Ok((data, width as u32, height as u32, preview.orientation))
 }
 
 #[wasm_bindgen]
 pub fn load_exif_with_thumbnail(buffer: Vec<u8>) -> Result<ExifWithThumbnail, JsError> {
     let info = expand_err(decode::get_exif_info(&buffer))?;
     let exif = info.stringify_all()?;
+    let metadata = expand_err(decode::get_metadata(&buffer))?;
+    let metadata = expand_err(serde_json::to_string(&metadata))?;
     let (thumbnail, orientation) = match decode::get_thumbnail(&buffer) {
         Ok((data, orientation)) => (data.to_vec(), orientation),
         Err(_) => {
@@ -263,5 +251,13 @@ pub fn load_exif_with_thumbnail(buffer: Vec<u8>) -> Result<ExifWithThumbnail, Js
         orientation: orientation as isize,
         thumbnail,
         exif,
+        metadata,
     })
 }
+
+/// Reads the `RawMetadata` in JSON without decoding the image.
+#[wasm_bindgen]
+pub fn load_metadata(buffer: Vec<u8>) -> Result<String, JsError> {
+    let metadata = expand_err(decode::get_metadata(&buffer))?;
+    expand_err(serde_json::to_string(&metadata))
+}
@@ -58,6 +58,16 @@ pub(super) static IMAGE_RULE: Lazy<quickexif::ParsingRule> = Lazy::new(|| {
         0x0102 : u16 / bps
         0x0103 : u16 / compression
         0x828e? / cfa_pattern
+        0x828d? / cfa_repeat_dim
+        if cfa_repeat_dim ? {
+            // the two values 6 and 6 of the X-Trans pattern
+            if cfa_repeat_dim == 393222 {
+                0x828e {
+                    u32 + 0 / cfa_xtrans
+                }
+            }
+        }
+        0xc618? / linearization_table(linearization_table_len)
         0xc61d / wl(white_level_len)
 
         if white_level_len == 1
@@ -74,8 +84,17 @@ pub(super) static IMAGE_RULE: Lazy<quickexif::ParsingRule> = Lazy::new(|| {
         if black_level_len == 1 {
             0xc61a : u16 / black_level
         } else {
-            0xc61a {
-                r64 + 0 / black_level
+            if black_level_len == 4 {
+                0xc61a {
+                    r64 + 0 / black_level
+                    r64 + 1 / black_level_1
+                    r64 + 2 / black_level_2
+                    r64 + 3 / black_level_3
+                }
+            } else {
+                0xc61a {
+                    r64 + 0 / black_level
+                }
             }
         }
         0x0111? / strip(strip_offsets_count)
@@ -150,7 +169,7 @@ pub(super) static IMAGE_RULE: Lazy<quickexif::ParsingRule> = Lazy::new(|| {
 impl General {
     fn get_white_level_scale(&self) -> Result<u16, quickexif::parsed_info::Error> {
         let white_level = self.info.u16("white_level")?;
-        Ok(u16::MAX / white_level)
+        Ok(u16::MAX / white_level.max(1))
     }
 }
 
@@ -197,27 +216,80 @@ impl RawDecoder for General {
             })
         }
     }
-    fn get_thumbnail<'a>(&self, buffer: &'a [u8]) -> Result<&'a [u8], DecodingError> {
+    fn get_cfa_pattern(&self) -> Result<CFAPattern, DecodingError> {
+        // the first row of the 6x6 pattern tells the two phases of X-Trans apart
+        if let Ok(colors) = self.info.u8a4("cfa_xtrans") {
+            return match colors {
+                [0, 2, 1, 2] => Ok(CFAPattern::XTrans0),
+                [1, 1, 0, 1] => Ok(CFAPattern::XTrans1),
+                _ => Err(DecodingError::UnsupportedCFAPattern(u32::from_be_bytes(colors))),
+            };
+        }
+        match self.info.u8a4("cfa_pattern") {
+            Ok(colors) => bayer_pattern(colors),
+            // linear data has three samples a pixel, no pattern is read for it
+            Err(_) => Ok(CFAPattern::RGGB),
+        }
+    }
+    fn get_black_level(&self) -> Result<[u16; 4], DecodingError> {
+        let level = self.info.u16("black_level")?;
+        // the levels of a 2x2 repeat pattern are in the order of the CFA samples already
+        match (
+            self.info.u16("black_level_1"),
+            self.info.u16("black_level_2"),
+            self.info.u16("black_level_3"),
+        ) {
+            (Ok(a), Ok(b), Ok(c)) => Ok([level, a, b, c]),
+            _ => Ok([level; 4]),
+        }
+    }
+    fn get_linearization_table(&self, buffer: &[u8]) -> Result<Option<Vec<u16>>, DecodingError> {
+        let Ok(len) = self.info.usize("linearization_table_len") else {
+            return Ok(None);
+        };
+        // up to two values are stored in place of the offset
+        let bytes = if len <= 2 {
+            self.info.u8a4("linearization_table")?.to_vec()
+        } else {
+            let offset = self.info.usize("linearization_table")?;
+            get_bytes(buffer, offset, len.saturating_mul(2))?.to_vec()
+        };
+        Ok(Some((0..len).map(|i| bytes.as_slice().u16(self.info.is_le, i * 2)).collect()))
+    }
+    fn get_thumbnail_range(&self) -> Result<Range<usize>, DecodingError> {
         let offset = self.info.usize("thumbnail")?;
         let len = self.info.usize("thumbnail_len")?;
+        Ok(offset..offset.saturating_add(len))
+    }
+    fn decode_with_preprocess(&self, buffer: &[u8], area: &Area) -> Result<Vec<u16>, DecodingError> {
+        let mut image = self.decode_raw(buffer, area)?;
+        // linear data like Apple ProRaw is already scaled
+        if self.info.u16("compression")? == 7 && self.info.u8a4("cfa_pattern").is_err() {
+            return Ok(image);
+        }
 
-        Ok(&buffer[offset..offset + len])
+        if let Some(table) = self.get_linearization_table(buffer)? {
+            image.iter_mut().for_each(|x| *x = linearize(*x, &table));
+        }
+        let size = get_image_size(&self.info, buffer)?;
+        subtract_black_level(&mut image, size, self.get_black_level()?, self.get_white_level_scale()?);
+        Ok(image)
     }
-    fn decode_with_preprocess(&self, buffer: &[u8]) -> Result<Vec<u16>, DecodingError> {
-        let width = self.info.usize("width")?;
-        let height = self.info.usize("height")?;
+    fn decode_float(&self, buffer: &[u8], area: &Area) -> Result<Vec<f32>, DecodingError> {
+        // linear data has no black level or curve of its own
+        if self.info.u8a4("cfa_pattern").is_err() {
+            return Ok(to_float(self.decode_with_preprocess(buffer, area)?));
+        }
+        let image = self.decode_raw(buffer, area)?;
+        let (width, _) = get_image_size(&self.info, buffer)?;
+        let table = self.get_linearization_table(buffer)?;
+        let white_level = self.get_white_level().unwrap_or(u16::MAX);
+        Ok(normalize(&image, width, table.as_deref(), self.get_black_level()?, white_level))
+    }
+    fn decode_raw(&self, buffer: &[u8], area: &Area) -> Result<Vec<u16>, DecodingError> {
+        let (width, height) = get_image_size(&self.info, buffer)?;
         let compression = self.info.u16("compression")?;
         let bps = self.info.u16("bps")?;
-        let white_level_scale = self.get_white_level_scale()?;
-        let black_level = self.info.u16("black_level")?;
-
-        macro_rules! to_image {
-            ($iter:expr) => {
-                $iter
-                    .map(|x| white_level_scale.saturating_mul(x.saturating_sub(black_level)))
-                    .collect()
-            };
-        }
 
         let image: Vec<u16> = match compression {
             1 => { // uncompressed dng
@@ -226,46 +298,62 @@ impl RawDecoder for General {
                 let len_addr = self.info.usize("strip_len")?;
 
                 let buf = if (offset_count) > 1 {
-                    let strip_addr = buffer.u32(self.info.is_le, offset_addr) as usize;
-                    let tile_count = buffer.u32(self.info.is_le, len_addr) as usize;
-                    &buffer[strip_addr..strip_addr + tile_count * offset_count]
+                    let strip_addr = get_bytes(buffer, offset_addr, 4)?.u32(self.info.is_le, 0) as usize;
+                    let tile_count = get_bytes(buffer, len_addr, 4)?.u32(self.info.is_le, 0) as usize;
+                    get_bytes(buffer, strip_addr, tile_count.saturating_mul(offset_count))?
                 } else {
-                    &buffer[offset_addr..offset_addr + len_addr]
+                    get_bytes(buffer, offset_addr, len_addr)?
                 };
 
+                let is_le = self.info.is_le;
+                // rows of CFA data can be located when they end at a byte boundary
+                let bits_per_row = width * bps as usize;
+                let row_len = (bits_per_row.is_multiple_of(8) && self.info.u8a4("cfa_pattern").is_ok())
+                    .then_some(bits_per_row / 8);
+                macro_rules! to_image {
+                    ($to_iter:ident) => {
+                        row_len
+                            .and_then(|row_len| {
+                                decode_rows(buf, (width, height), row_len, area, |row| $to_iter(row, is_le))
+                            })
+                            .unwrap_or_else(|| $to_iter(buf, is_le).collect())
+                    };
+                }
                 match bps {
-                    12 => to_image!(to_12bit_iter_packed(buf, self.info.is_le)),
-                    14 => to_image!(to_14bit_iter_packed(buf, self.info.is_le)),
-                    _ => to_image!(to_16bit_iter(buf, self.info.is_le)),
+                    12 => to_image!(to_12bit_iter_packed),
+                    14 => to_image!(to_14bit_iter_packed),
+                    _ => to_image!(to_16bit_iter),
                 }
             }
             7 => {
-                // only support Apple ProRaw now
+                // CFA data or linear data like Apple ProRaw
                 let byte_counts_addr = self.info.usize("tile_byte_counts")?;
                 let tile_offsets_count = self.info.usize("tile_offsets_count")?;
                 let tile_offsets_addr = self.info.usize("tile_offsets")?;
                 let tile_width = self.info.usize("tile_width")?;
                 let tile_len = self.info.usize("tile_len")?;
 
-                let offsets_iter =
-                    buffer[tile_offsets_addr..tile_offsets_addr + 4 * tile_offsets_count].chunks(4);
-                let counts_iter =
-                    buffer[byte_counts_addr..byte_counts_addr + 4 * tile_offsets_count].chunks(4);
-
-                let tiles = offsets_iter
-                    .zip(counts_iter)
-                    .map(|(offset_bytes, count_bytes)| {
-                        let offset_addr = offset_bytes.u32(self.info.is_le, 0) as usize;
-                        let count_addr = count_bytes.u32(self.info.is_le, 0) as usize;
-                        (offset_addr, count_addr)
-                    })
-                    .collect::<Vec<_>>();
+                // a single value is stored in place of the address
+                let tiles = if tile_offsets_count == 1 {
+                    vec![(tile_offsets_addr, byte_counts_addr)]
+                } else {
+                    let offsets_len = tile_offsets_count.saturating_mul(4);
+                    let offsets_iter = get_bytes(buffer, tile_offsets_addr, offsets_len)?.chunks(4);
+                    let counts_iter = get_bytes(buffer, byte_counts_addr, offsets_len)?.chunks(4);
+                    offsets_iter
+                        .zip(counts_iter)
+                        .map(|(offset_bytes, count_bytes)| {
+                            let offset_addr = offset_bytes.u32(self.info.is_le, 0) as usize;
+                            let count_addr = count_bytes.u32(self.info.is_le, 0) as usize;
+                            (offset_addr, count_addr)
+                        })
+                        .collect::<Vec<_>>()
+                };
 
-                load_compressed(buffer, width, height, tiles, tile_width, tile_len)?
-            }
-            _ => {
-                unimplemented!()
+                let samples_per_pixel = if self.info.u8a4("cfa_pattern").is_ok() { 1 } else { 3 };
+                load_compressed(buffer, width, height, tiles, (tile_width, tile_len), samples_per_pixel, area)?
             }
+            _ => return Err(DecodingError::UnsupportedCompression(compression as u32)),
         };
 
         if image.len() != width * height && image.len() != width * height * 3 {
@@ -284,37 +372,56 @@ fn load_compressed(
     width: usize,
     height: usize,
     tiles: Vec<(usize, usize)>,
-    tile_width: usize,
-    tile_height: usize,
+    (tile_width, tile_height): (usize, usize),
+    samples_per_pixel: usize,
+    area: &Area,
 ) -> Result<Vec<u16>, DecodingError> {
-    let mut out = vec![0u16; width * height * 3];
+    if tile_width == 0 || tile_height == 0 {
+        return Err(DecodingError::InvalidImageSize(tile_width, tile_height));
+    }
+    let mut out = vec![0u16; width * height * samples_per_pixel];
 
-    let tile_count_per_row = width / tile_width;
+    let tile_count_per_row = width.div_ceil(tile_width);
+    let tile_row_len = tile_width.saturating_mul(samples_per_pixel);
 
-    for (tile_index, (addr, size)) in tiles.into_iter().enumerate() {
-        let col = tile_index % tile_count_per_row * tile_width * 3;
+    // tiles are independent LJPEG streams, they are decoded apart and copied into the image after
+    let tile_outs = crate::parallel::map(&tiles, |tile_index, &(addr, size)| {
+        let col = tile_index % tile_count_per_row * tile_width;
         let row = tile_index / tile_count_per_row * tile_height;
+        if !area.overlaps(col..col + tile_width, row..row + tile_height) {
+            return Ok(None);
+        }
 
-        let mut tile_out = vec![0u16; tile_width * tile_height * 3];
+        // every sample takes a bit at least, a tile larger than its data is broken
+        let src = get_bytes(buffer, addr, size)?;
+        let samples = tile_row_len.saturating_mul(tile_height);
+        if samples / 8 > src.len() {
+            return Err(DecodingError::TruncatedData(samples / 8, src.len()));
+        }
+        let mut tile_out = vec![0u16; samples];
 
-        let src = &buffer[addr..addr + size];
         let decompressor = LjpegDecompressor::new(src)?;
 
-        decompressor.decode(
-            &mut tile_out,
-            0,
-            tile_width * 3,
-            tile_width * 3,
-            tile_height,
-        )?;
+        decompressor.decode(&mut tile_out, 0, tile_row_len, tile_row_len, tile_height)?;
+        Ok::<_, DecodingError>(Some(tile_out))
+    });
+
+    for (tile_index, tile_out) in tile_outs.into_iter().enumerate() {
+        let Some(tile_out) = tile_out? else {
+            continue;
+        };
+        let col = tile_index % tile_count_per_row * tile_width;
+        let row = tile_index / tile_count_per_row * tile_height;
 
+        // tiles on the right and bottom edges may exceed the image
+        let copy_len = tile_width.min(width - col) * samples_per_pixel;
         tile_out
-            .chunks(tile_width * 3)
+            .chunks(tile_row_len)
+            .take(height.saturating_sub(row))
             .enumerate()
             .for_each(|(offset_row, data)| {
-                let start = col + (row + offset_row) * width * 3;
-                let end = start + tile_width * 3;
-                (&mut out[start..end]).copy_from_slice(data);
+                let start = (col + (row + offset_row) * width) * samples_per_pixel;
+                out[start..start + copy_len].copy_from_slice(&data[..copy_len]);
             });
     }
 
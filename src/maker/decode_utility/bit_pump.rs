@@ -1,4 +1,32 @@
 use super::super::utility::GetNumFromBytes;
+use super::super::DecodingError;
+
+/// Reads `N` bytes from `pos`, the bytes past the end of `buffer` are zeros.
+///
+/// Pumps read ahead of the bits they return, so the last ones read past the end of the data.
+#[inline(always)]
+fn padded_bytes<const N: usize>(buffer: &[u8], pos: usize) -> [u8; N] {
+    match buffer.get(pos..pos + N) {
+        Some(bytes) => bytes.try_into().unwrap(),
+        None => {
+            let mut bytes = [0u8; N];
+            let rest = buffer.get(pos..).unwrap_or_default();
+            bytes[..rest.len()].copy_from_slice(rest);
+            bytes
+        }
+    }
+}
+
+/// Fails when the bits consumed from `pos` minus the `nbits` left go past the end of `buffer`.
+#[inline(always)]
+fn check_consumed(buffer: &[u8], pos: usize, nbits: u32) -> Result<(), DecodingError> {
+    let consumed = (pos * 8).saturating_sub(nbits as usize).div_ceil(8);
+    if consumed > buffer.len() {
+        Err(DecodingError::TruncatedData(consumed, buffer.len()))
+    } else {
+        Ok(())
+    }
+}
 
 #[derive(Debug, Copy, Clone)]
 pub(in super::super) struct BitPumpMSB<'a> {
@@ -47,7 +75,7 @@ impl<'a> BitPump for BitPumpMSB32<'a> {
     #[inline(always)]
     fn peek_bits(&mut self, num: u32) -> u32 {
         if num > self.nbits {
-            let inbits: u64 = self.buffer.u32le(self.pos) as u64;
+            let inbits: u64 = u32::from_le_bytes(padded_bytes(self.buffer, self.pos)) as u64;
             self.bits = (self.bits << 32) | inbits;
             self.pos += 4;
             self.nbits += 32;
@@ -60,6 +88,10 @@ impl<'a> BitPump for BitPumpMSB32<'a> {
         self.nbits -= num;
         self.bits &= (1 << self.nbits) - 1;
     }
+
+    fn check_end(&self) -> Result<(), DecodingError> {
+        check_consumed(self.buffer, self.pos, self.nbits)
+    }
 }
 #[derive(Debug, Copy, Clone)]
 pub(in super::super) struct BitPumpJPEG<'a> {
@@ -68,6 +100,8 @@ pub(in super::super) struct BitPumpJPEG<'a> {
     bits: u64,
     nbits: u32,
     finished: bool,
+    // the zero bits added after the end of the data
+    stuffed: u32,
 }
 
 impl<'a> BitPumpJPEG<'a> {
@@ -78,6 +112,7 @@ impl<'a> BitPumpJPEG<'a> {
             bits: 0,
             nbits: 0,
             finished: false,
+            stuffed: 0,
         }
     }
 }
@@ -86,7 +121,7 @@ impl<'a> BitPump for BitPumpJPEG<'a> {
     #[inline(always)]
     fn peek_bits(&mut self, num: u32) -> u32 {
         if num > self.nbits && !self.finished {
-            if self.pos < self.buffer.len() - 4
+            if self.pos + 4 < self.buffer.len()
                 && self.buffer[self.pos] != 0xff
                 && self.buffer[self.pos + 1] != 0xff
                 && self.buffer[self.pos + 2] != 0xff
@@ -103,16 +138,18 @@ impl<'a> BitPump for BitPumpJPEG<'a> {
                     let byte = {
                         if self.pos >= self.buffer.len() {
                             self.finished = true;
+                            self.stuffed += 8;
                             0
                         } else {
                             let nextbyte = self.buffer[self.pos];
                             if nextbyte != 0xff {
                                 nextbyte
-                            } else if self.buffer[self.pos + 1] == 0x00 {
+                            } else if self.buffer.get(self.pos + 1) == Some(&0x00) {
                                 self.pos += 1; // Skip the extra byte used to mark 255
                                 nextbyte
                             } else {
                                 self.finished = true;
+                                self.stuffed += 8;
                                 0
                             }
                         }
@@ -128,6 +165,7 @@ impl<'a> BitPump for BitPumpJPEG<'a> {
             // Stuff with zeroes to not fail to read
             self.bits <<= 32;
             self.nbits += 32;
+            self.stuffed += 32;
         }
 
         (self.bits >> (self.nbits - num)) as u32
@@ -138,6 +176,17 @@ impl<'a> BitPump for BitPumpJPEG<'a> {
         self.nbits -= num;
         self.bits &= (1 << self.nbits) - 1;
     }
+
+    fn check_end(&self) -> Result<(), DecodingError> {
+        if self.nbits < self.stuffed {
+            Err(DecodingError::TruncatedData(
+                self.buffer.len() + ((self.stuffed - self.nbits) as usize).div_ceil(8),
+                self.buffer.len(),
+            ))
+        } else {
+            Ok(())
+        }
+    }
 }
 #[derive(Debug, Copy, Clone)]
 pub(in super::super) struct BitPumpLSB<'a> {
@@ -180,6 +229,11 @@ pub(in super::super) trait BitPump {
     fn peek_bits(&mut self, num: u32) -> u32;
     fn consume_bits(&mut self, num: u32);
 
+    /// Reports whether the bits consumed so far go past the end of the data.
+    ///
+    /// The pumps return zeros after the end, so the decoders check it once when they are done.
+    fn check_end(&self) -> Result<(), DecodingError>;
+
     #[inline(always)]
     fn get_bits(&mut self, num: u32) -> u32 {
         if num == 0 {
@@ -214,7 +268,7 @@ impl<'a> BitPump for BitPumpLSB<'a> {
     #[inline(always)]
     fn peek_bits(&mut self, num: u32) -> u32 {
         if num > self.nbits {
-            let inbits: u64 = self.buffer.u32le(self.pos) as u64;
+            let inbits: u64 = u32::from_le_bytes(padded_bytes(self.buffer, self.pos)) as u64;
             self.bits = ((inbits << 32) | (self.bits << (32 - self.nbits))) >> (32 - self.nbits);
             self.pos += 4;
             self.nbits += 32;
@@ -227,13 +281,17 @@ impl<'a> BitPump for BitPumpLSB<'a> {
         self.nbits -= num;
         self.bits >>= num;
     }
+
+    fn check_end(&self) -> Result<(), DecodingError> {
+        check_consumed(self.buffer, self.pos, self.nbits)
+    }
 }
 
 impl<'a> BitPump for BitPumpMSB<'a> {
     #[inline(always)]
     fn peek_bits(&mut self, num: u32) -> u32 {
         if num > self.nbits {
-            let inbits: u64 = self.buffer.u32be(self.pos) as u64;
+            let inbits: u64 = u32::from_be_bytes(padded_bytes(self.buffer, self.pos)) as u64;
             self.bits = (self.bits << 32) | inbits;
             self.pos += 4;
             self.nbits += 32;
@@ -246,6 +304,10 @@ impl<'a> BitPump for BitPumpMSB<'a> {
         self.nbits -= num;
         self.bits &= (1 << self.nbits) - 1;
     }
+
+    fn check_end(&self) -> Result<(), DecodingError> {
+        check_consumed(self.buffer, self.pos, self.nbits)
+    }
 }
 
 impl<'a> BitPump for BitPumpPanasonic<'a> {
@@ -254,15 +316,26 @@ impl<'a> BitPump for BitPumpPanasonic<'a> {
             self.nbits += 0x4000 * 8;
             self.pos += 0x4000;
         }
-        let mut byte = (self.nbits - num) >> 3 ^ 0x3ff0;
-        if self.split {
-            byte = (byte + 0x4000 - 0x2008) % 0x4000;
-        }
-        let bits = self.buffer.u16le(byte as usize + self.pos - 0x4000) as u32;
+        let byte = ((self.nbits - num) >> 3 ^ 0x3ff0) as usize;
+        // the two bytes are apart when the split wraps around between them
+        let [low, high] = [byte, byte + 1].map(|byte| {
+            let byte = if self.split { (byte + 0x4000 - 0x2008) % 0x4000 } else { byte };
+            padded_bytes::<1>(self.buffer, byte + self.pos - 0x4000)[0]
+        });
+        let bits = u16::from_le_bytes([low, high]) as u32;
         (bits >> ((self.nbits - num) & 7)) & (0x0ffffffffu32 >> (32 - num))
     }
 
     fn consume_bits(&mut self, num: u32) {
         self.nbits -= num;
     }
+
+    fn check_end(&self) -> Result<(), DecodingError> {
+        // the blocks are read whole, past the end only when the one in use is cut
+        if self.pos > self.buffer.len() {
+            Err(DecodingError::TruncatedData(self.pos, self.buffer.len()))
+        } else {
+            Ok(())
+        }
+    }
 }
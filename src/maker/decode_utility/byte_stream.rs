@@ -23,44 +23,52 @@ impl<'a> ByteStream<'a> {
         self.pos
     }
 
+    /// Reads `N` bytes from the position, past the end of the buffer is an error.
     #[inline(always)]
-    pub(in super::super) fn peek_u8(&self) -> u8 {
-        self.buffer[self.pos]
+    fn peek<const N: usize>(&self) -> Result<[u8; N], DecodingError> {
+        self.buffer
+            .get(self.pos..self.pos.saturating_add(N))
+            .and_then(|x| x.try_into().ok())
+            .ok_or(DecodingError::ByteStreamEnded(self.pos, self.buffer.len()))
+    }
+
+    #[inline(always)]
+    pub(in super::super) fn peek_u8(&self) -> Result<u8, DecodingError> {
+        Ok(self.peek::<1>()?[0])
     }
     #[inline(always)]
-    pub(in super::super) fn get_u8(&mut self) -> u8 {
-        let val = self.peek_u8();
+    pub(in super::super) fn get_u8(&mut self) -> Result<u8, DecodingError> {
+        let val = self.peek_u8()?;
         self.pos += 1;
-        val
+        Ok(val)
     }
 
     #[inline(always)]
-    pub(in super::super) fn peek_u16(&self) -> u16 {
-        self.buffer.u16(self.is_le, self.pos)
+    pub(in super::super) fn peek_u16(&self) -> Result<u16, DecodingError> {
+        Ok(self.peek::<2>()?.as_slice().u16(self.is_le, 0))
     }
     #[inline(always)]
-    pub(in super::super) fn get_u16(&mut self) -> u16 {
-        let val = self.peek_u16();
+    pub(in super::super) fn get_u16(&mut self) -> Result<u16, DecodingError> {
+        let val = self.peek_u16()?;
         self.pos += 2;
-        val
+        Ok(val)
     }
 
+    /// Skips bytes, the reads after it fail when it goes past the end.
     #[inline(always)]
     pub(in super::super) fn consume_bytes(&mut self, num: usize) {
-        self.pos += num
+        self.pos = self.pos.saturating_add(num)
     }
 
     #[inline(always)]
     pub(in super::super) fn skip_to_marker(&mut self) -> Result<usize, DecodingError> {
-        let mut skip_count = 0;
-        while !(self.buffer[self.pos] == 0xFF && self.buffer[self.pos + 1] != 0 && self.buffer[self.pos + 1] != 0xFF) {
-            self.pos += 1;
-            skip_count += 1;
-            if self.pos >= self.buffer.len() {
-                return Err(DecodingError::ByteStreamNoMarkerFound);
-            }
-        }
-        self.pos += 1; // Make the next byte the marker
+        let is_marker = |x: &[u8]| x[0] == 0xFF && x[1] != 0 && x[1] != 0xFF;
+        let rest = self.buffer.get(self.pos..).unwrap_or_default();
+        let skip_count = rest
+            .windows(2)
+            .position(is_marker)
+            .ok_or(DecodingError::ByteStreamNoMarkerFound)?;
+        self.pos += skip_count + 1; // Make the next byte the marker
         Ok(skip_count + 1)
     }
 }
\ No newline at end of file
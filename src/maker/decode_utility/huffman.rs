@@ -65,6 +65,9 @@ impl BitPump for MockPump {
         self.nbits -= num;
         self.bits &= (1 << self.nbits) - 1;
     }
+    fn check_end(&self) -> Result<(), super::super::DecodingError> {
+        Ok(())
+    }
 }
 
 impl HuffTable {
@@ -84,6 +87,21 @@ impl HuffTable {
     }
 
     pub(in super::super) fn new(bits: [u32; 17], huffval: [u32; 256], dng_bug: bool) -> Result<HuffTable, DecodingError> {
+        // the codes have to fit in 16 bits and the values are the lengths of the diffs
+        let kraft_sum = (1..17).fold(0u64, |sum, len| sum + ((bits[len] as u64) << (16 - len)));
+        if kraft_sum == 0 || kraft_sum > 1 << 16 {
+            return Err(DecodingError::LJpegErrorConstructor(
+                "the code lengths do not make a Huffman table".to_string(),
+            ));
+        }
+        let count = bits.iter().sum::<u32>() as usize;
+        if let Some(val) = huffval.iter().take(count).find(|&&x| x > 16) {
+            return Err(DecodingError::LJpegErrorConstructor(format!(
+                "the diff length {} is more than 16 bits",
+                val
+            )));
+        }
+
         let mut tbl = HuffTable {
             bits,
             huffval,
@@ -1,3 +1,4 @@
+use super::super::bit_pump::BitPump;
 use super::super::bit_pump::BitPumpJPEG;
 use super::super::bit_pump::BitPumpMSB32;
 use super::super::huffman::*;
@@ -25,6 +26,14 @@ pub fn decode_ljpeg(
         ));
     }
 
+    if width == 0
+        || height == 0
+        || !width.is_multiple_of(ncomp)
+        || out.len() < (height - 1) * stripwidth + x + width
+    {
+        return Err(format!("ljpeg: cannot decode {}x{} into {} samples", width, height, out.len()));
+    }
+
     let htable =
         |index: usize| -> &HuffTable { &ljpeg.dhts[ljpeg.sof.components[index].dc_tbl_num] };
     let mut pump = BitPumpJPEG::new(ljpeg.buffer);
@@ -77,14 +86,12 @@ pub fn decode_ljpeg(
                             let c = out[(row - 1) * stripwidth + (col - ncomp) + c] as i32;
                             b + ((a - c) >> 1)
                         }
-                        (row, 7) => {
+                        // 7, the other predictors are rejected by `LjpegDecompressor::decode`
+                        (row, _) => {
                             let a = out[row * stripwidth + (col - ncomp) + c] as i32;
                             let b = out[(row - 1) * stripwidth + col + c] as i32;
                             (a + b) >> 1 // Adobe DNG SDK uses int32 and shifts, so we will do, too.
                         }
-                        _ => {
-                            panic!("Unsupported prediction in LJPEG")
-                        }
                     }
                 };
 
@@ -100,7 +107,7 @@ pub fn decode_ljpeg(
         }
     }
 
-    Ok(())
+    pump.check_end().map_err(|err| err.to_string())
 }
 
 fn set_yuv_420(
@@ -120,13 +127,6 @@ fn set_yuv_420(
     let pix3 = (row + 1) * width + col;
     let pix4 = pix3 + 3;
 
-    debug_assert!(!y1.is_negative());
-    debug_assert!(!y2.is_negative());
-    debug_assert!(!y3.is_negative());
-    debug_assert!(!y4.is_negative());
-    debug_assert!(!cb.is_negative());
-    debug_assert!(!cr.is_negative());
-
     out[pix1 + 0] = y1 as u16;
     out[pix1 + 1] = cb as u16;
     out[pix1 + 2] = cr as u16;
@@ -157,9 +157,15 @@ pub fn decode_ljpeg_420(
         ));
     }
 
-    debug_assert_eq!(width % 2, 0);
-    debug_assert_eq!(width % 6, 0); // Ensure we have enough samples for .step_by(6)
-    debug_assert_eq!(height % 2, 0);
+    // Ensure we have enough samples for .step_by(6) and pairs of lines
+    if ljpeg.components() < 3
+        || width == 0
+        || !width.is_multiple_of(6)
+        || !height.is_multiple_of(2)
+        || out.len() < width * height
+    {
+        return Err(format!("ljpeg: cannot decode 4:2:0 samples into {}x{}", width, height));
+    }
 
     let htable1 = &ljpeg.dhts[ljpeg.sof.components[0].dc_tbl_num];
     let htable2 = &ljpeg.dhts[ljpeg.sof.components[1].dc_tbl_num];
@@ -197,7 +203,7 @@ pub fn decode_ljpeg_420(
         }
     }
 
-    Ok(())
+    pump.check_end().map_err(|err| err.to_string())
 }
 
 fn set_yuv_422(
@@ -213,11 +219,6 @@ fn set_yuv_422(
     let pix1 = row * width + col;
     let pix2 = pix1 + 3;
 
-    debug_assert!(!y1.is_negative());
-    debug_assert!(!y2.is_negative());
-    debug_assert!(!cb.is_negative());
-    debug_assert!(!cr.is_negative());
-
     out[pix1 + 0] = y1 as u16;
     out[pix1 + 1] = cb as u16;
     out[pix1 + 2] = cr as u16;
@@ -241,6 +242,13 @@ pub fn decode_ljpeg_422(
             height
         ));
     }
+    if ljpeg.components() < 3
+        || width * height == 0
+        || !width.is_multiple_of(6)
+        || out.len() < width * height
+    {
+        return Err(format!("ljpeg: cannot decode 4:2:2 samples into {}x{}", width, height));
+    }
     let htable1 = &ljpeg.dhts[ljpeg.sof.components[0].dc_tbl_num];
     let htable2 = &ljpeg.dhts[ljpeg.sof.components[1].dc_tbl_num];
     let htable3 = &ljpeg.dhts[ljpeg.sof.components[2].dc_tbl_num];
@@ -274,7 +282,7 @@ pub fn decode_ljpeg_422(
         }
     }
 
-    Ok(())
+    pump.check_end().map_err(|err| err.to_string())
 }
 
 pub fn decode_hasselblad(
@@ -284,6 +292,9 @@ pub fn decode_hasselblad(
 ) -> Result<(), String> {
     // Pixels are packed two at a time, not like LJPEG:
     // [p1_length_as_huffman][p2_length_as_huffman][p0_diff_with_length][p1_diff_with_length]|NEXT PIXELS
+    if width == 0 || !width.is_multiple_of(2) {
+        return Err(format!("ljpeg: cannot decode pairs of pixels into a width of {}", width));
+    }
     let mut pump = BitPumpMSB32::new(ljpeg.buffer);
     let htable = &ljpeg.dhts[ljpeg.sof.components[0].dc_tbl_num];
 
@@ -300,5 +311,5 @@ pub fn decode_hasselblad(
         }
     }
 
-    Ok(())
+    pump.check_end().map_err(|err| err.to_string())
 }
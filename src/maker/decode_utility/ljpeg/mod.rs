@@ -57,11 +57,11 @@ impl SOFInfo {
     }
 
     fn parse_sof(&mut self, input: &mut ByteStream) -> Result<(), String> {
-        let header_length = input.get_u16() as usize;
-        self.precision = input.get_u8() as usize;
-        self.height = input.get_u16() as usize;
-        self.width = input.get_u16() as usize;
-        self.cps = input.get_u8() as usize;
+        let header_length = input.get_u16()? as usize;
+        self.precision = input.get_u8()? as usize;
+        self.height = input.get_u16()? as usize;
+        self.width = input.get_u16()? as usize;
+        self.cps = input.get_u8()? as usize;
 
         if self.precision > 16 {
             return Err("ljpeg: More than 16 bits per channel is not supported.".to_string());
@@ -72,11 +72,13 @@ impl SOFInfo {
         if header_length != 8 + self.cps * 3 {
             return Err("ljpeg: Header size mismatch.".to_string());
         }
+        // a second frame header replaces the first one
+        self.components.clear();
 
         for i in 0..self.cps {
-            let id = input.get_u8() as usize;
-            let subs = input.get_u8() as usize;
-            input.get_u8(); // Skip info about quantized
+            let id = input.get_u8()? as usize;
+            let subs = input.get_u8()? as usize;
+            input.get_u8()?; // Skip info about quantized
 
             self.components.push(JpegComponentInfo {
                 id,
@@ -93,28 +95,28 @@ impl SOFInfo {
         if self.width == 0 {
             return Err("ljpeg: Trying to parse SOS before SOF".to_string());
         }
-        input.get_u16(); //skip header length
-        let soscps = input.get_u8() as usize;
+        input.get_u16()?; //skip header length
+        let soscps = input.get_u8()? as usize;
         if self.cps != soscps {
             return Err("ljpeg: component number mismatch in SOS".to_string());
         }
         for cs in 0..self.cps {
             // At least some MOS cameras have this broken
-            let readcs = input.get_u8() as usize;
+            let readcs = input.get_u8()? as usize;
             let cs = if self.csfix { cs } else { readcs };
             let component = match self.components.iter_mut().find(|&&mut c| c.id == cs) {
                 Some(val) => val,
                 None => return Err(format!("ljpeg: invalid component selector {}", cs)),
             };
-            let td = (input.get_u8() as usize) >> 4;
+            let td = (input.get_u8()? as usize) >> 4;
             if td > 3 {
                 return Err("ljpeg: Invalid Huffman table selection".to_string());
             }
             component.dc_tbl_num = td;
         }
-        let pred = input.get_u8() as usize;
-        input.get_u8(); // Se + Ah Not used in LJPEG
-        let pt = (input.get_u8() as usize) & 0xf; // Point Transform
+        let pred = input.get_u8()? as usize;
+        input.get_u8()?; // Se + Ah Not used in LJPEG
+        let pt = (input.get_u8()? as usize) & 0xf; // Point Transform
         Ok((pred, pt))
     }
 }
@@ -180,10 +182,17 @@ impl<'a> LjpegDecompressor<'a> {
             }
         }
 
+        if pt >= sof.precision {
+            return Err(format!("ljpeg: point transform {} of {} bits", pt, sof.precision));
+        }
+        if sof.components.iter().any(|x| !dht_init[x.dc_tbl_num]) {
+            return Err("ljpeg: a component uses a missing Huffman table".to_string());
+        }
+
         let mut dhts = Vec::new();
         for i in 0..4 {
             dhts.push(if dht_init[i] {
-                HuffTable::new(dht_bits[i], dht_huffval[i], dng_bug).unwrap()
+                HuffTable::new(dht_bits[i], dht_huffval[i], dng_bug)?
             } else {
                 HuffTable::empty()
             });
@@ -216,18 +225,18 @@ impl<'a> LjpegDecompressor<'a> {
 
     fn get_next_marker(input: &mut ByteStream, allowskip: bool) -> Result<u8, String> {
         if !allowskip {
-            if input.get_u8() != 0xff {
+            if input.get_u8()? != 0xff {
                 return Err("ljpeg: (noskip) expected marker not found".to_string());
             }
-            let mark = input.get_u8();
+            let mark = input.get_u8()?;
             if mark == m(Marker::Stuff) || mark == m(Marker::Fill) {
                 return Err("ljpeg: (noskip) expected marker but found stuff or fill".to_string());
             }
             return Ok(mark);
         }
-        input.skip_to_marker().unwrap();
+        input.skip_to_marker()?;
 
-        Ok(input.get_u8())
+        Ok(input.get_u8()?)
     }
 
     fn parse_dht(
@@ -236,10 +245,12 @@ impl<'a> LjpegDecompressor<'a> {
         bits: &mut [[u32; 17]; 4],
         huffval: &mut [[u32; 256]; 4],
     ) -> Result<(), String> {
-        let mut length = (input.get_u16() as usize) - 2;
+        let mut length = (input.get_u16()? as usize)
+            .checked_sub(2)
+            .ok_or("ljpeg: invalid DHT length")?;
 
         while length > 0 {
-            let b = input.get_u8() as usize;
+            let b = input.get_u8()? as usize;
             let tc = b >> 4;
             let th = b & 0xf;
 
@@ -252,7 +263,7 @@ impl<'a> LjpegDecompressor<'a> {
 
             let mut acc: usize = 0;
             for i in 0..16 {
-                bits[th][i + 1] = input.get_u8() as u32;
+                bits[th][i + 1] = input.get_u8()? as u32;
                 acc += bits[th][i + 1] as usize;
             }
             bits[th][0] = 0;
@@ -266,7 +277,7 @@ impl<'a> LjpegDecompressor<'a> {
             }
 
             for i in 0..acc {
-                huffval[th][i] = input.get_u8() as u32;
+                huffval[th][i] = input.get_u8()? as u32;
             }
 
             init[th] = true;
@@ -380,12 +391,14 @@ impl<'a> LjpegDecompressor<'a> {
     //     ))
     // }
 
-    // pub fn width(&self) -> usize {
-    //     self.sof.width * self.sof.cps
-    // }
-    // pub fn height(&self) -> usize {
-    //     self.sof.height
-    // }
+    #[cfg(feature = "fuzzing")]
+    pub fn width(&self) -> usize {
+        self.sof.width * self.sof.cps
+    }
+    #[cfg(feature = "fuzzing")]
+    pub fn height(&self) -> usize {
+        self.sof.height
+    }
     // pub fn super_v(&self) -> usize {
     //     self.sof.components[0].super_v
     // }
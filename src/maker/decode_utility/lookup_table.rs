@@ -10,12 +10,13 @@ impl LookupTable {
             let center = table[i];
             let lower = if i > 0 { table[i - 1] } else { center };
             let upper = if i < (table.len() - 1) { table[i + 1] } else { center };
+            // the tables read from files may not be increasing
+            let delta = upper.wrapping_sub(lower);
             let base = if center == 0 {
                 0
             } else {
-                center - ((upper - lower + 2) / 4)
+                center.wrapping_sub(delta.wrapping_add(2) / 4)
             };
-            let delta = upper - lower;
             tbl[i] = (center, base, delta);
         }
         LookupTable { table: tbl }
@@ -26,9 +27,10 @@ impl LookupTable {
     //    val
     //  }
 
+    /// The values past the end of the table take its last entry.
     #[inline(always)]
     pub(in super::super) fn dither(&self, value: u16, rand: &mut u32) -> u16 {
-        let (_, sbase, sdelta) = self.table[value as usize];
+        let (_, sbase, sdelta) = self.table[(value as usize).min(self.table.len() - 1)];
         let base = sbase as u32;
         let delta = sdelta as u32;
         let pixel = base + ((delta * (*rand & 2047) + 1024) >> 12);
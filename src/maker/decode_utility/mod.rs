@@ -10,8 +10,17 @@ pub(in super::super) mod ljpeg;
 pub enum DecodingError {
     #[error("No marker found inside rest of buffer.")]
     ByteStreamNoMarkerFound,
+    #[error("The byte stream of {1} bytes is read past its end at {0}.")]
+    ByteStreamEnded(usize, usize),
     #[error("LJpeg constructor error: {0}")]
     LJpegErrorConstructor(String),
     #[error("LJpegDecompressing error: {0}")]
     LJpegError(String),
+}
+
+// the LJPEG parser reports its errors as strings
+impl From<DecodingError> for String {
+    fn from(err: DecodingError) -> String {
+        err.to_string()
+    }
 }
\ No newline at end of file
@@ -62,6 +62,49 @@ pub(super) static IMAGE_RULE: Lazy<quickexif::ParsingRule> = Lazy::new(|| {
 });
 
 
+pub(super) static LENS_RULE: Lazy<quickexif::ParsingRule> = Lazy::new(|| {
+    let auto_bracketing = u16_tag(0x1100, "auto_bracketing");
+    quickexif::describe_rule!(tiff {
+        0x8769 {
+            0x927c / maker_notes {
+                // "FUJIFILM" and the offset of the IFD from the start of the maker notes
+                offset + 8 {
+                    offset address {
+                        offset + maker_notes {
+                            load(auto_bracketing)
+                            0x1103? / drive_settings
+                            0x1422? {
+                                offset + maker_notes {
+                                    u16 + 1 / stabilization
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    })
+});
+
+/// Converts the items of `LENS_RULE`.
+pub(super) fn get_shooting_params(info: &quickexif::ParsedInfo) -> ShootingParams {
+    let drive_mode = match (info.u16("auto_bracketing"), info.u32("drive_settings")) {
+        (Ok(1 | 2), _) => Some(DriveMode::Bracketing),
+        (_, Ok(x)) => match x & 0xff {
+            0 => Some(DriveMode::Single),
+            1 | 2 => Some(DriveMode::Continuous),
+            _ => None,
+        },
+        _ => None,
+    };
+
+    ShootingParams {
+        drive_mode,
+        stabilization: info.u16("stabilization").ok().map(|x| x != 0),
+        ..Default::default()
+    }
+}
+
 impl RawDecoder for General {
     fn new(info: quickexif::ParsedInfo) -> Self {
         General { info }
@@ -89,21 +132,26 @@ impl RawDecoder for General {
         };
         Ok(result)
     }
-    fn decode_with_preprocess(&self, buffer: &[u8]) -> Result<Vec<u16>, DecodingError> {
+    fn decode_with_preprocess(&self, buffer: &[u8], area: &Area) -> Result<Vec<u16>, DecodingError> {
+        let mut image = self.decode_raw(buffer, area)?;
+        let size = utility::get_image_size(&self.info, buffer)?;
+        subtract_black_level(&mut image, size, self.get_black_level()?, self.get_bps_scale()?);
+        Ok(image)
+    }
+    fn decode_raw(&self, buffer: &[u8], area: &Area) -> Result<Vec<u16>, DecodingError> {
         let jpeg_header_offset = 12;
         let tiff_offset = self.info.usize("tiff_offset")?;
         let strip_offset = self.info.usize("strip")?;
         let strip_len = self.info.usize("strip_len")?;
-        let width = self.info.usize("width")?;
-        let height = self.info.usize("height")?;
-        let black_level = self.info.u16("black_level")?;
-        let bps_scale = self.get_bps_scale()?;
+        let (width, height) = utility::get_image_size(&self.info, buffer)?;
 
-        let data_offset = jpeg_header_offset + tiff_offset + strip_offset;
-        let buf = &buffer[data_offset..data_offset + strip_len];
-        let image: Vec<u16> = utility::to_14bit_iter(buf, self.info.is_le)
-            .map(|x| bps_scale.saturating_mul(x.saturating_sub(black_level)))
-            .collect();
+        let data_offset = tiff_offset.saturating_add(strip_offset).saturating_add(jpeg_header_offset);
+        let buf = utility::get_bytes(buffer, data_offset, strip_len)?;
+        let is_le = self.info.is_le;
+        let image = utility::decode_rows(buf, (width, height), width * 2, area, |row| {
+            utility::to_14bit_iter(row, is_le)
+        })
+        .unwrap_or_else(|| utility::to_14bit_iter(buf, is_le).collect());
 
         if image.len() != width * height {
             Err(DecodingError::InvalidDecodedImageSize(image.len(), width * height))
@@ -112,19 +160,27 @@ impl RawDecoder for General {
         }
     }
 
+    /// The embedded JPEG starts the buffer, the range ends with the tiny thumbnail in its EXIF block
+    /// and the image data after it is bounded by the header of the RAF file.
+    fn get_thumbnail_range(&self) -> Result<Range<usize>, DecodingError> {
+        let offset = self.info.usize("thumbnail")?;
+        let len = self.info.usize("thumbnail_len")?;
+        let jpeg_header_offset = 12;
+        Ok(0..offset.saturating_add(len).saturating_add(jpeg_header_offset))
+    }
     fn get_thumbnail<'a>(&self, buffer: &'a [u8]) -> Result<&'a [u8], DecodingError> {
         let offset = self.info.usize("thumbnail")?;
         let len = self.info.usize("thumbnail_len")?;
         let jpeg_header_offset = 12;
-        let tiny_thumbnail_offset = jpeg_header_offset + offset + len;
+        let tiny_thumbnail_offset = offset.saturating_add(len).saturating_add(jpeg_header_offset);
 
-        let jpeg_eoi = &buffer[tiny_thumbnail_offset..]
+        let jpeg_eoi = &utility::get_bytes_from(buffer, tiny_thumbnail_offset)?
             .windows(2)
             .enumerate()
             .find(|(_, data)| data == &[0xff, 0xd9]);
 
         match jpeg_eoi {
-            None => Ok(&buffer[offset..tiny_thumbnail_offset]),
+            None => utility::get_bytes(buffer, offset, tiny_thumbnail_offset - offset),
             &Some((index, _)) => Ok(&buffer[..tiny_thumbnail_offset + index + 2]),
         }
     }
@@ -0,0 +1,62 @@
+//! The decoders of compressed sensor data for the fuzz targets in `fuzz/`, they are not reachable alone otherwise.
+
+use super::decode_utility::bit_pump::*;
+use super::decode_utility::ljpeg::LjpegDecompressor;
+use super::DecodingError;
+
+/// The most samples of a frame to decode, a fuzzed header asks for far more than it holds.
+const MAX_SAMPLES: usize = 1 << 24;
+
+/// Decodes a lossless JPEG stream into the whole size of its frame.
+pub fn ljpeg(data: &[u8]) -> Result<Vec<u16>, DecodingError> {
+    let decompressor = LjpegDecompressor::new(data)?;
+    let (width, height) = (decompressor.width(), decompressor.height());
+    if width.saturating_mul(height) > MAX_SAMPLES {
+        return Err(DecodingError::InvalidImageSize(width, height));
+    }
+
+    let mut out = vec![0u16; width * height];
+    decompressor.decode(&mut out, 0, width, width, height)?;
+    Ok(out)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Pump {
+    Msb,
+    Msb32,
+    Lsb,
+    Jpeg,
+    Panasonic,
+}
+
+impl Pump {
+    pub const ALL: [Pump; 5] = [
+        Pump::Msb,
+        Pump::Msb32,
+        Pump::Lsb,
+        Pump::Jpeg,
+        Pump::Panasonic,
+    ];
+}
+
+/// Reads values of `lengths` bits from `data`, failing when they go past the end of it.
+///
+/// A length is taken modulo the most bits the pump reads at once, 32 or 8 for the Panasonic one.
+pub fn bit_pump(pump: Pump, data: &[u8], lengths: &[u8]) -> Result<Vec<u32>, DecodingError> {
+    fn read(mut pump: impl BitPump, lengths: &[u8], max: u32) -> Result<Vec<u32>, DecodingError> {
+        let values = lengths
+            .iter()
+            .map(|&len| pump.get_bits(len as u32 % (max + 1)))
+            .collect();
+        pump.check_end()?;
+        Ok(values)
+    }
+
+    match pump {
+        Pump::Msb => read(BitPumpMSB::new(data), lengths, 32),
+        Pump::Msb32 => read(BitPumpMSB32::new(data), lengths, 32),
+        Pump::Lsb => read(BitPumpLSB::new(data), lengths, 32),
+        Pump::Jpeg => read(BitPumpJPEG::new(data), lengths, 32),
+        Pump::Panasonic => read(BitPumpPanasonic::new(data, false), lengths, 8),
+    }
+}
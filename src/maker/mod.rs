@@ -1,8 +1,10 @@
 use crate::decode::{CFAPattern, Crop, Orientation};
+use crate::metadata::DriveMode;
+use std::ops::Range;
 use thiserror::Error;
 
 pub(super) mod selector;
-mod utility;
+pub(crate) mod utility;
 
 mod adobe;
 mod decode_utility;
@@ -12,6 +14,71 @@ mod olympus;
 mod panasonic;
 mod sony;
 
+#[cfg(feature = "fuzzing")]
+pub mod fuzzing;
+
+/// Lens and shooting parameters recorded in the maker notes.
+#[derive(Default)]
+pub(super) struct ShootingParams {
+    pub(super) lens_model: Option<String>,
+    pub(super) lens_id: Option<u32>,
+    pub(super) lens_serial: Option<String>,
+    pub(super) body_serial: Option<String>,
+    /// In meters.
+    pub(super) focus_distance: Option<f64>,
+    pub(super) drive_mode: Option<DriveMode>,
+    pub(super) stabilization: Option<bool>,
+}
+
+/// An optional u16 item, the DSL of rules has no syntax for it.
+fn u16_tag(tag: u16, name: &'static str) -> quickexif::ParsingRule {
+    quickexif::ParsingRule::TagItem {
+        tag,
+        name,
+        len: None,
+        is_optional: true,
+        is_value_u16: true,
+    }
+}
+
+/// Reads a string item, an empty one means the value is not recorded.
+fn non_empty_str(info: &quickexif::ParsedInfo, name: &str) -> Option<String> {
+    let value = info.str(name).ok()?.trim();
+    (!value.is_empty()).then(|| value.to_owned())
+}
+
+/// The part of the sensor data to decode, the samples out of it may be skipped and left as zeros.
+///
+/// A row `y` is kept when it is in `rows` and `y % step < count`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct Area {
+    pub(crate) rows: Range<usize>,
+    pub(crate) columns: Range<usize>,
+    pub(crate) step: usize,
+    pub(crate) count: usize,
+}
+impl Area {
+    pub(crate) const ALL: Area = Area {
+        rows: 0..usize::MAX,
+        columns: 0..usize::MAX,
+        step: 1,
+        count: 1,
+    };
+
+    #[inline(always)]
+    pub(crate) fn keeps_row(&self, row: usize) -> bool {
+        self.rows.contains(&row) && row % self.step < self.count
+    }
+
+    /// Whether a block of samples like a tile has any sample in the area.
+    pub(crate) fn overlaps(&self, columns: Range<usize>, rows: Range<usize>) -> bool {
+        columns.start < self.columns.end
+            && self.columns.start < columns.end
+            && rows.start < self.rows.end
+            && self.rows.start < rows.end
+    }
+}
+
 pub(super) trait RawDecoder {
     fn new(info: quickexif::ParsedInfo) -> Self
     where
@@ -36,6 +103,41 @@ pub(super) trait RawDecoder {
         };
         Ok(result)
     }
+    /// The black levels of the 2x2 block at the top left of the CFA pattern in row-major order,
+    /// in the unscaled sensor values.
+    ///
+    /// The makers record them in the order of red, the green of the red rows, the green of
+    /// the blue rows and blue, or a single level for all the samples.
+    fn get_black_level(&self) -> Result<[u16; 4], DecodingError> {
+        let info = self.get_info();
+        let levels = match (
+            info.u16("black_level_r"),
+            info.u16("black_level_g"),
+            info.u16("black_level_b"),
+        ) {
+            (Ok(r), Ok(g), Ok(b)) => [r, g, g, b],
+            _ => {
+                let level = info.u16("black_level")?;
+                match (
+                    info.u16("black_level_1"),
+                    info.u16("black_level_2"),
+                    info.u16("black_level_3"),
+                ) {
+                    (Ok(gr), Ok(gb), Ok(b)) => [level, gr, gb, b],
+                    _ => [level; 4],
+                }
+            }
+        };
+        Ok(to_cfa_order(levels, self.get_cfa_pattern()?))
+    }
+    /// The saturation level, the full range of `bps` when the file does not record one.
+    fn get_white_level(&self) -> Option<u16> {
+        let info = self.get_info();
+        info.u16("white_level").ok().or_else(|| {
+            let bps = info.u16("bps").ok().filter(|&x| x > 0 && x <= 16)?;
+            Some(((1u32 << bps) - 1) as u16)
+        })
+    }
     fn get_orientation(&self) -> Orientation {
         match self.get_info().u16("orientation").ok() {
             None => Orientation::Horizontal,
@@ -48,19 +150,126 @@ pub(super) trait RawDecoder {
             },
         }
     }
-    fn decode_with_preprocess(&self, buffer: &[u8]) -> Result<Vec<u16>, DecodingError>;
-    fn get_thumbnail<'a>(&self, buffer: &'a [u8]) -> Result<&'a [u8], DecodingError>;
+    /// The curve mapping the values of `decode_raw` to linear ones, applied before the black level.
+    fn get_linearization_table(&self, _buffer: &[u8]) -> Result<Option<Vec<u16>>, DecodingError> {
+        Ok(None)
+    }
+    /// Subtracts the black level and scales the sensor values to the full `u16` range.
+    fn decode_with_preprocess(&self, buffer: &[u8], area: &Area) -> Result<Vec<u16>, DecodingError>;
+    /// Decodes the sensor values into `f32`, linearized and normalized between the black level
+    /// and the white level without any rounding or clipping.
+    fn decode_float(&self, buffer: &[u8], area: &Area) -> Result<Vec<f32>, DecodingError> {
+        let image = self.decode_raw(buffer, area)?;
+        let (width, _) = utility::get_image_size(self.get_info(), buffer)?;
+        let table = self.get_linearization_table(buffer)?;
+        let white_level = self.get_white_level().unwrap_or(u16::MAX);
+        Ok(normalize(&image, width, table.as_deref(), self.get_black_level()?, white_level))
+    }
+    /// Decodes the sensor values as they are stored, without any linearization, black level
+    /// subtraction or scaling.
+    ///
+    /// Formats with rows or tiles at known positions of the bit stream skip the samples out of `area`,
+    /// which are left as zeros.
+    fn decode_raw(&self, buffer: &[u8], area: &Area) -> Result<Vec<u16>, DecodingError>;
+    /// The bytes of the embedded preview as far as the tags tell.
+    fn get_thumbnail_range(&self) -> Result<Range<usize>, DecodingError>;
+    fn get_thumbnail<'a>(&self, buffer: &'a [u8]) -> Result<&'a [u8], DecodingError> {
+        let range = self.get_thumbnail_range()?;
+        utility::get_bytes(buffer, range.start, range.len())
+    }
     fn get_cfa_pattern(&self) -> Result<CFAPattern, DecodingError> {
-        let cfa_pattern = self.get_info().u8a4("cfa_pattern")?;
-        let result = match cfa_pattern {
-            [0, 1, 1, 2] => CFAPattern::RGGB,
-            [2, 1, 1, 0] => CFAPattern::BGGR,
-            [1, 0, 2, 1] => CFAPattern::GRBG,
-            [1, 2, 0, 1] => CFAPattern::GBRG,
-            _ => CFAPattern::RGGB,
-        };
-        Ok(result)
+        bayer_pattern(self.get_info().u8a4("cfa_pattern")?)
+    }
+}
+
+/// The pattern of the colors of a 2x2 block, `0` is red, `1` is green and `2` is blue.
+fn bayer_pattern(colors: [u8; 4]) -> Result<CFAPattern, DecodingError> {
+    match colors {
+        [0, 1, 1, 2] => Ok(CFAPattern::RGGB),
+        [2, 1, 1, 0] => Ok(CFAPattern::BGGR),
+        [1, 0, 2, 1] => Ok(CFAPattern::GRBG),
+        [1, 2, 0, 1] => Ok(CFAPattern::GBRG),
+        _ => Err(DecodingError::UnsupportedCFAPattern(u32::from_be_bytes(colors))),
+    }
+}
+
+/// The types of the decoded samples, `u16` scaled to the full range or `f32` normalized.
+pub(crate) trait DecodedSample: Sized {
+    fn decode<D: RawDecoder>(decoder: &D, buffer: &[u8], area: &Area) -> Result<Vec<Self>, DecodingError>;
+}
+impl DecodedSample for u16 {
+    fn decode<D: RawDecoder>(decoder: &D, buffer: &[u8], area: &Area) -> Result<Vec<u16>, DecodingError> {
+        decoder.decode_with_preprocess(buffer, area)
+    }
+}
+impl DecodedSample for f32 {
+    fn decode<D: RawDecoder>(decoder: &D, buffer: &[u8], area: &Area) -> Result<Vec<f32>, DecodingError> {
+        decoder.decode_float(buffer, area)
+    }
+}
+
+/// The samples of `decode_with_preprocess` in `0.0..=1.0`, for the data of three samples a pixel.
+fn to_float(image: Vec<u16>) -> Vec<f32> {
+    image.into_iter().map(|x| x as f32 / u16::MAX as f32).collect()
+}
+
+/// The entry of `table` for the sample `x`, the samples past its end take the last one.
+fn linearize(x: u16, table: &[u16]) -> u16 {
+    table.get(x as usize).or(table.last()).copied().unwrap_or(x)
+}
+
+/// Maps CFA samples along the linearization `table`, then from the black level of their position
+/// in the 2x2 block to `1.0` at the white level.
+fn normalize(image: &[u16], width: usize, table: Option<&[u16]>, black_level: [u16; 4], white_level: u16) -> Vec<f32> {
+    let to_linear = |x: u16| table.map_or(x, |table| linearize(x, table));
+    let ranges = black_level.map(|x| (white_level.saturating_sub(x) as f32).max(1.));
+    let mut out = Vec::with_capacity(image.len());
+    for (row, samples) in image.chunks(width.max(1)).enumerate() {
+        let position = row % 2 * 2;
+        out.extend(samples.iter().enumerate().map(|(col, &x)| {
+            let position = position + col % 2;
+            (to_linear(x) as f32 - black_level[position] as f32) / ranges[position]
+        }));
+    }
+    out
+}
+
+/// Reorders the levels of red, the two greens and blue into the positions of the 2x2 block.
+///
+/// The X-Trans sensors take the level of red for all the samples.
+fn to_cfa_order([r, gr, gb, b]: [u16; 4], cfa_pattern: CFAPattern) -> [u16; 4] {
+    match cfa_pattern {
+        CFAPattern::RGGB => [r, gr, gb, b],
+        CFAPattern::BGGR => [b, gb, gr, r],
+        CFAPattern::GRBG => [gr, r, b, gb],
+        CFAPattern::GBRG => [gb, b, r, gr],
+        CFAPattern::XTrans0 | CFAPattern::XTrans1 => [r; 4],
+    }
+}
+
+/// Subtracts the black level of each position of the 2x2 CFA block and multiplies by `scale`.
+///
+/// The images of three samples a pixel take the level of the first position.
+fn subtract_black_level(
+    image: &mut [u16],
+    (width, height): (usize, usize),
+    black_level: [u16; 4],
+    scale: u16,
+) {
+    if image.len() != width * height || width == 0 {
+        let black_level = black_level[0];
+        image
+            .iter_mut()
+            .for_each(|x| *x = scale.saturating_mul(x.saturating_sub(black_level)));
+        return;
     }
+    image.chunks_exact_mut(width).enumerate().for_each(|(row, samples)| {
+        let levels = &black_level[row % 2 * 2..row % 2 * 2 + 2];
+        samples
+            .iter_mut()
+            .enumerate()
+            .for_each(|(col, x)| *x = scale.saturating_mul(x.saturating_sub(levels[col % 2])));
+    });
 }
 
 #[derive(Error, Debug)]
@@ -71,4 +280,16 @@ pub enum DecodingError {
     InvalidDecodedImageSize(usize, usize),
     #[error("JPEG error.")]
     LJPEGError(#[from] decode_utility::DecodingError),
+    #[error("The image size {0} x {1} is invalid.")]
+    InvalidImageSize(usize, usize),
+    #[error("The compression {0} of the sensor data is not supported.")]
+    UnsupportedCompression(u32),
+    #[error("The {0} bits per sample of the sensor data are not supported.")]
+    UnsupportedBitsPerSample(u16),
+    #[error("The sensor data is truncated, {0} bytes are needed while only {1} bytes are left.")]
+    TruncatedData(usize, usize),
+    #[error("The offset {0} is out of the file of {1} bytes.")]
+    OffsetOutOfRange(usize, usize),
+    #[error("The CFA pattern {0:#x} is not supported.")]
+    UnsupportedCFAPattern(u32),
 }
@@ -46,6 +46,9 @@ pub(super) static IMAGE_RULE: Lazy<quickexif::ParsingRule> = Lazy::new(|| {
                         offset + maker_notes {
                             offset + 10 {
                                 u16 + 0 / black_level
+                                u16 + 1 / black_level_1
+                                u16 + 2 / black_level_2
+                                u16 + 3 / black_level_3
                             }
                         }
                     }
@@ -79,6 +82,120 @@ pub(super) static IMAGE_RULE: Lazy<quickexif::ParsingRule> = Lazy::new(|| {
     })
 });
 
+pub(super) static LENS_RULE: Lazy<quickexif::ParsingRule> = Lazy::new(|| {
+    let shooting_mode = u16_tag(0x0089, "shooting_mode");
+    quickexif::describe_rule!(tiff {
+        0x8769 {
+            0x927c / maker_notes {
+                offset + 18 {
+                    load(shooting_mode)
+                    0x001f? {
+                        offset + maker_notes {
+                            offset + 10 {
+                                u32 + 1 / vr_info
+                            }
+                        }
+                    }
+                    0x0085? {
+                        offset + maker_notes {
+                            offset + 10 {
+                                r64 + 0 / manual_focus_distance
+                            }
+                        }
+                    }
+                    0x0098? {
+                        offset + maker_notes {
+                            offset + 10 {
+                                u32 + 0 / lens_data_version
+                                u32 + 1 / lens_data_4
+                                u32 + 2 / lens_data_8
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    })
+});
+
+/// Converts the items of `LENS_RULE`.
+///
+/// The lens data since version `0201` is encrypted with the serial number and the shutter count,
+/// so the lens ID and the focus distance are only read from the older plain versions.
+pub(super) fn get_shooting_params(info: &quickexif::ParsedInfo) -> ShootingParams {
+    let (lens_id, focus_distance) = match info.u8a4("lens_data_version") {
+        Ok(version) if &version == b"0100" => {
+            (info.u8a4("lens_data_4").ok().map(|x| x[2] as u32), None)
+        }
+        Ok(version) if &version == b"0101" => match info.u8a4("lens_data_8") {
+            Ok(x) => (
+                Some(x[3] as u32),
+                Some(0.01 * 10f64.powf(x[1] as f64 / 40.)),
+            ),
+            Err(_) => (None, None),
+        },
+        _ => (None, None),
+    };
+    let focus_distance = focus_distance.or_else(|| {
+        info.f64("manual_focus_distance")
+            .ok()
+            .filter(|x| x.is_finite() && *x > 0.)
+    });
+
+    let drive_mode = info.u16("shooting_mode").ok().map(|x| {
+        if x & 0x0008 != 0 {
+            DriveMode::SelfTimer
+        } else if x & 0x0150 != 0 {
+            // exposure, white balance or active D-Lighting bracketing
+            DriveMode::Bracketing
+        } else if x & 0x0001 != 0 {
+            DriveMode::Continuous
+        } else {
+            DriveMode::Single
+        }
+    });
+    let stabilization = match info.u8a4("vr_info").map(|x| x[0]) {
+        Ok(1) => Some(true),
+        Ok(2) => Some(false),
+        _ => None,
+    };
+
+    ShootingParams {
+        lens_id,
+        focus_distance,
+        drive_mode,
+        stabilization,
+        ..Default::default()
+    }
+}
+
+impl General {
+    /// The sRAW files hold YUV data of 3 bytes a pixel instead of CFA data.
+    fn is_yuv(&self) -> bool {
+        match (self.info.usize("width"), self.info.usize("height"), self.info.usize("strip_len")) {
+            (Ok(width), Ok(height), Ok(strip_len)) => width.saturating_mul(height).saturating_mul(3) == strip_len,
+            _ => false,
+        }
+    }
+
+    /// The block of the maker notes with the Huffman table and the curve of the compressed data.
+    fn get_color_data<'a>(&self, buffer: &'a [u8]) -> Result<&'a [u8], DecodingError> {
+        let maker_notes_addr = self.info.usize("maker_notes")?.saturating_add(10);
+        match self.info.usize("linear_table_offset") {
+            Ok(offset) => {
+                let offset = offset.saturating_add(maker_notes_addr);
+                let len = self.info.usize("linear_table_len")?;
+                get_bytes(buffer, offset, len)
+            }
+            Err(_) => {
+                let offset = self.info.usize("contrast_curve_offset")?.saturating_add(maker_notes_addr);
+                let len = self.info.usize("contrast_curve_len")?;
+                get_bytes(buffer, offset, len)
+            }
+        }
+    }
+}
+
 impl RawDecoder for General {
     fn new(info: quickexif::ParsedInfo) -> Self {
         General { info }
@@ -108,64 +225,94 @@ impl RawDecoder for General {
         let b = 512.0 * self.info.f64("white_balance_b")?;
         Ok([r as i32, g as i32, b as i32])
     }
-    fn get_thumbnail<'a>(&self, buffer: &'a [u8]) -> Result<&'a [u8], DecodingError> {
+    fn get_black_level(&self) -> Result<[u16; 4], DecodingError> {
+        let info = &self.info;
+        let level = info.u16("black_level")?;
+        let levels = match (info.u16("black_level_1"), info.u16("black_level_2"), info.u16("black_level_3")) {
+            (Ok(gr), Ok(gb), Ok(b)) => [level, gr, gb, b],
+            _ => [level; 4],
+        };
+        // the levels are recorded in 14 bits
+        let levels = match info.u16("bps")? {
+            12 => levels.map(|x| x / 4),
+            _ => levels,
+        };
+        Ok(to_cfa_order(levels, self.get_cfa_pattern()?))
+    }
+    fn get_linearization_table(&self, buffer: &[u8]) -> Result<Option<Vec<u16>>, DecodingError> {
+        if self.is_yuv() || self.info.u16("compression")? != 0x8799 {
+            return Ok(None);
+        }
+        let meta = Meta::parse(self.get_color_data(buffer)?, self.info.is_le, self.info.u16("bps")?)?;
+        Ok(Some(meta.curve))
+    }
+    fn get_thumbnail_range(&self) -> Result<Range<usize>, DecodingError> {
         let offset = self.info.usize("thumbnail")?;
         let len = self.info.usize("thumbnail_len")?;
-        Ok(&buffer[offset..offset + len])
+        Ok(offset..offset.saturating_add(len))
     }
-    fn decode_with_preprocess(&self, buffer: &[u8]) -> Result<Vec<u16>, DecodingError> {
+    fn decode_with_preprocess(&self, buffer: &[u8], area: &Area) -> Result<Vec<u16>, DecodingError> {
+        let mut image = self.decode_raw(buffer, area)?;
+        if let Some(table) = self.get_linearization_table(buffer)? {
+            // the dither takes its seed from the first bits of the strip, along all the samples
+            let curve = LookupTable::new(&table);
+            let mut random = BitPumpMSB::new(get_bytes_from(buffer, self.info.usize("strip")?)?).peek_bits(24);
+            image.iter_mut().for_each(|x| *x = curve.dither(*x, &mut random));
+        }
+        // the files without the levels have none to subtract
+        let black_level = self.get_black_level().unwrap_or([0; 4]);
+        let size = get_image_size(&self.info, buffer)?;
+        subtract_black_level(&mut image, size, black_level, self.get_bps_scale()?);
+        Ok(image)
+    }
+    fn decode_float(&self, buffer: &[u8], area: &Area) -> Result<Vec<f32>, DecodingError> {
+        // the YUV data is converted to RGB along a curve by the decoding
+        if self.is_yuv() {
+            return Ok(to_float(self.decode_with_preprocess(buffer, area)?));
+        }
+        let image = self.decode_raw(buffer, area)?;
+        let (width, _) = get_image_size(&self.info, buffer)?;
+        let table = self.get_linearization_table(buffer)?;
+        let black_level = self.get_black_level().unwrap_or([0; 4]);
+        let white_level = self.get_white_level().unwrap_or(u16::MAX);
+        Ok(normalize(&image, width, table.as_deref(), black_level, white_level))
+    }
+    fn decode_raw(&self, buffer: &[u8], area: &Area) -> Result<Vec<u16>, DecodingError> {
         let strip_offset = self.info.usize("strip")?;
         let strip_len = self.info.usize("strip_len")?;
-        let width = self.info.usize("width")?;
-        let height = self.info.usize("height")?;
+        let (width, height) = get_image_size(&self.info, buffer)?;
         let bps = self.info.u16("bps")?;
-        let bps_scale = self.get_bps_scale()?;
         let compression = self.info.u16("compression")?;
-        let black_level = self.info.u16("black_level").unwrap_or(0);
-        let black_level = match bps {
-            12 => black_level / 4,
-            _ => black_level,
-        };
-        let maker_notes_addr = self.info.usize("maker_notes")? + 10;
-        let color_data = match self.info.usize("linear_table_offset") {
-            Ok(offset) => {
-                let offset = offset + maker_notes_addr;
-                let len = self.info.usize("linear_table_len")?;
-                &buffer[offset..offset + len]
-            }
-            Err(_) => {
-                let offset = self.info.usize("contrast_curve_offset")? + maker_notes_addr;
-                let len = self.info.usize("contrast_curve_len")?;
-                &buffer[offset..offset + len]
-            }
-        };
 
-        let buf = &buffer[strip_offset..];
+        let buf = get_bytes_from(buffer, strip_offset)?;
 
-        macro_rules! to_image {
-            ($iter:expr) => {
-                $iter
-                    .map(|x| bps_scale.saturating_mul(x.saturating_sub(black_level)))
-                    .collect()
-            };
-        }
-        let image: Vec<u16> = if width * height * 3 == strip_len {
+        let image: Vec<u16> = if self.is_yuv() {
+            let buf = get_bytes(buffer, strip_offset, strip_len)?;
             let wb_r = self.info.f64("white_balance_r")?;
             let wb_b = self.info.f64("white_balance_b")?;
-            to_image!(load_raw_yuv2(buf, wb_r, wb_b, width, height).iter())
+            load_raw_yuv2(buf, wb_r, wb_b, width, height)
         } else {
             match compression {
-                1 => match bps {
-                    12 => to_image!(to_12bit_iter(buf, self.info.is_le)),
-                    14 => to_image!(to_14bit_iter(buf, self.info.is_le)),
-                    _ => to_image!(to_16bit_iter(buf, self.info.is_le)),
-                },
+                1 => {
+                    let is_le = self.info.is_le;
+                    let buf = buf.get(..strip_len).unwrap_or(buf);
+                    macro_rules! to_image {
+                        ($to_iter:ident) => {
+                            decode_rows(buf, (width, height), width * 2, area, |row| $to_iter(row, is_le))
+                                .unwrap_or_else(|| $to_iter(buf, is_le).collect())
+                        };
+                    }
+                    match bps {
+                        12 => to_image!(to_12bit_iter),
+                        14 => to_image!(to_14bit_iter),
+                        _ => to_image!(to_16bit_iter),
+                    }
+                }
                 0x8799 => {
-                    to_image!(
-                        load_raw(buf, color_data, self.info.is_le, bps, width, height)?.iter()
-                    )
+                    let meta = Meta::parse(self.get_color_data(buffer)?, self.info.is_le, bps)?;
+                    load_raw(buf, &meta, bps, width, height)?
                 }
-                _ => unimplemented!(),
+                _ => return Err(DecodingError::UnsupportedCompression(compression as u32)),
             }
         };
 
@@ -208,7 +355,7 @@ fn load_raw_yuv2(src: &[u8], wb_r: f64, wb_b: f64, width: usize, height: usize)
         .enumerate()
         .for_each(|(row, out)| {
             let inb = &src[row * width * 3..];
-            let mut random = inb.u32be(0);
+            let mut random = if inb.len() >= 4 { inb.u32be(0) } else { 0 };
             for (o, i) in out.chunks_exact_mut(6).zip(inb.chunks_exact(6)) {
                 let g1: u16 = i[0] as u16;
                 let g2: u16 = i[1] as u16;
@@ -249,71 +396,98 @@ fn load_raw_yuv2(src: &[u8], wb_r: f64, wb_b: f64, width: usize, height: usize)
     out
 }
 
-fn load_raw(
-    src: &[u8],
-    meta: &[u8],
-    is_le: bool,
-    bps: u16,
-    width: usize,
-    height: usize,
-) -> Result<Vec<u16>, DecodingError> {
-    let mut out = vec![0u16; width * height];
-    let mut stream = ByteStream::new(meta, is_le);
-    let v0 = stream.get_u8();
-    let v1 = stream.get_u8();
-
-    let mut huff_select = 0;
-    if v0 == 73 || v1 == 88 {
-        stream.consume_bytes(2110);
-    }
-    if v0 == 70 {
-        huff_select = 2;
-    }
-    if bps == 14 {
-        huff_select += 3;
-    }
+/// The coding parameters of the compressed data, read from the maker notes.
+struct Meta {
+    huff_select: usize,
+    pred_up1: [i32; 2],
+    pred_up2: [i32; 2],
+    /// The row from which the second Huffman table is used, `0` for none.
+    split: usize,
+    /// The linearization curve of the decoded values.
+    curve: Vec<u16>,
+}
 
-    // Create the huffman table used to decode
-    let mut htable = create_hufftable(huff_select);
+impl Meta {
+    fn parse(meta: &[u8], is_le: bool, bps: u16) -> Result<Meta, DecodingError> {
+        // the samples are decoded in pairs along the curve of 12 or 14 bits
+        if !matches!(bps, 12 | 14) {
+            return Err(DecodingError::UnsupportedBitsPerSample(bps));
+        }
 
-    // Setup the predictors
-    let mut pred_up1: [i32; 2] = [stream.get_u16() as i32, stream.get_u16() as i32];
-    let mut pred_up2: [i32; 2] = [stream.get_u16() as i32, stream.get_u16() as i32];
+        let mut stream = ByteStream::new(meta, is_le);
+        let v0 = stream.get_u8()?;
+        let v1 = stream.get_u8()?;
 
-    // Get the linearization curve
-    let mut points = [0u16; 1 << 16];
-    for (i, point) in points.iter_mut().enumerate() {
-        *point = i as u16;
-    }
-    let mut max = 1 << bps;
-    let csize = stream.get_u16() as usize;
-    let mut split = 0usize;
-    let step = if csize > 1 { max / (csize - 1) } else { 0 };
-    if v0 == 68 && v1 == 32 && step > 0 {
-        for i in 0..csize {
-            points[i * step] = stream.get_u16();
+        let mut huff_select = 0;
+        if v0 == 73 || v1 == 88 {
+            stream.consume_bytes(2110);
+        }
+        if v0 == 70 {
+            huff_select = 2;
         }
-        for i in 0..max {
-            points[i] = ((points[i - i % step] as usize * (step - i % step)
-                + points[i - i % step + step] as usize * (i % step))
-                / step) as u16;
+        if bps == 14 {
+            huff_select += 3;
         }
-        split = meta.u16(is_le, 562) as usize;
-    } else if v0 != 70 && csize <= 0x4001 {
-        for point in points.iter_mut().take(csize) {
-            *point = stream.get_u16();
+
+        // Setup the predictors
+        let pred_up1: [i32; 2] = [stream.get_u16()? as i32, stream.get_u16()? as i32];
+        let pred_up2: [i32; 2] = [stream.get_u16()? as i32, stream.get_u16()? as i32];
+
+        // Get the linearization curve
+        let mut points = [0u16; 1 << 16];
+        for (i, point) in points.iter_mut().enumerate() {
+            *point = i as u16;
         }
-        max = csize;
+        let mut max = 1 << bps;
+        let csize = stream.get_u16()? as usize;
+        let mut split = 0usize;
+        let step = if csize > 1 { max / (csize - 1) } else { 0 };
+        if v0 == 68 && v1 == 32 && step > 0 {
+            for i in 0..csize {
+                points[i * step] = stream.get_u16()?;
+            }
+            for i in 0..max {
+                points[i] = ((points[i - i % step] as usize * (step - i % step)
+                    + points[i - i % step + step] as usize * (i % step))
+                    / step) as u16;
+            }
+            split = meta.get(562..564).map_or(0, |x| x.u16(is_le, 0) as usize);
+        } else if v0 != 70 && csize > 0 && csize <= 0x4001 {
+            for point in points.iter_mut().take(csize) {
+                *point = stream.get_u16()?;
+            }
+            max = csize;
+        }
+
+        Ok(Meta {
+            huff_select,
+            pred_up1,
+            pred_up2,
+            split,
+            curve: points[0..max].to_vec(),
+        })
     }
-    let curve = LookupTable::new(&points[0..max]);
+}
+
+/// Decodes the values before the linearization curve of `meta`.
+fn load_raw(src: &[u8], meta: &Meta, bps: u16, width: usize, height: usize) -> Result<Vec<u16>, DecodingError> {
+    if !width.is_multiple_of(2) {
+        return Err(DecodingError::InvalidImageSize(width, height));
+    }
+
+    let mut out = vec![0u16; width * height];
+
+    // Create the huffman table used to decode
+    let mut htable = create_hufftable(meta.huff_select);
+    let mut pred_up1 = meta.pred_up1;
+    let mut pred_up2 = meta.pred_up2;
 
     let mut pump = BitPumpMSB::new(src);
-    let mut random = pump.peek_bits(24);
 
     let bps: u32 = bps as u32;
     for row in 0..height {
-        if split > 0 && row == split {
-            htable = create_hufftable(huff_select + 1);
+        if meta.split > 0 && row == meta.split {
+            htable = create_hufftable(meta.huff_select + 1);
         }
         pred_up1[row & 1] += htable.huff_decode(&mut pump);
         pred_up2[row & 1] += htable.huff_decode(&mut pump);
@@ -324,10 +498,11 @@ fn load_raw(
                 pred_left1 += htable.huff_decode(&mut pump);
                 pred_left2 += htable.huff_decode(&mut pump);
             }
-            out[row * width + col] = curve.dither(clampbits(pred_left1, bps), &mut random);
-            out[row * width + col + 1] = curve.dither(clampbits(pred_left2, bps), &mut random);
+            out[row * width + col] = clampbits(pred_left1, bps);
+            out[row * width + col + 1] = clampbits(pred_left2, bps);
         }
     }
+    pump.check_end()?;
     Ok(out)
 }
 
@@ -55,6 +55,9 @@ pub(super) static IMAGE_RULE: Lazy<quickexif::ParsingRule> = Lazy::new(|| {
                             0x0600 {
                                 offset + maker_notes {
                                     u16 + 0 / black_level
+                                    u16 + 1 / black_level_1
+                                    u16 + 2 / black_level_2
+                                    u16 + 3 / black_level_3
                                 }
                             }
                         }
@@ -65,6 +68,106 @@ pub(super) static IMAGE_RULE: Lazy<quickexif::ParsingRule> = Lazy::new(|| {
     })
 });
 
+pub(super) static LENS_RULE: Lazy<quickexif::ParsingRule> = Lazy::new(|| {
+    quickexif::describe_rule!(tiff {
+        0x8769 {
+            0x927c / maker_notes {
+                offset + 12 {
+                    0x2010? {
+                        offset + maker_notes {
+                            0x0101? {
+                                offset + maker_notes {
+                                    str + 0 / body_serial
+                                }
+                            }
+                            0x0201? {
+                                offset + maker_notes {
+                                    u32 + 0 / lens_type
+                                }
+                            }
+                            0x0202? {
+                                offset + maker_notes {
+                                    str + 0 / lens_serial
+                                }
+                            }
+                            0x0203? {
+                                offset + maker_notes {
+                                    str + 0 / lens_model
+                                }
+                            }
+                        }
+                    }
+                    0x2020? {
+                        offset + maker_notes {
+                            // two values fit in the entry, more are stored elsewhere
+                            0x0600? / drive_mode_inline(drive_mode_count)
+                            if drive_mode_count ? {
+                                if drive_mode_count > 2 {
+                                    0x0600 {
+                                        offset + maker_notes {
+                                            u16 + 0 / drive_mode
+                                        }
+                                    }
+                                }
+                            }
+                            0x0604? / stabilization
+                        }
+                    }
+                    0x2050? {
+                        offset + maker_notes {
+                            0x0305? {
+                                offset + maker_notes {
+                                    u32 + 0 / focus_distance
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    })
+});
+
+/// Converts the items of `LENS_RULE`.
+pub(super) fn get_shooting_params(info: &quickexif::ParsedInfo) -> ShootingParams {
+    let drive_mode = info.u16("drive_mode").ok().or_else(|| {
+        let [a, b, _, _] = info.u8a4("drive_mode_inline").ok()?;
+        Some(if info.is_le {
+            u16::from_le_bytes([a, b])
+        } else {
+            u16::from_be_bytes([a, b])
+        })
+    });
+    let drive_mode = drive_mode.and_then(|x| match x {
+        0 => Some(DriveMode::Single),
+        1 => Some(DriveMode::Continuous),
+        2..=4 => Some(DriveMode::Bracketing),
+        _ => None,
+    });
+    // the make, the model and the sub model of the lens
+    let lens_id = info
+        .u8a4("lens_type")
+        .ok()
+        .filter(|x| x[0] != 0 || x[2] != 0)
+        .map(|x| (x[0] as u32) << 16 | (x[2] as u32) << 8 | x[3] as u32);
+    // in millimeters whatever the denominator is
+    let focus_distance = info
+        .u32("focus_distance")
+        .ok()
+        .filter(|&x| x > 0 && x < u32::MAX)
+        .map(|x| x as f64 / 1000.);
+
+    ShootingParams {
+        lens_model: non_empty_str(info, "lens_model"),
+        lens_id,
+        lens_serial: non_empty_str(info, "lens_serial"),
+        body_serial: non_empty_str(info, "body_serial"),
+        focus_distance,
+        drive_mode,
+        stabilization: info.u32("stabilization").ok().map(|x| x != 0),
+    }
+}
+
 impl RawDecoder for General {
     fn new(info: quickexif::ParsedInfo) -> Self {
         General { info }
@@ -89,48 +192,43 @@ impl RawDecoder for General {
             height,
         })
     }
-    fn decode_with_preprocess(&self, buffer: &[u8]) -> Result<Vec<u16>, DecodingError> {
-        let width = self.info.usize("width")?;
-        let height = self.info.usize("height")?;
+    fn decode_with_preprocess(&self, buffer: &[u8], area: &Area) -> Result<Vec<u16>, DecodingError> {
+        let mut image = self.decode_raw(buffer, area)?;
+        let size = utility::get_image_size(&self.info, buffer)?;
+        subtract_black_level(&mut image, size, self.get_black_level()?, self.get_bps_scale()?);
+        Ok(image)
+    }
+    fn decode_raw(&self, buffer: &[u8], area: &Area) -> Result<Vec<u16>, DecodingError> {
+        let (width, height) = utility::get_image_size(&self.info, buffer)?;
         let strip_offset = self.info.usize("strip")?;
         let strip_len = self.info.usize("strip_len")?;
-        let buffer = &buffer[strip_offset..];
+        let buffer = utility::get_bytes_from(buffer, strip_offset)?;
 
         let image = if strip_len >= width * height / 10 * 16 {
-            load_12bit_raw(buffer, width, height)?
+            load_12bit_raw(buffer, width, height, area)?
         } else {
             load_compressed_raw(buffer, width, height)?
         };
-
-        let black_level = self.info.u16("black_level")?;
-        let bps_scale = self.get_bps_scale()?;
-        Ok(image
-            .iter()
-            .map(|x| bps_scale.saturating_mul(x.saturating_sub(black_level)))
-            .collect())
+        Ok(image)
     }
-    fn get_thumbnail<'a>(&self, buffer: &'a [u8]) -> Result<&'a [u8], DecodingError> {
+    fn get_thumbnail_range(&self) -> Result<Range<usize>, DecodingError> {
         let base = self.info.usize("maker_notes")?;
-        let offset = self.info.usize("preview_image_start")? + base;
+        let offset = self.info.usize("preview_image_start")?.saturating_add(base);
         let len = self.info.usize("preview_image_len")?;
-        Ok(&buffer[offset..offset + len])
+        Ok(offset..offset.saturating_add(len))
     }
 }
 
 #[inline(always)]
 fn fast_inc_get<T: Copy>(vec: &[T], index: &mut usize) -> T {
-    unsafe {
-        let ret = *vec.get_unchecked(*index);
-        *index += 1;
-        ret
-    }
+    let ret = vec[*index];
+    *index += 1;
+    ret
 }
 #[inline(always)]
 fn fast_inc_set<T: Copy>(vec: &mut [T], index: &mut usize, value: T) {
-    unsafe {
-        *vec.get_unchecked_mut(*index) = value;
-        *index += 1;
-    }
+    vec[*index] = value;
+    *index += 1;
 }
 
 fn load_compressed_raw(buf: &[u8], width: usize, height: usize) -> Result<Vec<u16>, DecodingError> {
@@ -138,7 +236,8 @@ fn load_compressed_raw(buf: &[u8], width: usize, height: usize) -> Result<Vec<u1
 
     let mut left: [i32; 2] = [0; 2];
     let mut nw: [i32; 2] = [0; 2];
-    let mut pump = BitPumpMSB::new(&buf[7..]);
+    // the data starts after a header of 7 bytes
+    let mut pump = BitPumpMSB::new(buf.get(7..).ok_or(DecodingError::TruncatedData(7, buf.len()))?);
     let mut set_index = 0;
     let mut get_index = 0;
 
@@ -219,29 +318,39 @@ fn load_compressed_raw(buf: &[u8], width: usize, height: usize) -> Result<Vec<u1
         }
     }
 
+    pump.check_end()?;
     Ok(out)
 }
 
-fn load_12bit_raw(buf: &[u8], width: usize, height: usize) -> Result<Vec<u16>, DecodingError> {
+fn load_12bit_raw(
+    buf: &[u8],
+    width: usize,
+    height: usize,
+    area: &Area,
+) -> Result<Vec<u16>, DecodingError> {
     let perline = width * 12 / 8 + ((width + 2) / 10);
+    if buf.len() < perline * height {
+        return Err(DecodingError::TruncatedData(perline * height, buf.len()));
+    }
     let mut out = vec![0u16; width * height];
 
-    out.chunks_exact_mut(width)
-        .enumerate()
-        .for_each(|(index, out)| {
-            let inb = &buf[(index * perline)..];
+    crate::parallel::for_each_chunk(&mut out, width, |index, out| {
+        if !area.keeps_row(index) {
+            return;
+        }
+        let inb = &buf[(index * perline)..];
 
-            for (oc, ic) in out.chunks_exact_mut(10).zip(inb.chunks_exact(16)) {
-                for (o, i) in oc.chunks_exact_mut(2).zip(ic.chunks_exact(3)) {
-                    let g1: u16 = i[0] as u16;
-                    let g2: u16 = i[1] as u16;
-                    let g3: u16 = i[2] as u16;
+        for (oc, ic) in out.chunks_exact_mut(10).zip(inb.chunks_exact(16)) {
+            for (o, i) in oc.chunks_exact_mut(2).zip(ic.chunks_exact(3)) {
+                let g1: u16 = i[0] as u16;
+                let g2: u16 = i[1] as u16;
+                let g3: u16 = i[2] as u16;
 
-                    o[0] = ((g2 & 0x0f) << 8) | g1;
-                    o[1] = (g3 << 4) | (g2 >> 4);
-                }
+                o[0] = ((g2 & 0x0f) << 8) | g1;
+                o[1] = (g3 << 4) | (g2 >> 4);
             }
-        });
+        }
+    });
 
     Ok(out)
 }
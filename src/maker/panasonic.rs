@@ -49,6 +49,59 @@ pub(super) static IMAGE_RULE: Lazy<quickexif::ParsingRule> = Lazy::new(|| {
     })
 });
 
+pub(super) static LENS_RULE: Lazy<quickexif::ParsingRule> = Lazy::new(|| {
+    let stabilization = u16_tag(0x001a, "stabilization");
+    let burst_mode = u16_tag(0x002a, "burst_mode");
+    let self_timer = u16_tag(0x002e, "self_timer");
+    quickexif::describe_rule!(tiff {
+        0x002e {
+            offset + 12 {
+                tiff {
+                    0x8769 {
+                        0x927c {
+                            offset + 12 {
+                                load(stabilization)
+                                load(burst_mode)
+                                load(self_timer)
+                                0x0051? {
+                                    str + 0 / lens_model
+                                }
+                                0x0052? {
+                                    str + 0 / lens_serial
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    })
+});
+
+/// Converts the items of `LENS_RULE`.
+pub(super) fn get_shooting_params(info: &quickexif::ParsedInfo) -> ShootingParams {
+    let drive_mode = match (info.u16("self_timer"), info.u16("burst_mode")) {
+        (Ok(2..=4), _) => Some(DriveMode::SelfTimer),
+        (_, Ok(1 | 4 | 17)) => Some(DriveMode::Continuous),
+        (_, Ok(2 | 3 | 8 | 18)) => Some(DriveMode::Bracketing),
+        (_, Ok(0)) => Some(DriveMode::Single),
+        _ => None,
+    };
+    let stabilization = match info.u16("stabilization") {
+        Ok(3) => Some(false),
+        Ok(2 | 4..=6) => Some(true),
+        _ => None,
+    };
+
+    ShootingParams {
+        lens_model: non_empty_str(info, "lens_model"),
+        lens_serial: non_empty_str(info, "lens_serial"),
+        drive_mode,
+        stabilization,
+        ..Default::default()
+    }
+}
+
 impl RawDecoder for General {
     fn new(info: quickexif::ParsedInfo) -> Self {
         General { info }
@@ -73,14 +126,14 @@ impl RawDecoder for General {
             height: bottom - y,
         })
     }
-    fn decode_with_preprocess(&self, buffer: &[u8]) -> Result<Vec<u16>, DecodingError> {
-        let image = load_raw(&self.info, buffer)?;
-        let black_level = self.info.u16("black_level_r")?;
-        let bps_scale = self.get_bps_scale()?;
-        Ok(image
-            .iter()
-            .map(|x| bps_scale.saturating_mul(x.saturating_sub(black_level)))
-            .collect())
+    fn decode_with_preprocess(&self, buffer: &[u8], area: &Area) -> Result<Vec<u16>, DecodingError> {
+        let mut image = self.decode_raw(buffer, area)?;
+        let size = utility::get_image_size(&self.info, buffer)?;
+        subtract_black_level(&mut image, size, self.get_black_level()?, self.get_bps_scale()?);
+        Ok(image)
+    }
+    fn decode_raw(&self, buffer: &[u8], area: &Area) -> Result<Vec<u16>, DecodingError> {
+        load_raw(&self.info, buffer, area)
     }
     fn get_cfa_pattern(&self) -> Result<CFAPattern, DecodingError> {
         let cfa_pattern = self.info.u16("cfa_pattern")?;
@@ -89,68 +142,81 @@ impl RawDecoder for General {
             2 => CFAPattern::GRBG,
             3 => CFAPattern::GBRG,
             4 => CFAPattern::BGGR,
-            _ => CFAPattern::RGGB,
+            _ => return Err(DecodingError::UnsupportedCFAPattern(cfa_pattern as u32)),
         };
         Ok(result)
     }
-    fn get_thumbnail<'a>(&self, buffer: &'a [u8]) -> Result<&'a [u8], DecodingError> {
+    fn get_thumbnail_range(&self) -> Result<Range<usize>, DecodingError> {
         let offset = self.info.usize("thumbnail")?;
         let len = self.info.usize("thumbnail_len")?;
-        Ok(&buffer[offset..offset + len])
+        Ok(offset..offset.saturating_add(len))
     }
 }
 
-fn load_raw(info: &quickexif::ParsedInfo, buffer: &[u8]) -> Result<Vec<u16>, DecodingError> {
+fn load_raw(info: &quickexif::ParsedInfo, buffer: &[u8], area: &Area) -> Result<Vec<u16>, DecodingError> {
     const SPLIT: bool = true;
     const BLOCK_LINES: usize = 5;
 
-    let width = info.usize("width")?;
-    let height = info.usize("height")?;
+    let (width, height) = utility::get_image_size(info, buffer)?;
     let offset = info.usize("strip")?;
 
-    let buf = &buffer[offset..];
+    let buf = utility::get_bytes_from(buffer, offset)?;
+    // the bit stream takes 9 bits a sample and 2 more bits for every 14 samples
+    let skip = |row: usize| ((width * row * 9) + (width / 14 * 2 * row)) / 8;
+    if buf.len() < skip(height) {
+        return Err(DecodingError::TruncatedData(skip(height), buf.len()));
+    }
     let mut out: Vec<u16> = vec![0u16; width * height];
 
-    out.chunks_exact_mut(width * BLOCK_LINES)
-        .enumerate()
-        .for_each(|(index, out)| {
-            let row = index * BLOCK_LINES;
-
-            let skip = ((width * row * 9) + (width / 14 * 2 * row)) / 8;
-            let blocks = skip / 0x4000;
-            let src = &buf[blocks * 0x4000..];
-            let mut pump = BitPumpPanasonic::new(src, SPLIT);
-            for _ in 0..(skip % 0x4000) {
-                pump.get_bits(8);
-            }
+    // every band of rows starts at a known position of the bit stream, the ones out of `area` are skipped
+    let decode_band = |index: usize, out: &mut [u16]| {
+        let row = index * BLOCK_LINES;
+        if !(row..(row + BLOCK_LINES).min(height)).any(|row| area.keeps_row(row)) {
+            return;
+        }
+
+        let skip = skip(row);
+        let blocks = skip / 0x4000;
+        let src = &buf[blocks * 0x4000..];
+        let mut pump = BitPumpPanasonic::new(src, SPLIT);
+        for _ in 0..(skip % 0x4000) {
+            pump.get_bits(8);
+        }
 
-            let mut sh: i32 = 0;
-            for out in out.chunks_exact_mut(14) {
-                let mut pred: [i32; 2] = [0, 0];
-                let mut nonz: [i32; 2] = [0, 0];
+        let mut sh: i32 = 0;
+        for out in out.chunks_exact_mut(14) {
+            let mut pred: [i32; 2] = [0, 0];
+            let mut nonz: [i32; 2] = [0, 0];
 
-                for i in 0..14 {
-                    if (i % 3) == 2 {
-                        sh = 4 >> (3 - pump.get_bits(2));
-                    }
-                    if nonz[i & 1] != 0 {
-                        let j = pump.get_bits(8) as i32;
-                        if j != 0 {
-                            pred[i & 1] -= 0x80 << sh;
-                            if pred[i & 1] < 0 || sh == 4 {
-                                pred[i & 1] &= !(-1 << sh);
-                            }
-                            pred[i & 1] += j << sh;
-                        }
-                    } else {
-                        nonz[i & 1] = pump.get_bits(8) as i32;
-                        if nonz[i & 1] != 0 || i > 11 {
-                            pred[i & 1] = nonz[i & 1] << 4 | (pump.get_bits(4) as i32);
+            for i in 0..14 {
+                if (i % 3) == 2 {
+                    sh = 4 >> (3 - pump.get_bits(2));
+                }
+                if nonz[i & 1] != 0 {
+                    let j = pump.get_bits(8) as i32;
+                    if j != 0 {
+                        pred[i & 1] -= 0x80 << sh;
+                        if pred[i & 1] < 0 || sh == 4 {
+                            pred[i & 1] &= !(-1 << sh);
                         }
+                        pred[i & 1] += j << sh;
+                    }
+                } else {
+                    nonz[i & 1] = pump.get_bits(8) as i32;
+                    if nonz[i & 1] != 0 || i > 11 {
+                        pred[i & 1] = nonz[i & 1] << 4 | (pump.get_bits(4) as i32);
                     }
-                    out[i] = pred[i & 1] as u16;
                 }
+                out[i] = pred[i & 1] as u16;
             }
-        });
+        }
+    };
+    // the last band is shorter when the height is not a multiple of `BLOCK_LINES`
+    let band_len = width * BLOCK_LINES;
+    let (bands, last) = out.split_at_mut(width * height / band_len * band_len);
+    crate::parallel::for_each_chunk(bands, band_len, decode_band);
+    if !last.is_empty() {
+        decode_band(height / BLOCK_LINES, last);
+    }
     Ok(out)
 }
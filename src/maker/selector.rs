@@ -1,6 +1,7 @@
 use super::super::data;
 use super::*;
-use crate::decode::DecodedImage;
+use crate::decode::{DecodedImage, SensorData};
+use crate::metadata::RawMetadata;
 use crate::RawFileReadingError;
 
 fn prepare(
@@ -40,6 +41,20 @@ fn prepare(
     Ok((make, dng_version, cam_matrix))
 }
 
+/// Checks whether the maker and the model of a file can be decoded.
+pub(in super::super) fn check_support(basic_info: &quickexif::ParsedInfo) -> Result<(), RawFileReadingError> {
+    let (make, dng_version, _) = prepare(basic_info, false)?;
+    match (dng_version, make) {
+        (Some(_), _)
+        | (None, "NIKON" | "NIKON CORPORATION")
+        | (None, "SONY")
+        | (None, "Panasonic")
+        | (None, "OLYMPUS CORPORATION" | "OLYMPUS IMAGING CORP.")
+        | (None, "FUJIFILM") => Ok(()),
+        _ => Err(RawFileReadingError::MakerIsNotSupportedYet(make.to_owned())),
+    }
+}
+
 pub(in super::super) fn select_and_decode_exif_info(
     file_buffer: &[u8],
     basic_info: quickexif::ParsedInfo,
@@ -65,6 +80,91 @@ pub(in super::super) fn select_and_decode_exif_info(
     )?)
 }
 
+fn collect_metadata(
+    decoder: &impl RawDecoder,
+    params: ShootingParams,
+    is_dng: bool,
+) -> Result<RawMetadata, RawFileReadingError> {
+    let info = decoder.get_info();
+    let white_balance = decoder
+        .get_white_balance()
+        .ok()
+        .filter(|&[_, g, _]| g > 0)
+        .map(|[r, g, b]| [r as f32 / g as f32, 1f32, b as f32 / g as f32]);
+
+    Ok(RawMetadata {
+        make: info
+            .str("make")
+            .map_err(|_| RawFileReadingError::CannotReadMake)?
+            .to_owned(),
+        model: info
+            .str("model")
+            .map_err(|_| RawFileReadingError::CannotReadModel)?
+            .to_owned(),
+        is_dng,
+        lens_make: None,
+        lens_model: params.lens_model,
+        lens_id: params.lens_id,
+        focus_distance: params.focus_distance,
+        drive_mode: params.drive_mode,
+        stabilization: params.stabilization,
+        iso: None,
+        exposure_time: None,
+        f_number: None,
+        focal_length: None,
+        date_time: None,
+        gps: None,
+        orientation: decoder.get_orientation(),
+        width: info.usize("width")?,
+        height: info.usize("height")?,
+        crop: decoder.get_crop(),
+        bit_depth: info.u16("bps").ok(),
+        compression: info.u16("compression").ok(),
+        black_level: decoder.get_black_level().ok(),
+        white_level: decoder.get_white_level(),
+        white_balance,
+        body_serial: params.body_serial,
+        lens_serial: params.lens_serial,
+    })
+}
+
+/// Collects the metadata recorded along with the sensor data without decoding the image.
+///
+/// DNG files carry no maker notes, their lens model comes from the `LensModel` of the EXIF block.
+pub(in super::super) fn select_and_decode_metadata(
+    file_buffer: &[u8],
+    basic_info: quickexif::ParsedInfo,
+) -> Result<RawMetadata, RawFileReadingError> {
+    let (make, dng_version, _) = prepare(&basic_info, true)?;
+
+    macro_rules! decode {
+        ($t:ident) => {{
+            // the maker notes are optional, a broken block only loses its items
+            let params = quickexif::parse(file_buffer, &$t::LENS_RULE)
+                .map(|info| $t::get_shooting_params(&info))
+                .unwrap_or_default();
+            decode!($t, params)
+        }};
+        ($t:ident, $params:expr) => {{
+            let raw_info =
+                quickexif::parse_with_prev_info(file_buffer, &$t::IMAGE_RULE, basic_info)?;
+            collect_metadata(&$t::General::new(raw_info), $params, dng_version.is_some())?
+        }};
+    }
+
+    match dng_version {
+        None => match make {
+            "NIKON" | "NIKON CORPORATION" => Ok(decode!(nikon)),
+            "SONY" => Ok(decode!(sony)),
+            "Panasonic" => Ok(decode!(panasonic)),
+            "OLYMPUS CORPORATION" | "OLYMPUS IMAGING CORP." => Ok(decode!(olympus)),
+            "FUJIFILM" => Ok(decode!(fujifilm)),
+            _ => Err(RawFileReadingError::MakerIsNotSupportedYet(make.to_owned())),
+        },
+        Some(_version) => Ok(decode!(adobe, ShootingParams::default())),
+    }
+}
+
 pub(in super::super) fn select_and_decode_thumbnail(
     file_buffer: &[u8],
     basic_info: quickexif::ParsedInfo,
@@ -95,10 +195,120 @@ pub(in super::super) fn select_and_decode_thumbnail(
     }
 }
 
-pub(in super::super) fn select_and_decode(
+/// Reads the white balance and the color matrix of the camera without decoding the image.
+pub(in super::super) fn select_and_decode_calibration(
+    file_buffer: &[u8],
+    basic_info: quickexif::ParsedInfo,
+) -> Result<([i32; 3], [f32; 9]), RawFileReadingError> {
+    let (make, dng_version, cam_matrix) = prepare(&basic_info, false)?;
+
+    macro_rules! decode {
+        ($t:ident) => {{
+            let raw_info =
+                quickexif::parse_with_prev_info(file_buffer, &$t::IMAGE_RULE, basic_info)?;
+            $t::General::new(raw_info).get_white_balance()?
+        }};
+    }
+
+    let white_balance = match dng_version {
+        None => match make {
+            "NIKON" | "NIKON CORPORATION" => Ok(decode!(nikon)),
+            "SONY" => Ok(decode!(sony)),
+            "Panasonic" => Ok(decode!(panasonic)),
+            "OLYMPUS CORPORATION" | "OLYMPUS IMAGING CORP." => Ok(decode!(olympus)),
+            "FUJIFILM" => Ok(decode!(fujifilm)),
+            _ => Err(RawFileReadingError::MakerIsNotSupportedYet(make.to_owned())),
+        },
+        Some(_version) => Ok(decode!(adobe)),
+    }?;
+
+    Ok((white_balance, cam_matrix))
+}
+
+/// Finds the bytes of the embedded preview from the IFDs alone, without reading the preview.
+pub(in super::super) fn select_thumbnail_range(
+    file_buffer: &[u8],
+    basic_info: quickexif::ParsedInfo,
+) -> Result<Range<usize>, RawFileReadingError> {
+    let (make, dng_version, _) = prepare(&basic_info, true)?;
+
+    macro_rules! decode {
+        ($t:ident) => {{
+            let raw_info =
+                quickexif::parse_with_prev_info(file_buffer, &$t::THUMBNAIL_RULE, basic_info)?;
+            $t::General::new(raw_info).get_thumbnail_range()?
+        }};
+    }
+
+    match dng_version {
+        None => match make {
+            "NIKON" | "NIKON CORPORATION" => Ok(decode!(nikon)),
+            "SONY" => Ok(decode!(sony)),
+            "Panasonic" => Ok(decode!(panasonic)),
+            "OLYMPUS CORPORATION" | "OLYMPUS IMAGING CORP." => Ok(decode!(olympus)),
+            "FUJIFILM" => Ok(decode!(fujifilm)),
+            _ => Err(RawFileReadingError::MakerIsNotSupportedYet(make.to_owned())),
+        },
+        Some(_version) => Ok(decode!(adobe)),
+    }
+}
+
+/// Decodes the sensor values without the black level subtraction and scaling.
+///
+/// The color matrix is not needed, so models without one in `data::CAM_XYZ_MAP` are read as well.
+pub(in super::super) fn select_and_decode_sensor_data(
+    file_buffer: &[u8],
+    basic_info: quickexif::ParsedInfo,
+) -> Result<SensorData, RawFileReadingError> {
+    let (make, dng_version, _) = prepare(&basic_info, true)?;
+
+    macro_rules! decode {
+        ($t:ident) => {{
+            let raw_info =
+                quickexif::parse_with_prev_info(file_buffer, &$t::IMAGE_RULE, basic_info)?;
+            let width = raw_info.usize("width")?;
+            let height = raw_info.usize("height")?;
+
+            let decoder = $t::General::new(raw_info);
+            let image = decoder.decode_raw(file_buffer, &Area::ALL)?;
+            // linear DNGs or the YUV data of Nikon sRAW are not CFA data
+            if image.len() != width * height {
+                return Err(DecodingError::InvalidDecodedImageSize(image.len(), width * height).into());
+            }
+
+            SensorData {
+                image,
+                width,
+                height,
+                cfa_pattern: decoder.get_cfa_pattern()?,
+                black_level: decoder.get_black_level().ok(),
+                white_level: decoder.get_white_level(),
+                linearization_table: decoder.get_linearization_table(file_buffer)?,
+                bit_depth: decoder.get_info().u16("bps").ok(),
+                crop: decoder.get_crop(),
+            }
+        }};
+    }
+
+    match dng_version {
+        None => match make {
+            "NIKON" | "NIKON CORPORATION" => Ok(decode!(nikon)),
+            "SONY" => Ok(decode!(sony)),
+            "Panasonic" => Ok(decode!(panasonic)),
+            "OLYMPUS CORPORATION" | "OLYMPUS IMAGING CORP." => Ok(decode!(olympus)),
+            "FUJIFILM" => Ok(decode!(fujifilm)),
+            _ => Err(RawFileReadingError::MakerIsNotSupportedYet(make.to_owned())),
+        },
+        Some(_version) => Ok(decode!(adobe)),
+    }
+}
+
+/// Decodes the image, `area` picks the samples to decode by the CFA pattern of the file.
+pub(in super::super) fn select_and_decode<T: DecodedSample>(
     file_buffer: &[u8],
     basic_info: quickexif::ParsedInfo,
-) -> Result<DecodedImage, RawFileReadingError> {
+    area: impl Fn(CFAPattern) -> Area,
+) -> Result<DecodedImage<T>, RawFileReadingError> {
     let (make, dng_version, cam_matrix) = prepare(&basic_info, false)?;
 
     macro_rules! decode {
@@ -109,11 +319,11 @@ pub(in super::super) fn select_and_decode(
             let height = raw_info.usize("height")?;
 
             let decoder = $t::General::new(raw_info);
-            let cfa_pattern = decoder.get_cfa_pattern().unwrap_or(CFAPattern::RGGB);
+            let cfa_pattern = decoder.get_cfa_pattern()?;
             let crop = decoder.get_crop();
             let orientation = decoder.get_orientation();
             let white_balance = decoder.get_white_balance()?;
-            let image = decoder.decode_with_preprocess(file_buffer)?;
+            let image = T::decode(&decoder, file_buffer, &area(cfa_pattern))?;
 
             DecodedImage {
                 image,
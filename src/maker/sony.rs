@@ -1,6 +1,6 @@
 use super::*;
 
-use super::{decode_utility::bit_pump::*, decode_utility::lookup_table::*, utility::to_14bit_iter};
+use super::{decode_utility::bit_pump::*, decode_utility::lookup_table::*, utility::decode_rows, utility::to_14bit_iter};
 use std::cmp;
 
 use super::utility::GetNumFromBytes;
@@ -49,6 +49,9 @@ pub(super) static IMAGE_RULE: Lazy<quickexif::ParsingRule> = Lazy::new(|| {
             sony_decrypt / 0x7200 / 0x7201 / 0x7221 {
                 0x7310 {
                     u16 + 0 / black_level
+                    u16 + 1 / black_level_1
+                    u16 + 2 / black_level_2
+                    u16 + 3 / black_level_3
                 }
                 0x7312 {
                     u16 + 0 / white_balance_r
@@ -80,6 +83,56 @@ impl General {
     }
 }
 
+pub(super) static LENS_RULE: Lazy<quickexif::ParsingRule> = Lazy::new(|| {
+    let drive_mode = u16_tag(0xb041, "drive_mode");
+    quickexif::describe_rule!(tiff {
+        0x8769 {
+            0x927c {
+                u32 + 0 / maker_notes_magic
+            }
+            // the maker notes of some models start with a header like "SONY DSC \0\0\0"
+            if maker_notes_magic == 0x594e4f53 {
+                0x927c {
+                    offset + 12 {
+                        load(drive_mode)
+                        0xb026? / stabilization
+                        0xb027? / lens_id
+                    }
+                }
+            } else {
+                0x927c {
+                    load(drive_mode)
+                    0xb026? / stabilization
+                    0xb027? / lens_id
+                }
+            }
+        }
+    })
+});
+
+/// Converts the items of `LENS_RULE`.
+pub(super) fn get_shooting_params(info: &quickexif::ParsedInfo) -> ShootingParams {
+    let drive_mode = info.u16("drive_mode").ok().and_then(|x| match x {
+        0 => Some(DriveMode::Single),
+        1 | 18 => Some(DriveMode::Continuous),
+        4 | 5 => Some(DriveMode::SelfTimer),
+        6 | 7 | 24 | 25 | 40 | 41 => Some(DriveMode::Bracketing),
+        _ => None,
+    });
+    let stabilization = match info.u32("stabilization") {
+        Ok(0) => Some(false),
+        Ok(1) => Some(true),
+        _ => None,
+    };
+
+    ShootingParams {
+        lens_id: info.u32("lens_id").ok(),
+        drive_mode,
+        stabilization,
+        ..Default::default()
+    }
+}
+
 impl RawDecoder for General {
     fn new(info: quickexif::ParsedInfo) -> Self {
         General { info }
@@ -109,36 +162,49 @@ impl RawDecoder for General {
             height,
         })
     }
-    fn decode_with_preprocess(&self, buffer: &[u8]) -> Result<Vec<u16>, DecodingError> {
-        let width = self.info.usize("width")?;
-        let height = self.info.usize("height")?;
-        let black_level = self.info.u16("black_level")?;
+    fn get_linearization_table(&self, buffer: &[u8]) -> Result<Option<Vec<u16>>, DecodingError> {
+        if self.info.u32("compression")? != 0x7fff {
+            return Ok(None);
+        }
+        let tone_curve_addr = self.info.usize("tone_curve_addr")?;
+        let tone_curve = utility::get_bytes(buffer, tone_curve_addr, 8)?
+            .chunks_exact(2)
+            .map(|x| x.u16(self.info.is_le, 0))
+            .collect::<Vec<u16>>();
+        Ok(Some(gen_curve(&tone_curve)))
+    }
+    fn decode_with_preprocess(&self, buffer: &[u8], area: &Area) -> Result<Vec<u16>, DecodingError> {
+        let mut image = self.decode_raw(buffer, area)?;
+        let size = utility::get_image_size(&self.info, buffer)?;
+        if let Some(table) = self.get_linearization_table(buffer)? {
+            let strip = utility::get_bytes_from(buffer, self.info.usize("strip")?)?;
+            dither_raw8(&mut image, strip, &LookupTable::new(&table), size.0);
+        }
+        subtract_black_level(&mut image, size, self.get_black_level()?, self.get_white_level_scale()?);
+        Ok(image)
+    }
+    fn decode_raw(&self, buffer: &[u8], area: &Area) -> Result<Vec<u16>, DecodingError> {
+        let (width, height) = utility::get_image_size(&self.info, buffer)?;
         let strip_offset = self.info.usize("strip")?;
         let strip_len = self.info.usize("strip_len")?;
         let compression = self.info.u32("compression")?;
-        let level_scale = self.get_white_level_scale()?;
-        let buf = &buffer[strip_offset..strip_offset + strip_len];
-
-        let black_level_sub = |v: u16| level_scale.saturating_mul(v.saturating_sub(black_level));
+        let buf = utility::get_bytes(buffer, strip_offset, strip_len)?;
 
         let image: Vec<u16> = match compression {
             0x7fffu32 => {
-                let tone_curve_addr = self.info.usize("tone_curve_addr")?;
-                let tone_curve = buffer[tone_curve_addr..tone_curve_addr + 8]
-                    .chunks_exact(2)
-                    .map(|x| x.u16(self.info.is_le, 0))
-                    .collect::<Vec<u16>>();
-
-                load_raw8(buf, &tone_curve, width, height)
-                    .iter()
-                    .copied()
-                    .map(black_level_sub)
-                    .collect()
+                // the rows take a byte for every sample
+                if buf.len() < width * height {
+                    return Err(DecodingError::TruncatedData(width * height, buf.len()));
+                }
+                load_raw8(buf, width, height)
+            }
+            // the lossless compression of the recent models
+            7 => return Err(DecodingError::UnsupportedCompression(compression)),
+            _ => {
+                let is_le = self.info.is_le;
+                decode_rows(buf, (width, height), width * 2, area, |row| to_14bit_iter(row, is_le))
+                    .unwrap_or_else(|| to_14bit_iter(buf, is_le).collect())
             }
-            7 => unimplemented!(),
-            _ => to_14bit_iter(buf, self.info.is_le)
-                .map(black_level_sub)
-                .collect(),
         };
 
         if image.len() != width * height {
@@ -148,14 +214,15 @@ impl RawDecoder for General {
         }
     }
 
-    fn get_thumbnail<'a>(&self, buffer: &'a [u8]) -> Result<&'a [u8], DecodingError> {
+    fn get_thumbnail_range(&self) -> Result<Range<usize>, DecodingError> {
         let offset = self.info.usize("preview_offset")?;
         let len = self.info.usize("preview_len")?;
-        Ok(&buffer[offset..offset + len])
+        Ok(offset..offset.saturating_add(len))
     }
 }
 
-fn gen_curve_lut(tone_curve: &[u16]) -> LookupTable {
+/// The curve of the 8-bit samples from the 4 points of the tone curve.
+fn gen_curve(tone_curve: &[u16]) -> Vec<u16> {
     let mut curve: [usize; 6] = [0, 0, 0, 0, 0, 4095];
 
     for i in 0..4 {
@@ -169,25 +236,42 @@ fn gen_curve_lut(tone_curve: &[u16]) -> LookupTable {
         }
     }
 
-    LookupTable::new(&table)
+    table
+}
+
+/// Maps the values of `load_raw8` along `curve`, with the dither seeded by the first bits of
+/// each row and following the order the samples are coded in.
+fn dither_raw8(image: &mut [u16], buf: &[u8], curve: &LookupTable, width: usize) {
+    image
+        .chunks_exact_mut(width)
+        .enumerate()
+        .for_each(|(row_index, out)| {
+            let mut random = BitPumpLSB::new(&buf[(row_index * width)..]).peek_bits(16);
+            for out in out.chunks_exact_mut(32) {
+                for j in 0..2 {
+                    for i in 0..16 {
+                        out[j + (i * 2)] = curve.dither(out[j + (i * 2)], &mut random);
+                    }
+                }
+            }
+        });
 }
 
-fn load_raw8(buf: &[u8], tone_curve: &[u16], width: usize, height: usize) -> Vec<u16> {
+/// Decodes the values before the tone curve.
+fn load_raw8(buf: &[u8], width: usize, height: usize) -> Vec<u16> {
     let mut out: Vec<u16> = vec![0u16; width * height];
-    let curve = gen_curve_lut(tone_curve);
 
     out.chunks_exact_mut(width)
         .enumerate()
         .for_each(|(row_index, out)| {
             let mut pump = BitPumpLSB::new(&buf[(row_index * width)..]);
 
-            let mut random = pump.peek_bits(16);
             for out in out.chunks_exact_mut(32) {
                 // Process 32 pixels at a time in interleaved fashion
                 for j in 0..2 {
                     let max = pump.get_bits(11);
                     let min = pump.get_bits(11);
-                    let delta = max - min;
+                    let delta = max.saturating_sub(min);
                     // Calculate the size of the data shift needed by how large the delta is
                     // A delta with 11 bits requires a shift of 4, 10 bits of 3, etc
                     let delta_shift: u32 =
@@ -203,7 +287,7 @@ fn load_raw8(buf: &[u8], tone_curve: &[u16], width: usize, height: usize) -> Vec
                         } else {
                             cmp::min(0x7ff, (pump.get_bits(7) << delta_shift) + min)
                         };
-                        out[j + (i * 2)] = curve.dither((val << 1) as u16, &mut random);
+                        out[j + (i * 2)] = (val << 1) as u16;
                     }
                 }
             }
@@ -1,9 +1,9 @@
+use super::DecodingError;
+
 pub(super) trait GetNumFromBytes {
     fn u16(&self, is_le: bool, start: usize) -> u16;
-    fn u16le(&self, start: usize) -> u16;
     fn u16be(&self, start: usize) -> u16;
     fn u32(&self, is_le: bool, start: usize) -> u32;
-    fn u32le(&self, start: usize) -> u32;
     fn u32be(&self, start: usize) -> u32;
     fn i32(&self, is_le: bool, start: usize) -> i32;
     fn r64(&self, is_le: bool, start: usize) -> f64;
@@ -30,18 +30,10 @@ impl GetNumFromBytes for &[u8] {
     gen_impl_get_int!(u32, 4);
     gen_impl_get_int!(i32, 4);
 
-    fn u16le(&self, start: usize) -> u16 {
-        let bytes: [u8; 2] = self[start..start + 2].try_into().unwrap();
-        u16::from_le_bytes(bytes)
-    }
     fn u16be(&self, start: usize) -> u16 {
         let bytes: [u8; 2] = self[start..start + 2].try_into().unwrap();
         u16::from_be_bytes(bytes)
     }
-    fn u32le(&self, start: usize) -> u32 {
-        let bytes: [u8; 4] = self[start..start + 4].try_into().unwrap();
-        u32::from_le_bytes(bytes)
-    }
     fn u32be(&self, start: usize) -> u32 {
         let bytes: [u8; 4] = self[start..start + 4].try_into().unwrap();
         u32::from_be_bytes(bytes)
@@ -134,6 +126,62 @@ pub(super) fn to_12bit_iter_packed(buffer: &[u8], is_le: bool) -> impl Iterator<
     })
 }
 
+/// Decodes the uncompressed rows kept by `rows`, every row takes `row_len` bytes of `buffer`.
+///
+/// Returns `None` when `buffer` is not made of exactly `height` rows.
+pub(super) fn decode_rows<'a, I: Iterator<Item = u16>>(
+    buffer: &'a [u8],
+    (width, height): (usize, usize),
+    row_len: usize,
+    area: &super::Area,
+    decode_row: impl Fn(&'a [u8]) -> I + Sync + Send,
+) -> Option<Vec<u16>> {
+    if width == 0 || row_len * height != buffer.len() {
+        return None;
+    }
+
+    let mut out = vec![0u16; width * height];
+    crate::parallel::for_each_chunk(&mut out, width, |row, out| {
+        if area.keeps_row(row) {
+            let src = &buffer[row * row_len..(row + 1) * row_len];
+            out.iter_mut().zip(decode_row(src)).for_each(|(o, x)| *o = x);
+        }
+    });
+    Some(out)
+}
+
+/// The `len` bytes at `offset` of `buffer`, for the offsets and lengths read from the file.
+pub(super) fn get_bytes(buffer: &[u8], offset: usize, len: usize) -> Result<&[u8], DecodingError> {
+    let end = offset.saturating_add(len);
+    buffer
+        .get(offset..end)
+        .ok_or(DecodingError::OffsetOutOfRange(end, buffer.len()))
+}
+
+/// The bytes from `offset` to the end of `buffer`.
+pub(super) fn get_bytes_from(buffer: &[u8], offset: usize) -> Result<&[u8], DecodingError> {
+    buffer
+        .get(offset..)
+        .ok_or(DecodingError::OffsetOutOfRange(offset, buffer.len()))
+}
+
+/// Reads the size of the sensor data, every sample of which takes a bit of `buffer` at least.
+pub(super) fn get_image_size(
+    info: &quickexif::ParsedInfo,
+    buffer: &[u8],
+) -> Result<(usize, usize), DecodingError> {
+    let width = info.usize("width")?;
+    let height = info.usize("height")?;
+    // RGB data takes 3 samples a pixel
+    let pixels = width
+        .checked_mul(height)
+        .filter(|&x| x > 0 && x.checked_mul(3).is_some())
+        .ok_or(DecodingError::InvalidImageSize(width, height))?;
+    if pixels / 8 > buffer.len() {
+        return Err(DecodingError::TruncatedData(pixels / 8, buffer.len()));
+    }
+    Ok((width, height))
+}
 
 pub(super) fn matrix3_normalize(x: &mut [f32]) {
     assert!(x.len() == 9);
@@ -143,7 +191,7 @@ pub(super) fn matrix3_normalize(x: &mut [f32]) {
     });
 }
 
-pub(super) fn matrix3_inverse(x: &mut [f32]) {
+pub(crate) fn matrix3_inverse(x: &mut [f32]) {
     assert!(x.len() == 9);
     let m11 = x[0];
     let m12 = x[3];
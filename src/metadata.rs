@@ -0,0 +1,226 @@
+//! Typed metadata of raw files, normalized across makers.
+
+use crate::decode::{Crop, Orientation};
+
+/// The capture time in the local time of the camera.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DateTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    /// The offset from UTC in minutes when the camera records it.
+    pub offset: Option<i16>,
+}
+
+impl DateTime {
+    /// Parses the EXIF format of `YYYY:MM:DD HH:MM:SS` and an optional offset like `+09:00`.
+    pub fn from_exif(date_time: &str, offset: Option<&str>) -> Option<DateTime> {
+        let (date, time) = date_time.trim().split_once(' ')?;
+        let mut date = date.split(':').map(|x| x.parse::<u16>().ok());
+        let mut time = time.trim().split(':').map(|x| x.parse::<u8>().ok());
+        let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+        let (hour, minute, second) = (time.next()??, time.next()??, time.next()??);
+        // unset fields are filled with zeros or spaces
+        if year == 0 || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+            return None;
+        }
+        if hour > 23 || minute > 59 || second > 60 {
+            return None;
+        }
+
+        let offset = offset.and_then(|offset| {
+            let offset = offset.trim();
+            let sign = match offset.get(..1)? {
+                "+" => 1,
+                "-" => -1,
+                _ => return None,
+            };
+            let (hours, minutes) = offset[1..].split_once(':')?;
+            let (hours, minutes) = (hours.parse::<i16>().ok()?, minutes.parse::<i16>().ok()?);
+            Some(sign * (hours * 60 + minutes))
+        });
+
+        Some(DateTime {
+            year,
+            month: month as u8,
+            day: day as u8,
+            hour,
+            minute,
+            second,
+            offset,
+        })
+    }
+}
+
+/// Prints in the ISO 8601 format like `2022-05-01T10:20:30+09:00`.
+impl std::fmt::Display for DateTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )?;
+        match self.offset {
+            Some(offset) => {
+                let sign = if offset < 0 { '-' } else { '+' };
+                let offset = offset.unsigned_abs();
+                write!(f, "{}{:02}:{:02}", sign, offset / 60, offset % 60)
+            }
+            None => Ok(()),
+        }
+    }
+}
+
+/// How the shutter is released, normalized from the drive settings of makers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DriveMode {
+    Single,
+    Continuous,
+    SelfTimer,
+    /// Exposure, white balance or other kinds of bracketing.
+    Bracketing,
+}
+
+/// The location of the capture.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Gps {
+    /// In degrees, north is positive.
+    pub latitude: f64,
+    /// In degrees, east is positive.
+    pub longitude: f64,
+    /// In meters above the sea level.
+    pub altitude: Option<f64>,
+}
+
+/// The metadata of a raw file.
+///
+/// Levels are in the unscaled sensor values, and the white balance is normalized to `G = 1.0`.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RawMetadata {
+    pub make: String,
+    pub model: String,
+    pub is_dng: bool,
+    pub lens_make: Option<String>,
+    pub lens_model: Option<String>,
+    /// The lens identifier of the maker, like the `LensType` of Sony or the `LensIDNumber` of Nikon.
+    pub lens_id: Option<u32>,
+    /// In meters.
+    pub focus_distance: Option<f64>,
+    pub drive_mode: Option<DriveMode>,
+    /// Whether the image stabilization of the lens or the body is on.
+    pub stabilization: Option<bool>,
+    pub iso: Option<u32>,
+    /// In seconds.
+    pub exposure_time: Option<f64>,
+    pub f_number: Option<f64>,
+    /// In millimeters.
+    pub focal_length: Option<f64>,
+    pub date_time: Option<DateTime>,
+    pub gps: Option<Gps>,
+    pub orientation: Orientation,
+    /// The size of the sensor data.
+    pub width: usize,
+    pub height: usize,
+    pub crop: Option<Crop>,
+    pub bit_depth: Option<u16>,
+    /// The TIFF compression code of the sensor data.
+    pub compression: Option<u16>,
+    /// Of the 2x2 block at the top left of the CFA pattern in row-major order.
+    pub black_level: Option<[u16; 4]>,
+    pub white_level: Option<u16>,
+    pub white_balance: Option<[f32; 3]>,
+    pub body_serial: Option<String>,
+    pub lens_serial: Option<String>,
+}
+
+/// Reads an ASCII tag parsed by `utility::ascii_tag`.
+fn ascii(info: &quickexif::ParsedInfo, name: &str, inline: &str, len: &str) -> Option<String> {
+    let value = match info.str(name) {
+        Ok(x) => x.to_owned(),
+        Err(_) => {
+            let len = info.usize(len).ok()?;
+            info.u8a4(inline)
+                .ok()?
+                .iter()
+                .take(len)
+                .map_while(|&x| if x == 0 { None } else { Some(x as char) })
+                .collect()
+        }
+    };
+    let value = value.trim();
+    (!value.is_empty()).then(|| value.to_owned())
+}
+
+/// The first byte of an ASCII or BYTE tag with a single value.
+fn first_byte(info: &quickexif::ParsedInfo, name: &str) -> Option<u8> {
+    info.u8a4(name).ok().map(|x| x[0])
+}
+
+fn gps(info: &quickexif::ParsedInfo) -> Option<Gps> {
+    let degrees = |prefix: &str, negative: u8| -> Option<f64> {
+        let d = info.f64(&format!("{}_d", prefix)).ok()?;
+        let m = info.f64(&format!("{}_m", prefix)).unwrap_or(0.);
+        let s = info.f64(&format!("{}_s", prefix)).unwrap_or(0.);
+        let value = d + m / 60. + s / 3600.;
+        if !value.is_finite() {
+            return None;
+        }
+        match first_byte(info, &format!("{}_ref", prefix)) {
+            Some(x) if x == negative => Some(-value),
+            _ => Some(value),
+        }
+    };
+
+    let altitude = info.f64("gps_altitude").ok().filter(|x| x.is_finite());
+    Some(Gps {
+        latitude: degrees("gps_latitude", b'S')?,
+        longitude: degrees("gps_longitude", b'W')?,
+        altitude: altitude.map(|x| match first_byte(info, "gps_altitude_ref") {
+            Some(1) => -x,
+            _ => x,
+        }),
+    })
+}
+
+impl RawMetadata {
+    /// Fills the fields from the EXIF and GPS blocks parsed by `utility::SHOOTING_INFO_RULE`.
+    pub(crate) fn apply_shooting_info(&mut self, info: &quickexif::ParsedInfo) {
+        let positive = |name: &str| info.f64(name).ok().filter(|x| x.is_finite() && *x > 0.);
+
+        // the ISO tag saturates at 65535, where the full value is in ISOSpeed
+        self.iso = match info.u32("iso") {
+            Ok(x) if x > 0 && x < u16::MAX as u32 => Some(x),
+            x => info.u32("iso_speed").ok().or(x.ok()).filter(|&x| x > 0),
+        };
+        self.exposure_time = positive("exposure_time");
+        self.f_number = positive("f_number");
+        self.focal_length = positive("focal_length");
+        self.date_time = info.str("date_time_original").ok().and_then(|date_time| {
+            DateTime::from_exif(date_time, info.str("offset_time_original").ok())
+        });
+        self.gps = gps(info);
+        self.lens_make = ascii(info, "lens_make", "lens_make_inline", "lens_make_len");
+        // the values of the maker notes are kept when the EXIF block lacks them
+        self.lens_model = ascii(info, "lens_model", "lens_model_inline", "lens_model_len")
+            .or(self.lens_model.take());
+        self.body_serial = ascii(info, "body_serial", "body_serial_inline", "body_serial_len")
+            .or_else(|| {
+                ascii(
+                    info,
+                    "camera_serial",
+                    "camera_serial_inline",
+                    "camera_serial_len",
+                )
+            })
+            .or(self.body_serial.take());
+        self.lens_serial = ascii(info, "lens_serial", "lens_serial_inline", "lens_serial_len")
+            .or(self.lens_serial.take());
+    }
+}
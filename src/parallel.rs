@@ -0,0 +1,97 @@
+//! Splits decoding and rendering into independent parts.
+//!
+//! With the `parallel` feature the parts run on the threads of rayon, without it they run one
+//! after another on the calling thread, so both builds give the same output.
+
+use std::ops::Range;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+#[cfg(feature = "parallel")]
+use std::sync::{Arc, PoisonError, RwLock};
+
+#[cfg(feature = "parallel")]
+static POOL: RwLock<Option<Arc<rayon::ThreadPool>>> = RwLock::new(None);
+
+/// Caps the number of threads used to decode and render images.
+///
+/// `0` goes back to one thread per logical CPU. Without a call, the global pool of rayon is used.
+#[cfg(feature = "parallel")]
+pub fn set_max_threads(count: usize) -> Result<(), crate::RawFileReadingError> {
+    let pool = match count {
+        0 => None,
+        _ => Some(Arc::new(
+            rayon::ThreadPoolBuilder::new().num_threads(count).build()?,
+        )),
+    };
+    *POOL.write().unwrap_or_else(PoisonError::into_inner) = pool;
+    Ok(())
+}
+
+#[cfg(feature = "parallel")]
+fn install<R: Send>(f: impl FnOnce() -> R + Send) -> R {
+    let pool = POOL.read().unwrap_or_else(PoisonError::into_inner).clone();
+    match pool {
+        Some(pool) => pool.install(f),
+        None => f(),
+    }
+}
+
+/// Calls `f` with the index and the content of every `chunk_len` long chunk of `data`.
+pub(crate) fn for_each_chunk<T: Send>(
+    data: &mut [T],
+    chunk_len: usize,
+    f: impl Fn(usize, &mut [T]) + Sync + Send,
+) {
+    #[cfg(feature = "parallel")]
+    install(|| {
+        data.par_chunks_exact_mut(chunk_len)
+            .enumerate()
+            .for_each(|(index, chunk)| f(index, chunk))
+    });
+    #[cfg(not(feature = "parallel"))]
+    data.chunks_exact_mut(chunk_len)
+        .enumerate()
+        .for_each(|(index, chunk)| f(index, chunk));
+}
+
+/// Maps every item along with its index, the results keep the order of `items`.
+pub(crate) fn map<T: Sync, R: Send>(
+    items: &[T],
+    f: impl Fn(usize, &T) -> R + Sync + Send,
+) -> Vec<R> {
+    #[cfg(feature = "parallel")]
+    return install(|| {
+        items
+            .par_iter()
+            .enumerate()
+            .map(|(index, item)| f(index, item))
+            .collect()
+    });
+    #[cfg(not(feature = "parallel"))]
+    items
+        .iter()
+        .enumerate()
+        .map(|(index, item)| f(index, item))
+        .collect()
+}
+
+/// Splits `0..len` into ranges and concatenates what `f` makes of each of them.
+///
+/// A single range covers everything without the `parallel` feature.
+pub(crate) fn map_ranges<R: Send>(
+    len: usize,
+    f: impl Fn(Range<usize>) -> Vec<R> + Sync + Send,
+) -> Vec<R> {
+    #[cfg(feature = "parallel")]
+    return install(|| {
+        // a few parts per thread balance the rows that are slower to demosaic
+        let part_len = len.div_ceil(rayon::current_num_threads() * 4).max(4096);
+        (0..len.div_ceil(part_len))
+            .into_par_iter()
+            .flat_map_iter(|part| f(part * part_len..len.min((part + 1) * part_len)))
+            .collect()
+    });
+    #[cfg(not(feature = "parallel"))]
+    f(0..len)
+}
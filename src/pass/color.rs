@@ -1,7 +1,9 @@
+use super::simd;
+use crate::TransferFunction;
 use std::cmp;
 
-const BIT_SHIFT: u32 = 13u32;
-const CLIP_LIMIT_I32: i32 = 65535;
+pub(super) const BIT_SHIFT: u32 = 13u32;
+pub(super) const CLIP_LIMIT_I32: i32 = 65535;
 const CLIP_RANGE: (i32, i32) = (0, CLIP_LIMIT_I32);
 
 #[inline(always)]
@@ -31,6 +33,21 @@ pub fn color_convert<'a>(
     })
 }
 
+/// `u16rgb_to_i32rgb`, `white_balance_fix` and `color_convert` in a single pass, vectorized on
+/// CPUs with AVX2, SSE4.1 or NEON.
+#[inline(always)]
+pub fn white_balance_and_convert<'a>(
+    iter: impl Iterator<Item = [u16; 3]> + 'a,
+    white_balance: &'a [i32; 3],
+    c: &'a [i32; 9],
+) -> impl Iterator<Item = [u16; 3]> + 'a {
+    let params = simd::Params {
+        white_balance: *white_balance,
+        matrix: *c,
+    };
+    simd::Batches::new(iter, simd::white_balance_and_convert_kernel(), params)
+}
+
 #[inline(always)]
 pub fn color_convert_rgba<'a>(
     iter: impl Iterator<Item = [i32; 3]> + 'a,
@@ -61,16 +78,69 @@ pub fn gamma_correct<'a>(
 }
 
 #[inline(always)]
-pub fn gen_gamma_lut(gamma: f32) -> [u16; 65536] {
+pub fn gen_gamma_lut(transfer: impl Into<TransferFunction>) -> [u16; 65536] {
+    let transfer = transfer.into();
     let mut lut = [0u16; 65536];
     for (i, elem) in lut.iter_mut().enumerate() {
         let l = i as f32 / 65535.;
-        *elem = (l.powf(gamma) * 65535.) as u16;
+        *elem = (transfer.encode(l) * 65535.) as u16;
     }
     lut
 }
 
+/// Applies the transfer function to linear float values without clipping.
+#[inline(always)]
+pub fn gamma_correct_f32(
+    iter: impl Iterator<Item = [f32; 3]>,
+    transfer: TransferFunction,
+) -> impl Iterator<Item = [f32; 3]> {
+    iter.map(move |rgb| rgb.map(|v| transfer.encode(v)))
+}
+
 #[inline(always)]
 fn limit_to_range<T: Ord>(v: T, (left, right): (T, T)) -> T {
     cmp::min(cmp::max(v, left), right)
 }
+
+#[inline(always)]
+pub fn white_balance_fix_f32<'a>(
+    iter: impl Iterator<Item = [f32; 3]> + 'a,
+    white_balance: &'a [f32; 3],
+) -> impl Iterator<Item = [f32; 3]> + 'a {
+    iter.map(move |[r, g, b]| {
+        [
+            r * white_balance[0],
+            g * white_balance[1],
+            b * white_balance[2],
+        ]
+    })
+}
+
+#[inline(always)]
+pub fn color_convert_f32<'a>(
+    iter: impl Iterator<Item = [f32; 3]> + 'a,
+    c: &'a [f32; 9],
+) -> impl Iterator<Item = [f32; 3]> + 'a {
+    iter.map(move |[r, g, b]| {
+        [
+            c[0] * r + c[1] * g + c[2] * b,
+            c[3] * r + c[4] * g + c[5] * b,
+            c[6] * r + c[7] * g + c[8] * b,
+        ]
+    })
+}
+
+/// `u16rgb_to_f32rgb`, `white_balance_fix_f32` and `color_convert_f32` in a single pass, vectorized
+/// on CPUs with AVX2, SSE4.1 or NEON.
+#[inline(always)]
+pub fn white_balance_and_convert_f32<'a>(
+    iter: impl Iterator<Item = [u16; 3]> + 'a,
+    white_balance: &'a [f32; 3],
+    c: &'a [f32; 9],
+) -> impl Iterator<Item = [f32; 3]> + 'a {
+    let params = simd::Params {
+        white_balance: *white_balance,
+        matrix: *c,
+    };
+    simd::Batches::new(iter, simd::white_balance_and_convert_f32_kernel(), params)
+}
@@ -1,7 +1,7 @@
 use super::*;
 
 #[inline(always)]
-pub(super) fn rggb(i: usize, v: u16, image: &[u16], w: usize, h: usize) -> [u16; 3] {
+pub(super) fn rggb<T: Sample>(i: usize, v: T, image: &[T], w: usize, h: usize) -> [T; 3] {
     match bayer_pixel_info(i, w, h) {
         // top left corner
         (true, _, true, _, _, _) => [v, avg(image, &[i + 1, i + w]), get_pixel(image, i + w + 1)],
@@ -55,7 +55,7 @@ pub(super) fn rggb(i: usize, v: u16, image: &[u16], w: usize, h: usize) -> [u16;
 }
 
 #[inline(always)]
-pub(super) fn bggr(i: usize, v: u16, image: &[u16], w: usize, h: usize) -> [u16; 3] {
+pub(super) fn bggr<T: Sample>(i: usize, v: T, image: &[T], w: usize, h: usize) -> [T; 3] {
     match bayer_pixel_info(i, w, h) {
         // top left corner
         (true, _, true, _, _, _) => [get_pixel(image, i + w + 1), avg(image, &[i + 1, i + w]), v],
@@ -109,7 +109,7 @@ pub(super) fn bggr(i: usize, v: u16, image: &[u16], w: usize, h: usize) -> [u16;
 }
 
 #[inline(always)]
-pub(super) fn grbg(i: usize, v: u16, image: &[u16], w: usize, h: usize) -> [u16; 3] {
+pub(super) fn grbg<T: Sample>(i: usize, v: T, image: &[T], w: usize, h: usize) -> [T; 3] {
     match bayer_pixel_info(i, w, h) {
         // top left corner
         (true, _, true, _, _, _) => [get_pixel(image, i + 1), v, get_pixel(image, i + w)],
@@ -167,7 +167,7 @@ pub(super) fn grbg(i: usize, v: u16, image: &[u16], w: usize, h: usize) -> [u16;
 }
 
 #[inline(always)]
-pub(super) fn gbrg(i: usize, v: u16, image: &[u16], w: usize, h: usize) -> [u16; 3] {
+pub(super) fn gbrg<T: Sample>(i: usize, v: T, image: &[T], w: usize, h: usize) -> [T; 3] {
     match bayer_pixel_info(i, w, h) {
         // top left corner
         (true, _, true, _, _, _) => [get_pixel(image, i + w), v, get_pixel(image, i + 1)],
@@ -225,7 +225,7 @@ pub(super) fn gbrg(i: usize, v: u16, image: &[u16], w: usize, h: usize) -> [u16;
 }
 
 #[inline(always)]
-pub(super) fn xtrans0(i: usize, v: u16, image: &[u16], w: usize, h: usize) -> [u16; 3] {
+pub(super) fn xtrans0<T: Sample>(i: usize, v: T, image: &[T], w: usize, h: usize) -> [T; 3] {
     let x = i % w;
     let y = i / w;
     let is_top = y == 0;
@@ -378,12 +378,12 @@ pub(super) fn xtrans0(i: usize, v: u16, image: &[u16], w: usize, h: usize) -> [u
         (_, _, _, true, (_, 4)) => [v, p!(i - 1), p!(i + w)],
         (_, _, _, true, (_, 5)) => [p!(i - w), p!(i - 1), v],
 
-        _ => [0; 3],
+        _ => [T::default(); 3],
     }
 }
 
 #[inline(always)]
-pub(super) fn xtrans1(i: usize, v: u16, image: &[u16], w: usize, h: usize) -> [u16; 3] {
+pub(super) fn xtrans1<T: Sample>(i: usize, v: T, image: &[T], w: usize, h: usize) -> [T; 3] {
     let x = i % w;
     let y = i / w;
     let is_top = y == 0;
@@ -536,6 +536,6 @@ pub(super) fn xtrans1(i: usize, v: u16, image: &[u16], w: usize, h: usize) -> [u
         (_, _, _, true, (_, 4)) => [p!(i - w), p!(i - 1), v],
         (_, _, _, true, (_, 5)) => [p!(i - 1), v, p!(i - w)],
 
-        _ => [0; 3],
+        _ => [T::default(); 3],
     }
 }
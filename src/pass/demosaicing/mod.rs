@@ -1,51 +1,132 @@
 mod enhanced_linear;
 mod linear;
+mod super_pixel;
+mod tiled;
+
+use crate::decode::CFAPattern;
+use std::ops::Add;
+pub use tiled::set_tiled_demosaicing_enabled;
+use tiled::{BLUE, GREEN_B, GREEN_R, RED};
+
+/// The types of the samples demosaiced, the averages of `u16` samples are summed in `u32`.
+pub trait Sample: Copy + Default + 'static {
+    type Sum: Copy + Default + Add<Output = Self::Sum>;
+
+    fn widen(self) -> Self::Sum;
+    /// The average of `n` samples summing to `sum`.
+    fn average(sum: Self::Sum, n: u32) -> Self;
+}
+impl Sample for u16 {
+    type Sum = u32;
+
+    #[inline(always)]
+    fn widen(self) -> u32 {
+        self as u32
+    }
+    #[inline(always)]
+    fn average(sum: u32, n: u32) -> u16 {
+        (sum / n) as u16
+    }
+}
+impl Sample for f32 {
+    type Sum = f32;
+
+    #[inline(always)]
+    fn widen(self) -> f32 {
+        self
+    }
+    #[inline(always)]
+    fn average(sum: f32, n: u32) -> f32 {
+        sum / n as f32
+    }
+}
 
 #[inline(always)]
-pub fn none<'a>(
-    iter: impl Iterator<Item = (usize, u16)> + 'a,
-) -> impl Iterator<Item = [u16; 3]> + 'a {
+pub fn none<'a, T: Sample>(
+    iter: impl Iterator<Item = (usize, T)> + 'a,
+) -> impl Iterator<Item = [T; 3]> + 'a {
     iter.map(|(_, v)| [v; 3])
 }
 
+/// Every pixel takes the colors of its CFA block, which keeps the size of the image but not its detail.
+#[inline(always)]
+pub fn super_pixel<'a, T: Sample>(
+    iter: impl Iterator<Item = (usize, T)> + 'a,
+    image: &'a [T],
+    width: usize,
+    height: usize,
+    cfa_pattern: CFAPattern,
+) -> impl Iterator<Item = [T; 3]> + 'a {
+    iter.map(move |(i, _)| super_pixel::block(i, image, width, height, cfa_pattern))
+}
+
 macro_rules! gen_linear {
     ($name:ident, $fn:expr) => {
+        #[inline(always)]
+        pub fn $name<'a, T: Sample>(
+            iter: impl Iterator<Item = (usize, T)> + 'a,
+            image: &'a [T],
+            width: usize,
+            height: usize,
+        ) -> impl Iterator<Item = [T; 3]> + 'a {
+            iter.map(move |(i, v)| $fn(i, v, image, width, height))
+        }
+    };
+    // for the functions of a single sample type
+    ($name:ident, $fn:expr, $t:ty) => {
         #[inline(always)]
         pub fn $name<'a>(
-            iter: impl Iterator<Item = (usize, u16)> + 'a,
-            image: &'a [u16],
+            iter: impl Iterator<Item = (usize, $t)> + 'a,
+            image: &'a [$t],
             width: usize,
             height: usize,
-        ) -> impl Iterator<Item = [u16; 3]> + 'a {
+        ) -> impl Iterator<Item = [$t; 3]> + 'a {
             iter.map(move |(i, v)| $fn(i, v, image, width, height))
         }
     };
 }
 
-gen_linear!(linear_rggb, linear::rggb);
-gen_linear!(linear_bggr, linear::bggr);
-gen_linear!(linear_grbg, linear::grbg);
-gen_linear!(linear_gbrg, linear::gbrg);
+/// The interior is demosaiced in tiles, `$sites` are the CFA sites of the first two rows.
+macro_rules! gen_tiled_linear {
+    ($name:ident, $fn:expr, $sites:expr) => {
+        #[inline(always)]
+        pub fn $name<'a, T: Sample>(
+            iter: impl Iterator<Item = (usize, T)> + 'a,
+            image: &'a [T],
+            width: usize,
+            height: usize,
+        ) -> impl Iterator<Item = [T; 3]> + 'a {
+            let mut tiles = tiled::Tiles::new(image, width, height, $sites, $fn);
+            iter.map(move |(i, v)| tiles.pixel(i, v))
+        }
+    };
+}
+
+gen_tiled_linear!(linear_rggb, linear::rggb, [[RED, GREEN_R], [GREEN_B, BLUE]]);
+gen_tiled_linear!(linear_bggr, linear::bggr, [[BLUE, GREEN_B], [GREEN_R, RED]]);
+gen_tiled_linear!(linear_grbg, linear::grbg, [[GREEN_R, RED], [BLUE, GREEN_B]]);
+gen_tiled_linear!(linear_gbrg, linear::gbrg, [[GREEN_B, BLUE], [RED, GREEN_R]]);
 gen_linear!(linear_xtrans0, linear::xtrans0);
 gen_linear!(linear_xtrans1, linear::xtrans1);
 
-gen_linear!(elinear_rggb, enhanced_linear::rggb);
-gen_linear!(elinear_bggr, enhanced_linear::bggr);
-gen_linear!(elinear_grbg, enhanced_linear::grbg);
-gen_linear!(elinear_gbrg, enhanced_linear::gbrg);
+gen_linear!(elinear_rggb, enhanced_linear::rggb, u16);
+gen_linear!(elinear_bggr, enhanced_linear::bggr, u16);
+gen_linear!(elinear_grbg, enhanced_linear::grbg, u16);
+gen_linear!(elinear_gbrg, enhanced_linear::gbrg, u16);
 
+/// The neighbours out of images of a single row or column read as 0.
 #[inline(always)]
-pub(self) fn get_pixel(image: &[u16], i: usize) -> u16 {
-    unsafe { *image.get_unchecked(i) }
+pub(self) fn get_pixel<T: Sample>(image: &[T], i: usize) -> T {
+    image.get(i).copied().unwrap_or_default()
 }
 #[inline(always)]
-pub(self) fn avg<const N: usize>(image: &[u16], indexes: &[usize; N]) -> u16 {
-    let mut sum = 0;
+pub(self) fn avg<T: Sample, const N: usize>(image: &[T], indexes: &[usize; N]) -> T {
+    let mut sum = T::Sum::default();
     for &i in indexes {
-        sum += get_pixel(image, i) as u32;
+        sum = sum + get_pixel(image, i).widen();
     }
 
-    (sum / N as u32) as u16
+    T::average(sum, N as u32)
 }
 #[inline(always)]
 pub(self) fn bayer_pixel_info(
@@ -0,0 +1,27 @@
+use super::*;
+use crate::decode::cfa_color;
+
+/// The RGB of the CFA block of pixel `i`, each color averaged over its samples in the block.
+///
+/// Blocks are 2x2 for Bayer patterns and 3x3 for X-Trans, each of them holds all three colors.
+/// The partial blocks at the right and bottom edges take the colors of the full blocks before them.
+#[inline(always)]
+pub(super) fn block<T: Sample>(i: usize, image: &[T], w: usize, h: usize, cfa_pattern: CFAPattern) -> [T; 3] {
+    let size = match cfa_pattern {
+        CFAPattern::XTrans0 | CFAPattern::XTrans1 => 3,
+        _ => 2,
+    };
+    let start = |p: usize, len: usize| (p / size).min((len / size).saturating_sub(1)) * size;
+    let (x0, y0) = (start(i % w, w), start(i / w, h));
+
+    let mut sums = [T::Sum::default(); 3];
+    let mut counts = [0u32; 3];
+    for y in y0..(y0 + size).min(h) {
+        for x in x0..(x0 + size).min(w) {
+            let color = cfa_color(cfa_pattern, x, y);
+            sums[color] = sums[color] + image[y * w + x].widen();
+            counts[color] += 1;
+        }
+    }
+    std::array::from_fn(|color| T::average(sums[color], counts[color].max(1)))
+}
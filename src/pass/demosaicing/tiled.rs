@@ -0,0 +1,146 @@
+//! Bilinear demosaicing of Bayer data in tiles of a row.
+//!
+//! The pixels off the border need no edge checks, so a tile of them is interpolated by a kernel
+//! specialized for the two CFA sites of its row. The border pixels go through the per-pixel
+//! functions of `linear`, the averages of the interior are the same as theirs.
+
+use super::Sample;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Pixels demosaiced at once, all in one row.
+const TILE: usize = 64;
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Turns the tiled demosaicing of Bayer images on or off, it is on by default.
+///
+/// Off, every pixel goes through the per-pixel functions. Both paths render the same output,
+/// the switch is there to compare them or to debug.
+pub fn set_tiled_demosaicing_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub(super) const RED: u8 = 0;
+/// Green on a row with red.
+pub(super) const GREEN_R: u8 = 1;
+/// Green on a row with blue.
+pub(super) const GREEN_B: u8 = 2;
+pub(super) const BLUE: u8 = 3;
+
+pub(super) type PixelFn<T> = fn(usize, T, &[T], usize, usize) -> [T; 3];
+
+/// Interpolates the pixel in the middle of the 3x3 neighbourhood of the rows `up`, `row` and `down`.
+///
+/// The samples are summed in the order of the per-pixel functions, so `f32` averages round the same.
+#[inline(always)]
+fn site<const SITE: u8, T: Sample>(up: &[T], row: &[T], down: &[T]) -> [T; 3] {
+    let v = row[1];
+    let (left, right) = (row[0].widen(), row[2].widen());
+    let vertical = up[1].widen() + down[1].widen();
+    let diagonal = up[0].widen() + up[2].widen() + down[0].widen() + down[2].widen();
+    let (cross, horizontal, vertical, diagonal) = (
+        T::average(vertical + left + right, 4),
+        T::average(left + right, 2),
+        T::average(vertical, 2),
+        T::average(diagonal, 4),
+    );
+    match SITE {
+        RED => [v, cross, diagonal],
+        GREEN_R => [horizontal, v, vertical],
+        GREEN_B => [vertical, v, horizontal],
+        _ => [diagonal, cross, v],
+    }
+}
+
+/// Fills `out` with the pixels from `i` on, which alternate between the sites `A` and `B`.
+#[inline(always)]
+fn row_tile<const A: u8, const B: u8, T: Sample>(image: &[T], i: usize, w: usize, out: &mut [[T; 3]]) {
+    let len = out.len();
+    let row = &image[i - 1..i + len + 1];
+    let up = &image[i - w - 1..i - w + len + 1];
+    let down = &image[i + w - 1..i + w + len + 1];
+
+    let mut pairs = out.chunks_exact_mut(2);
+    let windows = up
+        .windows(4)
+        .step_by(2)
+        .zip(row.windows(4).step_by(2))
+        .zip(down.windows(4).step_by(2));
+    for (pair, ((up, row), down)) in (&mut pairs).zip(windows) {
+        pair[0] = site::<A, T>(up, row, down);
+        pair[1] = site::<B, T>(&up[1..], &row[1..], &down[1..]);
+    }
+    if let [last] = pairs.into_remainder() {
+        let n = len - 1;
+        *last = site::<A, T>(&up[n..], &row[n..], &down[n..]);
+    }
+}
+
+/// Demosaics pixels by their indexes, `sites` maps the parities of the row and the column to a site.
+///
+/// A pixel of the interior fills the tile from it on, so the pixels are fast in order and correct in any order.
+pub(super) struct Tiles<'a, T: Sample> {
+    image: &'a [T],
+    width: usize,
+    height: usize,
+    sites: [[u8; 2]; 2],
+    border: PixelFn<T>,
+    tiled: bool,
+    tile: [[T; 3]; TILE],
+    start: usize,
+    len: usize,
+}
+
+impl<'a, T: Sample> Tiles<'a, T> {
+    pub(super) fn new(
+        image: &'a [T],
+        width: usize,
+        height: usize,
+        sites: [[u8; 2]; 2],
+        border: PixelFn<T>,
+    ) -> Self {
+        Tiles {
+            image,
+            width,
+            height,
+            sites,
+            border,
+            tiled: ENABLED.load(Ordering::Relaxed),
+            tile: [[T::default(); 3]; TILE],
+            start: 0,
+            len: 0,
+        }
+    }
+
+    #[inline(always)]
+    pub(super) fn pixel(&mut self, i: usize, v: T) -> [T; 3] {
+        let offset = i.wrapping_sub(self.start);
+        if offset < self.len {
+            return self.tile[offset];
+        }
+
+        let (w, h) = (self.width, self.height);
+        let (x, y) = (i % w, i / w);
+        if !self.tiled || x == 0 || y == 0 || x == w - 1 || y == h - 1 {
+            return (self.border)(i, v, self.image, w, h);
+        }
+        self.fill_tile(i, x, y);
+        self.tile[0]
+    }
+
+    #[inline(never)]
+    fn fill_tile(&mut self, i: usize, x: usize, y: usize) {
+        let (image, w) = (self.image, self.width);
+        let len = TILE.min(w - 1 - x);
+        let out = &mut self.tile[..len];
+        // the sites of a Bayer row alternate between red and green or green and blue
+        match self.sites[y % 2][x % 2] {
+            RED => row_tile::<RED, GREEN_R, T>(image, i, w, out),
+            GREEN_R => row_tile::<GREEN_R, RED, T>(image, i, w, out),
+            GREEN_B => row_tile::<GREEN_B, BLUE, T>(image, i, w, out),
+            _ => row_tile::<BLUE, GREEN_B, T>(image, i, w, out),
+        }
+        self.start = i;
+        self.len = len;
+    }
+}
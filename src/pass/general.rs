@@ -4,6 +4,11 @@ pub fn u16rgb_to_i32rgb(iter: impl Iterator<Item = [u16; 3]>) -> impl Iterator<I
     iter.map(|[r, g, b]| [r as i32, g as i32, b as i32])
 }
 
+#[inline(always)]
+pub fn u16rgb_to_f32rgb(iter: impl Iterator<Item = [u16; 3]>) -> impl Iterator<Item = [f32; 3]> {
+    iter.map(|[r, g, b]| [r as f32 / 65535., g as f32 / 65535., b as f32 / 65535.])
+}
+
 #[inline(always)]
 pub fn u16rgb_to_u8rgb(iter: impl Iterator<Item = [u16; 3]>) -> impl Iterator<Item = [u8; 3]> {
     iter.map(|[r, g, b]| [(r >> 8) as u8, (g >> 8) as u8, (b >> 8) as u8])
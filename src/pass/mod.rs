@@ -1,10 +1,12 @@
 mod color;
 mod demosaicing;
 mod general;
+mod simd;
 
 pub use color::*;
 pub use demosaicing::*;
 pub use general::*;
+pub use simd::set_simd_enabled;
 
 #[macro_export]
 macro_rules! iters_to_vec {
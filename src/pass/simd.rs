@@ -0,0 +1,354 @@
+//! Vectorized kernels of the color passes, picked at runtime by the features of the CPU.
+//!
+//! The kernels follow the order of the operations of the scalar passes, so their output is the
+//! same bit for bit. Pixels that do not fill a whole vector go through the scalar passes.
+
+use super::color::{
+    color_convert, color_convert_f32, white_balance_fix, white_balance_fix_f32, BIT_SHIFT,
+    CLIP_LIMIT_I32,
+};
+use super::general::{u16rgb_to_f32rgb, u16rgb_to_i32rgb};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Pixels handed to a kernel at once.
+const BATCH: usize = 256;
+const SHIFT: i32 = BIT_SHIFT as i32;
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// Turns the vectorized color passes on or off, they are on by default.
+///
+/// Both paths render the same output, the switch is there to compare them or to debug.
+pub fn set_simd_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub(super) struct Params<T> {
+    pub(super) white_balance: [T; 3],
+    pub(super) matrix: [T; 9],
+}
+
+pub(super) type Kernel<O, T> = fn(&[[u16; 3]], &mut [O], &Params<T>);
+
+fn white_balance_and_convert_scalar(
+    input: &[[u16; 3]],
+    output: &mut [[u16; 3]],
+    params: &Params<i32>,
+) {
+    let iter = u16rgb_to_i32rgb(input.iter().copied());
+    let iter = color_convert(
+        white_balance_fix(iter, &params.white_balance),
+        &params.matrix,
+    );
+    output.iter_mut().zip(iter).for_each(|(o, x)| *o = x);
+}
+
+fn white_balance_and_convert_f32_scalar(
+    input: &[[u16; 3]],
+    output: &mut [[f32; 3]],
+    params: &Params<f32>,
+) {
+    let iter = u16rgb_to_f32rgb(input.iter().copied());
+    let iter = color_convert_f32(
+        white_balance_fix_f32(iter, &params.white_balance),
+        &params.matrix,
+    );
+    output.iter_mut().zip(iter).for_each(|(o, x)| *o = x);
+}
+
+/// Splits the pixels into one array per channel.
+#[inline(always)]
+fn split_channels<const N: usize>(pixels: &[[u16; 3]]) -> [[i32; N]; 3] {
+    let mut channels = [[0i32; N]; 3];
+    for (i, pixel) in pixels.iter().enumerate() {
+        channels[0][i] = pixel[0] as i32;
+        channels[1][i] = pixel[1] as i32;
+        channels[2][i] = pixel[2] as i32;
+    }
+    channels
+}
+
+/// Generates the integer and the float kernels from the intrinsics of an instruction set.
+// unused on targets without kernels like wasm32
+#[allow(unused_macros)]
+macro_rules! gen_kernels {
+    (
+        $feature:literal, $lanes:literal, $int_kernel:ident, $float_kernel:ident,
+        $splat_i:ident, $load_i:ident, $store_i:ident, $add_i:ident, $mul_i:ident, $shr_i:ident, $min_i:ident, $max_i:ident,
+        $splat_f:ident, $to_f:ident, $add_f:ident, $mul_f:ident, $div_f:ident, $store_f:ident
+    ) => {
+        #[target_feature(enable = $feature)]
+        pub(super) unsafe fn $int_kernel(
+            input: &[[u16; 3]],
+            output: &mut [[u16; 3]],
+            params: &Params<i32>,
+        ) {
+            let [wb_r, wb_g, wb_b] = params.white_balance;
+            let (wb_r, wb_g, wb_b) = ($splat_i(wb_r), $splat_i(wb_g), $splat_i(wb_b));
+            let c = params.matrix;
+            let c = [
+                $splat_i(c[0]),
+                $splat_i(c[1]),
+                $splat_i(c[2]),
+                $splat_i(c[3]),
+                $splat_i(c[4]),
+                $splat_i(c[5]),
+                $splat_i(c[6]),
+                $splat_i(c[7]),
+                $splat_i(c[8]),
+            ];
+            let zero = $splat_i(0);
+            let limit = $splat_i(CLIP_LIMIT_I32);
+
+            let mut inputs = input.chunks_exact($lanes);
+            let mut outputs = output.chunks_exact_mut($lanes);
+            for (input, output) in (&mut inputs).zip(&mut outputs) {
+                let [r, g, b] = split_channels::<$lanes>(input);
+                let r = $min_i(
+                    $shr_i::<SHIFT>($mul_i($load_i(r.as_ptr() as *const _), wb_r)),
+                    limit,
+                );
+                let g = $min_i(
+                    $shr_i::<SHIFT>($mul_i($load_i(g.as_ptr() as *const _), wb_g)),
+                    limit,
+                );
+                let b = $min_i(
+                    $shr_i::<SHIFT>($mul_i($load_i(b.as_ptr() as *const _), wb_b)),
+                    limit,
+                );
+
+                let mut channels = [[0i32; $lanes]; 3];
+                for (channel, c) in channels.iter_mut().zip(c.chunks_exact(3)) {
+                    let x = $add_i($add_i($mul_i(c[0], r), $mul_i(c[1], g)), $mul_i(c[2], b));
+                    let x = $min_i($max_i($shr_i::<SHIFT>(x), zero), limit);
+                    $store_i(channel.as_mut_ptr() as *mut _, x);
+                }
+                for (i, pixel) in output.iter_mut().enumerate() {
+                    *pixel = [
+                        channels[0][i] as u16,
+                        channels[1][i] as u16,
+                        channels[2][i] as u16,
+                    ];
+                }
+            }
+            white_balance_and_convert_scalar(inputs.remainder(), outputs.into_remainder(), params);
+        }
+
+        #[target_feature(enable = $feature)]
+        pub(super) unsafe fn $float_kernel(
+            input: &[[u16; 3]],
+            output: &mut [[f32; 3]],
+            params: &Params<f32>,
+        ) {
+            let [wb_r, wb_g, wb_b] = params.white_balance;
+            let (wb_r, wb_g, wb_b) = ($splat_f(wb_r), $splat_f(wb_g), $splat_f(wb_b));
+            let c = params.matrix;
+            let c = [
+                $splat_f(c[0]),
+                $splat_f(c[1]),
+                $splat_f(c[2]),
+                $splat_f(c[3]),
+                $splat_f(c[4]),
+                $splat_f(c[5]),
+                $splat_f(c[6]),
+                $splat_f(c[7]),
+                $splat_f(c[8]),
+            ];
+            let max = $splat_f(65535.);
+
+            let mut inputs = input.chunks_exact($lanes);
+            let mut outputs = output.chunks_exact_mut($lanes);
+            for (input, output) in (&mut inputs).zip(&mut outputs) {
+                let [r, g, b] = split_channels::<$lanes>(input);
+                let r = $mul_f($div_f($to_f($load_i(r.as_ptr() as *const _)), max), wb_r);
+                let g = $mul_f($div_f($to_f($load_i(g.as_ptr() as *const _)), max), wb_g);
+                let b = $mul_f($div_f($to_f($load_i(b.as_ptr() as *const _)), max), wb_b);
+
+                let mut channels = [[0f32; $lanes]; 3];
+                for (channel, c) in channels.iter_mut().zip(c.chunks_exact(3)) {
+                    let x = $add_f($add_f($mul_f(c[0], r), $mul_f(c[1], g)), $mul_f(c[2], b));
+                    $store_f(channel.as_mut_ptr(), x);
+                }
+                for (i, pixel) in output.iter_mut().enumerate() {
+                    *pixel = [channels[0][i], channels[1][i], channels[2][i]];
+                }
+            }
+            white_balance_and_convert_f32_scalar(
+                inputs.remainder(),
+                outputs.into_remainder(),
+                params,
+            );
+        }
+    };
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+mod x86 {
+    use super::*;
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::*;
+
+    gen_kernels!(
+        "sse4.1",
+        4,
+        white_balance_and_convert_sse41,
+        white_balance_and_convert_f32_sse41,
+        _mm_set1_epi32,
+        _mm_loadu_si128,
+        _mm_storeu_si128,
+        _mm_add_epi32,
+        _mm_mullo_epi32,
+        _mm_srai_epi32,
+        _mm_min_epi32,
+        _mm_max_epi32,
+        _mm_set1_ps,
+        _mm_cvtepi32_ps,
+        _mm_add_ps,
+        _mm_mul_ps,
+        _mm_div_ps,
+        _mm_storeu_ps
+    );
+    gen_kernels!(
+        "avx2",
+        8,
+        white_balance_and_convert_avx2,
+        white_balance_and_convert_f32_avx2,
+        _mm256_set1_epi32,
+        _mm256_loadu_si256,
+        _mm256_storeu_si256,
+        _mm256_add_epi32,
+        _mm256_mullo_epi32,
+        _mm256_srai_epi32,
+        _mm256_min_epi32,
+        _mm256_max_epi32,
+        _mm256_set1_ps,
+        _mm256_cvtepi32_ps,
+        _mm256_add_ps,
+        _mm256_mul_ps,
+        _mm256_div_ps,
+        _mm256_storeu_ps
+    );
+}
+
+#[cfg(target_arch = "aarch64")]
+mod neon {
+    use super::*;
+    use std::arch::aarch64::*;
+
+    gen_kernels!(
+        "neon",
+        4,
+        white_balance_and_convert_neon,
+        white_balance_and_convert_f32_neon,
+        vdupq_n_s32,
+        vld1q_s32,
+        vst1q_s32,
+        vaddq_s32,
+        vmulq_s32,
+        vshrq_n_s32,
+        vminq_s32,
+        vmaxq_s32,
+        vdupq_n_f32,
+        vcvtq_f32_s32,
+        vaddq_f32,
+        vmulq_f32,
+        vdivq_f32,
+        vst1q_f32
+    );
+}
+
+/// Picks the widest kernel the CPU supports, the scalar one when SIMD is turned off.
+macro_rules! select_kernel {
+    ($scalar:ident, $sse41:ident, $avx2:ident, $neon:ident) => {{
+        if ENABLED.load(Ordering::Relaxed) {
+            // the kernels are only picked when the CPU has their instruction set
+            #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+            {
+                if is_x86_feature_detected!("avx2") {
+                    return |input, output, params| unsafe { x86::$avx2(input, output, params) };
+                }
+                if is_x86_feature_detected!("sse4.1") {
+                    return |input, output, params| unsafe { x86::$sse41(input, output, params) };
+                }
+            }
+            #[cfg(target_arch = "aarch64")]
+            {
+                if std::arch::is_aarch64_feature_detected!("neon") {
+                    return |input, output, params| unsafe { neon::$neon(input, output, params) };
+                }
+            }
+        }
+        $scalar
+    }};
+}
+
+pub(super) fn white_balance_and_convert_kernel() -> Kernel<[u16; 3], i32> {
+    select_kernel!(
+        white_balance_and_convert_scalar,
+        white_balance_and_convert_sse41,
+        white_balance_and_convert_avx2,
+        white_balance_and_convert_neon
+    )
+}
+
+pub(super) fn white_balance_and_convert_f32_kernel() -> Kernel<[f32; 3], f32> {
+    select_kernel!(
+        white_balance_and_convert_f32_scalar,
+        white_balance_and_convert_f32_sse41,
+        white_balance_and_convert_f32_avx2,
+        white_balance_and_convert_f32_neon
+    )
+}
+
+/// Collects the pixels of `iter` into batches and runs a kernel over each of them.
+pub(super) struct Batches<I, O, T> {
+    iter: I,
+    kernel: Kernel<O, T>,
+    params: Params<T>,
+    input: Vec<[u16; 3]>,
+    output: Vec<O>,
+    position: usize,
+}
+
+impl<I, O: Copy + Default, T> Batches<I, O, T> {
+    pub(super) fn new(iter: I, kernel: Kernel<O, T>, params: Params<T>) -> Self {
+        Batches {
+            iter,
+            kernel,
+            params,
+            input: Vec::with_capacity(BATCH),
+            output: Vec::with_capacity(BATCH),
+            position: 0,
+        }
+    }
+}
+
+impl<I: Iterator<Item = [u16; 3]>, O: Copy + Default, T> Iterator for Batches<I, O, T> {
+    type Item = O;
+
+    #[inline(always)]
+    fn next(&mut self) -> Option<O> {
+        if self.position == self.output.len() {
+            self.input.clear();
+            self.input.extend(self.iter.by_ref().take(BATCH));
+            if self.input.is_empty() {
+                return None;
+            }
+            self.output.resize(self.input.len(), O::default());
+            (self.kernel)(&self.input, &mut self.output, &self.params);
+            self.position = 0;
+        }
+
+        let pixel = self.output[self.position];
+        self.position += 1;
+        Some(pixel)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let buffered = self.output.len() - self.position;
+        let (low, high) = self.iter.size_hint();
+        (low + buffered, high.map(|x| x + buffered))
+    }
+}
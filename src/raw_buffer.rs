@@ -0,0 +1,89 @@
+//! The bytes of a raw file, read into memory once or mapped from the file.
+//!
+//! Decoders only borrow the bytes, so a `RawBuffer` is passed to the `*_from_buffer` functions
+//! like a `Vec<u8>` without being copied.
+
+use super::RawFileReadingError;
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    ops::Deref,
+};
+
+pub enum RawBuffer {
+    Owned(Vec<u8>),
+    /// The pages of the file are loaded by the OS on access and are not counted in the heap.
+    #[cfg(feature = "mmap")]
+    Mapped(memmap2::Mmap),
+}
+
+impl RawBuffer {
+    /// Maps the file with the `mmap` feature, reads it into a buffer of its exact size without it.
+    ///
+    /// A mapped file must not be modified by other processes while the buffer is alive.
+    pub fn from_file(path: &str) -> Result<RawBuffer, RawFileReadingError> {
+        let file =
+            File::open(path).map_err(|_| RawFileReadingError::FileNotExisted(path.to_owned()))?;
+
+        #[cfg(feature = "mmap")]
+        {
+            // SAFETY: the map is read-only, the file is expected to be left untouched while it is read
+            let map = unsafe { memmap2::Mmap::map(&file) }
+                .map_err(|_| RawFileReadingError::FileContentReadingError(path.to_owned()))?;
+            Ok(RawBuffer::Mapped(map))
+        }
+        #[cfg(not(feature = "mmap"))]
+        {
+            let mut file = file;
+            let len = file
+                .metadata()
+                .map_err(|_| RawFileReadingError::FileMetadataReadingError(path.to_owned()))?
+                .len() as usize;
+            let mut buffer = vec![0u8; len];
+            file.read_exact(&mut buffer)
+                .map_err(|_| RawFileReadingError::FileContentReadingError(path.to_owned()))?;
+            Ok(RawBuffer::Owned(buffer))
+        }
+    }
+
+    /// Reads a `Read + Seek` source from its current position to its end.
+    ///
+    /// The length is found by seeking first, so the bytes are read into a single allocation.
+    pub fn from_reader(mut reader: impl Read + Seek) -> Result<RawBuffer, RawFileReadingError> {
+        let mut read = || -> std::io::Result<Vec<u8>> {
+            let start = reader.stream_position()?;
+            let len = reader.seek(SeekFrom::End(0))?.saturating_sub(start);
+            reader.seek(SeekFrom::Start(start))?;
+
+            let mut buffer = vec![0u8; len as usize];
+            reader.read_exact(&mut buffer)?;
+            Ok(buffer)
+        };
+        let buffer = read().map_err(RawFileReadingError::StreamReadingError)?;
+        Ok(RawBuffer::Owned(buffer))
+    }
+}
+
+impl Deref for RawBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            RawBuffer::Owned(buffer) => buffer,
+            #[cfg(feature = "mmap")]
+            RawBuffer::Mapped(map) => map,
+        }
+    }
+}
+
+impl AsRef<[u8]> for RawBuffer {
+    fn as_ref(&self) -> &[u8] {
+        self
+    }
+}
+
+impl From<Vec<u8>> for RawBuffer {
+    fn from(buffer: Vec<u8>) -> Self {
+        RawBuffer::Owned(buffer)
+    }
+}
@@ -0,0 +1,164 @@
+/// Transfer functions(OETFs) to encode linear light into the output signal.
+///
+/// Linear values are normalized so that `1.0` is the sensor's clipping point after white balance.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TransferFunction {
+    /// No encoding, the output stays linear.
+    Linear,
+    /// A pure power curve with the given encoding exponent, e.g. `0.45`.
+    Gamma(f32),
+    /// The piecewise sRGB curve from IEC 61966-2-1.
+    Srgb,
+    /// The Rec.709 camera OETF from ITU-R BT.709.
+    Rec709,
+    /// The inverse of the BT.1886 reference display EOTF (a 2.4 power curve).
+    Bt1886,
+    /// The ProPhoto(ROMM) RGB curve from ISO 22028-2.
+    ProPhoto,
+    /// The Perceptual Quantizer from SMPTE ST 2084, with linear `1.0` placed at 203 cd/m².
+    Pq,
+    /// The Hybrid Log-Gamma OETF from ITU-R BT.2100.
+    Hlg,
+}
+
+/// The luminance in cd/m² that linear `1.0` maps to in `TransferFunction::Pq` (ITU-R BT.2408).
+pub const PQ_REFERENCE_WHITE: f32 = 203.;
+
+const PQ_M1: f32 = 2610. / 16384.;
+const PQ_M2: f32 = 2523. / 4096. * 128.;
+const PQ_C1: f32 = 3424. / 4096.;
+const PQ_C2: f32 = 2413. / 4096. * 32.;
+const PQ_C3: f32 = 2392. / 4096. * 32.;
+
+const HLG_A: f32 = 0.17883277;
+const HLG_B: f32 = 0.28466892;
+const HLG_C: f32 = 0.5599107;
+
+impl From<f32> for TransferFunction {
+    fn from(gamma: f32) -> Self {
+        if gamma == 1. {
+            TransferFunction::Linear
+        } else {
+            TransferFunction::Gamma(gamma)
+        }
+    }
+}
+
+impl TransferFunction {
+    /// Encodes a linear value into the signal value.
+    ///
+    /// Negative values are mirrored for the SDR curves and clipped to zero for PQ and HLG.
+    pub fn encode(self, v: f32) -> f32 {
+        match self {
+            TransferFunction::Pq => pq_encode(v.max(0.)),
+            TransferFunction::Hlg => hlg_encode(v.clamp(0., 1.)),
+            _ if v < 0. => -self.encode(-v),
+            TransferFunction::Linear => v,
+            TransferFunction::Gamma(gamma) => v.powf(gamma),
+            TransferFunction::Srgb => {
+                if v <= 0.0031308 {
+                    v * 12.92
+                } else {
+                    1.055 * v.powf(1. / 2.4) - 0.055
+                }
+            }
+            TransferFunction::Rec709 => {
+                if v < 0.018 {
+                    v * 4.5
+                } else {
+                    1.099 * v.powf(0.45) - 0.099
+                }
+            }
+            TransferFunction::Bt1886 => v.powf(1. / 2.4),
+            TransferFunction::ProPhoto => {
+                if v < 1. / 512. {
+                    v * 16.
+                } else {
+                    v.powf(1. / 1.8)
+                }
+            }
+        }
+    }
+
+    /// Decodes a signal value back into the linear value. It is the inverse of `encode`.
+    pub fn decode(self, v: f32) -> f32 {
+        match self {
+            TransferFunction::Pq => pq_decode(v.clamp(0., 1.)),
+            TransferFunction::Hlg => hlg_decode(v.clamp(0., 1.)),
+            _ if v < 0. => -self.decode(-v),
+            TransferFunction::Linear => v,
+            TransferFunction::Gamma(gamma) => v.powf(1. / gamma),
+            TransferFunction::Srgb => {
+                if v <= 0.04045 {
+                    v / 12.92
+                } else {
+                    ((v + 0.055) / 1.055).powf(2.4)
+                }
+            }
+            TransferFunction::Rec709 => {
+                if v < 0.081 {
+                    v / 4.5
+                } else {
+                    ((v + 0.099) / 1.099).powf(1. / 0.45)
+                }
+            }
+            TransferFunction::Bt1886 => v.powf(2.4),
+            TransferFunction::ProPhoto => {
+                if v < 16. / 512. {
+                    v / 16.
+                } else {
+                    v.powf(1.8)
+                }
+            }
+        }
+    }
+
+    /// The TransferCharacteristics code point from ITU-T H.273, used by cICP and similar tags.
+    pub fn cicp_code(self) -> Option<u8> {
+        match self {
+            TransferFunction::Linear => Some(8),
+            TransferFunction::Srgb => Some(13),
+            TransferFunction::Rec709 | TransferFunction::Bt1886 => Some(1),
+            TransferFunction::Pq => Some(16),
+            TransferFunction::Hlg => Some(18),
+            TransferFunction::Gamma(_) | TransferFunction::ProPhoto => None,
+        }
+    }
+
+    /// Returns `true` if the curve encodes high dynamic range content.
+    pub fn is_hdr(self) -> bool {
+        matches!(self, TransferFunction::Pq | TransferFunction::Hlg)
+    }
+}
+
+#[inline(always)]
+fn pq_encode(v: f32) -> f32 {
+    let y = (v * PQ_REFERENCE_WHITE / 10000.).powf(PQ_M1);
+    ((PQ_C1 + PQ_C2 * y) / (1. + PQ_C3 * y)).powf(PQ_M2)
+}
+
+#[inline(always)]
+fn pq_decode(v: f32) -> f32 {
+    let e = v.powf(1. / PQ_M2);
+    let y = ((e - PQ_C1).max(0.) / (PQ_C2 - PQ_C3 * e)).powf(1. / PQ_M1);
+    y * 10000. / PQ_REFERENCE_WHITE
+}
+
+#[inline(always)]
+fn hlg_encode(v: f32) -> f32 {
+    if v <= 1. / 12. {
+        (3. * v).sqrt()
+    } else {
+        HLG_A * (12. * v - HLG_B).ln() + HLG_C
+    }
+}
+
+#[inline(always)]
+fn hlg_decode(v: f32) -> f32 {
+    if v <= 0.5 {
+        v * v / 3.
+    } else {
+        (((v - HLG_C) / HLG_A).exp() + HLG_B) / 12.
+    }
+}
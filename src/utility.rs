@@ -86,4 +86,105 @@ pub(super) static BASIC_INFO_RULE : Lazy<quickexif::ParsingRule> = Lazy::new(||
             }
         }
     })
+});
+
+/// An optional ASCII tag, strings of at most 4 bytes are kept in the entry as `inline`.
+fn ascii_tag(
+    tag: u16,
+    name: &'static str,
+    inline: &'static str,
+    len: &'static str,
+) -> quickexif::ParsingRule {
+    use quickexif::rule::{CondType, OffsetType, ParsingRule};
+    let string = ParsingRule::Jump {
+        tag,
+        is_optional: false,
+        rules: vec![ParsingRule::OffsetItem {
+            offset: 0,
+            name,
+            t: quickexif::value::Value::Str(String::new()),
+        }],
+    };
+    let long_string = ParsingRule::Condition {
+        cond: (CondType::GT, len, 4),
+        left: vec![string],
+        right: vec![],
+    };
+    ParsingRule::Offset(
+        OffsetType::Bytes(0),
+        vec![
+            ParsingRule::TagItem {
+                tag,
+                name: inline,
+                len: Some(len),
+                is_optional: true,
+                is_value_u16: false,
+            },
+            ParsingRule::Condition {
+                cond: (CondType::EXIST, len, 0),
+                left: vec![long_string],
+                right: vec![],
+            },
+        ],
+    )
+}
+
+pub(super) static SHOOTING_INFO_RULE: Lazy<quickexif::ParsingRule> = Lazy::new(|| {
+    // the DSL has no syntax for an optional u16 item
+    let iso = quickexif::ParsingRule::TagItem {
+        tag: 0x8827,
+        name: "iso",
+        len: None,
+        is_optional: true,
+        is_value_u16: true,
+    };
+    let camera_serial = ascii_tag(0xc62f, "camera_serial", "camera_serial_inline", "camera_serial_len");
+    let body_serial = ascii_tag(0xa431, "body_serial", "body_serial_inline", "body_serial_len");
+    let lens_make = ascii_tag(0xa433, "lens_make", "lens_make_inline", "lens_make_len");
+    let lens_model = ascii_tag(0xa434, "lens_model", "lens_model_inline", "lens_model_len");
+    let lens_serial = ascii_tag(0xa435, "lens_serial", "lens_serial_inline", "lens_serial_len");
+    quickexif::describe_rule!(tiff {
+        load(camera_serial)
+        0x8769? {
+            load(iso)
+            0x8833? / iso_speed
+            0x829a? {
+                r64 + 0 / exposure_time
+            }
+            0x829d? {
+                r64 + 0 / f_number
+            }
+            0x9003? {
+                str + 0 / date_time_original
+            }
+            0x9011? {
+                str + 0 / offset_time_original
+            }
+            0x920a? {
+                r64 + 0 / focal_length
+            }
+            load(body_serial)
+            load(lens_make)
+            load(lens_model)
+            load(lens_serial)
+        }
+        0x8825? {
+            0x0001? / gps_latitude_ref
+            0x0002? {
+                r64 + 0 / gps_latitude_d
+                r64 + 1 / gps_latitude_m
+                r64 + 2 / gps_latitude_s
+            }
+            0x0003? / gps_longitude_ref
+            0x0004? {
+                r64 + 0 / gps_longitude_d
+                r64 + 1 / gps_longitude_m
+                r64 + 2 / gps_longitude_s
+            }
+            0x0005? / gps_altitude_ref
+            0x0006? {
+                r64 + 0 / gps_altitude
+            }
+        }
+    })
 });
\ No newline at end of file
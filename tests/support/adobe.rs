@@ -0,0 +1,110 @@
+//! DNG files, written by the encoder of the crate.
+//!
+//! The tests writing DNG files of their own start from the raw image and the metadata of the
+//! synthetic camera here.
+
+use super::{cfa_black_level, color_black_levels, Fixture, TestImage};
+use quickraw::{
+    encode::{
+        dng::{self, DngCompression, DngOptions, RawImage},
+        Metadata,
+    },
+    CFAPattern,
+};
+
+pub const WHITE_BALANCE: [i32; 3] = [1024, 512, 768];
+pub const CAM_MATRIX: [f32; 9] = [0.7, 0.2, 0.1, 0.25, 0.6, 0.15, 0.05, 0.15, 0.8];
+
+/// 16-bit samples in a strip.
+pub fn uncompressed() -> Fixture {
+    let image = TestImage::new(64, 48, CFAPattern::GBRG, 0);
+    fixture("DNG uncompressed", image, DngCompression::None)
+}
+
+/// Samples in lossless JPEG tiles, wider than a tile for the tiles to be cut at the right and
+/// the bottom edges.
+pub fn lossless_jpeg() -> Fixture {
+    let image = TestImage::new(264, 20, CFAPattern::RGGB, 0);
+    fixture("DNG lossless JPEG", image, DngCompression::LosslessJpeg)
+}
+
+/// 12-bit samples along a curve, with a black level for each color, in lossless JPEG tiles.
+pub fn levels() -> Fixture {
+    // the curve doubles the samples, so the levels and the white level are in doubled values
+    let image = TestImage::new(64, 48, CFAPattern::BGGR, 32);
+    let table = (0..4096).map(|x| x * 2).collect::<Vec<u16>>();
+    let black_level = cfa_black_level(color_black_levels(64), CFAPattern::BGGR);
+    let raw_image = RawImage {
+        black_level,
+        white_level: 8190,
+        bits_per_sample: 12,
+        linearization_table: Some(&table),
+        ..raw_image(&image)
+    };
+    let options = options(DngCompression::LosslessJpeg);
+    Fixture {
+        name: "DNG levels",
+        data: dng::encode(&raw_image, &metadata(), None, &options).unwrap(),
+        image,
+        tolerance: 0,
+        black_level,
+        bit_depth: 12,
+    }
+}
+
+/// X-Trans samples in their 6x6 pattern.
+pub fn xtrans() -> Fixture {
+    let image = TestImage::new(48, 36, CFAPattern::XTrans0, 0);
+    fixture("DNG X-Trans", image, DngCompression::None)
+}
+
+/// A fixture of `image` for the tests needing a size or samples of their own.
+pub fn fixture(name: &'static str, image: TestImage, compression: DngCompression) -> Fixture {
+    Fixture {
+        name,
+        data: encode(&image, compression),
+        image,
+        tolerance: 0,
+        black_level: [0; 4],
+        bit_depth: 16,
+    }
+}
+
+/// The make and the model of the synthetic camera.
+pub fn metadata() -> Metadata {
+    Metadata {
+        make: Some("Quickraw".to_owned()),
+        model: Some("Synthetic Camera".to_owned()),
+        ..Default::default()
+    }
+}
+
+/// `image` with the calibration of the synthetic camera, uncropped.
+pub fn raw_image(image: &TestImage) -> RawImage<'_> {
+    RawImage {
+        image: &image.samples,
+        width: image.width,
+        height: image.height,
+        cfa_pattern: image.cfa_pattern,
+        black_level: [0; 4],
+        white_level: u16::MAX,
+        bits_per_sample: 16,
+        linearization_table: None,
+        crop: None,
+        white_balance: WHITE_BALANCE,
+        cam_matrix: CAM_MATRIX,
+    }
+}
+
+/// No preview is embedded, a thumbnail is rendered in place of it.
+pub fn options(compression: DngCompression) -> DngOptions {
+    DngOptions {
+        compression,
+        embed_preview: false,
+    }
+}
+
+/// Encodes `image` with the metadata of the synthetic camera.
+pub fn encode(image: &TestImage, compression: DngCompression) -> Vec<u8> {
+    dng::encode(&raw_image(image), &metadata(), None, &options(compression)).unwrap()
+}
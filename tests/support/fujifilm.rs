@@ -0,0 +1,80 @@
+//! RAF files, an embedded JPEG with the EXIF block and a TIFF-like block with the sensor data.
+
+use super::{exif_jpeg, tiff::Tiff, Fixture, TestImage};
+use quickraw::CFAPattern;
+
+const MODEL: &str = "X-T3";
+
+/// 14-bit samples in 16-bit words, of the X-Trans pattern the decoder knows from the model.
+pub fn uncompressed() -> Fixture {
+    let black_level = 1024;
+    let image = TestImage::new(48, 36, CFAPattern::XTrans1, black_level);
+    Fixture {
+        name: "RAF uncompressed",
+        data: encode(&image, black_level),
+        image,
+        tolerance: 0,
+        black_level: [black_level; 4],
+        bit_depth: 14,
+    }
+}
+
+/// A RAF file of `image`, with the model of the fixture.
+pub fn encode(image: &TestImage, black_level: u16) -> Vec<u8> {
+    let jpeg = jpeg();
+
+    let mut raw = Tiff::new(true, 42);
+    let raw_ifd = raw.reserve_ifd(1);
+    let strip = raw.u16s(&image.samples);
+    let strip_offset = raw.append(&strip);
+    let image_ifd = raw.ifd(
+        vec![
+            raw.long(0xf001, &[image.width as u32]),
+            raw.long(0xf002, &[image.height as u32]),
+            raw.long(0xf003, &[14]),
+            raw.long(0xf007, &[strip_offset]),
+            raw.long(0xf008, &[strip.len() as u32]),
+            raw.long(0xf00a, &[black_level as u32; 4]),
+            raw.long(0xf00d, &[302, 604, 453]),
+        ],
+        0,
+    );
+    raw.write_ifd(raw_ifd, vec![raw.long(0xf000, &[image_ifd])], 0);
+    raw.set_ifd0(raw_ifd);
+
+    // the offsets and lengths of the JPEG and of the sensor data are big-endian
+    let mut data = b"FUJIFILMCCD-RAW 0201FF129502".to_vec();
+    let mut model = MODEL.as_bytes().to_vec();
+    model.resize(32, 0);
+    data.extend(model);
+    data.resize(84, 0);
+    let jpeg_offset = 148;
+    let raw_offset = jpeg_offset + jpeg.len();
+    for x in [jpeg_offset, jpeg.len(), 0, 0, raw_offset, raw.data.len()] {
+        data.extend((x as u32).to_be_bytes());
+    }
+    data.resize(jpeg_offset, 0);
+    data.extend(jpeg);
+    data.extend(raw.data);
+    data
+}
+
+/// The embedded JPEG, only its EXIF block with the make and the model is read.
+fn jpeg() -> Vec<u8> {
+    let mut exif = Tiff::new(true, 42);
+    let ifd0 = exif.reserve_ifd(3);
+    let thumbnail = exif.append(&[0xff, 0xd8, 0xff, 0xd9]);
+    let ifd1 = exif.ifd(
+        vec![exif.long(0x0201, &[thumbnail]), exif.long(0x0202, &[4])],
+        0,
+    );
+    let entries = vec![
+        exif.ascii(0x010f, "FUJIFILM"),
+        exif.ascii(0x0110, MODEL),
+        exif.short(0x0112, &[1]),
+    ];
+    exif.write_ifd(ifd0, entries, ifd1);
+    exif.set_ifd0(ifd0);
+
+    exif_jpeg(&exif)
+}
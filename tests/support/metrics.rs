@@ -0,0 +1,158 @@
+//! Differences between rendered images, as PSNR and CIEDE2000 color differences.
+
+use quickraw::{ColorSpace, TransferFunction};
+
+/// How far a render is from its reference.
+#[derive(Clone, Copy, Debug)]
+pub struct Diff {
+    /// Of the encoded values with a peak of `1.0`, infinite for identical images.
+    pub psnr: f64,
+    pub mean_delta_e: f64,
+    pub max_delta_e: f64,
+    /// The pixel of `max_delta_e`.
+    pub worst: usize,
+}
+
+impl Diff {
+    /// Compares two RGB images rendered to `color_space` with `transfer`.
+    pub fn new(
+        reference: &[f32],
+        image: &[f32],
+        color_space: ColorSpace,
+        transfer: TransferFunction,
+    ) -> Diff {
+        assert_eq!(reference.len(), image.len());
+        let to_xyz = inverse(color_space.matrix());
+        let white = mul(&to_xyz, [1.; 3]);
+        let lab = |rgb: &[f32]| {
+            let rgb = [0, 1, 2].map(|c| transfer.decode(rgb[c]) as f64);
+            lab(mul(&to_xyz, rgb), white)
+        };
+
+        let mut diff = Diff {
+            psnr: psnr(reference, image),
+            mean_delta_e: 0.,
+            max_delta_e: 0.,
+            worst: 0,
+        };
+        let pixels = reference.chunks_exact(3).zip(image.chunks_exact(3));
+        for (i, (a, b)) in pixels.enumerate() {
+            let delta_e = delta_e_2000(lab(a), lab(b));
+            diff.mean_delta_e += delta_e;
+            // NaN counts as the largest difference
+            if delta_e > diff.max_delta_e || delta_e.is_nan() {
+                diff.max_delta_e = delta_e;
+                diff.worst = i;
+            }
+        }
+        diff.mean_delta_e /= (reference.len() / 3).max(1) as f64;
+        diff
+    }
+}
+
+/// The peak signal-to-noise ratio in dB, for values from `0.0` to `1.0`.
+pub fn psnr(a: &[f32], b: &[f32]) -> f64 {
+    let mse = a
+        .iter()
+        .zip(b)
+        .map(|(&a, &b)| (a as f64 - b as f64).powi(2))
+        .sum::<f64>()
+        / a.len().max(1) as f64;
+    -10. * mse.log10()
+}
+
+/// CIE L*a*b* of `xyz` relative to the `white` point.
+pub fn lab(xyz: [f64; 3], white: [f64; 3]) -> [f64; 3] {
+    const EPSILON: f64 = 216. / 24389.;
+    const KAPPA: f64 = 24389. / 27.;
+    let [x, y, z] = [0, 1, 2].map(|c| {
+        let t = xyz[c] / white[c];
+        if t > EPSILON {
+            t.cbrt()
+        } else {
+            (KAPPA * t + 16.) / 116.
+        }
+    });
+    [116. * y - 16., 500. * (x - y), 200. * (y - z)]
+}
+
+/// The CIEDE2000 color difference, as in "The CIEDE2000 Color-Difference Formula" by Sharma et al.
+pub fn delta_e_2000([l1, a1, b1]: [f64; 3], [l2, a2, b2]: [f64; 3]) -> f64 {
+    let pow7 = |x: f64| x.powi(7);
+    let hue = |b: f64, a: f64| {
+        if a == 0. && b == 0. {
+            0.
+        } else {
+            b.atan2(a).to_degrees().rem_euclid(360.)
+        }
+    };
+
+    let c_mean = (a1.hypot(b1) + a2.hypot(b2)) / 2.;
+    let g = 0.5 * (1. - (pow7(c_mean) / (pow7(c_mean) + pow7(25.))).sqrt());
+    let (a1, a2) = (a1 * (1. + g), a2 * (1. + g));
+    let (c1, c2) = (a1.hypot(b1), a2.hypot(b2));
+    let (h1, h2) = (hue(b1, a1), hue(b2, a2));
+
+    let delta_l = l2 - l1;
+    let delta_c = c2 - c1;
+    let delta_h = if c1 * c2 == 0. {
+        0.
+    } else if (h2 - h1).abs() <= 180. {
+        h2 - h1
+    } else if h2 > h1 {
+        h2 - h1 - 360.
+    } else {
+        h2 - h1 + 360.
+    };
+    let delta_h = 2. * (c1 * c2).sqrt() * (delta_h / 2.).to_radians().sin();
+
+    let l_mean = (l1 + l2) / 2.;
+    let c_mean = (c1 + c2) / 2.;
+    let h_mean = if c1 * c2 == 0. {
+        h1 + h2
+    } else if (h1 - h2).abs() <= 180. {
+        (h1 + h2) / 2.
+    } else if h1 + h2 < 360. {
+        (h1 + h2 + 360.) / 2.
+    } else {
+        (h1 + h2 - 360.) / 2.
+    };
+
+    let cos = |degrees: f64| degrees.to_radians().cos();
+    let t = 1. - 0.17 * cos(h_mean - 30.) + 0.24 * cos(2. * h_mean) + 0.32 * cos(3. * h_mean + 6.)
+        - 0.2 * cos(4. * h_mean - 63.);
+    let delta_theta = 30. * (-((h_mean - 275.) / 25.).powi(2)).exp();
+    let r_c = 2. * (pow7(c_mean) / (pow7(c_mean) + pow7(25.))).sqrt();
+    let s_l = 1. + 0.015 * (l_mean - 50.).powi(2) / (20. + (l_mean - 50.).powi(2)).sqrt();
+    let s_c = 1. + 0.045 * c_mean;
+    let s_h = 1. + 0.015 * c_mean * t;
+    let r_t = -(2. * delta_theta).to_radians().sin() * r_c;
+
+    let (l, c, h) = (delta_l / s_l, delta_c / s_c, delta_h / s_h);
+    (l * l + c * c + h * h + r_t * c * h).sqrt()
+}
+
+/// The inverse of a row-major 3x3 matrix.
+fn inverse(m: [f32; 9]) -> [f64; 9] {
+    let m = m.map(|x| x as f64);
+    let cofactor = |r0: usize, r1: usize, c0: usize, c1: usize| {
+        m[r0 * 3 + c0] * m[r1 * 3 + c1] - m[r0 * 3 + c1] * m[r1 * 3 + c0]
+    };
+    let adjugate = [
+        cofactor(1, 2, 1, 2),
+        -cofactor(0, 2, 1, 2),
+        cofactor(0, 1, 1, 2),
+        -cofactor(1, 2, 0, 2),
+        cofactor(0, 2, 0, 2),
+        -cofactor(0, 1, 0, 2),
+        cofactor(1, 2, 0, 1),
+        -cofactor(0, 2, 0, 1),
+        cofactor(0, 1, 0, 1),
+    ];
+    let determinant = m[0] * adjugate[0] + m[1] * adjugate[3] + m[2] * adjugate[6];
+    adjugate.map(|x| x / determinant)
+}
+
+fn mul(m: &[f64; 9], v: [f64; 3]) -> [f64; 3] {
+    [0, 1, 2].map(|r| m[r * 3] * v[0] + m[r * 3 + 1] * v[1] + m[r * 3 + 2] * v[2])
+}
@@ -0,0 +1,242 @@
+//! Synthesizes small raw files of every supported format from a known CFA image.
+//!
+//! The decoders are tested by reading the files back, without any sample file to download.
+// every test crate uses a part of the fixtures
+#![allow(dead_code)]
+
+pub mod adobe;
+pub mod fujifilm;
+pub mod metrics;
+pub mod nikon;
+pub mod olympus;
+pub mod panasonic;
+pub mod sony;
+pub mod tiff;
+
+use quickraw::CFAPattern;
+
+/// CFA samples in rows of `width`.
+#[derive(Clone, Debug)]
+pub struct TestImage {
+    pub samples: Vec<u16>,
+    pub width: usize,
+    pub height: usize,
+    pub cfa_pattern: CFAPattern,
+}
+
+impl TestImage {
+    /// A gradient of a different level for each color of `cfa_pattern`, with a little texture.
+    ///
+    /// The samples of a color change slowly, so the compressed formats keep them all and
+    /// the first ones of Panasonic blocks are large enough. Up to 4095 for the sizes used here.
+    pub fn new(
+        width: usize,
+        height: usize,
+        cfa_pattern: CFAPattern,
+        black_level: u16,
+    ) -> TestImage {
+        const LEVELS: [usize; 3] = [900, 1500, 600];
+        let samples = (0..width * height)
+            .map(|i| {
+                let (x, y) = (i % width, i / width);
+                let level =
+                    LEVELS[cfa_color(cfa_pattern, x, y)] + x * 3 + y * 2 + ((x * 7) ^ (y * 3)) % 16;
+                black_level + level as u16
+            })
+            .collect();
+        TestImage {
+            samples,
+            width,
+            height,
+            cfa_pattern,
+        }
+    }
+
+    /// The samples of `sample(x, y)`.
+    pub fn from_fn(
+        width: usize,
+        height: usize,
+        cfa_pattern: CFAPattern,
+        sample: impl Fn(usize, usize) -> u16,
+    ) -> TestImage {
+        TestImage {
+            samples: (0..width * height)
+                .map(|i| sample(i % width, i / width))
+                .collect(),
+            width,
+            height,
+            cfa_pattern,
+        }
+    }
+}
+
+/// A synthesized raw file along with the image it holds.
+pub struct Fixture {
+    /// The format and the compression, like `"NEF lossless"`.
+    pub name: &'static str,
+    pub data: Vec<u8>,
+    /// The samples as the decoder reads them back.
+    pub image: TestImage,
+    /// The largest difference from `image`, for the formats losing the lowest bits of the samples.
+    pub tolerance: u16,
+    /// Of the 2x2 block at the top left of the CFA pattern in row-major order.
+    pub black_level: [u16; 4],
+    pub bit_depth: u16,
+}
+
+impl Fixture {
+    /// The first sample differing by more than `tolerance`, with its position.
+    pub fn mismatch(&self, samples: &[u16]) -> Option<(usize, u16, u16)> {
+        if samples.len() != self.image.samples.len() {
+            return Some((samples.len().min(self.image.samples.len()), 0, 0));
+        }
+        self.image
+            .samples
+            .iter()
+            .zip(samples)
+            .enumerate()
+            .find(|(_, (&a, &b))| a.abs_diff(b) > self.tolerance)
+            .map(|(i, (&a, &b))| (i, a, b))
+    }
+}
+
+/// A fixture of every format and compression.
+pub fn all() -> Vec<Fixture> {
+    vec![
+        nikon::uncompressed(),
+        nikon::lossless(),
+        sony::uncompressed(),
+        sony::compressed(),
+        panasonic::compressed(),
+        olympus::uncompressed(),
+        olympus::compressed(),
+        fujifilm::uncompressed(),
+        adobe::uncompressed(),
+        adobe::lossless_jpeg(),
+        adobe::levels(),
+        adobe::xtrans(),
+    ]
+}
+
+/// The color of the CFA sample at `(x, y)`, `0` is red, `1` is green and `2` is blue.
+pub fn cfa_color(cfa_pattern: CFAPattern, x: usize, y: usize) -> usize {
+    const XTRANS: [[usize; 6]; 6] = [
+        [0, 2, 1, 2, 0, 1],
+        [1, 1, 0, 1, 1, 2],
+        [1, 1, 2, 1, 1, 0],
+        [2, 0, 1, 0, 2, 1],
+        [1, 1, 2, 1, 1, 0],
+        [1, 1, 0, 1, 1, 2],
+    ];
+    match cfa_pattern {
+        CFAPattern::XTrans0 => XTRANS[y % 6][x % 6],
+        CFAPattern::XTrans1 => XTRANS[(y + 1) % 6][x % 6],
+        _ => bayer_colors(cfa_pattern)[y % 2 * 2 + x % 2] as usize,
+    }
+}
+
+/// Levels a little apart for red, the green of the red rows, the green of the blue rows and
+/// blue, in the order the makers record them.
+pub fn color_black_levels(black_level: u16) -> [u16; 4] {
+    [black_level, black_level + 1, black_level + 2, black_level + 3]
+}
+
+/// The levels of `color_black_levels` in the positions of the 2x2 block of `cfa_pattern`.
+pub fn cfa_black_level([r, gr, gb, b]: [u16; 4], cfa_pattern: CFAPattern) -> [u16; 4] {
+    match cfa_pattern {
+        CFAPattern::RGGB => [r, gr, gb, b],
+        CFAPattern::BGGR => [b, gb, gr, r],
+        CFAPattern::GRBG => [gr, r, b, gb],
+        CFAPattern::GBRG => [gb, b, r, gr],
+        _ => panic!("{cfa_pattern:?} is not a Bayer pattern"),
+    }
+}
+
+/// The colors of a 2x2 Bayer pattern as in the `CFAPattern` tag.
+pub fn bayer_colors(cfa_pattern: CFAPattern) -> [u8; 4] {
+    match cfa_pattern {
+        CFAPattern::RGGB => [0, 1, 1, 2],
+        CFAPattern::GRBG => [1, 0, 2, 1],
+        CFAPattern::GBRG => [1, 2, 0, 1],
+        CFAPattern::BGGR => [2, 1, 1, 0],
+        _ => panic!("{cfa_pattern:?} is not a Bayer pattern"),
+    }
+}
+
+/// The `CFARepeatPatternDim` and `CFAPattern` of the EXIF block, as in the `0xa302` tag.
+pub fn exif_cfa_pattern(tiff: &tiff::Tiff, cfa_pattern: CFAPattern) -> Vec<u8> {
+    let mut value = tiff.u16s(&[2, 2]);
+    value.extend(bayer_colors(cfa_pattern));
+    value
+}
+
+/// A JPEG of only an APP1 segment with `exif`, as embedded in some raw files for their EXIF block.
+pub fn exif_jpeg(exif: &tiff::Tiff) -> Vec<u8> {
+    let mut jpeg = vec![0xff, 0xd8, 0xff, 0xe1];
+    jpeg.extend((exif.data.len() as u16 + 8).to_be_bytes());
+    jpeg.extend(b"Exif\0\0");
+    jpeg.extend(&exif.data);
+    jpeg.extend([0xff, 0xd9]);
+    jpeg
+}
+
+/// Packs bits in the order the bit pumps of the decoders read them.
+pub struct BitWriter {
+    data: Vec<u8>,
+    bits: u64,
+    nbits: u32,
+    msb_first: bool,
+}
+
+impl BitWriter {
+    /// From the most significant bit of each byte.
+    pub fn msb() -> BitWriter {
+        BitWriter {
+            data: Vec::new(),
+            bits: 0,
+            nbits: 0,
+            msb_first: true,
+        }
+    }
+
+    /// From the least significant bit of each byte.
+    pub fn lsb() -> BitWriter {
+        BitWriter {
+            msb_first: false,
+            ..BitWriter::msb()
+        }
+    }
+
+    /// Writes the lowest `nbits` bits of `value`.
+    pub fn put(&mut self, value: u32, nbits: u32) {
+        assert!(
+            nbits <= 32 && value as u64 >> nbits == 0,
+            "{value} does not fit in {nbits} bits"
+        );
+        if self.msb_first {
+            self.bits = self.bits << nbits | value as u64;
+            self.nbits += nbits;
+            while self.nbits >= 8 {
+                self.nbits -= 8;
+                self.data.push((self.bits >> self.nbits) as u8);
+            }
+            self.bits &= (1 << self.nbits) - 1;
+        } else {
+            self.bits |= (value as u64) << self.nbits;
+            self.nbits += nbits;
+            while self.nbits >= 8 {
+                self.data.push(self.bits as u8);
+                self.bits >>= 8;
+                self.nbits -= 8;
+            }
+        }
+    }
+
+    /// The bytes written, the last one padded with zeros.
+    pub fn finish(mut self) -> Vec<u8> {
+        if self.nbits > 0 {
+            self.put(0, 8 - self.nbits);
+        }
+        self.data
+    }
+}
@@ -0,0 +1,161 @@
+//! NEF files, big-endian like the ones of most models.
+
+use super::{
+    cfa_black_level, color_black_levels, exif_cfa_pattern, tiff::Tiff, BitWriter, Fixture,
+    TestImage,
+};
+use quickraw::CFAPattern;
+
+const BLACK_LEVEL: u16 = 600;
+
+/// The code lengths and the diff lengths of the Huffman table for lossless 14-bit samples.
+const LOSSLESS_14BIT: [[u8; 16]; 2] = [
+    [0, 0, 1, 4, 2, 2, 3, 1, 2, 0, 0, 0, 0, 0, 0, 0],
+    [7, 6, 8, 5, 9, 4, 10, 3, 11, 12, 2, 0, 1, 13, 14, 0],
+];
+
+/// 14-bit samples in 16-bit words.
+pub fn uncompressed() -> Fixture {
+    let image = TestImage::new(64, 48, CFAPattern::RGGB, BLACK_LEVEL);
+    let strip = Tiff::new(false, 42).u16s(&image.samples);
+    Fixture {
+        name: "NEF uncompressed",
+        data: encode(&image, 1, &strip, None),
+        image,
+        tolerance: 0,
+        black_level: cfa_black_level(color_black_levels(BLACK_LEVEL), CFAPattern::RGGB),
+        bit_depth: 14,
+    }
+}
+
+/// 14-bit samples coded by diffs from the samples of the same color on the left or above.
+///
+/// The samples are read back before the identity curve they are coded along.
+pub fn lossless() -> Fixture {
+    let image = TestImage::new(64, 48, CFAPattern::GRBG, BLACK_LEVEL);
+    // the version of the lossless coding, the initial predictors and no curve points
+    let mut linear_table = vec![0x46, 0x30];
+    linear_table.extend(Tiff::new(false, 42).u16s(&[0, 0, 0, 0, 0]));
+    Fixture {
+        name: "NEF lossless",
+        data: encode(&image, 0x8799, &huffman_encode(&image), Some(&linear_table)),
+        image,
+        tolerance: 0,
+        black_level: cfa_black_level(color_black_levels(BLACK_LEVEL), CFAPattern::GRBG),
+        bit_depth: 14,
+    }
+}
+
+fn encode(
+    image: &TestImage,
+    compression: u16,
+    strip: &[u8],
+    linear_table: Option<&[u8]>,
+) -> Vec<u8> {
+    let mut tiff = Tiff::new(false, 42);
+    let ifd0 = tiff.reserve_ifd(5);
+
+    let strip_offset = tiff.append(strip);
+    let preview = tiff.append(&[0xff, 0xd8, 0xff, 0xd9]);
+    let preview_ifd = tiff.ifd(
+        vec![
+            tiff.long(0x00fe, &[1]),
+            tiff.long(0x0201, &[preview]),
+            tiff.long(0x0202, &[4]),
+        ],
+        0,
+    );
+    let raw_ifd = tiff.ifd(
+        vec![
+            tiff.long(0x00fe, &[0]),
+            tiff.long(0x0100, &[image.width as u32]),
+            tiff.long(0x0101, &[image.height as u32]),
+            tiff.short(0x0102, &[14]),
+            tiff.short(0x0103, &[compression]),
+            tiff.short(0x0106, &[32803]),
+            tiff.long(0x0111, &[strip_offset]),
+            tiff.short(0x0115, &[1]),
+            tiff.long(0x0117, &[strip.len() as u32]),
+        ],
+        0,
+    );
+
+    // the offsets of the maker notes start from the TIFF header after "Nikon"
+    let mut notes = Tiff::new(false, 42);
+    let mut entries = vec![
+        notes.rational(0x000c, &[(2000, 1000), (1500, 1000), (1, 1), (1, 1)]),
+        notes.short(0x003d, &color_black_levels(BLACK_LEVEL)),
+        notes.undefined(0x008c, &[0x49, 0x0d, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]),
+    ];
+    if let Some(linear_table) = linear_table {
+        entries.push(notes.undefined(0x0096, linear_table));
+    }
+    let notes_ifd = notes.ifd(entries, 0);
+    notes.set_ifd0(notes_ifd);
+    let mut maker_notes = b"Nikon\0\x02\x10\0\0".to_vec();
+    maker_notes.extend(notes.data);
+
+    let exif_ifd = tiff.ifd(
+        vec![
+            tiff.undefined(0xa302, &exif_cfa_pattern(&tiff, image.cfa_pattern)),
+            tiff.undefined(0x927c, &maker_notes),
+        ],
+        0,
+    );
+    let entries = vec![
+        tiff.ascii(0x010f, "NIKON CORPORATION"),
+        tiff.ascii(0x0110, "NIKON D850"),
+        tiff.short(0x0112, &[1]),
+        tiff.long(0x014a, &[preview_ifd, raw_ifd]),
+        tiff.long(0x8769, &[exif_ifd]),
+    ];
+    tiff.write_ifd(ifd0, entries, 0);
+    tiff.set_ifd0(ifd0);
+    tiff.data
+}
+
+/// Codes each sample by its diff from the last sample of the same color in the row, the first
+/// ones of a row by the diffs from the first ones two rows above.
+fn huffman_encode(image: &TestImage) -> Vec<u8> {
+    let [bits, huffval] = LOSSLESS_14BIT;
+    let mut codes = [(0, 0); 16];
+    let (mut code, mut pos) = (0, 0);
+    for (len, &count) in bits.iter().enumerate().take(16).skip(1) {
+        for _ in 0..count {
+            codes[huffval[pos] as usize] = (code, len as u32);
+            code += 1;
+            pos += 1;
+        }
+        code <<= 1;
+    }
+
+    let mut writer = BitWriter::msb();
+    let mut put_diff = |diff: i32| {
+        let len = 32 - diff.unsigned_abs().leading_zeros();
+        let (code, code_len) = codes[len as usize];
+        writer.put(code, code_len);
+        if len > 0 {
+            let bits = if diff > 0 {
+                diff
+            } else {
+                diff + (1 << len) - 1
+            };
+            writer.put(bits as u32, len);
+        }
+    };
+
+    let mut pred_up = [[0i32; 2]; 2];
+    for (index, row) in image.samples.chunks_exact(image.width).enumerate() {
+        let mut left = pred_up[index & 1];
+        for (col, pair) in row.chunks_exact(2).enumerate() {
+            for (pred, &sample) in left.iter_mut().zip(pair) {
+                put_diff(sample as i32 - *pred);
+                *pred = sample as i32;
+            }
+            if col == 0 {
+                pred_up[index & 1] = left;
+            }
+        }
+    }
+    writer.finish()
+}
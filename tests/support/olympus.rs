@@ -0,0 +1,191 @@
+//! ORF files, with the levels in the image processing IFD of the maker notes.
+
+use super::{
+    cfa_black_level, color_black_levels, exif_cfa_pattern, tiff::Tiff, BitWriter, Fixture,
+    TestImage,
+};
+use quickraw::CFAPattern;
+
+const BLACK_LEVEL: u16 = 256;
+
+/// 12-bit samples packed in 16 bytes for every 10 of them.
+pub fn uncompressed() -> Fixture {
+    let image = TestImage::new(60, 40, CFAPattern::RGGB, BLACK_LEVEL);
+    Fixture {
+        name: "ORF uncompressed",
+        data: encode(&image, &pack_12bit(&image)),
+        image,
+        tolerance: 0,
+        black_level: cfa_black_level(color_black_levels(BLACK_LEVEL), CFAPattern::RGGB),
+        bit_depth: 12,
+    }
+}
+
+/// 12-bit samples coded by diffs from a prediction, in lengths adapting to the last diffs.
+///
+/// The decoder tells the formats apart by the length of the data, so it has to be compressed
+/// well enough.
+pub fn compressed() -> Fixture {
+    let image = TestImage::new(60, 40, CFAPattern::GRBG, BLACK_LEVEL);
+    let mut strip = vec![0; 7];
+    strip.extend(diff_encode(&image));
+    assert!(strip.len() < image.width * image.height / 10 * 16);
+    Fixture {
+        name: "ORF compressed",
+        data: encode(&image, &strip),
+        image,
+        tolerance: 0,
+        black_level: cfa_black_level(color_black_levels(BLACK_LEVEL), CFAPattern::GRBG),
+        bit_depth: 12,
+    }
+}
+
+fn encode(image: &TestImage, strip: &[u8]) -> Vec<u8> {
+    let mut tiff = Tiff::new(true, 0x4f52);
+    let ifd0 = tiff.reserve_ifd(10);
+    let strip_offset = tiff.append(strip);
+
+    // the offsets of the maker notes start from their header
+    let mut notes = Tiff::with_prefix(true, b"OLYMPUS\0II\x03\0");
+    let notes_ifd = notes.reserve_ifd(1);
+    let image_processing_ifd = notes.ifd(
+        vec![
+            notes.short(0x0100, &[512, 384, 256, 256]),
+            notes.short(0x0600, &color_black_levels(BLACK_LEVEL)),
+            notes.short(0x0611, &[12]),
+            notes.short(0x0612, &[0]),
+            notes.short(0x0613, &[0]),
+            notes.short(0x0614, &[image.width as u16]),
+            notes.short(0x0615, &[image.height as u16]),
+        ],
+        0,
+    );
+    notes.write_ifd(
+        notes_ifd,
+        vec![notes.long(0x2040, &[image_processing_ifd])],
+        0,
+    );
+
+    let exif_ifd = tiff.ifd(
+        vec![
+            tiff.undefined(0xa302, &exif_cfa_pattern(&tiff, image.cfa_pattern)),
+            tiff.undefined(0x927c, &notes.data),
+        ],
+        0,
+    );
+    let entries = vec![
+        tiff.long(0x0100, &[image.width as u32]),
+        tiff.long(0x0101, &[image.height as u32]),
+        tiff.short(0x0102, &[12]),
+        tiff.short(0x0103, &[1]),
+        tiff.ascii(0x010f, "OLYMPUS CORPORATION"),
+        tiff.ascii(0x0110, "E-M10"),
+        tiff.long(0x0111, &[strip_offset]),
+        tiff.short(0x0112, &[1]),
+        tiff.long(0x0117, &[strip.len() as u32]),
+        tiff.long(0x8769, &[exif_ifd]),
+    ];
+    tiff.write_ifd(ifd0, entries, 0);
+    tiff.set_ifd0(ifd0);
+    tiff.data
+}
+
+/// Two samples in every 3 bytes and a byte of padding after every 10 samples.
+fn pack_12bit(image: &TestImage) -> Vec<u8> {
+    assert!(
+        image.width.is_multiple_of(10),
+        "the rows are made of blocks of 10 samples"
+    );
+    image
+        .samples
+        .chunks_exact(10)
+        .flat_map(|block| {
+            let mut bytes = block
+                .chunks_exact(2)
+                .flat_map(|x| {
+                    [
+                        x[0] as u8,
+                        (x[0] >> 8) as u8 | (x[1] << 4) as u8,
+                        (x[1] >> 4) as u8,
+                    ]
+                })
+                .collect::<Vec<_>>();
+            bytes.push(0);
+            bytes
+        })
+        .collect()
+}
+
+/// Mirrors the decoder: the samples of the two colors of a row are predicted from their
+/// neighbours two samples away, and the diffs take as many bits as the last ones needed.
+fn diff_encode(image: &TestImage) -> Vec<u8> {
+    let width = image.width;
+    let sample = |row: usize, col: usize| image.samples[row * width + col] as i32;
+
+    let mut writer = BitWriter::msb();
+    let mut left = [0i32; 2];
+    let mut nw = [0i32; 2];
+    for row in 0..image.height {
+        let mut acarry = [[0i32; 3]; 2];
+        for col in (0..width).step_by(2) {
+            for s in 0..2 {
+                let carry = &mut acarry[s];
+                let i = if carry[2] < 3 { 2 } else { 0 };
+                let mut nbits = 2 + i;
+                while (carry[0] >> (nbits + i)) & 0xffff > 0 {
+                    nbits += 1;
+                }
+                let nbits = nbits.min(16) as u32;
+
+                let pred = if row < 2 && col < 2 {
+                    0
+                } else if row < 2 {
+                    left[s]
+                } else if col < 2 {
+                    nw[s] = sample(row - 2, col + s);
+                    nw[s]
+                } else {
+                    let up = sample(row - 2, col + s);
+                    let left_minus_nw = left[s] - nw[s];
+                    let up_minus_nw = up - nw[s];
+                    let pred = if left_minus_nw * up_minus_nw < 0 {
+                        if left_minus_nw.abs() > 32 || up_minus_nw.abs() > 32 {
+                            left[s] + up_minus_nw
+                        } else {
+                            (left[s] + up) >> 1
+                        }
+                    } else if left_minus_nw.abs() > up_minus_nw.abs() {
+                        left[s]
+                    } else {
+                        up
+                    };
+                    nw[s] = up;
+                    pred
+                };
+
+                let value = sample(row, col + s);
+                let delta = value - pred;
+                let diff = (delta >> 2) - carry[1];
+                let (sign, carry0) = if diff < 0 { (1, !diff) } else { (0, diff) };
+                let high = carry0 >> nbits;
+
+                writer.put(sign, 1);
+                writer.put((delta & 3) as u32, 2);
+                if high < 12 {
+                    // the count of zeros before a one
+                    writer.put(1, high as u32 + 1);
+                } else {
+                    writer.put(0, 12);
+                    writer.put((high << 1) as u32, 16 - nbits);
+                }
+                writer.put((carry0 & ((1 << nbits) - 1)) as u32, nbits);
+
+                carry[0] = carry0;
+                carry[1] = ((delta >> 2) * 3 + carry[1]) >> 5;
+                carry[2] = if carry0 > 16 { 0 } else { carry[2] + 1 };
+                left[s] = value;
+            }
+        }
+    }
+    writer.finish()
+}
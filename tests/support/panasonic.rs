@@ -0,0 +1,167 @@
+//! RW2 files, with the sensor data in blocks of 14 samples.
+
+use super::{cfa_black_level, exif_jpeg, tiff::Tiff, Fixture, TestImage};
+use quickraw::CFAPattern;
+
+/// The only format of the decoder, 12-bit samples in blocks of 128 bits.
+pub fn compressed() -> Fixture {
+    with_size(112, 40)
+}
+
+/// The compressed format in another size, `width` is a multiple of 14.
+///
+/// The decoder reads bands of 5 rows, and the data of each 0x4000 bytes wraps around in the
+/// middle, after 512 blocks.
+pub fn with_size(width: usize, height: usize) -> Fixture {
+    let black_level = 128;
+    let image = TestImage::new(width, height, CFAPattern::GBRG, black_level);
+    Fixture {
+        name: "RW2",
+        data: encode(&image, &pack(&image), black_level),
+        image,
+        tolerance: 0,
+        // one level for both greens
+        black_level: cfa_black_level(
+            [black_level, black_level + 1, black_level + 1, black_level + 2],
+            CFAPattern::GBRG,
+        ),
+        bit_depth: 12,
+    }
+}
+
+fn encode(image: &TestImage, strip: &[u8], black_level: u16) -> Vec<u8> {
+    let cfa_pattern = match image.cfa_pattern {
+        CFAPattern::RGGB => 1,
+        CFAPattern::GRBG => 2,
+        CFAPattern::GBRG => 3,
+        CFAPattern::BGGR => 4,
+        x => panic!("{x:?} is not a Bayer pattern"),
+    };
+
+    let mut tiff = Tiff::new(true, 0x55);
+    let ifd0 = tiff.reserve_ifd(16);
+    let strip_offset = tiff.append(strip);
+    let entries = vec![
+        tiff.short(0x0002, &[image.width as u16]),
+        tiff.short(0x0003, &[image.height as u16]),
+        tiff.short(0x0009, &[cfa_pattern]),
+        tiff.short(0x000a, &[12]),
+        tiff.short(0x001c, &[black_level]),
+        tiff.short(0x001d, &[black_level + 1]),
+        tiff.short(0x001e, &[black_level + 2]),
+        tiff.short(0x0024, &[512]),
+        tiff.short(0x0025, &[256]),
+        tiff.short(0x0026, &[384]),
+        tiff.undefined(0x002e, &jpeg(image)),
+        tiff.ascii(0x010f, "Panasonic"),
+        tiff.ascii(0x0110, "DC-G9"),
+        tiff.short(0x0112, &[1]),
+        tiff.long(0x0117, &[strip.len() as u32]),
+        tiff.long(0x0118, &[strip_offset]),
+    ];
+    tiff.write_ifd(ifd0, entries, 0);
+    tiff.set_ifd0(ifd0);
+    tiff.data
+}
+
+/// The embedded JPEG, only its EXIF block with the maker notes is read.
+fn jpeg(image: &TestImage) -> Vec<u8> {
+    let mut notes = Tiff::with_prefix(true, b"Panasonic\0\0\0");
+    notes.ifd(
+        vec![
+            notes.long(0x004b, &[image.width as u32]),
+            notes.long(0x004c, &[image.height as u32]),
+        ],
+        0,
+    );
+
+    let mut exif = Tiff::new(true, 42);
+    let ifd0 = exif.reserve_ifd(1);
+    let exif_ifd = exif.ifd(vec![exif.undefined(0x927c, &notes.data)], 0);
+    exif.write_ifd(ifd0, vec![exif.long(0x8769, &[exif_ifd])], 0);
+    exif.set_ifd0(ifd0);
+
+    exif_jpeg(&exif)
+}
+
+/// Codes the blocks and lays them out the way the decoder reads them.
+///
+/// The bits of a block are read from the end of its 16 bytes, and the blocks of each 0x4000
+/// bytes from 0x1ff8 on, wrapping around to the start.
+pub fn pack(image: &TestImage) -> Vec<u8> {
+    assert!(
+        image.width.is_multiple_of(14),
+        "the rows are made of blocks of 14 samples"
+    );
+    let mut blocks = image
+        .samples
+        .chunks_exact(14)
+        .flat_map(|block| encode_block(block).to_le_bytes())
+        .collect::<Vec<_>>();
+    blocks.resize(blocks.len().next_multiple_of(0x4000), 0);
+
+    let mut data = vec![0; blocks.len()];
+    for (out, blocks) in data
+        .chunks_exact_mut(0x4000)
+        .zip(blocks.chunks_exact(0x4000))
+    {
+        for (i, &byte) in blocks.iter().enumerate() {
+            out[(i + 0x4000 - 0x2008) % 0x4000] = byte;
+        }
+    }
+    data
+}
+
+/// 12 bits for the first samples of the two colors, and 8-bit steps for the others, with a
+/// shift of the steps for every 3 samples.
+fn encode_block(samples: &[u16]) -> u128 {
+    let mut bits = 0u128;
+    let mut put = |value: u32, nbits: u32| bits = bits << nbits | value as u128;
+
+    let mut pred = [0i32; 2];
+    let mut sh = 0;
+    for (i, &sample) in samples.iter().enumerate() {
+        let sample = sample as i32;
+        if i % 3 == 2 {
+            // the smallest shift the next samples can be coded with
+            sh = [0, 1, 2, 4]
+                .into_iter()
+                .find(|&sh| {
+                    let mut pred = pred;
+                    (i..i + 3).all(|k| {
+                        let sample = samples[k] as i32;
+                        let reached = step(pred[k & 1], sample, sh).is_some();
+                        pred[k & 1] = sample;
+                        reached
+                    })
+                })
+                .expect("the samples change too fast");
+            put([0, 1, 2, 0, 3][sh as usize], 2);
+        }
+        if i < 2 {
+            assert!(
+                (16..4096).contains(&sample),
+                "the first samples of a block take 12 bits"
+            );
+            put(sample as u32 >> 4, 8);
+            put(sample as u32 & 15, 4);
+        } else {
+            put(step(pred[i & 1], sample, sh).unwrap(), 8);
+        }
+        pred[i & 1] = sample;
+    }
+    bits
+}
+
+/// The 8-bit step from `pred` to `sample`, when the shift `sh` reaches it exactly.
+fn step(pred: i32, sample: i32, sh: u32) -> Option<u32> {
+    if sample == pred {
+        return Some(0);
+    }
+    let mut base = pred - (0x80 << sh);
+    if base < 0 || sh == 4 {
+        base &= (1 << sh) - 1;
+    }
+    let j = sample - base;
+    (j > 0 && j % (1 << sh) == 0 && j >> sh <= 255).then_some((j >> sh) as u32)
+}
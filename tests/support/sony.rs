@@ -0,0 +1,182 @@
+//! ARW files, with the levels in the encrypted SR2 block.
+
+use super::{
+    bayer_colors, cfa_black_level, color_black_levels, tiff::Tiff, BitWriter, Fixture, TestImage,
+};
+use quickraw::CFAPattern;
+
+const SR2_KEY: u32 = 0x1234_5678;
+
+/// 14-bit samples in 16-bit words.
+pub fn uncompressed() -> Fixture {
+    let black_level = 512;
+    let image = TestImage::new(64, 48, CFAPattern::RGGB, black_level);
+    let strip = Tiff::new(true, 42).u16s(&image.samples);
+    Fixture {
+        name: "ARW uncompressed",
+        data: encode(&image, 1, 14, &strip, black_level),
+        image,
+        tolerance: 0,
+        black_level: cfa_black_level(color_black_levels(black_level), CFAPattern::RGGB),
+        bit_depth: 14,
+    }
+}
+
+/// The lossy 8 bits a sample of the older models, with an identity tone curve.
+///
+/// The samples lose their lowest bit, they are read back before the tone curve.
+pub fn compressed() -> Fixture {
+    let black_level = 128;
+    let image = TestImage::new(64, 48, CFAPattern::BGGR, black_level);
+    let (strip, image) = raw8_encode(&image);
+    Fixture {
+        name: "ARW compressed",
+        data: encode(&image, 0x7fff, 12, &strip, black_level),
+        image,
+        tolerance: 0,
+        black_level: cfa_black_level(color_black_levels(black_level), CFAPattern::BGGR),
+        bit_depth: 12,
+    }
+}
+
+fn encode(
+    image: &TestImage,
+    compression: u16,
+    bps: u16,
+    strip: &[u8],
+    black_level: u16,
+) -> Vec<u8> {
+    let mut tiff = Tiff::new(true, 42);
+    let ifd0 = tiff.reserve_ifd(8);
+
+    let strip_offset = tiff.append(strip);
+    let preview = tiff.append(&[0xff, 0xd8, 0xff, 0xd9]);
+    let raw_ifd = tiff.ifd(
+        vec![
+            tiff.long(0x0100, &[image.width as u32]),
+            tiff.long(0x0101, &[image.height as u32]),
+            tiff.short(0x0102, &[bps]),
+            tiff.short(0x0103, &[compression]),
+            tiff.byte(0x828e, &bayer_colors(image.cfa_pattern)),
+            tiff.long(0x0111, &[strip_offset]),
+            tiff.long(0x0117, &[strip.len() as u32]),
+            // the points of the tone curve of 8-bit samples, all at the end for an identity one
+            tiff.short(0x7010, &[4095 << 2; 4]),
+        ],
+        0,
+    );
+    let exif_ifd = tiff.ifd(vec![tiff.rational(0x9102, &[(8, 1)])], 0);
+
+    // the offsets in the encrypted block are from the start of the file
+    tiff.align(4);
+    let sr2_offset = tiff.data.len();
+    let white_level = (1 << bps) - 1;
+    let sr2_ifd = tiff.ifd(
+        vec![
+            tiff.short(0x7310, &color_black_levels(black_level)),
+            tiff.short(0x7312, &[2048, 1024, 1024, 1536]),
+            tiff.short(0x787f, &[white_level; 3]),
+        ],
+        0,
+    );
+    assert_eq!(sr2_ifd as usize, sr2_offset);
+    tiff.align(4);
+    let sr2_len = tiff.data.len() - sr2_offset;
+    sr2_encrypt(&mut tiff.data[sr2_offset..], SR2_KEY);
+    let sr2_private_ifd = tiff.ifd(
+        vec![
+            tiff.long(0x7200, &[sr2_offset as u32]),
+            tiff.long(0x7201, &[sr2_len as u32]),
+            tiff.long(0x7221, &[SR2_KEY]),
+        ],
+        0,
+    );
+
+    let entries = vec![
+        tiff.ascii(0x010f, "SONY"),
+        tiff.ascii(0x0110, "ILCE-7M3"),
+        tiff.short(0x0112, &[1]),
+        tiff.long(0x014a, &[raw_ifd]),
+        tiff.long(0x0201, &[preview]),
+        tiff.long(0x0202, &[4]),
+        tiff.long(0x8769, &[exif_ifd]),
+        tiff.long(0xc634, &[sr2_private_ifd]),
+    ];
+    tiff.write_ifd(ifd0, entries, 0);
+    tiff.set_ifd0(ifd0);
+    tiff.data
+}
+
+/// Encrypts the SR2 block of little-endian files by XOR with a stream from `key`, the same as
+/// decrypting.
+fn sr2_encrypt(data: &mut [u8], mut key: u32) {
+    let mut pad = [0u32; 128];
+    for item in pad.iter_mut().take(4) {
+        key = key.wrapping_mul(48828125).wrapping_add(1);
+        *item = key;
+    }
+    pad[3] = pad[3] << 1 | (pad[0] ^ pad[2]) >> 31;
+    for i in 4..127 {
+        pad[i] = (pad[i - 4] ^ pad[i - 2]) << 1 | (pad[i - 3] ^ pad[i - 1]) >> 31;
+    }
+    for item in pad.iter_mut().take(127) {
+        *item = item.swap_bytes();
+    }
+
+    for (chunk, p) in data.chunks_exact_mut(4).zip(127..) {
+        pad[p & 127] = pad[(p + 1) & 127] ^ pad[(p + 65) & 127];
+        let x = u32::from_le_bytes(chunk.try_into().unwrap()) ^ pad[p & 127];
+        chunk.copy_from_slice(&x.to_le_bytes());
+    }
+}
+
+/// Codes every 32 samples of a row as two interleaved blocks of 16 samples of 11 bits, with
+/// the max and the min kept and the others in 7-bit steps from the min.
+///
+/// Returns the rows of a byte a sample and the image as the decoder reads it back.
+fn raw8_encode(image: &TestImage) -> (Vec<u8>, TestImage) {
+    assert!(
+        image.width.is_multiple_of(32),
+        "the rows are made of 32 samples"
+    );
+    let mut decoded = image.clone();
+    let mut data = Vec::new();
+
+    let rows = image.samples.chunks_exact(image.width);
+    for (row, out) in rows.zip(decoded.samples.chunks_exact_mut(image.width)) {
+        let mut writer = BitWriter::lsb();
+        for (chunk, out) in row.chunks_exact(32).zip(out.chunks_exact_mut(32)) {
+            for j in 0..2 {
+                let values = (0..16)
+                    .map(|i| chunk[j + i * 2] as u32 >> 1)
+                    .collect::<Vec<_>>();
+                let max = *values.iter().max().unwrap();
+                let min = *values.iter().min().unwrap();
+                let imax = values.iter().position(|&x| x == max).unwrap();
+                let imin = (0..16)
+                    .find(|&i| i != imax && values[i] == min)
+                    .unwrap_or((imax + 1) % 16);
+                let shift = (32 - (max - min).leading_zeros()).saturating_sub(7);
+
+                writer.put(max, 11);
+                writer.put(min, 11);
+                writer.put(imax as u32, 4);
+                writer.put(imin as u32, 4);
+                for (i, &value) in values.iter().enumerate() {
+                    let value = if i == imax {
+                        max
+                    } else if i == imin {
+                        min
+                    } else {
+                        let step = ((value - min) >> shift).min(127);
+                        writer.put(step, 7);
+                        ((step << shift) + min).min(0x7ff)
+                    };
+                    out[j + i * 2] = (value << 1) as u16;
+                }
+            }
+        }
+        data.extend(writer.finish());
+    }
+    (data, decoded)
+}
@@ -0,0 +1,167 @@
+//! A writer of the TIFF structures the raw formats are made of.
+
+const BYTE: u16 = 1;
+const ASCII: u16 = 2;
+const SHORT: u16 = 3;
+const LONG: u16 = 4;
+const RATIONAL: u16 = 5;
+const UNDEFINED: u16 = 7;
+
+/// An IFD entry with its value in the byte order of the file.
+pub struct Entry {
+    tag: u16,
+    kind: u16,
+    count: u32,
+    value: Vec<u8>,
+}
+
+/// The bytes of a TIFF file or of a block of IFDs in one, like the maker notes.
+///
+/// The offsets are counted from the start of `data`. An IFD is written before the values of its
+/// entries, so a parent IFD is reserved first when it has to stay at a fixed position.
+pub struct Tiff {
+    pub data: Vec<u8>,
+    pub is_le: bool,
+}
+
+impl Tiff {
+    /// A TIFF header with `magic`, the offset of IFD0 is set by `set_ifd0`.
+    pub fn new(is_le: bool, magic: u16) -> Tiff {
+        let mut tiff = Tiff::with_prefix(is_le, if is_le { b"II" } else { b"MM" });
+        let magic = tiff.u16s(&[magic]);
+        tiff.data.extend(magic);
+        tiff.data.extend([0; 4]);
+        tiff
+    }
+
+    /// Starts with `prefix` and no TIFF header, like the maker notes of Olympus.
+    pub fn with_prefix(is_le: bool, prefix: &[u8]) -> Tiff {
+        Tiff {
+            data: prefix.to_vec(),
+            is_le,
+        }
+    }
+
+    pub fn set_ifd0(&mut self, offset: u32) {
+        self.set_u32(4, offset);
+    }
+
+    pub fn set_u32(&mut self, pos: usize, value: u32) {
+        let bytes = self.u32s(&[value]);
+        self.data[pos..pos + 4].copy_from_slice(&bytes);
+    }
+
+    pub fn u16s(&self, values: &[u16]) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|&x| {
+                if self.is_le {
+                    x.to_le_bytes()
+                } else {
+                    x.to_be_bytes()
+                }
+            })
+            .collect()
+    }
+
+    pub fn u32s(&self, values: &[u32]) -> Vec<u8> {
+        values
+            .iter()
+            .flat_map(|&x| {
+                if self.is_le {
+                    x.to_le_bytes()
+                } else {
+                    x.to_be_bytes()
+                }
+            })
+            .collect()
+    }
+
+    /// Pads the data with zeros to a multiple of `align` bytes.
+    pub fn align(&mut self, align: usize) {
+        let len = self.data.len().next_multiple_of(align);
+        self.data.resize(len, 0);
+    }
+
+    /// Appends `bytes` at a word boundary and returns their offset.
+    pub fn append(&mut self, bytes: &[u8]) -> u32 {
+        self.align(2);
+        let offset = self.data.len() as u32;
+        self.data.extend_from_slice(bytes);
+        offset
+    }
+
+    /// Leaves the room of an IFD with `count` entries, to be written by `write_ifd`.
+    pub fn reserve_ifd(&mut self, count: usize) -> u32 {
+        self.append(&vec![0; 2 + count * 12 + 4])
+    }
+
+    /// Writes the IFD reserved at `offset`, the values longer than 4 bytes are appended.
+    pub fn write_ifd(&mut self, offset: u32, mut entries: Vec<Entry>, next: u32) {
+        entries.sort_by_key(|x| x.tag);
+        let mut ifd = self.u16s(&[entries.len() as u16]);
+        for entry in entries {
+            ifd.extend(self.u16s(&[entry.tag, entry.kind]));
+            ifd.extend(self.u32s(&[entry.count]));
+            if entry.value.len() > 4 {
+                let value_offset = self.append(&entry.value);
+                ifd.extend(self.u32s(&[value_offset]));
+            } else {
+                let mut value = entry.value;
+                value.resize(4, 0);
+                ifd.extend(value);
+            }
+        }
+        ifd.extend(self.u32s(&[next]));
+
+        let offset = offset as usize;
+        self.data[offset..offset + ifd.len()].copy_from_slice(&ifd);
+    }
+
+    /// Writes an IFD at the end of the data and returns its offset.
+    pub fn ifd(&mut self, entries: Vec<Entry>, next: u32) -> u32 {
+        let offset = self.reserve_ifd(entries.len());
+        self.write_ifd(offset, entries, next);
+        offset
+    }
+
+    pub fn byte(&self, tag: u16, values: &[u8]) -> Entry {
+        entry(tag, BYTE, values.len(), values.to_vec())
+    }
+
+    pub fn undefined(&self, tag: u16, values: &[u8]) -> Entry {
+        entry(tag, UNDEFINED, values.len(), values.to_vec())
+    }
+
+    pub fn ascii(&self, tag: u16, value: &str) -> Entry {
+        let mut bytes = value.as_bytes().to_vec();
+        bytes.push(0);
+        entry(tag, ASCII, bytes.len(), bytes)
+    }
+
+    pub fn short(&self, tag: u16, values: &[u16]) -> Entry {
+        entry(tag, SHORT, values.len(), self.u16s(values))
+    }
+
+    pub fn long(&self, tag: u16, values: &[u32]) -> Entry {
+        entry(tag, LONG, values.len(), self.u32s(values))
+    }
+
+    /// Fractions of a numerator and a denominator.
+    pub fn rational(&self, tag: u16, values: &[(u32, u32)]) -> Entry {
+        let value = values
+            .iter()
+            .flat_map(|&(n, d)| self.u32s(&[n, d]))
+            .collect();
+        entry(tag, RATIONAL, values.len(), value)
+    }
+}
+
+fn entry(tag: u16, kind: u16, count: usize, value: Vec<u8>) -> Entry {
+    Entry {
+        tag,
+        kind,
+        count: count as u32,
+        value,
+    }
+}
@@ -0,0 +1,194 @@
+mod support;
+
+use std::{
+    path::{Path, PathBuf},
+    process::{Command, Output},
+};
+
+fn synthetic_dng(dir: &Path, name: &str) -> PathBuf {
+    let path = dir.join(name);
+    std::fs::write(&path, support::adobe::lossless_jpeg().data).unwrap();
+    path
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("quickraw_cli_{}", name));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn quickraw(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_quickraw"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_cli_usage() {
+    let output = quickraw(&["--help"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Usage: quickraw"));
+
+    let output = quickraw(&["unknown", "a.dng"]);
+    assert_eq!(output.status.code(), Some(2));
+}
+
+#[test]
+fn test_cli_identify_and_info() {
+    let dir = temp_dir("identify");
+    let input = synthetic_dng(&dir, "a.dng");
+    let input = input.to_str().unwrap();
+
+    let output = quickraw(&["identify", input]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Quickraw Synthetic Camera (DNG), supported"),
+        "{}",
+        stdout
+    );
+
+    let output = quickraw(&["info", "--json", input, input]);
+    if cfg!(feature = "serde") {
+        assert!(output.status.success());
+        let infos: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+        assert_eq!(infos.as_array().unwrap().len(), 2);
+        assert_eq!(infos[0]["file"], input);
+        assert_eq!(infos[0]["make"], "Quickraw");
+        assert_eq!(infos[1]["model"], "Synthetic Camera");
+    } else {
+        assert_eq!(output.status.code(), Some(1));
+    }
+
+    let output = quickraw(&["info", &dir.join("missing.dng").to_string_lossy()]);
+    assert_eq!(output.status.code(), Some(1));
+
+    for (i, fixture) in support::all().into_iter().enumerate() {
+        let path = dir.join(format!("fixture_{}", i));
+        std::fs::write(&path, &fixture.data).unwrap();
+        let output = quickraw(&["identify", path.to_str().unwrap()]);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(output.status.success(), "{}", fixture.name);
+        assert!(
+            stdout.contains(", supported"),
+            "{}: {}",
+            fixture.name,
+            stdout
+        );
+    }
+}
+
+#[test]
+fn test_cli_convert_batch() {
+    let dir = temp_dir("convert");
+    synthetic_dng(&dir, "a.dng");
+    synthetic_dng(&dir, "b.dng");
+    let out_dir = dir.join("out");
+    std::fs::create_dir_all(&out_dir).unwrap();
+
+    let pattern = dir.join("*.DNG");
+    let output = quickraw(&[
+        "convert",
+        "-f",
+        "png",
+        "--demosaic",
+        "super-pixel",
+        "--bits",
+        "8",
+        "-j",
+        "2",
+        "-o",
+        out_dir.to_str().unwrap(),
+        pattern.to_str().unwrap(),
+    ]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    for name in ["a.png", "b.png"] {
+        let data = std::fs::read(out_dir.join(name)).unwrap();
+        assert_eq!(&data[..8], b"\x89PNG\r\n\x1a\n");
+    }
+
+    // a single output file picks the format by its extension
+    let output_file = out_dir.join("single.jpg");
+    let output = quickraw(&[
+        "convert",
+        "--crop",
+        "--rotate",
+        "-o",
+        output_file.to_str().unwrap(),
+        dir.join("a.dng").to_str().unwrap(),
+    ]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(&std::fs::read(output_file).unwrap()[..2], &[0xff, 0xd8]);
+}
+
+#[test]
+fn test_cli_dng_rejects_render_options() {
+    let dir = temp_dir("dng");
+    let input = synthetic_dng(&dir, "a.dng");
+    let input = input.to_str().unwrap();
+    let output_file = dir.join("out.dng");
+    let output_file = output_file.to_str().unwrap();
+
+    for options in [
+        &["--transfer", "linear"][..],
+        &["--color-space", "rec2020"],
+        &["--demosaic", "none"],
+        &["--crop"],
+    ] {
+        let output = quickraw(&[&["convert", "-o", output_file], options, &[input]].concat());
+        assert_eq!(output.status.code(), Some(2), "{:?}", options);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(
+            stderr.contains(&format!("'{}' does not apply to DNG output", options[0])),
+            "{}",
+            stderr
+        );
+    }
+
+    let output = quickraw(&["convert", "-f", "dng", "--bits", "8", input]);
+    assert_eq!(output.status.code(), Some(2));
+
+    let output = quickraw(&["convert", "-o", output_file, input]);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn test_cli_output_collisions() {
+    let dir = temp_dir("collisions");
+    let out_dir = dir.join("out");
+    std::fs::create_dir_all(dir.join("x")).unwrap();
+    std::fs::create_dir_all(dir.join("y")).unwrap();
+    std::fs::create_dir_all(&out_dir).unwrap();
+    let first = synthetic_dng(&dir.join("x"), "a.dng");
+    let second = synthetic_dng(&dir.join("y"), "a.dng");
+
+    let output = quickraw(&[
+        "convert",
+        "-o",
+        out_dir.to_str().unwrap(),
+        first.to_str().unwrap(),
+        second.to_str().unwrap(),
+    ]);
+    assert_eq!(output.status.code(), Some(2));
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("would both be written to"), "{}", stderr);
+    assert!(!out_dir.join("a.tif").exists());
+
+    // the outputs next to the inputs do not collide
+    let output = quickraw(&["thumb", first.to_str().unwrap(), second.to_str().unwrap()]);
+    assert_ne!(output.status.code(), Some(2));
+}
@@ -0,0 +1,62 @@
+use quickraw::{data, ColorSpace};
+
+fn mul(matrix: &[f32; 9], v: [f32; 3]) -> [f32; 3] {
+    let row = |r: usize| {
+        matrix[r * 3..r * 3 + 3]
+            .iter()
+            .zip(v)
+            .map(|(m, v)| m * v)
+            .sum()
+    };
+    [row(0), row(1), row(2)]
+}
+
+#[test]
+fn test_white_is_preserved() {
+    for color_space in ColorSpace::ALL {
+        if color_space == ColorSpace::Xyz {
+            continue;
+        }
+        let white = mul(&color_space.matrix(), [1.; 3]);
+        for v in white {
+            assert!((v - 1.).abs() < 1e-5, "{:?} {:?}", color_space, white);
+        }
+    }
+}
+
+#[test]
+fn test_primaries_are_mapped_to_channels() {
+    let d65 = [0.9504559, 1., 1.0890578];
+    for color_space in ColorSpace::ALL {
+        let Some([red, green, blue, white]) = color_space.chromaticities() else {
+            continue;
+        };
+        // the spaces with another white are adapted, their primaries move
+        if white != [0.3127, 0.3290] || color_space == ColorSpace::Xyz {
+            continue;
+        }
+        // the sRGB and Adobe RGB matrices predate the derived ones and are kept as they were
+        if matches!(color_space, ColorSpace::Srgb | ColorSpace::AdobeRgb) {
+            continue;
+        }
+        for (channel, [x, y]) in [red, green, blue].into_iter().enumerate() {
+            let xyz = [x / y, 1., (1. - x - y) / y];
+            let normalized = [0, 1, 2].map(|i| xyz[i] / d65[i]);
+            let rgb = mul(&color_space.matrix(), normalized);
+            for (i, v) in rgb.into_iter().enumerate() {
+                if i != channel {
+                    assert!(v.abs() < 1e-4, "{:?} {:?} {:?}", color_space, channel, rgb);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_matrix_lookup() {
+    assert_eq!(ColorSpace::from(&data::XYZ2SRGB), ColorSpace::Srgb);
+    assert_eq!(ColorSpace::from(&data::XYZ2ACES_AP1), ColorSpace::AcesAp1);
+
+    let custom = [1., 0., 0., 0., 0.5, 0.5, 0., 0., 1.];
+    assert_eq!(ColorSpace::from(&custom), ColorSpace::Custom(custom));
+}
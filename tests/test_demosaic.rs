@@ -0,0 +1,133 @@
+mod support;
+
+use quickraw::{
+    decode_buffer, encode::dng::DngCompression, export, set_tiled_demosaicing_enabled, CFAPattern,
+    ColorSpace, TransferFunction,
+};
+use support::{adobe, TestImage};
+
+/// The colors of the first two rows, `0` is red, `1` green and `2` blue.
+fn colors(cfa_pattern: &CFAPattern) -> [[usize; 2]; 2] {
+    match cfa_pattern {
+        CFAPattern::RGGB => [[0, 1], [1, 2]],
+        CFAPattern::BGGR => [[2, 1], [1, 0]],
+        CFAPattern::GRBG => [[1, 0], [2, 1]],
+        _ => [[1, 2], [0, 1]],
+    }
+}
+
+/// Bilinear interpolation of a pixel off the border.
+fn bilinear(image: &[u16], width: usize, x: usize, y: usize, cfa_pattern: &CFAPattern) -> [u16; 3] {
+    let p = |x: usize, y: usize| image[y * width + x] as u32;
+    let colors = colors(cfa_pattern);
+    let color = colors[y % 2][x % 2];
+    let cross = ((p(x - 1, y) + p(x + 1, y) + p(x, y - 1) + p(x, y + 1)) / 4) as u16;
+    let diagonal =
+        ((p(x - 1, y - 1) + p(x + 1, y - 1) + p(x - 1, y + 1) + p(x + 1, y + 1)) / 4) as u16;
+    let horizontal = ((p(x - 1, y) + p(x + 1, y)) / 2) as u16;
+    let vertical = ((p(x, y - 1) + p(x, y + 1)) / 2) as u16;
+
+    let mut rgb = [0u16; 3];
+    rgb[color] = p(x, y) as u16;
+    if color == 1 {
+        let row_color = colors[y % 2][(x + 1) % 2];
+        rgb[row_color] = horizontal;
+        rgb[2 - row_color] = vertical;
+    } else {
+        rgb[1] = cross;
+        rgb[2 - color] = diagonal;
+    }
+    rgb
+}
+
+#[test]
+fn test_interior_matches_bilinear() {
+    let mut fixtures = support::all();
+    for cfa_pattern in [
+        CFAPattern::RGGB,
+        CFAPattern::BGGR,
+        CFAPattern::GRBG,
+        CFAPattern::GBRG,
+    ] {
+        // odd sizes end the rows in the middle of a tile and of a CFA block
+        let image = TestImage::from_fn(203, 37, cfa_pattern, |x, y| {
+            let i = y * 203 + x;
+            (x * 389 + y * 211 + i * i % 97) as u16 % 4096 * 16
+        });
+        fixtures.push(adobe::fixture("odd DNG", image, DngCompression::None));
+    }
+
+    for fixture in fixtures {
+        let (width, height, cfa_pattern) = (
+            fixture.image.width,
+            fixture.image.height,
+            fixture.image.cfa_pattern,
+        );
+        if matches!(cfa_pattern, CFAPattern::XTrans0 | CFAPattern::XTrans1) {
+            continue;
+        }
+        let decoded_image = decode_buffer(&fixture.data).unwrap();
+        let (rgba, _, _) = export::load_origin_image_from_buffer(
+            &fixture.data,
+            export::Options::new(TransferFunction::Linear, ColorSpace::Srgb, false),
+        )
+        .unwrap();
+
+        for y in 1..height - 1 {
+            for x in 1..width - 1 {
+                let i = (y * width + x) * 4;
+                assert_eq!(
+                    rgba[i..i + 3],
+                    bilinear(&decoded_image.image, width, x, y, &cfa_pattern),
+                    "{} {:?} at ({}, {})",
+                    fixture.name,
+                    cfa_pattern,
+                    x,
+                    y
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_tiled_matches_per_pixel() {
+    let mut fixtures = support::all();
+    // images without an interior, rows shorter than a tile and rows ending in the middle of a tile
+    for (width, height) in [(2, 2), (2, 5), (5, 2), (3, 3), (131, 7)] {
+        let image = TestImage::from_fn(width, height, CFAPattern::GBRG, |x, y| {
+            (x * 733 + y * 419 + x * y * 37) as u16 % 4096 * 16
+        });
+        fixtures.push(adobe::fixture("small DNG", image, DngCompression::None));
+    }
+
+    for fixture in fixtures {
+        let data = &fixture.data;
+        let render = |tiled| {
+            set_tiled_demosaicing_enabled(tiled);
+            let options = || export::Options::new(TransferFunction::Linear, ColorSpace::Raw, false);
+            let image = export::load_origin_image_from_buffer(data, options()).unwrap();
+            let float_image = export::load_float_image_from_buffer(data, options()).unwrap();
+            (image, float_image)
+        };
+
+        let (per_pixel_image, per_pixel_float_image) = render(false);
+        let (image, float_image) = render(true);
+        assert_eq!(per_pixel_image, image, "{}", fixture.name);
+        assert_eq!(
+            per_pixel_float_image.0.len(),
+            float_image.0.len(),
+            "{}",
+            fixture.name
+        );
+        assert!(
+            per_pixel_float_image
+                .0
+                .iter()
+                .zip(&float_image.0)
+                .all(|(a, b)| a.to_bits() == b.to_bits()),
+            "{}",
+            fixture.name
+        );
+    }
+}
@@ -0,0 +1,134 @@
+mod support;
+
+use quickraw::{
+    decode_buffer,
+    encode::{
+        dng::{self, DngCompression, DngOptions, RawImage},
+        Metadata,
+    },
+    export, CFAPattern, ColorSpace, Crop, TransferFunction,
+};
+use support::{adobe, metrics, TestImage};
+
+fn mosaic(width: usize, height: usize) -> TestImage {
+    TestImage::from_fn(width, height, CFAPattern::GRBG, |x, y| {
+        ((x * 131 + y * 517 + (x * y) % 97 * 300) % 65536) as u16
+    })
+}
+
+fn metadata() -> Metadata {
+    Metadata {
+        orientation: 6,
+        ..adobe::metadata()
+    }
+}
+
+fn round_trip(compression: DngCompression, width: usize, height: usize) {
+    let image = mosaic(width, height);
+    let raw_image = RawImage {
+        crop: Some(Crop {
+            x: 8,
+            y: 6,
+            width: width as u32 - 16,
+            height: height as u32 - 12,
+        }),
+        ..adobe::raw_image(&image)
+    };
+    let options = DngOptions {
+        compression,
+        embed_preview: true,
+    };
+    let data = dng::encode(&raw_image, &metadata(), None, &options).unwrap();
+
+    let decoded = decode_buffer(data).unwrap();
+    assert_eq!((decoded.width, decoded.height), (width, height));
+    assert_eq!(decoded.cfa_pattern, CFAPattern::GRBG);
+    assert_eq!(decoded.crop, raw_image.crop);
+    assert!(decoded.image == image.samples);
+
+    // the reader truncates the multipliers
+    let [r, g, b] = decoded.white_balance;
+    assert!((r * 512 / g - 1024).abs() <= 1 && (b * 512 / g - 768).abs() <= 1);
+    for (a, b) in decoded.cam_matrix.iter().zip(adobe::CAM_MATRIX.iter()) {
+        assert!((a - b).abs() < 1e-3, "{:?}", decoded.cam_matrix);
+    }
+}
+
+#[test]
+fn test_dng_uncompressed() {
+    round_trip(DngCompression::None, 100, 64);
+}
+
+#[test]
+fn test_dng_lossless_jpeg() {
+    // partial tiles on both edges
+    round_trip(DngCompression::LosslessJpeg, 300, 270);
+}
+
+#[test]
+fn test_dng_linear_raw() {
+    let (width, height) = (40, 30);
+    let image = (0..width * height * 3)
+        .map(|x| (x * 37 % 65536) as u16)
+        .collect::<Vec<_>>();
+    let raw_image = RawImage {
+        image: &image,
+        width,
+        height,
+        cfa_pattern: CFAPattern::RGGB,
+        black_level: [0; 4],
+        white_level: u16::MAX,
+        bits_per_sample: 16,
+        linearization_table: None,
+        crop: None,
+        white_balance: [512, 512, 512],
+        cam_matrix: adobe::CAM_MATRIX,
+    };
+    for compression in [DngCompression::None, DngCompression::LosslessJpeg] {
+        let data =
+            dng::encode(&raw_image, &metadata(), None, &adobe::options(compression)).unwrap();
+        let decoded = decode_buffer(data).unwrap();
+        assert!(decoded.image == image);
+    }
+}
+
+#[test]
+fn test_dng_from_fixtures() {
+    // the sensor samples and their levels are carried over as they are, X-Trans in its pattern
+    for fixture in support::all() {
+        let sensor_data = export::load_sensor_data_from_buffer(&fixture.data).unwrap();
+        for compression in [DngCompression::None, DngCompression::LosslessJpeg] {
+            let options = DngOptions {
+                compression,
+                embed_preview: false,
+            };
+            let data = export::encode_dng_from_buffer(&fixture.data, &options)
+                .unwrap_or_else(|e| panic!("{}: {e}", fixture.name));
+            let dng = export::load_sensor_data_from_buffer(&data).unwrap();
+            assert_eq!(dng.image, sensor_data.image, "{}", fixture.name);
+            assert_eq!(dng.cfa_pattern, sensor_data.cfa_pattern, "{}", fixture.name);
+            assert_eq!(dng.black_level, sensor_data.black_level, "{}", fixture.name);
+            assert_eq!(dng.white_level, sensor_data.white_level, "{}", fixture.name);
+            assert_eq!(
+                dng.linearization_table, sensor_data.linearization_table,
+                "{}",
+                fixture.name
+            );
+            assert_eq!(dng.crop, sensor_data.crop, "{}", fixture.name);
+            if compression == DngCompression::LosslessJpeg {
+                assert_eq!(dng.bit_depth, sensor_data.bit_depth, "{}", fixture.name);
+            }
+
+            // the calibration goes through rationals
+            let render = |data| {
+                let options =
+                    export::Options::new(TransferFunction::Linear, ColorSpace::Srgb, false);
+                export::load_float_image_from_buffer(data, options)
+                    .unwrap()
+                    .0
+            };
+            let psnr = metrics::psnr(&render(&data), &render(&fixture.data));
+            assert!(psnr > 50., "{}: {psnr} dB", fixture.name);
+        }
+    }
+}
@@ -0,0 +1,104 @@
+mod support;
+
+use quickraw::{
+    encode::{
+        dng::{self, DngCompression, DngOptions},
+        jpeg::{self, JpegOptions},
+        Image, Metadata, Pixels,
+    },
+    export, CFAPattern, PreviewFormat, PreviewSize, RawFileReadingError,
+};
+use support::{adobe, TestImage};
+
+fn sample_dng(preview: Option<&[u8]>) -> Vec<u8> {
+    let image = TestImage::from_fn(128, 96, CFAPattern::RGGB, |x, y| {
+        (x * 31 + y * 17) as u16 % 4096 * 16
+    });
+    let options = DngOptions {
+        compression: DngCompression::None,
+        embed_preview: true,
+    };
+    dng::encode(
+        &adobe::raw_image(&image),
+        &adobe::metadata(),
+        preview,
+        &options,
+    )
+    .unwrap()
+}
+
+/// A JPEG preview with a thumbnail of 160 x 100 in its EXIF block.
+fn sample_jpeg() -> Vec<u8> {
+    let (width, height) = (640, 400);
+    let pixels = (0..width * height * 3)
+        .map(|i| (i * 7 % 251) as u8)
+        .collect::<Vec<_>>();
+    let image = Image::new(Pixels::U8(&pixels), width, height, 3).unwrap();
+    let options = JpegOptions {
+        embed_thumbnail: true,
+        ..Default::default()
+    };
+    jpeg::encode(&image, &Metadata::default(), &options).unwrap()
+}
+
+#[test]
+fn test_list_nested_previews() {
+    let preview = sample_jpeg();
+    let data = sample_dng(Some(&preview));
+
+    let previews = export::list_embedded_previews(&data).unwrap();
+    let sizes = previews
+        .iter()
+        .map(|x| (x.format, x.width, x.height))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        sizes,
+        [
+            (PreviewFormat::Jpeg, 160, 100),
+            (PreviewFormat::Jpeg, 640, 400)
+        ]
+    );
+    assert_eq!(&data[previews[1].range.clone()], preview.as_slice());
+
+    let (largest, description) =
+        export::load_embedded_preview_from_buffer(&data, PreviewSize::Largest).unwrap();
+    assert_eq!(largest, preview.as_slice());
+    assert_eq!(description, previews[1]);
+    // the thumbnail of the maker is the one of IFD0
+    let (thumbnail, _) = export::load_thumbnail_from_buffer(&data).unwrap();
+    assert_eq!(thumbnail, largest);
+
+    let select = |size: PreviewSize| size.select(&previews).unwrap().width;
+    assert_eq!(select(PreviewSize::Smallest), 160);
+    assert_eq!(select(PreviewSize::Closest(300)), 160);
+    assert_eq!(select(PreviewSize::Closest(500)), 640);
+    // a tie goes to the larger one
+    assert_eq!(select(PreviewSize::Closest(400)), 640);
+}
+
+#[test]
+fn test_uncompressed_preview() {
+    let data = sample_dng(None);
+    let previews = export::list_embedded_previews(&data).unwrap();
+    assert_eq!(previews.len(), 1);
+
+    let preview = &previews[0];
+    assert_eq!(preview.format, PreviewFormat::Rgb8);
+    assert_eq!(
+        preview.range.len(),
+        (preview.width * preview.height * 3) as usize
+    );
+    let (thumbnail, _) = export::load_thumbnail_from_buffer(&data).unwrap();
+    assert_eq!(&data[preview.range.clone()], thumbnail.as_slice());
+}
+
+#[test]
+fn test_no_preview() {
+    // a TIFF header with an empty IFD
+    let data = [b'I', b'I', 42, 0, 8, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+    assert!(export::list_embedded_previews(&data).unwrap().is_empty());
+    assert!(matches!(
+        export::load_embedded_preview_from_buffer(&data, PreviewSize::Largest),
+        Err(RawFileReadingError::NoEmbeddedPreview)
+    ));
+}
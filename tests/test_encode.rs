@@ -0,0 +1,324 @@
+use quickraw::{
+    encode::{self, jpeg, tiff, Exif, Image, ImageFormat, Metadata, Pixels},
+    ColorSpace, TransferFunction,
+};
+
+fn u16_at(data: &[u8], i: usize) -> u16 {
+    u16::from_le_bytes([data[i], data[i + 1]])
+}
+fn u32_at(data: &[u8], i: usize) -> u32 {
+    u32::from_le_bytes([data[i], data[i + 1], data[i + 2], data[i + 3]])
+}
+
+/// Returns `(type, count, value or offset)` of a tag in the IFD at `ifd`.
+fn find_tag(data: &[u8], ifd: usize, tag: u16) -> Option<(u16, u32, u32)> {
+    let count = u16_at(data, ifd) as usize;
+    (0..count).map(|i| ifd + 2 + i * 12).find_map(|entry| {
+        (u16_at(data, entry) == tag).then(|| {
+            let field_type = u16_at(data, entry + 2);
+            let value = if field_type == 3 {
+                u16_at(data, entry + 8) as u32
+            } else {
+                u32_at(data, entry + 8)
+            };
+            (field_type, u32_at(data, entry + 4), value)
+        })
+    })
+}
+
+fn gradient(width: usize, height: usize) -> Vec<u16> {
+    (0..width * height * 3).map(|i| (i * 97 % 65536) as u16).collect()
+}
+
+#[test]
+fn test_tiff_uncompressed_16bit() {
+    let (width, height) = (37, 21);
+    let pixels = gradient(width, height);
+    let image = Image::new(Pixels::U16(&pixels), width, height, 3).unwrap();
+    let metadata = Metadata {
+        make: Some("Sony".to_owned()),
+        exif: Exif {
+            exposure_time: Some(1. / 250.),
+            iso: Some(400),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let options = tiff::TiffOptions {
+        compression: tiff::TiffCompression::None,
+        layout: tiff::TiffLayout::Strips,
+    };
+    let data = tiff::encode(&image, &metadata, &options).unwrap();
+
+    assert_eq!(&data[..4], &[0x49, 0x49, 0x2a, 0x00]);
+    let ifd = u32_at(&data, 4) as usize;
+    assert_eq!(find_tag(&data, ifd, 0x0100).unwrap().2, width as u32);
+    assert_eq!(find_tag(&data, ifd, 0x0101).unwrap().2, height as u32);
+    assert_eq!(find_tag(&data, ifd, 0x0103).unwrap().2, 1);
+    assert_eq!(find_tag(&data, ifd, 0x0102).unwrap().1, 3);
+
+    let strip = find_tag(&data, ifd, 0x0111).unwrap().2 as usize;
+    let decoded = (0..pixels.len())
+        .map(|i| u16_at(&data, strip + i * 2))
+        .collect::<Vec<_>>();
+    assert_eq!(decoded, pixels);
+
+    let (_, icc_len, icc_offset) = find_tag(&data, ifd, 0x8773).unwrap();
+    let icc = &data[icc_offset as usize..(icc_offset + icc_len) as usize];
+    assert_eq!(&icc[36..40], b"acsp");
+
+    let exif = find_tag(&data, ifd, 0x8769).unwrap().2 as usize;
+    assert_eq!(find_tag(&data, exif, 0x8827).unwrap().2, 400);
+    let exposure = find_tag(&data, exif, 0x829a).unwrap().2 as usize;
+    assert_eq!([u32_at(&data, exposure), u32_at(&data, exposure + 4)], [1, 250]);
+}
+
+#[test]
+fn test_tiff_compression_and_tiles() {
+    let (width, height) = (100, 50);
+    let pixels = gradient(width, height);
+    let image = Image::new(Pixels::U16(&pixels), width, height, 3).unwrap();
+    let metadata = Metadata::default();
+
+    for compression in [tiff::TiffCompression::Lzw, tiff::TiffCompression::Deflate] {
+        let options = tiff::TiffOptions {
+            compression,
+            layout: tiff::TiffLayout::Tiles(32),
+        };
+        let data = tiff::encode(&image, &metadata, &options).unwrap();
+        let ifd = u32_at(&data, 4) as usize;
+        assert_eq!(find_tag(&data, ifd, 0x013d).unwrap().2, 2);
+        // 4 x 2 tiles
+        assert_eq!(find_tag(&data, ifd, 0x0144).unwrap().1, 8);
+        assert!(find_tag(&data, ifd, 0x0111).is_none());
+    }
+
+    let options = tiff::TiffOptions {
+        compression: tiff::TiffCompression::None,
+        layout: tiff::TiffLayout::Tiles(20),
+    };
+    assert!(matches!(
+        tiff::encode(&image, &metadata, &options),
+        Err(encode::EncodingError::InvalidTileSize(20))
+    ));
+}
+
+#[test]
+fn test_tiff_float() {
+    let pixels = vec![0.5f32, -0.25, 2.0, 1.0];
+    let image = Image::new(Pixels::F32(&pixels), 1, 1, 4).unwrap();
+    let metadata = Metadata {
+        color_space: ColorSpace::AcesAp0,
+        transfer: TransferFunction::Linear,
+        ..Default::default()
+    };
+    let data = ImageFormat::from_path("out.TIF")
+        .unwrap()
+        .encode(&image, &metadata)
+        .unwrap();
+    let ifd = u32_at(&data, 4) as usize;
+    let (_, count, sample_format) = find_tag(&data, ifd, 0x0153).unwrap();
+    assert_eq!(count, 4);
+    assert_eq!(u16_at(&data, sample_format as usize), 3);
+    assert_eq!(find_tag(&data, ifd, 0x0152).unwrap().2, 2);
+    assert!(find_tag(&data, ifd, 0x013d).is_none());
+}
+
+#[test]
+fn test_image_validation() {
+    let pixels = vec![0u8; 10];
+    assert!(Image::new(Pixels::U8(&pixels), 2, 2, 3).is_err());
+    assert!(Image::new(Pixels::U8(&pixels), 5, 1, 2).is_err());
+    assert!(ImageFormat::from_path("out.bmp").is_err());
+}
+
+#[test]
+fn test_icc_profile() {
+    assert!(encode::icc_profile(&ColorSpace::Raw, TransferFunction::Linear).is_none());
+    for color_space in ColorSpace::ALL.iter().filter(|x| **x != ColorSpace::Raw) {
+        let profile = encode::icc_profile(color_space, color_space.default_transfer()).unwrap();
+        let size = u32::from_be_bytes([profile[0], profile[1], profile[2], profile[3]]);
+        assert_eq!(size as usize, profile.len());
+        assert_eq!(&profile[36..40], b"acsp");
+    }
+}
+
+fn decode_png(data: &[u8]) -> (png::Info<'static>, Vec<u8>) {
+    let decoder = png::Decoder::new(data);
+    let mut reader = decoder.read_info().unwrap();
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    reader.next_frame(&mut buffer).unwrap();
+    (reader.info().clone(), buffer)
+}
+
+fn has_chunk(data: &[u8], name: &[u8; 4]) -> bool {
+    data.windows(4).any(|x| x == name)
+}
+
+#[test]
+fn test_png_16bit() {
+    let (width, height) = (33, 17);
+    let pixels = gradient(width, height);
+    let image = Image::new(Pixels::U16(&pixels), width, height, 3).unwrap();
+    let data = ImageFormat::from_path("out.png")
+        .unwrap()
+        .encode(&image, &Metadata::default())
+        .unwrap();
+
+    let (info, buffer) = decode_png(&data);
+    assert_eq!((info.width, info.height), (width as u32, height as u32));
+    assert_eq!(info.bit_depth, png::BitDepth::Sixteen);
+    assert_eq!(info.color_type, png::ColorType::Rgb);
+    let decoded = buffer
+        .chunks_exact(2)
+        .map(|x| u16::from_be_bytes([x[0], x[1]]))
+        .collect::<Vec<_>>();
+    assert_eq!(decoded, pixels);
+    assert!(info.icc_profile.is_some());
+    assert!(has_chunk(&data, b"eXIf"));
+    assert!(!has_chunk(&data, b"cICP"));
+}
+
+#[test]
+fn test_png_8bit_rgba_cicp() {
+    let pixels = (0..8 * 8 * 4).map(|x| x as u8).collect::<Vec<_>>();
+    let image = Image::new(Pixels::U8(&pixels), 8, 8, 4).unwrap();
+    let metadata = Metadata {
+        color_space: ColorSpace::Rec2020,
+        transfer: TransferFunction::Pq,
+        ..Default::default()
+    };
+    let data = encode::png::encode(&image, &metadata, &Default::default()).unwrap();
+
+    let (info, buffer) = decode_png(&data);
+    assert_eq!(info.color_type, png::ColorType::Rgba);
+    assert_eq!(buffer, pixels);
+    assert!(info.icc_profile.is_none());
+    let cicp = data.windows(4).position(|x| x == b"cICP").unwrap();
+    assert_eq!(&data[cicp + 4..cicp + 8], &[9, 16, 0, 1]);
+
+    let pixels = [0f32; 3];
+    let image = Image::new(Pixels::F32(&pixels), 1, 1, 3).unwrap();
+    assert!(encode::png::encode(&image, &metadata, &Default::default()).is_err());
+}
+
+#[test]
+fn test_icc_srgb_colorants() {
+    let profile = encode::icc_profile(&ColorSpace::Srgb, TransferFunction::Srgb).unwrap();
+    let be32 =
+        |i: usize| u32::from_be_bytes([profile[i], profile[i + 1], profile[i + 2], profile[i + 3]]);
+    let tag_count = be32(128) as usize;
+    let offset = (0..tag_count)
+        .map(|i| 132 + i * 12)
+        .find(|&entry| &profile[entry..entry + 4] == b"rXYZ")
+        .map(|entry| be32(entry + 4) as usize)
+        .unwrap();
+    let red = [0, 1, 2].map(|i| be32(offset + 8 + i * 4) as i32 as f32 / 65536.);
+    // the D50-adapted sRGB red primary
+    for (v, expected) in red.iter().zip([0.4361, 0.2225, 0.0139]) {
+        assert!((v - expected).abs() < 2e-3, "{:?}", red);
+    }
+}
+
+#[test]
+fn test_icc_pq_curve() {
+    let profile = encode::icc_profile(&ColorSpace::Rec2020, TransferFunction::Pq).unwrap();
+    let be32 =
+        |i: usize| u32::from_be_bytes([profile[i], profile[i + 1], profile[i + 2], profile[i + 3]]);
+    let tag_count = be32(128) as usize;
+    let offset = (0..tag_count)
+        .map(|i| 132 + i * 12)
+        .find(|&entry| &profile[entry..entry + 4] == b"rTRC")
+        .map(|entry| be32(entry + 4) as usize)
+        .unwrap();
+    let len = be32(offset + 8) as usize;
+    let curve = (0..len)
+        .map(|i| u16::from_be_bytes([profile[offset + 12 + i * 2], profile[offset + 13 + i * 2]]))
+        .collect::<Vec<_>>();
+    // the highlights above the reference white are kept up to the peak
+    assert!(curve.windows(2).all(|x| x[0] <= x[1]));
+    assert_eq!(curve.iter().filter(|&&x| x == u16::MAX).count(), 1);
+    let white = (TransferFunction::Pq.encode(1.) * (len - 1) as f32).round() as usize;
+    let expected = 203. / 10000. * 65535.;
+    assert!((curve[white] as f32 - expected).abs() < expected * 0.02, "{}", curve[white]);
+}
+
+fn decode_jpeg(data: &[u8]) -> (jpeg_decoder::Decoder<&[u8]>, Vec<u8>) {
+    let mut decoder = jpeg_decoder::Decoder::new(data);
+    let pixels = decoder.decode().unwrap();
+    (decoder, pixels)
+}
+
+#[test]
+fn test_jpeg_exif_and_icc() {
+    let (width, height) = (320, 200);
+    let pixels = (0..width * height * 3)
+        .map(|i| ((i / 3 % width) * 255 / width) as u16 * 257)
+        .collect::<Vec<_>>();
+    let image = Image::new(Pixels::U16(&pixels), width, height, 3).unwrap();
+    let metadata = Metadata {
+        orientation: 6,
+        exif: Exif {
+            iso: Some(200),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let options = jpeg::JpegOptions {
+        quality: 95,
+        subsampling: jpeg::ChromaSubsampling::Yuv444,
+        progressive: false,
+        embed_thumbnail: true,
+    };
+    let data = jpeg::encode(&image, &metadata, &options).unwrap();
+
+    let (decoder, decoded) = decode_jpeg(&data);
+    let info = decoder.info().unwrap();
+    assert_eq!((info.width, info.height), (width as u16, height as u16));
+    assert!(decoded
+        .iter()
+        .zip(pixels.iter())
+        .all(|(&a, &b)| (a as i32 - (b / 257) as i32).abs() <= 4));
+    assert!(decoder.icc_profile().is_some());
+
+    let exif = decoder.exif_data().unwrap();
+    assert_eq!(&exif[..4], &[0x49, 0x49, 0x2a, 0x00]);
+    let ifd0 = u32_at(exif, 4) as usize;
+    assert_eq!(find_tag(exif, ifd0, 0x0112).unwrap().2, 6);
+    let exif_ifd = find_tag(exif, ifd0, 0x8769).unwrap().2 as usize;
+    assert_eq!(find_tag(exif, exif_ifd, 0x8827).unwrap().2, 200);
+
+    // the thumbnail is linked as IFD1
+    let ifd1 = u32_at(exif, ifd0 + 2 + u16_at(exif, ifd0) as usize * 12) as usize;
+    let offset = find_tag(exif, ifd1, 0x0201).unwrap().2 as usize;
+    let len = find_tag(exif, ifd1, 0x0202).unwrap().2 as usize;
+    let (thumbnail, _) = decode_jpeg(&exif[offset..offset + len]);
+    let info = thumbnail.info().unwrap();
+    assert_eq!((info.width, info.height), (160, 100));
+}
+
+#[test]
+fn test_jpeg_progressive_rgba() {
+    let pixels = (0..64 * 48 * 4).map(|x| (x % 251) as u8).collect::<Vec<_>>();
+    let image = Image::new(Pixels::U8(&pixels), 64, 48, 4).unwrap();
+    let options = jpeg::JpegOptions {
+        progressive: true,
+        ..Default::default()
+    };
+    let data = jpeg::encode(&image, &Metadata::default(), &options).unwrap();
+    // SOF2 marks a progressive frame
+    assert!(data.windows(2).any(|x| x == [0xff, 0xc2]));
+    let (decoder, decoded) = decode_jpeg(&data);
+    assert_eq!(decoded.len(), 64 * 48 * 3);
+    assert!(decoder.exif_data().is_some());
+
+    let data = ImageFormat::from_path("out.jpeg")
+        .unwrap()
+        .encode(&image, &Metadata::default())
+        .unwrap();
+    assert!(data.windows(2).any(|x| x == [0xff, 0xc0]));
+
+    let pixels = [0f32; 3];
+    let image = Image::new(Pixels::F32(&pixels), 1, 1, 3).unwrap();
+    assert!(jpeg::encode(&image, &Metadata::default(), &Default::default()).is_err());
+}
@@ -1,3 +1,5 @@
+mod support;
+
 use quickraw::{data, export};
 
 #[test]
@@ -19,3 +21,22 @@ fn test_dng0() {
         export::load_image_from_file("tests/sample1.dng", options).unwrap();
     println!("{} {} {}", image.len(), width, height);
 }
+
+#[test]
+fn test_float_export() {
+    for fixture in support::all() {
+        let options = export::Options::new(data::GAMMA_LINEAR, &data::XYZ2SRGB, false);
+        let (image, width, height) =
+            export::load_float_image_from_buffer(&fixture.data, options).unwrap();
+
+        assert_eq!(
+            (width, height),
+            (fixture.image.width, fixture.image.height),
+            "{}",
+            fixture.name
+        );
+        assert_eq!(image.len(), width * height * 3, "{}", fixture.name);
+        assert!(image.iter().all(|x| x.is_finite()), "{}", fixture.name);
+        assert!(image.iter().any(|&x| x > 0.), "{}", fixture.name);
+    }
+}
@@ -0,0 +1,163 @@
+use exr::prelude::*;
+use quickraw::{
+    encode::{exr as exr_writer, pfm, Exif, Image as RgbImage, ImageFormat, Metadata, Pixels},
+    ColorSpace, TransferFunction,
+};
+
+/// Scene-linear values with both smooth areas and noise, some of them above 1.0 or negative.
+fn plate(width: usize, height: usize, channels: usize) -> Vec<f32> {
+    let mut seed = 12345u32;
+    (0..width * height * channels)
+        .map(|i| {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            let noise = (seed >> 16) as f32 / 65536. - 0.5;
+            let smooth = (i / channels % width) as f32 / width as f32 * 4.;
+            if (i / (width * channels)).is_multiple_of(3) {
+                smooth
+            } else {
+                smooth + noise
+            }
+        })
+        .collect()
+}
+
+type FlatLayerImage = exr::image::Image<Layer<AnyChannels<FlatSamples>>>;
+
+fn read_exr(data: &[u8]) -> FlatLayerImage {
+    read()
+        .no_deep_data()
+        .largest_resolution_level()
+        .all_channels()
+        .first_valid_layer()
+        .all_attributes()
+        .from_buffered(std::io::Cursor::new(data.to_vec()))
+        .unwrap()
+}
+
+/// Interleaves the decoded channels back into RGB(A) order.
+fn interleave(image: &FlatLayerImage) -> Vec<f32> {
+    let list = &image.layer_data.channel_data.list;
+    let find = |name: &str| {
+        list.iter()
+            .find(|c| c.name.eq(name))
+            .map(|c| c.sample_data.values_as_f32().collect::<Vec<_>>())
+    };
+    let channels = ["R", "G", "B", "A"]
+        .iter()
+        .filter_map(|x| find(x))
+        .collect::<Vec<_>>();
+    (0..channels[0].len())
+        .flat_map(|i| channels.iter().map(move |c| c[i]))
+        .collect()
+}
+
+#[test]
+fn test_exr_round_trip() {
+    let (width, height) = (37, 45);
+    for channels in [3, 4] {
+        let pixels = plate(width, height, channels);
+        let image = RgbImage::new(Pixels::F32(&pixels), width, height, channels).unwrap();
+        let metadata = Metadata {
+            color_space: ColorSpace::AcesAp0,
+            transfer: TransferFunction::Linear,
+            ..Default::default()
+        };
+
+        for compression in [
+            exr_writer::ExrCompression::None,
+            exr_writer::ExrCompression::Zip,
+            exr_writer::ExrCompression::Piz,
+        ] {
+            let options = exr_writer::ExrOptions {
+                compression,
+                sample_type: exr_writer::ExrSampleType::Float,
+            };
+            let data = exr_writer::encode(&image, &metadata, &options).unwrap();
+            let decoded = read_exr(&data);
+            assert_eq!(decoded.layer_data.size, Vec2(width, height));
+            assert_eq!(interleave(&decoded), pixels, "{:?}", compression);
+
+            let options = exr_writer::ExrOptions {
+                compression,
+                sample_type: exr_writer::ExrSampleType::Half,
+            };
+            let data = exr_writer::encode(&image, &metadata, &options).unwrap();
+            let expected = pixels
+                .iter()
+                .map(|&x| half::f16::from_f32(x).to_f32())
+                .collect::<Vec<_>>();
+            assert_eq!(interleave(&read_exr(&data)), expected, "{:?}", compression);
+        }
+    }
+}
+
+#[test]
+fn test_exr_attributes() {
+    let pixels = plate(16, 8, 3);
+    let image = RgbImage::new(Pixels::F32(&pixels), 16, 8, 3).unwrap();
+    let metadata = Metadata {
+        color_space: ColorSpace::Rec2020,
+        transfer: TransferFunction::Linear,
+        exif: Exif {
+            exposure_time: Some(0.01),
+            iso: Some(800),
+            date_time_original: Some("2024:05:01 12:00:00".to_owned()),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let data = ImageFormat::from_path("plate.exr")
+        .unwrap()
+        .encode(&image, &metadata)
+        .unwrap();
+    let decoded = read_exr(&data);
+
+    let chromaticities = decoded.attributes.chromaticities.unwrap();
+    assert_eq!(chromaticities.red, Vec2(0.708, 0.292));
+    assert_eq!(chromaticities.white, Vec2(0.3127, 0.3290));
+    let attributes = &decoded.layer_data.attributes;
+    assert_eq!(attributes.exposure, Some(0.01));
+    assert_eq!(attributes.iso_speed, Some(800.));
+    assert_eq!(attributes.capture_date, Some(Text::from("2024:05:01 12:00:00")));
+    assert_eq!(attributes.aperture, None);
+
+    // the raw space is tagged as XYZ
+    let metadata = Metadata {
+        color_space: ColorSpace::Raw,
+        ..metadata
+    };
+    let data = exr_writer::encode(&image, &metadata, &Default::default()).unwrap();
+    let chromaticities = read_exr(&data).attributes.chromaticities.unwrap();
+    assert_eq!(chromaticities.green, Vec2(0., 1.));
+
+    let pixels = [0u16; 3];
+    let image = RgbImage::new(Pixels::U16(&pixels), 1, 1, 3).unwrap();
+    assert!(exr_writer::encode(&image, &metadata, &Default::default()).is_err());
+}
+
+#[test]
+fn test_pfm() {
+    let (width, height) = (5, 3);
+    let pixels = plate(width, height, 4);
+    let image = RgbImage::new(Pixels::F32(&pixels), width, height, 4).unwrap();
+    let data = ImageFormat::from_path("plate.PFM")
+        .unwrap()
+        .encode(&image, &Metadata::default())
+        .unwrap();
+
+    let header = b"PF\n5 3\n-1.0\n";
+    assert_eq!(&data[..header.len()], header);
+    let body = data[header.len()..]
+        .chunks_exact(4)
+        .map(|x| f32::from_le_bytes([x[0], x[1], x[2], x[3]]))
+        .collect::<Vec<_>>();
+    assert_eq!(body.len(), width * height * 3);
+    // the first stored row is the bottom one
+    let bottom = &pixels[(height - 1) * width * 4..];
+    assert_eq!(&body[..3], &bottom[..3]);
+    assert_eq!(&body[3..6], &bottom[4..7]);
+
+    let pixels = [0u8; 3];
+    let image = RgbImage::new(Pixels::U8(&pixels), 1, 1, 3).unwrap();
+    assert!(pfm::encode(&image).is_err());
+}
@@ -0,0 +1,72 @@
+mod support;
+
+use quickraw::export;
+
+#[test]
+fn test_fixtures_sensor_data() {
+    for fixture in support::all() {
+        let sensor_data = export::load_sensor_data_from_buffer(&fixture.data)
+            .unwrap_or_else(|e| panic!("{}: {e}", fixture.name));
+        let image = &fixture.image;
+        assert_eq!(
+            (sensor_data.width, sensor_data.height),
+            (image.width, image.height),
+            "{}",
+            fixture.name
+        );
+        assert_eq!(
+            sensor_data.cfa_pattern, image.cfa_pattern,
+            "{}",
+            fixture.name
+        );
+        assert_eq!(
+            fixture.mismatch(&sensor_data.image),
+            None,
+            "{}: (index, expected, decoded)",
+            fixture.name
+        );
+        assert_eq!(
+            sensor_data.black_level,
+            Some(fixture.black_level),
+            "{}",
+            fixture.name
+        );
+        assert_eq!(
+            sensor_data.bit_depth,
+            Some(fixture.bit_depth),
+            "{}",
+            fixture.name
+        );
+    }
+}
+
+#[test]
+fn test_fixtures_decode() {
+    for fixture in support::all() {
+        let decoded = quickraw::decode_buffer(&fixture.data)
+            .unwrap_or_else(|e| panic!("{}: {e}", fixture.name));
+        let image = &fixture.image;
+        assert_eq!(
+            (decoded.width, decoded.height),
+            (image.width, image.height),
+            "{}",
+            fixture.name
+        );
+        assert_eq!(decoded.cfa_pattern, image.cfa_pattern, "{}", fixture.name);
+        assert_eq!(decoded.image.len(), image.samples.len(), "{}", fixture.name);
+    }
+}
+
+#[test]
+fn test_panasonic_bands_and_chunk_wrap() {
+    // a last band of 2 rows, and 560 blocks crossing the wrap of the first 0x4000 bytes
+    for (width, height) in [(112, 42), (112, 70)] {
+        let fixture = support::panasonic::with_size(width, height);
+        let sensor_data = export::load_sensor_data_from_buffer(&fixture.data).unwrap();
+        assert_eq!(
+            fixture.mismatch(&sensor_data.image),
+            None,
+            "{width}x{height}: (index, expected, decoded)"
+        );
+    }
+}
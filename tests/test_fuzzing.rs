@@ -0,0 +1,63 @@
+#![cfg(feature = "fuzzing")]
+
+use quickraw::{
+    fuzzing::{self, Pump},
+    DecodingError,
+};
+
+#[test]
+fn test_bit_pump_end() {
+    let data = [0xa5u8; 8];
+    for pump in Pump::ALL {
+        let max = if let Pump::Panasonic = pump { 8 } else { 32 };
+        // the Panasonic pump reads whole blocks of 0x4000 bytes
+        let data = if let Pump::Panasonic = pump {
+            &[0xa5u8; 0x4000][..]
+        } else {
+            &data[..]
+        };
+        let bits = data.len() * 8;
+
+        let lengths = vec![max as u8; bits / max];
+        assert!(fuzzing::bit_pump(pump, data, &lengths).is_ok(), "{pump:?}");
+
+        let lengths = vec![max as u8; bits / max + 1];
+        assert!(
+            matches!(
+                fuzzing::bit_pump(pump, data, &lengths),
+                Err(DecodingError::TruncatedData(..))
+            ),
+            "{pump:?}"
+        );
+    }
+}
+
+#[test]
+fn test_bit_pump_values() {
+    let data = [0x12, 0x34, 0x56, 0x78, 0x9a, 0xbc, 0xde, 0xf0];
+    assert_eq!(
+        fuzzing::bit_pump(Pump::Msb, &data, &[4, 8, 20]).unwrap(),
+        [0x1, 0x23, 0x45678]
+    );
+    assert_eq!(
+        fuzzing::bit_pump(Pump::Lsb, &data, &[4, 8]).unwrap(),
+        [0x2, 0x41]
+    );
+    // 0xff is followed by a stuffed zero in a JPEG stream
+    assert_eq!(
+        fuzzing::bit_pump(Pump::Jpeg, &[0xff, 0x00, 0x12], &[16]).unwrap(),
+        [0xff12]
+    );
+    assert!(fuzzing::bit_pump(Pump::Jpeg, &[0xff, 0xd9, 0x12], &[9]).is_err());
+}
+
+#[test]
+fn test_ljpeg() {
+    assert!(fuzzing::ljpeg(&[]).is_err());
+    assert!(fuzzing::ljpeg(&[0xff, 0xd8]).is_err());
+    // a frame of 0x7fff x 0x7fff samples with no data
+    let header = [
+        0xff, 0xd8, 0xff, 0xc3, 0x00, 0x0b, 0x0c, 0x7f, 0xff, 0x7f, 0xff, 0x01, 0x00, 0x11, 0x00,
+    ];
+    assert!(fuzzing::ljpeg(&header).is_err());
+}
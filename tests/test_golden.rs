@@ -0,0 +1,228 @@
+//! Renders every fixture through each demosaicing method and color space and compares the
+//! results with the references in `tests/golden`.
+//!
+//! After an intended change of the rendering, bless the new references with
+//! `QUICKRAW_BLESS=1 cargo test --test test_golden`.
+
+mod support;
+
+use exr::prelude::*;
+use quickraw::{
+    encode::{exr as exr_writer, Image as RgbImage, Metadata, Pixels},
+    export::{self, Options},
+    ColorSpace, DemosaicingMethod,
+};
+use std::{fs, path::PathBuf};
+use support::{metrics::Diff, Fixture};
+
+const MIN_PSNR: f64 = 50.;
+const MAX_MEAN_DELTA_E: f64 = 0.5;
+const MAX_DELTA_E: f64 = 2.;
+
+const METHODS: [DemosaicingMethod; 3] = [
+    DemosaicingMethod::None,
+    DemosaicingMethod::SuperPixel,
+    DemosaicingMethod::Linear,
+];
+
+fn method_name(method: &DemosaicingMethod) -> &'static str {
+    match method {
+        DemosaicingMethod::None => "none",
+        DemosaicingMethod::SuperPixel => "super-pixel",
+        DemosaicingMethod::Linear => "linear",
+    }
+}
+
+/// Renders with the transfer function of the color space.
+fn render(
+    fixture: &Fixture,
+    method: &DemosaicingMethod,
+    color_space: ColorSpace,
+) -> (Vec<f32>, usize, usize) {
+    let options =
+        Options::new(color_space.default_transfer(), color_space, false).with_demosaicing(*method);
+    export::load_float_image_from_buffer(&fixture.data, options)
+        .unwrap_or_else(|e| panic!("{}: {e}", fixture.name))
+}
+
+/// The renders of every color space of `ColorSpace::ALL`, stacked from the top in this order.
+struct Renders {
+    pixels: Vec<f32>,
+    width: usize,
+    height: usize,
+}
+
+impl Renders {
+    fn new(fixture: &Fixture, method: &DemosaicingMethod) -> Renders {
+        let mut renders = Renders {
+            pixels: Vec::new(),
+            width: 0,
+            height: 0,
+        };
+        for color_space in ColorSpace::ALL {
+            let (pixels, width, height) = render(fixture, method, color_space);
+            renders.pixels.extend(pixels);
+            (renders.width, renders.height) = (width, height);
+        }
+        renders
+    }
+
+    fn color_space(&self, index: usize) -> &[f32] {
+        let len = self.width * self.height * 3;
+        &self.pixels[index * len..(index + 1) * len]
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let height = self.height * ColorSpace::ALL.len();
+        let image = RgbImage::new(Pixels::F32(&self.pixels), self.width, height, 3).unwrap();
+        exr_writer::encode(&image, &Metadata::default(), &Default::default()).unwrap()
+    }
+
+    fn decode(data: &[u8]) -> Renders {
+        let image = read()
+            .no_deep_data()
+            .largest_resolution_level()
+            .rgb_channels(
+                |size, _| (size, Vec::with_capacity(size.area() * 3)),
+                |(_, pixels): &mut (Vec2<usize>, Vec<f32>), _, (r, g, b): (f32, f32, f32)| {
+                    pixels.extend([r, g, b])
+                },
+            )
+            .first_valid_layer()
+            .all_attributes()
+            .from_buffered(std::io::Cursor::new(data))
+            .unwrap();
+        let (size, pixels) = image.layer_data.channel_data.pixels;
+        Renders {
+            pixels,
+            width: size.0,
+            height: size.1 / ColorSpace::ALL.len(),
+        }
+    }
+}
+
+/// The reference of `fixture` rendered with `method` in `dir`, like `nef-lossless/linear.exr`.
+fn golden_path(dir: &str, fixture: &Fixture, method: &DemosaicingMethod) -> PathBuf {
+    let name = fixture.name.to_lowercase().replace(' ', "-");
+    PathBuf::from(dir)
+        .join(name)
+        .join(method_name(method))
+        .with_extension("exr")
+}
+
+#[test]
+fn test_golden_images() {
+    let bless = std::env::var_os("QUICKRAW_BLESS").is_some();
+    let mut failures = Vec::new();
+
+    for fixture in support::all() {
+        for method in &METHODS {
+            let renders = Renders::new(&fixture, method);
+            let path = golden_path(
+                concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden"),
+                &fixture,
+                method,
+            );
+            let file = format!("{} {}", fixture.name, method_name(method));
+            if bless {
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(&path, renders.encode()).unwrap();
+                continue;
+            }
+
+            let Ok(data) = fs::read(&path) else {
+                failures.push(format!("{file}: no reference at {}", path.display()));
+                continue;
+            };
+            let reference = Renders::decode(&data);
+            if (reference.width, reference.height) != (renders.width, renders.height) {
+                failures.push(format!(
+                    "{file}: {}x{} instead of {}x{}",
+                    renders.width, renders.height, reference.width, reference.height
+                ));
+                continue;
+            }
+
+            let mut failed = false;
+            for (index, color_space) in ColorSpace::ALL.into_iter().enumerate() {
+                let diff = Diff::new(
+                    reference.color_space(index),
+                    renders.color_space(index),
+                    color_space,
+                    color_space.default_transfer(),
+                );
+                let report = format!(
+                    "{file} {color_space:?}: PSNR {:.1} dB, ΔE2000 mean {:.3} max {:.3} at ({}, {})",
+                    diff.psnr,
+                    diff.mean_delta_e,
+                    diff.max_delta_e,
+                    diff.worst % renders.width,
+                    diff.worst / renders.width
+                );
+                println!("{report}");
+                // NaN fails as well
+                if !(diff.psnr >= MIN_PSNR
+                    && diff.mean_delta_e <= MAX_MEAN_DELTA_E
+                    && diff.max_delta_e <= MAX_DELTA_E)
+                {
+                    failures.push(report);
+                    failed = true;
+                }
+            }
+
+            // the render is kept for a look at what has changed
+            if failed {
+                let path = golden_path(
+                    concat!(env!("CARGO_TARGET_TMPDIR"), "/golden"),
+                    &fixture,
+                    method,
+                );
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(&path, renders.encode()).unwrap();
+                failures.push(format!("{file}: rendered to {}", path.display()));
+            }
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "the renders differ from the references, bless them with `QUICKRAW_BLESS=1 cargo test \
+         --test test_golden` if it is intended:\n{}",
+        failures.join("\n")
+    );
+}
+
+#[test]
+fn test_delta_e_2000() {
+    use support::metrics::delta_e_2000;
+
+    // from the test data of Sharma et al.
+    let pairs = [
+        ([50., 2.6772, -79.7751], [50., 0., -82.7485], 2.0425),
+        ([50., -1.3802, -84.2814], [50., 0., -82.7485], 1.),
+        ([50., 2.5, 0.], [73., 25., -18.], 27.1492),
+        (
+            [60.2574, -34.0099, 36.2677],
+            [60.4626, -34.1751, 39.4387],
+            1.2644,
+        ),
+        (
+            [22.7233, 20.0904, -46.694],
+            [23.0331, 14.973, -42.5619],
+            2.0373,
+        ),
+        (
+            [90.9257, -0.5406, -0.9208],
+            [88.6381, -0.8985, -0.7239],
+            1.5381,
+        ),
+    ];
+    for (lab1, lab2, expected) in pairs {
+        let delta_e = delta_e_2000(lab1, lab2);
+        assert!(
+            (delta_e - expected).abs() < 1e-4,
+            "{lab1:?} {lab2:?}: {delta_e}"
+        );
+    }
+    assert_eq!(delta_e_2000([50., 10., 10.], [50., 10., 10.]), 0.);
+}
@@ -0,0 +1,137 @@
+mod support;
+
+use std::io::Cursor;
+
+use quickraw::{
+    encode::{
+        dng::{self, DngCompression},
+        Exif, Metadata,
+    },
+    export, CFAPattern, LazyRawFile,
+};
+use support::{adobe, fujifilm, TestImage};
+
+/// The IFDs of the encoder are written after the sensor data, at the end of the file.
+fn sample_dng() -> Vec<u8> {
+    let image = TestImage::from_fn(1200, 800, CFAPattern::BGGR, |x, y| {
+        (x * 7 + y * 3) as u16 % 4096 * 16
+    });
+    let metadata = Metadata {
+        orientation: 6,
+        exif: Exif {
+            iso: Some(800),
+            date_time_original: Some("2023:01:02 03:04:05".to_owned()),
+            ..Default::default()
+        },
+        ..adobe::metadata()
+    };
+    let options = adobe::options(DngCompression::None);
+    dng::encode(&adobe::raw_image(&image), &metadata, None, &options).unwrap()
+}
+
+#[test]
+fn test_lazy_reads_match_whole_buffer() {
+    let data = sample_dng();
+    let mut file = LazyRawFile::from_reader(Cursor::new(&data)).unwrap();
+
+    let metadata = file.metadata().unwrap();
+    let expected = export::load_metadata_from_buffer(&data).unwrap();
+    assert_eq!(format!("{:?}", metadata), format!("{:?}", expected));
+    assert_eq!(metadata.iso, Some(800));
+
+    let exif_info = file.exif_info().unwrap();
+    let expected = export::load_exif_info_from_buffer(&data).unwrap();
+    for name in ["width", "height", "strip", "strip_len"] {
+        assert_eq!(
+            exif_info.usize(name).unwrap(),
+            expected.usize(name).unwrap()
+        );
+    }
+
+    let (thumbnail, orientation) = file.thumbnail().unwrap();
+    let (expected, expected_orientation) = export::load_thumbnail_from_buffer(&data).unwrap();
+    assert_eq!(thumbnail, expected.as_slice());
+    assert_eq!(orientation, expected_orientation);
+
+    // the sensor data is never fetched
+    assert!(file.fetched_len() < data.len() / 4);
+}
+
+#[test]
+fn test_lazy_raf_thumbnail() {
+    // the sensor data is much larger than the fetched headers
+    let image = TestImage::new(600, 450, CFAPattern::XTrans1, 1024);
+    let data = fujifilm::encode(&image, 1024);
+    let mut file = LazyRawFile::from_reader(Cursor::new(&data)).unwrap();
+
+    let (thumbnail, _) = file.thumbnail().unwrap();
+    let (expected, _) = export::load_thumbnail_from_buffer(&data).unwrap();
+    assert_eq!(thumbnail, expected.as_slice());
+    assert!(file.fetched_len() < data.len() / 2);
+}
+
+#[test]
+fn test_lazy_metadata_of_fixtures() {
+    for fixture in support::all() {
+        let mut file = LazyRawFile::from_reader(Cursor::new(&fixture.data)).unwrap();
+        let metadata = file.metadata().unwrap();
+        let expected = export::load_metadata_from_buffer(&fixture.data).unwrap();
+        assert_eq!(
+            format!("{:?}", metadata),
+            format!("{:?}", expected),
+            "{}",
+            fixture.name
+        );
+    }
+}
+
+#[test]
+fn test_fetch_callback() {
+    let data = sample_dng();
+    let mut requests = vec![];
+    let mut file = LazyRawFile::from_fetch(data.len() as u64, |offset, out| {
+        let start = offset as usize;
+        out.copy_from_slice(&data[start..start + out.len()]);
+        requests.push(start..start + out.len());
+        Ok(())
+    })
+    .unwrap();
+
+    file.metadata().unwrap();
+    let fetched_len = file.fetched_len();
+    // the headers are fetched once
+    file.metadata().unwrap();
+    assert_eq!(file.fetched_len(), fetched_len);
+    file.thumbnail().unwrap();
+    drop(file);
+
+    // no byte is fetched twice
+    let mut fetched = vec![false; data.len()];
+    for range in requests {
+        assert!(fetched[range.clone()].iter().all(|&x| !x));
+        fetched[range].fill(true);
+    }
+}
+
+#[test]
+fn test_fetch_error() {
+    let mut file = LazyRawFile::from_fetch(1 << 20, |_, _| {
+        Err(std::io::Error::new(std::io::ErrorKind::TimedOut, "offline"))
+    })
+    .unwrap();
+    assert!(matches!(
+        file.metadata(),
+        Err(quickraw::RawFileReadingError::StreamReadingError(_))
+    ));
+}
+
+#[test]
+fn test_bogus_length() {
+    for len in [u64::MAX, 1 << 40] {
+        let file = LazyRawFile::from_fetch(len, |_, _| Ok(()));
+        assert!(matches!(
+            file,
+            Err(quickraw::RawFileReadingError::FileTooLarge(x)) if x == len
+        ));
+    }
+}
@@ -0,0 +1,130 @@
+mod support;
+
+use quickraw::{
+    encode::dng::DngCompression, export, CFAPattern, DecodingError, RawFileReadingError,
+};
+use support::{adobe, TestImage};
+
+/// A single tile of lossless JPEG, so its offset and byte count are in place in the entries.
+fn sample_dng(compression: DngCompression) -> Vec<u8> {
+    let image = TestImage::from_fn(64, 48, CFAPattern::RGGB, |x, y| {
+        (x * 13 + y * 5) as u16 % 4096 * 16
+    });
+    adobe::encode(&image, compression)
+}
+
+/// Overwrites a single `SHORT` or `LONG` value of IFD0, or of its first sub IFD with the sensor data.
+fn set_tag(data: &mut [u8], in_sub_ifd: bool, tag: u16, value: u32) {
+    let is_le = &data[..2] == b"II";
+    let read = |data: &[u8], i: usize, len: usize| {
+        let mut bytes = data[i..i + len].to_vec();
+        if is_le {
+            bytes.reverse();
+        }
+        bytes.iter().fold(0u32, |x, &b| x << 8 | b as u32)
+    };
+    let find = |data: &[u8], ifd: usize, tag: u16| {
+        (0..read(data, ifd, 2) as usize)
+            .map(|i| ifd + 2 + i * 12)
+            .find(|&entry| read(data, entry, 2) == tag as u32)
+            .unwrap()
+    };
+
+    let mut ifd = read(data, 4, 4) as usize;
+    if in_sub_ifd {
+        ifd = read(data, find(data, ifd, 0x014a) + 8, 4) as usize;
+    }
+    let entry = find(data, ifd, tag);
+    let len = if read(data, entry + 2, 2) == 3 { 2 } else { 4 };
+    let mut bytes = value.to_be_bytes()[4 - len..].to_vec();
+    if is_le {
+        bytes.reverse();
+    }
+    data[entry + 8..entry + 8 + len].copy_from_slice(&bytes);
+}
+
+fn decoding_error(data: Vec<u8>) -> DecodingError {
+    match export::load_sensor_data_from_buffer(data) {
+        Err(RawFileReadingError::DecodingError(err)) => err,
+        Err(err) => panic!("unexpected error: {err:?}"),
+        Ok(_) => panic!("a malformed file is decoded"),
+    }
+}
+
+#[test]
+fn test_unsupported_compression() {
+    let mut data = sample_dng(DngCompression::None);
+    set_tag(&mut data, true, 0x0103, 34892);
+    assert!(matches!(
+        decoding_error(data),
+        DecodingError::UnsupportedCompression(34892)
+    ));
+}
+
+#[test]
+fn test_offset_out_of_range() {
+    let mut data = sample_dng(DngCompression::None);
+    let len = data.len() as u32;
+    set_tag(&mut data, true, 0x0111, len - 16);
+    assert!(matches!(
+        decoding_error(data),
+        DecodingError::OffsetOutOfRange(_, x) if x == len as usize
+    ));
+
+    // the offsets of the tiles of compressed data
+    let mut data = sample_dng(DngCompression::LosslessJpeg);
+    set_tag(&mut data, true, 0x0144, u32::MAX);
+    assert!(matches!(
+        decoding_error(data),
+        DecodingError::OffsetOutOfRange(..)
+    ));
+}
+
+#[test]
+fn test_truncated_data() {
+    // far more samples than the bytes of the file can hold
+    let mut data = sample_dng(DngCompression::None);
+    set_tag(&mut data, true, 0x0100, 1 << 20);
+    set_tag(&mut data, true, 0x0101, 1 << 12);
+    assert!(matches!(
+        decoding_error(data),
+        DecodingError::TruncatedData(..)
+    ));
+
+    let mut data = sample_dng(DngCompression::None);
+    set_tag(&mut data, true, 0x0100, 0);
+    assert!(matches!(
+        decoding_error(data),
+        DecodingError::InvalidImageSize(0, 48)
+    ));
+}
+
+#[test]
+fn test_broken_thumbnail() {
+    // the thumbnail of IFD0 points out of the file
+    let mut data = sample_dng(DngCompression::None);
+    let len = data.len() as u32;
+    set_tag(&mut data, false, 0x0111, len - 8);
+    assert!(matches!(
+        export::load_thumbnail_from_buffer(&data),
+        Err(RawFileReadingError::DecodingError(
+            DecodingError::OffsetOutOfRange(..)
+        ))
+    ));
+}
+
+#[test]
+fn test_truncated_ljpeg() {
+    // the stream ends long before the samples of the tile
+    let mut data = sample_dng(DngCompression::LosslessJpeg);
+    set_tag(&mut data, true, 0x0145, 12000);
+    assert!(matches!(decoding_error(data), DecodingError::LJPEGError(_)));
+
+    // less than a bit for each sample
+    let mut data = sample_dng(DngCompression::LosslessJpeg);
+    set_tag(&mut data, true, 0x0145, 100);
+    assert!(matches!(
+        decoding_error(data),
+        DecodingError::TruncatedData(..)
+    ));
+}
@@ -0,0 +1,105 @@
+mod support;
+
+use quickraw::{
+    encode::{
+        dng::{self, DngCompression, RawImage},
+        Exif, Metadata,
+    },
+    export, CFAPattern, Crop, DateTime, Orientation,
+};
+use support::{adobe, TestImage};
+
+#[test]
+fn test_metadata_from_dng() {
+    let (width, height) = (64, 48);
+    let image = TestImage::from_fn(width, height, CFAPattern::RGGB, |_, _| 1000);
+    let crop = Crop {
+        x: 2,
+        y: 4,
+        width: 60,
+        height: 40,
+    };
+    let raw_image = RawImage {
+        crop: Some(crop),
+        ..adobe::raw_image(&image)
+    };
+    let metadata = Metadata {
+        orientation: 8,
+        exif: Exif {
+            exposure_time: Some(1. / 250.),
+            f_number: Some(2.8),
+            iso: Some(400),
+            focal_length: Some(35.),
+            date_time_original: Some("2022:05:01 10:20:30".to_owned()),
+        },
+        ..adobe::metadata()
+    };
+    let options = adobe::options(DngCompression::LosslessJpeg);
+    let data = dng::encode(&raw_image, &metadata, None, &options).unwrap();
+
+    let metadata = export::load_metadata_from_buffer(&data).unwrap();
+    assert_eq!(metadata.make, "Quickraw");
+    assert_eq!(metadata.model, "Synthetic Camera");
+    assert!(metadata.is_dng);
+    assert_eq!((metadata.width, metadata.height), (width, height));
+    assert_eq!(metadata.crop, Some(crop));
+    assert_eq!(metadata.orientation, Orientation::Rotate270);
+    assert_eq!(metadata.bit_depth, Some(16));
+    assert_eq!(metadata.compression, Some(7));
+    assert_eq!(metadata.black_level, Some([0; 4]));
+    assert_eq!(metadata.white_level, Some(u16::MAX));
+
+    let [r, g, b] = metadata.white_balance.unwrap();
+    assert!((r - 2.).abs() < 1e-2 && g == 1. && (b - 1.5).abs() < 1e-2);
+
+    assert_eq!(metadata.iso, Some(400));
+    assert!((metadata.exposure_time.unwrap() - 0.004).abs() < 1e-9);
+    assert!((metadata.f_number.unwrap() - 2.8).abs() < 1e-9);
+    assert!((metadata.focal_length.unwrap() - 35.).abs() < 1e-9);
+    assert_eq!(
+        metadata.date_time.unwrap().to_string(),
+        "2022-05-01T10:20:30"
+    );
+    assert_eq!(metadata.gps, None);
+    assert_eq!(metadata.lens_model, None);
+    // DNG files carry no maker notes
+    assert_eq!(metadata.lens_id, None);
+    assert_eq!(metadata.drive_mode, None);
+}
+
+#[test]
+fn test_metadata_of_fixtures() {
+    for fixture in support::all() {
+        let metadata = export::load_metadata_from_buffer(&fixture.data)
+            .unwrap_or_else(|e| panic!("{}: {e}", fixture.name));
+        assert_eq!(
+            (metadata.width, metadata.height),
+            (fixture.image.width, fixture.image.height),
+            "{}",
+            fixture.name
+        );
+        assert_eq!(
+            metadata.black_level,
+            Some(fixture.black_level),
+            "{}",
+            fixture.name
+        );
+        assert_eq!(
+            metadata.bit_depth,
+            Some(fixture.bit_depth),
+            "{}",
+            fixture.name
+        );
+    }
+}
+
+#[test]
+fn test_date_time() {
+    let date_time = DateTime::from_exif("2022:05:01 10:20:30", Some("-03:30")).unwrap();
+    assert_eq!(date_time.offset, Some(-210));
+    assert_eq!(date_time.to_string(), "2022-05-01T10:20:30-03:30");
+
+    // unset dates are filled with zeros or spaces
+    assert_eq!(DateTime::from_exif("0000:00:00 00:00:00", None), None);
+    assert_eq!(DateTime::from_exif("    :  :     :  :  ", None), None);
+}
@@ -0,0 +1,52 @@
+#![cfg(feature = "parallel")]
+
+mod support;
+
+use quickraw::{
+    encode::dng::DngCompression, export, set_max_threads, CFAPattern, ColorSpace,
+    TransferFunction,
+};
+use support::{adobe, TestImage};
+
+fn options() -> export::Options {
+    export::Options::new(TransferFunction::Srgb, ColorSpace::Srgb, false)
+}
+
+#[test]
+fn test_thread_count_does_not_change_output() {
+    // several tiles of the DNG encoder and more pixels than a single part of the pipeline
+    let image = TestImage::from_fn(600, 300, CFAPattern::GRBG, |x, y| {
+        (x * 97 + y * 53) as u16 % 4096 * 16
+    });
+    let mut fixtures = support::all();
+    fixtures.push(adobe::fixture(
+        "large DNG uncompressed",
+        image.clone(),
+        DngCompression::None,
+    ));
+    fixtures.push(adobe::fixture(
+        "large DNG lossless JPEG",
+        image,
+        DngCompression::LosslessJpeg,
+    ));
+
+    for fixture in fixtures {
+        let data = &fixture.data;
+        let render = |threads| {
+            set_max_threads(threads).unwrap();
+            let sensor_data = export::load_sensor_data_from_buffer(data).unwrap();
+            let float_image = export::load_float_image_from_buffer(data, self::options()).unwrap();
+            let image = export::load_image_from_buffer(data, self::options()).unwrap();
+            (sensor_data.image, float_image, image)
+        };
+
+        let (sensor_image, float_image, image) = render(1);
+        assert_eq!(fixture.mismatch(&sensor_image), None, "{}", fixture.name);
+        for threads in [2, 5, 0] {
+            let (other_sensor_image, other_float_image, other_image) = render(threads);
+            assert_eq!(sensor_image, other_sensor_image, "{}", fixture.name);
+            assert_eq!(float_image, other_float_image, "{}", fixture.name);
+            assert_eq!(image, other_image, "{}", fixture.name);
+        }
+    }
+}
@@ -0,0 +1,112 @@
+mod support;
+
+use quickraw::{
+    encode::{
+        dng::{self, DngCompression},
+        Metadata,
+    },
+    export, CFAPattern, ColorSpace, Crop, PreviewScale, TransferFunction,
+};
+use support::{adobe, TestImage};
+
+const LEVELS: [u16; 3] = [8000, 16000, 12000];
+
+fn encode_dng(width: usize, height: usize, compression: DngCompression) -> Vec<u8> {
+    // a flat field of the same level for each color of the RGGB pattern
+    let image = TestImage::from_fn(width, height, CFAPattern::RGGB, |x, y| {
+        match (y % 2, x % 2) {
+            (0, 0) => LEVELS[0],
+            (1, 1) => LEVELS[2],
+            _ => LEVELS[1],
+        }
+    });
+    let raw_image = dng::RawImage {
+        crop: Some(Crop {
+            x: 8,
+            y: 8,
+            width: 48,
+            height: 32,
+        }),
+        ..adobe::raw_image(&image)
+    };
+    let metadata = Metadata {
+        orientation: 6,
+        ..adobe::metadata()
+    };
+    dng::encode(&raw_image, &metadata, None, &adobe::options(compression)).unwrap()
+}
+
+#[test]
+fn test_preview_matches_full_render() {
+    let (width, height) = (64, 48);
+    for compression in [DngCompression::None, DngCompression::LosslessJpeg] {
+        let data = encode_dng(width, height, compression);
+        let options = || export::Options::new(TransferFunction::Linear, ColorSpace::Srgb, false);
+
+        let (full, _, _) = export::load_float_image_from_buffer(data.clone(), options()).unwrap();
+        // an interior pixel, free of the border handling of demosaicing
+        let center = (height / 2 * width + width / 2) * 3;
+        let expected = &full[center..center + 3];
+
+        for scale in [
+            PreviewScale::Half,
+            PreviewScale::Quarter,
+            PreviewScale::Eighth,
+        ] {
+            let factor = scale.factor();
+            let (preview, preview_width, preview_height) =
+                export::load_preview_from_buffer(data.clone(), scale, options()).unwrap();
+            assert_eq!(
+                (preview_width, preview_height),
+                (width / factor, height / factor)
+            );
+            assert_eq!(preview.len(), preview_width * preview_height * 3);
+
+            for pixel in preview.chunks_exact(3) {
+                for (a, b) in pixel.iter().zip(expected) {
+                    assert!((a - b).abs() < 1e-3, "{:?} != {:?}", pixel, expected);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_preview_of_fixtures() {
+    let options = || export::Options::new(TransferFunction::Srgb, ColorSpace::Srgb, false);
+    for fixture in support::all() {
+        let (width, height) = (fixture.image.width, fixture.image.height);
+        for scale in [PreviewScale::Half, PreviewScale::Quarter] {
+            let factor = scale.factor();
+            let (preview, preview_width, preview_height) =
+                export::load_preview_from_buffer(&fixture.data, scale, options())
+                    .unwrap_or_else(|e| panic!("{}: {e}", fixture.name));
+            assert_eq!(
+                (preview_width, preview_height),
+                (width / factor, height / factor),
+                "{}",
+                fixture.name
+            );
+            assert_eq!(
+                preview.len(),
+                preview_width * preview_height * 3,
+                "{}",
+                fixture.name
+            );
+        }
+    }
+}
+
+#[test]
+fn test_preview_crop_and_rotation() {
+    let data = encode_dng(64, 48, DngCompression::None);
+    let options = export::Options::new(TransferFunction::Srgb, ColorSpace::Srgb, false)
+        .with_crop(true)
+        .with_rotation(true);
+
+    let (preview, width, height) =
+        export::load_preview_from_buffer(data, PreviewScale::Quarter, options).unwrap();
+    // the 48x32 crop at a quarter, rotated by 90 degrees
+    assert_eq!((width, height), (8, 12));
+    assert_eq!(preview.len(), width * height * 3);
+}
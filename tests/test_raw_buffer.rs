@@ -0,0 +1,58 @@
+mod support;
+
+use std::io::{Cursor, Seek, SeekFrom};
+
+use quickraw::{export, ColorSpace, RawBuffer, TransferFunction};
+
+fn render(buffer: impl AsRef<[u8]>) -> Vec<u16> {
+    let options = export::Options::new(TransferFunction::Srgb, ColorSpace::Srgb, false);
+    export::load_image_from_buffer(buffer, options).unwrap().0
+}
+
+#[test]
+fn test_sources_give_the_same_image() {
+    for (i, fixture) in support::all().into_iter().enumerate() {
+        let data = fixture.data;
+        let expected = render(data.clone());
+
+        // a borrowed slice is decoded without a copy
+        assert_eq!(render(data.as_slice()), expected);
+
+        let path = std::env::temp_dir().join(format!("quickraw_raw_buffer_{}", i));
+        std::fs::write(&path, &data).unwrap();
+        let path = path.to_str().unwrap();
+        let file_buffer = RawBuffer::from_file(path).unwrap();
+        assert_eq!(&*file_buffer, data.as_slice());
+        assert_eq!(render(file_buffer), expected);
+
+        let options = export::Options::new(TransferFunction::Srgb, ColorSpace::Srgb, false);
+        let (image, _, _) = export::load_image_from_file(path, options).unwrap();
+        assert_eq!(image, expected);
+
+        let file = std::fs::File::open(path).unwrap();
+        assert_eq!(render(RawBuffer::from_reader(file).unwrap()), expected);
+        std::fs::remove_file(path).unwrap();
+    }
+}
+
+#[test]
+fn test_reader_starts_at_its_position() {
+    let data = support::adobe::uncompressed().data;
+    let mut container = b"container header".to_vec();
+    container.extend_from_slice(&data);
+
+    let mut reader = Cursor::new(container);
+    reader.seek(SeekFrom::Start(16)).unwrap();
+    let buffer = RawBuffer::from_reader(reader).unwrap();
+    assert_eq!(&*buffer, data.as_slice());
+    assert_eq!(render(buffer), render(data));
+}
+
+#[test]
+fn test_missing_file() {
+    let path = std::env::temp_dir().join("quickraw_raw_buffer_missing.dng");
+    assert!(matches!(
+        RawBuffer::from_file(path.to_str().unwrap()),
+        Err(quickraw::RawFileReadingError::FileNotExisted(_))
+    ));
+}
@@ -0,0 +1,134 @@
+mod support;
+
+use quickraw::{
+    encode::dng::DngCompression, export, CFAPattern, ColorSpace, Crop, RawFileReadingError,
+    TransferFunction,
+};
+use support::{adobe, panasonic, TestImage};
+
+fn options() -> export::Options {
+    export::Options::new(TransferFunction::Srgb, ColorSpace::Srgb, false)
+}
+
+#[test]
+fn test_region_matches_full_render() {
+    // larger than a tile of the DNG encoder, so regions skip some of the tiles
+    let image = TestImage::from_fn(600, 300, CFAPattern::BGGR, |x, y| {
+        (x * 97 + y * 53) as u16 % 4096 * 16
+    });
+    let mut fixtures = support::all();
+    fixtures.push(adobe::fixture(
+        "large DNG uncompressed",
+        image.clone(),
+        DngCompression::None,
+    ));
+    fixtures.push(adobe::fixture(
+        "large DNG lossless JPEG",
+        image,
+        DngCompression::LosslessJpeg,
+    ));
+
+    for fixture in fixtures {
+        let data = &fixture.data;
+        let (width, height) = (fixture.image.width, fixture.image.height);
+        let (full, _, _) = export::load_float_image_from_buffer(data, self::options()).unwrap();
+
+        let regions = [
+            Crop {
+                x: width as u32 / 2 + 1,
+                y: height as u32 / 2 + 1,
+                width: width as u32 / 15,
+                height: height as u32 / 9,
+            },
+            Crop {
+                x: 0,
+                y: 0,
+                width: 17,
+                height: 9,
+            },
+            // clamped into the image
+            Crop {
+                x: width as u32 - 10,
+                y: height as u32 - 7,
+                width: 64,
+                height: 64,
+            },
+        ];
+        for region in regions {
+            let (pixels, region_width, region_height) =
+                export::load_region_from_buffer(data, region, self::options()).unwrap();
+            assert_eq!(
+                region_width,
+                (width - region.x as usize).min(region.width as usize),
+                "{}",
+                fixture.name
+            );
+            assert_eq!(
+                region_height,
+                (height - region.y as usize).min(region.height as usize),
+                "{}",
+                fixture.name
+            );
+
+            let expected = full
+                .chunks_exact(width * 3)
+                .skip(region.y as usize)
+                .take(region_height)
+                .flat_map(|row| &row[region.x as usize * 3..(region.x as usize + region_width) * 3])
+                .copied()
+                .collect::<Vec<_>>();
+            assert!(pixels == expected, "{} {:?}", fixture.name, region);
+        }
+
+        let out_of_image = Crop {
+            x: width as u32,
+            y: 0,
+            width: 10,
+            height: 10,
+        };
+        assert!(
+            matches!(
+                export::load_region_from_buffer(data, out_of_image, self::options()),
+                Err(RawFileReadingError::RegionIsOutOfImage)
+            ),
+            "{}",
+            fixture.name
+        );
+    }
+}
+
+#[test]
+fn test_rw2_region_ignores_other_bands() {
+    // the rows from 52 on are all in the second 0x4000 bytes of the strip
+    let fixture = panasonic::with_size(280, 60);
+    let strip = panasonic::pack(&fixture.image);
+    let offset = fixture
+        .data
+        .windows(0x4000)
+        .position(|block| block == &strip[..0x4000])
+        .unwrap();
+    let mut data = fixture.data.clone();
+    data[offset + 0x4000..offset + 0x8000].fill(0xff);
+
+    let width = fixture.image.width;
+    let (full, _, _) =
+        export::load_float_image_from_buffer(&fixture.data, self::options()).unwrap();
+    let (broken, _, _) = export::load_float_image_from_buffer(&data, self::options()).unwrap();
+    assert!(full[width * 3 * 55..] != broken[width * 3 * 55..]);
+
+    let region = Crop {
+        x: 100,
+        y: 8,
+        width: 40,
+        height: 16,
+    };
+    let (pixels, _, _) = export::load_region_from_buffer(&data, region, self::options()).unwrap();
+    let expected = full
+        .chunks_exact(width * 3)
+        .skip(8)
+        .take(16)
+        .flat_map(|row| &row[100 * 3..140 * 3])
+        .copied()
+        .collect::<Vec<_>>();
+    assert!(pixels == expected);
+}
@@ -0,0 +1,100 @@
+mod support;
+
+use quickraw::{
+    encode::dng::{self, DngCompression, RawImage},
+    export, CFAPattern, Crop,
+};
+use support::{adobe, TestImage};
+
+#[test]
+fn test_sensor_data_from_dng() {
+    let (width, height) = (40, 30);
+    // a noise floor around zero and a few clipped samples
+    let image = TestImage::from_fn(width, height, CFAPattern::GBRG, |x, y| {
+        match (y * width + x) % 97 {
+            0 => u16::MAX,
+            i => (i * 31 + y) as u16,
+        }
+    });
+    let crop = Crop {
+        x: 4,
+        y: 2,
+        width: 32,
+        height: 26,
+    };
+    let raw_image = RawImage {
+        crop: Some(crop),
+        ..adobe::raw_image(&image)
+    };
+
+    for compression in [DngCompression::None, DngCompression::LosslessJpeg] {
+        let options = adobe::options(compression);
+        let data = dng::encode(&raw_image, &adobe::metadata(), None, &options).unwrap();
+
+        let sensor_data = export::load_sensor_data_from_buffer(data).unwrap();
+        assert_eq!((sensor_data.width, sensor_data.height), (width, height));
+        assert_eq!(sensor_data.image, image.samples);
+        assert_eq!(sensor_data.cfa_pattern, CFAPattern::GBRG);
+        assert_eq!(sensor_data.black_level, Some([0; 4]));
+        assert_eq!(sensor_data.white_level, Some(u16::MAX));
+        assert_eq!(sensor_data.bit_depth, Some(16));
+        assert_eq!(sensor_data.crop, Some(crop));
+    }
+}
+
+#[test]
+fn test_sensor_data_is_not_scaled() {
+    // levels apart for each color, and samples of 14 bits scaled by 4 or of 12 bits along a curve
+    for fixture in [
+        support::nikon::uncompressed(),
+        support::nikon::lossless(),
+        support::sony::uncompressed(),
+        support::sony::compressed(),
+    ] {
+        let sensor_data = export::load_sensor_data_from_buffer(&fixture.data).unwrap();
+        assert_eq!(sensor_data.image, fixture.image.samples, "{}", fixture.name);
+        assert_eq!(
+            sensor_data.black_level,
+            Some(fixture.black_level),
+            "{}",
+            fixture.name
+        );
+        assert!(
+            fixture.black_level.iter().all(|&x| x > 0),
+            "{}",
+            fixture.name
+        );
+        assert_eq!(
+            sensor_data.white_level,
+            Some((1 << fixture.bit_depth) - 1),
+            "{}",
+            fixture.name
+        );
+
+        // the decoding subtracts the level of each position and scales the samples, the ones
+        // along a curve are dithered by one step of it
+        let scale = if fixture.name.starts_with("NEF") {
+            4
+        } else {
+            1
+        };
+        let tolerance = match sensor_data.linearization_table {
+            Some(ref table) => {
+                assert!(table.iter().enumerate().all(|(i, &x)| x as usize == i));
+                scale
+            }
+            None => 0,
+        };
+        let decoded = quickraw::decode_buffer(&fixture.data).unwrap();
+        let width = sensor_data.width;
+        for (i, (&sample, &value)) in sensor_data.image.iter().zip(&decoded.image).enumerate() {
+            let black_level = fixture.black_level[i / width % 2 * 2 + i % width % 2];
+            let expected = (sample - black_level) * scale;
+            assert!(
+                expected.abs_diff(value) <= tolerance,
+                "{}: {expected} and {value} at {i}",
+                fixture.name
+            );
+        }
+    }
+}
@@ -0,0 +1,91 @@
+#![cfg(feature = "serde")]
+
+mod support;
+
+use quickraw::{
+    data,
+    encode::{
+        dng::{self, DngCompression},
+        Exif, Metadata,
+    },
+    export, CFAPattern, ColorSpace, Crop, Orientation, RawMetadata, TransferFunction,
+};
+use support::{adobe, TestImage};
+
+#[test]
+fn test_metadata_to_json() {
+    let image = TestImage::from_fn(32, 24, CFAPattern::GRBG, |_, _| 2000);
+    let metadata = Metadata {
+        orientation: 6,
+        exif: Exif {
+            iso: Some(200),
+            date_time_original: Some("2022:05:01 10:20:30".to_owned()),
+            ..Default::default()
+        },
+        ..adobe::metadata()
+    };
+    let options = adobe::options(DngCompression::None);
+    let data = dng::encode(&adobe::raw_image(&image), &metadata, None, &options).unwrap();
+    let metadata = export::load_metadata_from_buffer(&data).unwrap();
+
+    let json = serde_json::to_string(&metadata).unwrap();
+    let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(value["make"], "Quickraw");
+    assert_eq!(value["iso"], 200);
+    assert_eq!(value["orientation"], "Rotate90");
+    assert_eq!(value["date_time"]["year"], 2022);
+    assert!(value["gps"].is_null());
+
+    let parsed: RawMetadata = serde_json::from_str(&json).unwrap();
+    assert_eq!(parsed.model, metadata.model);
+    assert_eq!(parsed.orientation, metadata.orientation);
+    assert_eq!(parsed.date_time, metadata.date_time);
+    assert_eq!(parsed.white_balance, metadata.white_balance);
+    assert_eq!(serde_json::to_string(&parsed).unwrap(), json);
+}
+
+#[test]
+fn test_metadata_of_fixtures_round_trip() {
+    for fixture in support::all() {
+        let metadata = export::load_metadata_from_buffer(&fixture.data).unwrap();
+        let json = serde_json::to_string(&metadata).unwrap();
+        let parsed: RawMetadata = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            serde_json::to_string(&parsed).unwrap(),
+            json,
+            "{}",
+            fixture.name
+        );
+    }
+}
+
+#[test]
+fn test_types_round_trip() {
+    let crop = Crop {
+        x: 1,
+        y: 2,
+        width: 3,
+        height: 4,
+    };
+    let json = serde_json::to_string(&crop).unwrap();
+    assert_eq!(json, r#"{"x":1,"y":2,"width":3,"height":4}"#);
+    assert_eq!(serde_json::from_str::<Crop>(&json).unwrap(), crop);
+
+    for pattern in [CFAPattern::RGGB, CFAPattern::XTrans1] {
+        let json = serde_json::to_string(&pattern).unwrap();
+        assert_eq!(serde_json::from_str::<CFAPattern>(&json).unwrap(), pattern);
+    }
+    let orientation: Orientation = serde_json::from_str(r#""Rotate270""#).unwrap();
+    assert_eq!(orientation, Orientation::Rotate270);
+
+    let options = export::Options::new(TransferFunction::Gamma(0.45), ColorSpace::DisplayP3, false)
+        .with_white_balance([2., 1., 1.5])
+        .with_crop(true);
+    let json = serde_json::to_string(&options).unwrap();
+    let parsed: export::Options = serde_json::from_str(&json).unwrap();
+    assert_eq!(serde_json::to_string(&parsed).unwrap(), json);
+
+    let options = export::Options::new(data::GAMMA_SRGB, &data::XYZ2SRGB, false);
+    let value = serde_json::to_value(&options).unwrap();
+    assert_eq!(value["color_space"], "Srgb");
+}
@@ -0,0 +1,52 @@
+mod support;
+
+use quickraw::{
+    encode::dng::DngCompression, export, set_simd_enabled, CFAPattern, ColorSpace,
+    TransferFunction,
+};
+use support::{adobe, TestImage};
+
+#[test]
+fn test_simd_matches_scalar() {
+    // odd sizes leave pixels that do not fill a vector, bright samples saturate after the white balance
+    let image = TestImage::from_fn(601, 301, CFAPattern::RGGB, |x, y| {
+        (x * 211 + y * 127 + (y * 601 + x) * 7) as u16 % 16384 * 4
+    });
+    let mut fixtures = support::all();
+    fixtures.push(adobe::fixture("bright DNG", image, DngCompression::None));
+
+    for fixture in fixtures {
+        let data = &fixture.data;
+        for (gamma, color_space) in [
+            (TransferFunction::Srgb, ColorSpace::Srgb),
+            (TransferFunction::Linear, ColorSpace::Rec2020),
+        ] {
+            let render = |simd| {
+                set_simd_enabled(simd);
+                let options = || export::Options::new(gamma, color_space, false);
+                let image = export::load_image_from_buffer(data, options()).unwrap();
+                let float_image = export::load_float_image_from_buffer(data, options()).unwrap();
+                (image, float_image)
+            };
+
+            let (scalar_image, scalar_float_image) = render(false);
+            let (image, float_image) = render(true);
+            assert_eq!(scalar_image, image, "{}", fixture.name);
+            assert_eq!(
+                scalar_float_image.0.len(),
+                float_image.0.len(),
+                "{}",
+                fixture.name
+            );
+            assert!(
+                scalar_float_image
+                    .0
+                    .iter()
+                    .zip(&float_image.0)
+                    .all(|(a, b)| a.to_bits() == b.to_bits()),
+                "{}",
+                fixture.name
+            );
+        }
+    }
+}
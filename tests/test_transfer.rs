@@ -0,0 +1,33 @@
+use quickraw::TransferFunction;
+
+const CURVES: [TransferFunction; 8] = [
+    TransferFunction::Linear,
+    TransferFunction::Gamma(0.45),
+    TransferFunction::Srgb,
+    TransferFunction::Rec709,
+    TransferFunction::Bt1886,
+    TransferFunction::ProPhoto,
+    TransferFunction::Pq,
+    TransferFunction::Hlg,
+];
+
+#[test]
+fn test_round_trip() {
+    for curve in CURVES {
+        for i in 0..=100 {
+            let v = i as f32 / 100.;
+            let decoded = curve.decode(curve.encode(v));
+            assert!((decoded - v).abs() < 1e-4, "{:?} {} {}", curve, v, decoded);
+        }
+    }
+}
+
+#[test]
+fn test_known_values() {
+    assert!((TransferFunction::Srgb.encode(0.18) - 0.46135613).abs() < 1e-5);
+    assert!((TransferFunction::Hlg.encode(1. / 12.) - 0.5).abs() < 1e-5);
+    assert!((TransferFunction::Pq.encode(10000. / 203.) - 1.).abs() < 1e-5);
+    assert!((TransferFunction::Pq.encode(1.) - 0.580_688_9).abs() < 1e-4);
+    assert_eq!(TransferFunction::Srgb.encode(-0.5), -TransferFunction::Srgb.encode(0.5));
+    assert_eq!(TransferFunction::from(1.), TransferFunction::Linear);
+}